@@ -2,6 +2,7 @@
 // 提供图标搜索、选择、保存等功能的响应式状态管理
 
 import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
 import { computed, reactive, ref } from 'vue'
 import {
   type ClearCacheRequest,
@@ -10,6 +11,7 @@ import {
   type IconCacheStats,
   type IconConfig,
   type IconItem,
+  type IconSaveProgress,
   type IconSaveRequest,
   type IconSaveResult,
   type IconSearchParams,
@@ -117,6 +119,7 @@ export function useIconSearch() {
         page: searchParams.page,
         page_size: searchParams.pageSize,
         from_collection: searchParams.fromCollection,
+        from_favorites: searchParams.fromFavorites,
       }
 
       const result = await invoke<IconSearchResultRaw>('search_icons', { request })
@@ -276,12 +279,19 @@ export function useIconSearch() {
   }
 
   /**
-   * 保存图标到本地
+   * 保存图标到本地（后端并发保存，通过 onProgress 回调进度）
    */
-  async function saveIcons(request: IconSaveRequest): Promise<IconSaveResult | null> {
+  async function saveIcons(
+    request: IconSaveRequest,
+    onProgress?: (progress: IconSaveProgress) => void,
+  ): Promise<IconSaveResult | null> {
     loading.value = true
     error.value = null
 
+    const unlisten = onProgress
+      ? await listen<IconSaveProgress>('icon-save-progress', event => onProgress(event.payload))
+      : null
+
     try {
       // 转换为后端格式
       const backendRequest = {
@@ -300,6 +310,8 @@ export function useIconSearch() {
         save_path: request.savePath,
         format: request.format,
         png_size: request.pngSize,
+        png_background: request.pngBackground,
+        write_manifest: request.writeManifest ?? false,
       }
 
       const result = await invoke<any>('save_icons', { request: backendRequest })
@@ -322,6 +334,7 @@ export function useIconSearch() {
       return null
     }
     finally {
+      unlisten?.()
       loading.value = false
     }
   }
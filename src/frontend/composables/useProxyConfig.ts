@@ -11,6 +11,10 @@ export interface ProxyConfig {
   host: string // 代理主机地址
   port: number // 代理端口
   only_for_cn: boolean // 仅在中国大陆地区使用代理
+  test_url?: string | null // 代理可用性探测地址（可选）
+  scan_hosts?: string[] | null // 自动检测时扫描的代理主机（可选）
+  custom_proxies?: string[] // 自动检测时优先尝试的自定义代理（host:port）
+  force_direct?: boolean // 强制直连，忽略所有代理
 }
 
 /**
@@ -18,6 +18,8 @@ export interface IconSearchParams {
   pageSize?: number
   /** 是否仅从精选集搜索 */
   fromCollection?: boolean
+  /** 是否仅从本地收藏夹搜索 */
+  fromFavorites?: boolean
 }
 
 /** 单个图标项 */
@@ -73,6 +75,10 @@ export interface IconSaveRequest {
   format: IconFormat
   /** PNG 尺寸（仅当 format 包含 PNG 时有效） */
   pngSize?: number
+  /** PNG 背景色（#RGB / #RRGGBB / #RRGGBBAA 或 transparent，默认透明） */
+  pngBackground?: string
+  /** 保存后生成/合并 icons.json 与 index.ts 清单 */
+  writeManifest?: boolean
 }
 
 /** 单个图标保存结果 */
@@ -101,6 +107,20 @@ export interface IconSaveResult {
   savePath: string
 }
 
+/** 批量保存进度（后端 icon-save-progress 事件） */
+export interface IconSaveProgress {
+  /** 已完成数量（含失败） */
+  saved: number
+  /** 总数量 */
+  total: number
+  /** 刚完成的图标 ID */
+  id: number
+  /** 刚完成的图标名称 */
+  name: string
+  /** 该图标是否保存成功 */
+  success: boolean
+}
+
 // ============ 内容获取相关类型 ============
 
 /** 获取图标内容请求 */
@@ -111,6 +131,8 @@ export interface IconContentRequest {
   format?: IconFormat
   /** PNG 尺寸（仅 PNG 格式有效） */
   pngSize?: number
+  /** PNG 背景色（#RGB / #RRGGBB / #RRGGBBAA 或 transparent，默认透明） */
+  pngBackground?: string
 }
 
 /** 图标内容响应 */
@@ -125,6 +147,8 @@ export interface IconContentResult {
   pngBase64?: string
   /** 文件 MIME 类型 */
   mimeType: string
+  /** 获取失败时的错误信息 */
+  error?: string
 }
 
 // ============ 缓存管理相关类型 ============
@@ -230,4 +254,5 @@ export const DEFAULT_SEARCH_PARAMS: IconSearchParams = {
   page: 1,
   pageSize: 50,
   fromCollection: false,
+  fromFavorites: false,
 }
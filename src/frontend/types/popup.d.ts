@@ -9,6 +9,9 @@ export interface McpRequest {
   uiux_intent?: 'none' | 'beautify' | 'page_refactor' | 'uiux_search'
   uiux_context_policy?: 'auto' | 'force' | 'forbid'
   uiux_reason?: string
+  default_input?: string
+  timeout_secs?: number
+  default_on_timeout?: string
 }
 
 // 自定义prompt类型定义
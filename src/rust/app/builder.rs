@@ -111,6 +111,8 @@ pub fn build_tauri_app() -> Builder<tauri::Wry> {
             crate::mcp::tools::context7::commands::test_context7_connection,
             crate::mcp::tools::context7::commands::get_context7_config,
             crate::mcp::tools::context7::commands::save_context7_config,
+            crate::mcp::tools::context7::commands::get_context7_cache_stats,
+            crate::mcp::tools::context7::commands::clear_context7_cache,
 
             // 自定义prompt命令
             get_custom_prompt_config,
@@ -145,6 +147,7 @@ pub fn build_tauri_app() -> Builder<tauri::Wry> {
 
             // 更新命令
             check_for_updates,
+            check_for_update,
             download_and_install_update,
             get_current_version,
             restart_app,
@@ -163,15 +166,25 @@ pub fn build_tauri_app() -> Builder<tauri::Wry> {
             crate::mcp::tools::icon::commands::save_icons,
             crate::mcp::tools::icon::commands::get_icon_cache_stats,
             crate::mcp::tools::icon::commands::clear_icon_cache,
+            crate::mcp::tools::icon::commands::list_icon_favorites,
+            crate::mcp::tools::icon::commands::add_icon_favorite,
+            crate::mcp::tools::icon::commands::remove_icon_favorite,
             crate::mcp::tools::icon::commands::get_icon_config,
             crate::mcp::tools::icon::commands::set_icon_config,
             crate::mcp::tools::icon::commands::copy_icon_to_clipboard,
             crate::mcp::tools::icon::commands::select_icon_save_directory,
 
+            // UI/UX 命令
+            crate::mcp::tools::uiux::commands::reload_uiux_corpus,
+
             // 提示词增强命令
             crate::mcp::tools::enhance::commands::enhance_prompt_stream,
             crate::mcp::tools::enhance::commands::enhance_prompt,
             crate::mcp::tools::enhance::commands::cancel_enhance_request,
+            crate::mcp::tools::enhance::commands::validate_enhance_config,
+            crate::mcp::tools::enhance::commands::list_active_enhance_requests,
+            crate::mcp::tools::enhance::commands::cancel_all_enhance_requests,
+            crate::mcp::tools::enhance::commands::get_enhance_blobs,
             crate::mcp::tools::enhance::commands::add_chat_history,
             crate::mcp::tools::enhance::commands::get_chat_history,
             crate::mcp::tools::enhance::commands::clear_chat_history,
@@ -179,7 +192,15 @@ pub fn build_tauri_app() -> Builder<tauri::Wry> {
             // zhi 交互历史命令
             crate::mcp::tools::interaction::commands::add_zhi_history,
             crate::mcp::tools::interaction::commands::get_zhi_history,
+            crate::mcp::tools::interaction::commands::get_zhi_history_by_ids,
+            crate::mcp::tools::interaction::commands::search_zhi_history,
+            crate::mcp::tools::interaction::commands::remove_zhi_history_entry,
             crate::mcp::tools::interaction::commands::clear_zhi_history,
+            crate::mcp::tools::interaction::commands::preview_zhi_message,
+
+            // 跨项目历史浏览命令
+            list_history_projects,
+            clear_all_history,
 
             // 记忆管理命令
             get_memory_list,
@@ -188,7 +209,10 @@ pub fn build_tauri_app() -> Builder<tauri::Wry> {
             save_memory_config,
             deduplicate_memories,
             preview_similarity,
-            delete_memory
+            delete_memory,
+            delete_memories,
+            set_memory_pinned,
+            update_memory_category
         ])
         .setup(|app| {
             let app_handle = app.handle().clone();
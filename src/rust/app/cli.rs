@@ -1,15 +1,29 @@
 use crate::config::load_standalone_telegram_config;
+use crate::config::keys::{available_keys, config_get, config_set};
 use crate::mcp::types::PopupRequest;
+use crate::mcp::handlers::{read_request_source, MCP_REQUEST_BASE64_PREFIX, MCP_REQUEST_STDIN_ARG};
 use crate::mcp::utils::generate_request_id;
 use crate::telegram::handle_telegram_only_mcp_request;
 use crate::log_important;
 use crate::app::builder::run_tauri_app;
+use crate::mcp::tools::enhance::{EnhanceRequest, HistoryMode, PromptEnhancer};
+use crate::mcp::tools::icon::api::search_icons;
+use crate::mcp::tools::icon::types::IconSearchRequest;
+use crate::constants::network::DEFAULT_TOOL_REQUEST_TIMEOUT_SECS;
 use anyhow::Result;
+use serde_json::json;
+use std::io::Write;
+
+/// 全局参数：以 JSON 输出机器可读结果（便于脚本与 CI 集成）
+///
+/// 仅在程序名之后或 `--version` / `--config get|set` 完整参数之后识别，其余位置按普通参数值处理
+pub const JSON_FLAG: &str = "--json";
 
 /// 处理命令行参数
 pub fn handle_cli_args() -> Result<()> {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
     crate::log_debug!("CLI启动参数: {:?}", args);
+    let json_output = take_json_flag(&mut args);
 
     match args.len() {
         // 无参数：正常启动GUI
@@ -21,7 +35,7 @@ pub fn handle_cli_args() -> Result<()> {
         2 => {
             match args[1].as_str() {
                 "--help" | "-h" => print_help(),
-                "--version" | "-v" => print_version(),
+                "--version" | "-v" => print_version(json_output),
                 _ => {
                     eprintln!("未知参数: {}", args[1]);
                     print_help();
@@ -33,7 +47,7 @@ pub fn handle_cli_args() -> Result<()> {
         _ => {
             if args[1] == "--mcp-request" {
                 if args.len() >= 3 {
-                    crate::log_important!(info, "进入MCP请求模式: request_file={}", args[2]);
+                    crate::log_important!(info, "进入MCP请求模式: request_len={}", args[2].len());
                     handle_mcp_request(&args[2])?;
                 } else {
                     eprintln!("缺少必填参数: --mcp-request <文件>");
@@ -44,8 +58,14 @@ pub fn handle_cli_args() -> Result<()> {
                 // CLI 模式：解析参数并启动 GUI 交互
                 crate::log_important!(info, "进入CLI交互模式（--cli）");
                 handle_cli_mode(&args[2..])?;
+            } else if args[1] == "--enhance" {
+                // 增强模式：不启动 GUI，流式输出到 stdout
+                crate::log_important!(info, "进入提示词增强模式（--enhance）");
+                handle_enhance_mode(&args[2..])?;
+            } else if args[1] == "--config" {
+                handle_config_command(&args[2..], json_output)?;
             } else if args[1] == "--icon-search" {
-                // 图标搜索模式：解析参数并启动 GUI
+                // 图标搜索模式：解析参数并启动 GUI（--no-gui 时直接输出 JSON 结果）
                 crate::log_important!(info, "进入图标搜索模式（--icon-search）");
                 handle_icon_search(&args[2..])?;
             } else {
@@ -71,6 +91,7 @@ fn handle_cli_mode(args: &[String]) -> Result<()> {
     let mut uiux_intent: Option<String> = None;
     let mut uiux_context_policy: Option<String> = None;
     let mut uiux_reason: Option<String> = None;
+    let mut default_input: Option<String> = None;
 
     let mut i = 0;
     while i < args.len() {
@@ -112,6 +133,10 @@ fn handle_cli_mode(args: &[String]) -> Result<()> {
                 uiux_reason = Some(args[i + 1].clone());
                 i += 2;
             }
+            "--default-input" if i + 1 < args.len() => {
+                default_input = Some(args[i + 1].clone());
+                i += 2;
+            }
             "--help" | "-h" => {
                 print_help();
                 return Ok(());
@@ -172,6 +197,9 @@ fn handle_cli_mode(args: &[String]) -> Result<()> {
         uiux_intent,
         uiux_context_policy,
         uiux_reason,
+        default_input,
+        timeout_secs: None,
+        default_on_timeout: None,
     };
     let request_json = serde_json::to_string(&request)?;
     std::env::set_var("SANSHU_CLI_MODE", "true");
@@ -182,6 +210,9 @@ fn handle_cli_mode(args: &[String]) -> Result<()> {
     Ok(())
 }
 
+/// 内联 MCP 请求（stdin / base64 参数）解码后的 JSON 环境变量，供前端读取
+pub const MCP_REQUEST_JSON_ENV: &str = "SANSHU_MCP_REQUEST_JSON";
+
 /// 拆分 CLI 选项列表
 fn split_cli_options(raw: &str) -> Vec<String> {
     raw.split(',')
@@ -191,8 +222,23 @@ fn split_cli_options(raw: &str) -> Vec<String> {
 }
 
 /// 处理MCP请求
-fn handle_mcp_request(request_file: &str) -> Result<()> {
-    log_important!(info, "[handle_mcp_request] 收到请求文件: {}", request_file);
+///
+/// `request_source` 可为请求文件路径、`-`（stdin）或 `base64:<data>`
+fn handle_mcp_request(request_source: &str) -> Result<()> {
+    let is_inline = request_source == MCP_REQUEST_STDIN_ARG
+        || request_source.starts_with(MCP_REQUEST_BASE64_PREFIX);
+    log_important!(
+        info,
+        "[handle_mcp_request] 收到请求: source={}",
+        if is_inline { "inline" } else { request_source }
+    );
+
+    // 中文注释：内联请求需在启动前读取（stdin 只能读取一次），并写入环境变量供前端读取
+    if is_inline {
+        let request_json = read_request_source(request_source, std::io::stdin())?;
+        std::env::set_var(MCP_REQUEST_JSON_ENV, &request_json);
+    }
+
     // 检查Telegram配置，决定是否启用纯Telegram模式
     match load_standalone_telegram_config() {
         Ok(telegram_config) => {
@@ -202,9 +248,13 @@ fn handle_mcp_request(request_file: &str) -> Result<()> {
                     info,
                     "[handle_mcp_request] 进入纯Telegram模式（hide_frontend_popup=true）"
                 );
+                let request_json = match std::env::var(MCP_REQUEST_JSON_ENV) {
+                    Ok(json) if is_inline => json,
+                    _ => read_request_source(request_source, std::io::empty())?,
+                };
                 if let Err(e) = tokio::runtime::Runtime::new()
                     .unwrap()
-                    .block_on(handle_telegram_only_mcp_request(request_file))
+                    .block_on(handle_telegram_only_mcp_request(&request_json))
                 {
                     log_important!(error, "处理Telegram请求失败: {}", e);
                     std::process::exit(1);
@@ -229,15 +279,174 @@ fn handle_mcp_request(request_file: &str) -> Result<()> {
     Ok(())
 }
 
+/// 处理提示词增强模式
+///
+/// 不启动 GUI：调用增强 API，将流式片段实时写入 stdout，结束后输出最终增强结果
+fn handle_enhance_mode(args: &[String]) -> Result<()> {
+    let mut prompt: Option<String> = None;
+    let mut project_root: Option<String> = None;
+    let mut include_history = true;
+    let mut history_limit: Option<usize> = None;
+    let mut zhi_history_limit: Option<usize> = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--project-root" if i + 1 < args.len() => {
+                project_root = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--no-history" => {
+                include_history = false;
+                i += 1;
+            }
+            flag @ ("--history-limit" | "--zhi-history-limit") if i + 1 < args.len() => {
+                let value = match args[i + 1].parse::<usize>() {
+                    Ok(value) => value,
+                    Err(_) => {
+                        eprintln!("{} 需要非负整数: {}", flag, args[i + 1]);
+                        std::process::exit(2);
+                    }
+                };
+                if flag == "--history-limit" {
+                    history_limit = Some(value);
+                } else {
+                    zhi_history_limit = Some(value);
+                }
+                i += 2;
+            }
+            "--help" | "-h" => {
+                print_help();
+                return Ok(());
+            }
+            value if prompt.is_none() && !value.starts_with("--") => {
+                prompt = Some(value.to_string());
+                i += 1;
+            }
+            _ => {
+                eprintln!("无效的命令行参数: {}", args[i]);
+                print_help();
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let prompt = match prompt {
+        Some(value) if !value.trim().is_empty() => value,
+        _ => {
+            eprintln!("缺少必填参数: --enhance <提示词>");
+            print_help();
+            std::process::exit(2);
+        }
+    };
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let response = runtime.block_on(async {
+        let mut enhancer = PromptEnhancer::from_acemcp_config().await?;
+        if let Some(ref root) = project_root {
+            enhancer = enhancer.with_project_root(root);
+        }
+        let request = EnhanceRequest {
+            prompt,
+            original_prompt: None,
+            project_root_path: project_root.clone(),
+            current_file_path: None,
+            // 中文注释：未指定项目根目录时没有可用历史，直接关闭历史注入
+            include_history: include_history && project_root.is_some(),
+            selected_history_ids: None,
+            selected_zhi_ids: None,
+            regenerate: false,
+            blob_names: None,
+            request_id: None,
+            conversation_id: None,
+            history_limit,
+            zhi_history_limit,
+            history_mode: HistoryMode::Raw,
+            persona_type: None,
+            mode: None,
+            dry_run: false,
+            verbose: false,
+            record_to: None,
+            cancel_flag: None,
+        };
+        enhancer
+            .enhance_stream(request, |event| {
+                if event.event_type == "chunk" {
+                    if let Some(chunk) = event.chunk {
+                        let mut stdout = std::io::stdout();
+                        let _ = stdout.write_all(chunk.as_bytes());
+                        let _ = stdout.flush();
+                    }
+                }
+            })
+            .await
+    });
+
+    match response {
+        Ok(response) if response.success => {
+            println!();
+            println!();
+            println!("=== 增强结果 ===");
+            println!("{}", response.enhanced_prompt);
+            Ok(())
+        }
+        Ok(response) => {
+            eprintln!("提示词增强失败: {}", response.error.unwrap_or_default());
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("提示词增强失败: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// 处理配置读写子命令：`--config get <键>` / `--config set <键> <值>`
+///
+/// JSON 模式下输出 `{"key":..,"value":..}` / `{"key":..,"updated":true}`，失败时输出 `{"error":..}`
+fn handle_config_command(args: &[String], json_output: bool) -> Result<()> {
+    let result = match args {
+        [action, key] if action == "get" => config_get(key).map(|value| {
+            if json_output {
+                println!("{}", json!({ "key": key, "value": value }));
+            } else {
+                println!("{}", value);
+            }
+        }),
+        [action, key, value] if action == "set" => config_set(key, value).map(|_| {
+            if json_output {
+                println!("{}", json!({ "key": key, "updated": true }));
+            } else {
+                println!("已更新 {}", key);
+            }
+        }),
+        _ => {
+            eprintln!("用法: --config get <键> | --config set <键> <值>");
+            eprintln!("可用配置项: {}", available_keys());
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        if json_output {
+            println!("{}", json!({ "error": e.to_string() }));
+        } else {
+            eprintln!("{}", e);
+        }
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
 /// 处理图标搜索请求
 /// 
-/// 解析 CLI 参数并设置环境变量，启动 GUI 进入图标选择模式
+/// 解析 CLI 参数并设置环境变量，启动 GUI 进入图标选择模式；`--no-gui` 时直接搜索并输出 JSON
 fn handle_icon_search(args: &[String]) -> Result<()> {
     // 解析参数
     let mut query = String::new();
     let mut style = String::new();
     let mut save_path = String::new();
     let mut project_root = String::new();
+    let mut no_gui = false;
     
     let mut i = 0;
     while i < args.len() {
@@ -258,6 +467,10 @@ fn handle_icon_search(args: &[String]) -> Result<()> {
                 project_root = args[i + 1].clone();
                 i += 2;
             }
+            "--no-gui" => {
+                no_gui = true;
+                i += 1;
+            }
             _ => {
                 // 如果第一个参数不是选项，假设它是搜索关键词
                 if i == 0 && !args[i].starts_with("--") {
@@ -268,6 +481,10 @@ fn handle_icon_search(args: &[String]) -> Result<()> {
         }
     }
     
+    if no_gui {
+        return handle_icon_search_headless(query, style);
+    }
+
     // 设置环境变量，供 Tauri 应用读取
     std::env::set_var("SANSHU_ICON_MODE", "true");
     if !query.is_empty() {
@@ -289,18 +506,53 @@ fn handle_icon_search(args: &[String]) -> Result<()> {
     Ok(())
 }
 
+/// 无界面图标搜索：调用图标 API 并将 `IconSearchResult` 以 JSON 输出到 stdout
+fn handle_icon_search_headless(query: String, style: String) -> Result<()> {
+    if query.trim().is_empty() {
+        eprintln!("缺少必填参数: --icon-search --no-gui --query <关键词>");
+        std::process::exit(2);
+    }
+    let request = IconSearchRequest {
+        query,
+        style: Some(style).filter(|s| !s.is_empty()),
+        ..Default::default()
+    };
+    let timeout_secs = crate::config::load_standalone_config()
+        .ok()
+        .and_then(|c| c.mcp_config.icon_request_timeout_secs)
+        .unwrap_or(DEFAULT_TOOL_REQUEST_TIMEOUT_SECS);
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    match runtime.block_on(search_icons(request, timeout_secs)) {
+        Ok(result) => {
+            println!("{}", serde_json::to_string(&result)?);
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("图标搜索失败: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
 /// 显示帮助信息
 fn print_help() {
     println!("三术 - 智能代码审查工具");
     println!();
     println!("用法:");
     println!("  等一下                              启动设置界面");
-    println!("  等一下 --mcp-request <文件>          处理 MCP 请求");
+    println!("  等一下 --mcp-request <文件>          处理 MCP 请求（- 表示 stdin，base64:<数据> 表示内联）");
     println!("  等一下 --cli [选项]                  命令行独立调用 zhi 交互");
+    println!("  等一下 --enhance <提示词> [选项]      命令行增强提示词（流式输出到 stdout）");
+    println!("  等一下 --config get <键>             读取配置项（敏感值脱敏显示）");
+    println!("  等一下 --config set <键> <值>        修改配置项（如 tools.sou、proxy.host、enhance.base_url）");
     println!("  等一下 --icon-search [选项]          打开图标选择界面");
     println!("  等一下 --help                       显示此帮助信息");
     println!("  等一下 --version                    显示版本信息");
     println!();
+    println!("全局选项:");
+    println!("  --json                               以 JSON 输出（--version、--config get/set）");
+    println!();
     println!("CLI 交互选项:");
     println!("  --message, -m <内容>                 必填，弹窗消息");
     println!("  --options, -o <选项1,选项2>           预定义选项（逗号分隔）");
@@ -310,16 +562,50 @@ fn print_help() {
     println!("  --uiux-intent <值>                   none/beautify/page_refactor/uiux_search");
     println!("  --uiux-context-policy <值>           auto/force/forbid");
     println!("  --uiux-reason <内容>                  UI/UX 上下文追加原因");
+    println!("  --default-input <内容>                输入框预填的默认文本");
+    println!();
+    println!("增强选项:");
+    println!("  --project-root <路径>                项目根目录（启用历史与代码上下文）");
+    println!("  --no-history                         不注入对话历史");
+    println!("  --history-limit <条数>               注入的对话历史条数（上限 20）");
+    println!("  --zhi-history-limit <条数>           注入的 zhi 交互历史条数（上限 20）");
     println!();
     println!("图标搜索选项:");
     println!("  --query <关键词>      预设搜索关键词");
     println!("  --style <风格>        图标风格: line/fill/flat/all");
     println!("  --save-path <路径>    保存目录路径");
     println!("  --project-root <路径> 项目根目录");
+    println!("  --no-gui              不打开界面，直接搜索并以 JSON 输出结果");
 }
 
 /// 显示版本信息
-fn print_version() {
-    println!("三术 v{}", env!("CARGO_PKG_VERSION"));
+fn print_version(json_output: bool) {
+    if json_output {
+        println!("{}", json!({ "version": env!("CARGO_PKG_VERSION") }));
+    } else {
+        println!("三术 v{}", env!("CARGO_PKG_VERSION"));
+    }
+}
+
+/// 移除处于开关位置的 `--json`，返回是否出现过
+///
+/// 作为选项值（如 `--enhance --json`、`--config set <键> --json`）出现时保留原样
+fn take_json_flag(args: &mut Vec<String>) -> bool {
+    if args.get(1).map(String::as_str) == Some(JSON_FLAG) {
+        args.remove(1);
+        return true;
+    }
+    // 中文注释：仅固定参数个数的命令支持末尾开关，参数齐全后多出的 --json 才视为开关
+    let complete_len = match (args.get(1).map(String::as_str), args.get(2).map(String::as_str)) {
+        (Some("--version" | "-v"), _) => 2,
+        (Some("--config"), Some("get")) => 4,
+        (Some("--config"), Some("set")) => 5,
+        _ => return false,
+    };
+    if args.len() == complete_len + 1 && args.last().map(String::as_str) == Some(JSON_FLAG) {
+        args.pop();
+        return true;
+    }
+    false
 }
 
@@ -7,5 +7,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     auto_init_logger()?;
 
     log_important!(info, "启动 MCP 服务器");
+    sanshu::network::log_force_direct_status();
     run_server().await
 }
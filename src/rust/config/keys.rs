@@ -0,0 +1,180 @@
+// 命令行配置读写
+// 通过点分键名（如 proxy.host、tools.sou）读取/修改独立配置，供 `--config get/set` 使用
+
+use anyhow::{bail, Result};
+
+use super::settings::{default_mcp_tools, AppConfig};
+use super::storage::{load_standalone_config, save_standalone_config};
+use crate::constants::mcp;
+
+/// 支持的非工具类配置键（工具开关使用 `tools.<工具ID>`）
+pub const CONFIG_KEYS: &[&str] = &[
+    "proxy.auto_detect",
+    "proxy.enabled",
+    "proxy.type",
+    "proxy.host",
+    "proxy.port",
+    "proxy.only_for_cn",
+    "proxy.test_url",
+    "proxy.force_direct",
+    "enhance.base_url",
+    "enhance.token",
+];
+
+/// 读取时需要脱敏的配置键
+const SECRET_KEYS: &[&str] = &["enhance.token"];
+
+/// 未设置的可选配置项的显示值
+const UNSET_VALUE: &str = "(未设置)";
+
+/// 可用配置键说明（用于错误提示与帮助信息）
+pub fn available_keys() -> String {
+    mcp::DEFAULT_ENABLED_TOOLS
+        .iter()
+        .map(|id| format!("tools.{}", id))
+        .chain(CONFIG_KEYS.iter().map(|k| k.to_string()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 脱敏：仅保留前 4 个字符
+fn mask_secret(value: &str) -> String {
+    if value.chars().count() <= 8 {
+        return "****".to_string();
+    }
+    format!("{}****", value.chars().take(4).collect::<String>())
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Ok(true),
+        "false" | "0" | "no" | "off" => Ok(false),
+        _ => bail!("配置项 {} 需要布尔值（true/false），实际为: {}", key, value),
+    }
+}
+
+fn optional_string(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// 读取配置项的值（敏感项脱敏）
+pub fn get_config_value(config: &AppConfig, key: &str) -> Result<String> {
+    if let Some(tool_id) = key.strip_prefix("tools.") {
+        if !mcp::is_valid_tool_id(tool_id) {
+            bail!("未知的配置项: {}。可用配置项: {}", key, available_keys());
+        }
+        // 中文注释：旧配置可能缺少新增工具，按默认启用状态返回
+        let enabled = config.mcp_config.tools.get(tool_id).copied()
+            .or_else(|| default_mcp_tools().get(tool_id).copied())
+            .unwrap_or(false);
+        return Ok(enabled.to_string());
+    }
+
+    let proxy = &config.proxy_config;
+    let value = match key {
+        "proxy.auto_detect" => proxy.auto_detect.to_string(),
+        "proxy.enabled" => proxy.enabled.to_string(),
+        "proxy.type" => proxy.proxy_type.clone(),
+        "proxy.host" => proxy.host.clone(),
+        "proxy.port" => proxy.port.to_string(),
+        "proxy.only_for_cn" => proxy.only_for_cn.to_string(),
+        "proxy.test_url" => proxy.test_url.clone().unwrap_or_else(|| UNSET_VALUE.to_string()),
+        "proxy.force_direct" => proxy.force_direct.to_string(),
+        "enhance.base_url" => config.mcp_config.acemcp_base_url.clone().unwrap_or_else(|| UNSET_VALUE.to_string()),
+        "enhance.token" => match config.mcp_config.acemcp_token.as_deref() {
+            Some(token) => token.to_string(),
+            None => return Ok(UNSET_VALUE.to_string()),
+        },
+        _ => bail!("未知的配置项: {}。可用配置项: {}", key, available_keys()),
+    };
+
+    if SECRET_KEYS.contains(&key) {
+        return Ok(mask_secret(&value));
+    }
+    Ok(value)
+}
+
+/// 修改配置项（仅修改内存中的配置，持久化由调用方负责）
+pub fn set_config_value(config: &mut AppConfig, key: &str, value: &str) -> Result<()> {
+    if let Some(tool_id) = key.strip_prefix("tools.") {
+        if !mcp::is_valid_tool_id(tool_id) {
+            bail!("未知的配置项: {}。可用配置项: {}", key, available_keys());
+        }
+        let enabled = parse_bool(key, value)?;
+        if tool_id == mcp::TOOL_ZHI && !enabled {
+            bail!("{} 为核心工具，不可禁用", mcp::TOOL_ZHI);
+        }
+        config.mcp_config.tools.insert(tool_id.to_string(), enabled);
+        return Ok(());
+    }
+
+    let proxy = &mut config.proxy_config;
+    match key {
+        "proxy.auto_detect" => proxy.auto_detect = parse_bool(key, value)?,
+        "proxy.enabled" => proxy.enabled = parse_bool(key, value)?,
+        "proxy.type" => {
+            let proxy_type = value.trim().to_lowercase();
+            if !matches!(proxy_type.as_str(), "http" | "https" | "socks5") {
+                bail!("配置项 proxy.type 仅支持 http | https | socks5，实际为: {}", value);
+            }
+            proxy.proxy_type = proxy_type;
+        }
+        "proxy.host" => {
+            let host = value.trim();
+            if host.is_empty() {
+                bail!("配置项 proxy.host 不能为空");
+            }
+            proxy.host = host.to_string();
+        }
+        "proxy.port" => {
+            proxy.port = value
+                .trim()
+                .parse::<u16>()
+                .ok()
+                .filter(|p| *p > 0)
+                .ok_or_else(|| anyhow::anyhow!("配置项 proxy.port 需要 1~65535 的端口号，实际为: {}", value))?;
+        }
+        "proxy.only_for_cn" => proxy.only_for_cn = parse_bool(key, value)?,
+        "proxy.test_url" => proxy.test_url = optional_string(value),
+        "proxy.force_direct" => proxy.force_direct = parse_bool(key, value)?,
+        "enhance.base_url" => config.mcp_config.acemcp_base_url = optional_string(value),
+        "enhance.token" => config.mcp_config.acemcp_token = optional_string(value),
+        _ => bail!("未知的配置项: {}。可用配置项: {}", key, available_keys()),
+    }
+    Ok(())
+}
+
+/// 读取独立配置中的配置项
+pub fn config_get(key: &str) -> Result<String> {
+    let config = load_standalone_config()?;
+    get_config_value(&config, key)
+}
+
+/// 修改独立配置中的配置项并保存
+pub fn config_set(key: &str, value: &str) -> Result<()> {
+    let mut config = load_standalone_config()?;
+    set_config_value(&mut config, key, value)?;
+    save_standalone_config(&config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_is_masked_and_unknown_key_rejected() {
+        let mut config = AppConfig::default();
+        set_config_value(&mut config, "enhance.token", "sk-1234567890").unwrap();
+
+        assert_eq!(get_config_value(&config, "enhance.token").unwrap(), "sk-1****");
+        assert!(get_config_value(&config, "proxy.password").is_err());
+        assert!(set_config_value(&mut config, "tools.unknown", "true").is_err());
+        assert!(set_config_value(&mut config, "tools.zhi", "false").is_err());
+        assert!(set_config_value(&mut config, "proxy.port", "0").is_err());
+    }
+}
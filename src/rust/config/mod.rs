@@ -1,3 +1,4 @@
+pub mod keys;
 pub mod settings;
 pub mod storage;
 
@@ -133,7 +133,31 @@ pub struct McpConfig {
     pub acemcp_proxy_username: Option<String>, // 代理用户名（可选）
     pub acemcp_proxy_password: Option<String>, // 代理密码（可选）
     pub context7_api_key: Option<String>, // Context7 API密钥 (可选，免费使用时可为空)
+    pub context7_cache_ttl_minutes: Option<u64>, // Context7 文档缓存过期时间（分钟），默认 1440
+    pub context7_timeout_secs: Option<u64>, // Context7 单次请求超时（秒），默认 15
+    pub enhance_offline_fallback: Option<bool>, // 增强 API 不可达时使用离线兜底（默认关闭）
+    pub enhance_max_prompt_chars: Option<usize>, // 增强请求总长度上限（字符数），默认 100000
+    pub enhance_history_summary_chars: Option<usize>, // 对话历史中 AI 响应摘要的保存长度（字符数），默认 500
+    pub enhance_strict_no_tools: Option<bool>, // 增强系统提示词使用严格的 NO TOOLS 警示（默认开启）
+    pub enhance_history_entries: Option<usize>, // 增强时注入的对话历史条数，默认 5（上限 20）
+    pub enhance_zhi_history_entries: Option<usize>, // 增强时注入的 zhi 交互历史条数，默认 5（上限 20）
+    pub enhance_region_endpoints: Option<HashMap<String, String>>, // 未配置 acemcp_base_url 时按国家代码选择增强 API 地址（如 {"CN": "https://..."}）
+    pub enhance_max_concurrent_streams: Option<usize>, // 同时进行的流式增强请求上限，默认 3（超出时直接返回繁忙错误）
+    pub enhance_history_repair_on_load: Option<bool>, // 对话历史文件损坏时隔离为 .corrupt.<时间戳> 并重建（默认关闭）
+    pub enhance_post_process: Option<Vec<String>>, // 增强结果后处理步骤（按顺序执行）：trim | strip_markdown_headers | prepend_template
+    pub enhance_prepend_template: Option<String>, // prepend_template 使用的模板，包含 {prompt} 时替换为增强结果
+    pub enhance_persona_type: Option<u32>, // 增强请求体 persona_type（0~3），默认 1
+    pub enhance_mode: Option<String>, // 增强请求体 mode：CHAT | AGENT，默认 CHAT
+    pub zhi_max_images: Option<usize>, // zhi 单次回复最多返回的图片数，默认 8（超出部分省略并附说明）
+    pub zhi_strip_image_metadata: Option<bool>, // 保存图片前移除 EXIF/XMP 等元数据（默认开启）
+    pub zhi_image_mode: Option<String>, // 图片返回方式：inline（base64 内联）| file（保存到临时目录返回路径），默认按客户端自动判断
+    pub request_id_format: Option<String>, // 请求 ID 格式：uuid | prefixed（sanshu-<毫秒时间戳>-<进程号>-<序号>），默认 uuid，修改后需重启生效
+    pub memory_audit_enabled: Option<bool>, // 记忆变更审计日志（JSONL，写入 <data_dir>/memory_audit），默认关闭
+    pub data_dir: Option<String>, // 本地数据目录（历史记录、文档缓存），默认 ~/.sanshu；环境变量 SANSHU_DATA_DIR 优先
     pub skill_python_path: Option<String>, // Skill Python 路径（可选，默认走 PATH）
+    pub skill_enabled: Option<HashMap<String, bool>>, // 技能启用状态（按技能名，未列出的技能视为启用）
+    pub skill_timeout_secs: Option<u64>, // 技能单次执行超时（秒），默认 60
+    pub skill_max_output_bytes: Option<usize>, // 技能输出上限（字节），默认 1MB
 
     // UI/UX Pro Max 配置
     /// 默认语言（"zh" | "en"）
@@ -154,6 +178,8 @@ pub struct McpConfig {
     pub icon_default_png_size: Option<u32>,
     /// 缓存过期时间（分钟），默认 30
     pub icon_cache_expiry_minutes: Option<u64>,
+    /// 单次请求超时（秒），默认 15
+    pub icon_request_timeout_secs: Option<u64>,
 }
 
 
@@ -261,6 +287,22 @@ pub struct ProxyConfig {
     /// 仅在中国大陆地区使用代理
     #[serde(default = "default_proxy_only_for_cn")]
     pub only_for_cn: bool,
+
+    /// 代理可用性探测地址（可选，默认 gstatic generate_204；环境变量 SANSHU_PROXY_TEST_URL 优先）
+    #[serde(default)]
+    pub test_url: Option<String>,
+
+    /// 自动检测时扫描的代理主机（可选，默认 127.0.0.1 与 ::1）
+    #[serde(default)]
+    pub scan_hosts: Option<Vec<String>>,
+
+    /// 自动检测时优先尝试的自定义代理，格式 `[http|socks5://]host:port`（IPv6 写作 `[::1]:7890`）
+    #[serde(default)]
+    pub custom_proxies: Vec<String>,
+
+    /// 强制直连：忽略自动检测与手动代理（环境变量 SANSHU_NO_PROXY_ALL=1 同效）
+    #[serde(default)]
+    pub force_direct: bool,
 }
 
 #[derive(Debug)]
@@ -335,7 +377,31 @@ pub fn default_mcp_config() -> McpConfig {
         acemcp_proxy_username: None,
         acemcp_proxy_password: None,
         context7_api_key: None,
+        context7_cache_ttl_minutes: None, // 默认 24 小时
+        context7_timeout_secs: None,      // 默认 15 秒
+        enhance_offline_fallback: None,   // 默认关闭：API 不可达时直接返回失败
+        enhance_max_prompt_chars: None,   // 默认 100000 字符
+        enhance_history_summary_chars: None, // 默认 500 字符
+        enhance_strict_no_tools: None,    // 默认开启：保留 NO TOOLS 警示
+        enhance_history_entries: None,    // 默认 5 条
+        enhance_zhi_history_entries: None, // 默认 5 条
+        enhance_region_endpoints: None,   // 默认不按地区切换
+        enhance_max_concurrent_streams: None, // 默认 3 个
+        enhance_history_repair_on_load: None, // 默认关闭：损坏时直接报错
+        enhance_post_process: None,       // 默认不做后处理
+        enhance_prepend_template: None,   // 默认无模板
+        enhance_persona_type: None,       // 默认 1
+        enhance_mode: None,               // 默认 CHAT
+        zhi_max_images: None,             // 默认 8 张
+        zhi_strip_image_metadata: None,   // 默认开启
+        zhi_image_mode: None,             // 默认自动：Augment 客户端保存文件，其余内联
+        request_id_format: None,          // 默认 uuid
+        memory_audit_enabled: None,       // 默认关闭
+        data_dir: None,                   // 默认 ~/.sanshu
         skill_python_path: None,
+        skill_enabled: None,              // 默认全部启用
+        skill_timeout_secs: None,         // 默认 60 秒
+        skill_max_output_bytes: None,     // 默认 1MB
         // UI/UX Pro Max 默认配置
         uiux_default_lang: Some("zh".to_string()),
         uiux_output_format: Some("json".to_string()),
@@ -346,6 +412,7 @@ pub fn default_mcp_config() -> McpConfig {
         icon_default_format: None,          // 默认 SVG
         icon_default_png_size: None,        // 默认 64px
         icon_cache_expiry_minutes: None,    // 默认 30 分钟
+        icon_request_timeout_secs: None,    // 默认 15 秒
     }
 }
 
@@ -862,6 +929,10 @@ pub fn default_proxy_config() -> ProxyConfig {
         host: default_proxy_host(),
         port: default_proxy_port(),
         only_for_cn: default_proxy_only_for_cn(),
+        test_url: None, // 默认使用 gstatic generate_204
+        scan_hosts: None, // 默认扫描 127.0.0.1 与 ::1
+        custom_proxies: Vec::new(),
+        force_direct: false, // 默认不强制直连
     }
 }
 
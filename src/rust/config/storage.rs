@@ -24,13 +24,8 @@ pub async fn save_config(state: &State<'_, AppState>, app: &AppHandle) -> Result
         .map_err(|e| anyhow::anyhow!("获取配置失败: {}", e))?;
     let config_json = serde_json::to_string_pretty(&*config)?;
 
-    // 写入文件
-    fs::write(&config_path, config_json)?;
-
-    // 强制刷新文件系统缓存
-    if let Ok(file) = std::fs::OpenOptions::new().write(true).open(&config_path) {
-        let _ = file.sync_all();
-    }
+    // 原子写入（临时文件落盘后 rename），避免中途崩溃留下截断的配置文件
+    crate::utils::write_atomic(&config_path, config_json)?;
 
     log::debug!("配置已保存到: {:?}", config_path);
 
@@ -147,12 +142,27 @@ pub fn load_standalone_telegram_config() -> Result<super::settings::TelegramConf
     Ok(config.telegram_config)
 }
 
+/// 独立保存配置（不依赖Tauri，供 CLI 等无 GUI 场景使用）
+pub fn save_standalone_config(config: &AppConfig) -> Result<()> {
+    let config_path = get_standalone_config_path()?;
+    let config_json = serde_json::to_string_pretty(config)?;
+    crate::utils::write_atomic(&config_path, config_json)?;
+    log::debug!("配置已保存到: {:?}", config_path);
+    Ok(())
+}
+
+/// 配置目录覆盖环境变量（优先于系统标准配置目录，便于便携安装与测试隔离）
+pub const CONFIG_DIR_ENV: &str = "SANSHU_CONFIG_DIR";
+
 /// 获取独立配置文件路径（不依赖Tauri）
 fn get_standalone_config_path() -> Result<PathBuf> {
-    // 使用标准的配置目录
-    let config_dir = dirs::config_dir()
-        .ok_or_else(|| anyhow::anyhow!("无法获取配置目录"))?
-        .join("sanshu");
+    let config_dir = match std::env::var(CONFIG_DIR_ENV).ok().filter(|v| !v.trim().is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        // 使用标准的配置目录
+        None => dirs::config_dir()
+            .ok_or_else(|| anyhow::anyhow!("无法获取配置目录"))?
+            .join("sanshu"),
+    };
 
     // 确保目录存在
     fs::create_dir_all(&config_dir)?;
@@ -43,6 +43,33 @@ pub const REQUEST_TIMEOUT_MS: u64 = 30000;
 /// MCP 重试次数
 pub const MAX_RETRY_COUNT: u32 = 3;
 
+/// zhi 弹窗 GUI 进程最长等待时间 (秒)，超时后终止进程
+pub const DEFAULT_POPUP_TIMEOUT_SECS: u64 = 600;
+
+/// 覆盖 zhi 弹窗等待时间的环境变量 (秒)
+pub const POPUP_TIMEOUT_ENV: &str = "SANSHU_POPUP_TIMEOUT_SECS";
+
+/// 图标弹窗 GUI 进程最长等待时间 (秒)，超时后终止进程并视为取消
+pub const DEFAULT_ICON_POPUP_TIMEOUT_SECS: u64 = 600;
+
+/// 覆盖图标弹窗等待时间的环境变量 (秒)
+pub const ICON_POPUP_TIMEOUT_ENV: &str = "SANSHU_ICON_POPUP_TIMEOUT_SECS";
+
+/// zhi 弹窗消息最大字符数，超出部分截断并附加标记
+pub const MAX_ZHI_MESSAGE_CHARS: usize = 20_000;
+
+/// zhi 单次回复最多返回的图片数
+pub const DEFAULT_MAX_RESPONSE_IMAGES: usize = 8;
+
+/// 请求自带 timeout_secs 时，服务端在其基础上额外等待的时间 (秒)，留给 GUI 自行提交默认回复
+pub const POPUP_TIMEOUT_GRACE_SECS: u64 = 5;
+
+/// zhi 请求 timeout_secs 的上限 (秒)，超出时按上限处理（前端定时器最长约 24.8 天）
+pub const MAX_ZHI_TIMEOUT_SECS: u64 = 86_400;
+
+/// list_tools 单页返回的工具数量
+pub const LIST_TOOLS_PAGE_SIZE: usize = 100;
+
 // MCP 工具配置结构体
 #[derive(Debug, Clone)]
 pub struct McpToolConfig {
@@ -3,6 +3,9 @@
 /// 默认请求超时时间 (ms)
 pub const DEFAULT_TIMEOUT_MS: u64 = 30000;
 
+/// 外部工具 API（Context7、图标）单次请求超时 (s)
+pub const DEFAULT_TOOL_REQUEST_TIMEOUT_SECS: u64 = 15;
+
 /// 默认重试次数
 pub const DEFAULT_RETRY_COUNT: u32 = 3;
 
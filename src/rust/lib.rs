@@ -41,6 +41,7 @@ pub mod config;
 pub mod constants;
 pub mod mcp;
 pub mod network;
+pub mod paths;
 pub mod telegram;
 pub mod ui;
 pub mod utils;
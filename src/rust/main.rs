@@ -12,6 +12,7 @@ fn main() -> Result<()> {
     if let Err(e) = auto_init_logger() {
         eprintln!("初始化日志系统失败: {}", e);
     }
+    sanshu::network::log_force_direct_status();
 
     // 处理命令行参数
     handle_cli_args()
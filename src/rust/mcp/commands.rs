@@ -192,7 +192,8 @@ pub async fn reset_mcp_tools_config(
 
 // ============ 记忆管理相关命令 ============
 
-use crate::mcp::tools::memory::{MemoryManager, MemoryConfig};
+use crate::mcp::tools::memory::{MemoryManager, MemoryConfig, MemoryCategory};
+use crate::mcp::utils::normalize_and_validate_project_root;
 
 /// 记忆条目 DTO（用于前端展示）
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -201,6 +202,7 @@ pub struct MemoryEntryDto {
     pub content: String,
     pub category: String,
     pub created_at: String,
+    pub pinned: bool,
 }
 
 /// 记忆配置 DTO（用于前端交互）
@@ -209,6 +211,8 @@ pub struct MemoryConfigDto {
     pub similarity_threshold: f64,
     pub dedup_on_startup: bool,
     pub enable_dedup: bool,
+    #[serde(default)]
+    pub recall_fuzzy_threshold: Option<f64>,
 }
 
 /// 去重结果 DTO
@@ -220,6 +224,13 @@ pub struct DedupResultDto {
     pub removed_ids: Vec<String>,
 }
 
+/// 批量删除结果 DTO
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct BatchDeleteResultDto {
+    pub deleted: Vec<String>,
+    pub not_found: Vec<String>,
+}
+
 /// 记忆统计 DTO
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct MemoryStatsDto {
@@ -240,11 +251,16 @@ pub struct SimilarityPreviewDto {
     pub threshold: f64,
 }
 
+/// 规范化项目路径后创建记忆管理器
+fn open_memory_manager(project_path: &str) -> Result<MemoryManager, String> {
+    let project_path = normalize_and_validate_project_root(project_path).map_err(|e| e.to_string())?;
+    MemoryManager::new(&project_path).map_err(|e| format!("创建记忆管理器失败: {}", e))
+}
+
 /// 获取记忆列表
 #[tauri::command]
 pub async fn get_memory_list(project_path: String) -> Result<Vec<MemoryEntryDto>, String> {
-    let manager = MemoryManager::new(&project_path)
-        .map_err(|e| format!("创建记忆管理器失败: {}", e))?;
+    let manager = open_memory_manager(&project_path)?;
     
     let memories = manager.get_all_memories();
     let entries: Vec<MemoryEntryDto> = memories.iter().map(|m| MemoryEntryDto {
@@ -252,6 +268,7 @@ pub async fn get_memory_list(project_path: String) -> Result<Vec<MemoryEntryDto>
         content: m.content.clone(),
         category: m.category.display_name().to_string(),
         created_at: m.created_at.to_rfc3339(),
+        pinned: m.pinned,
     }).collect();
     
     Ok(entries)
@@ -260,8 +277,7 @@ pub async fn get_memory_list(project_path: String) -> Result<Vec<MemoryEntryDto>
 /// 获取记忆统计
 #[tauri::command]
 pub async fn get_memory_stats(project_path: String) -> Result<MemoryStatsDto, String> {
-    let manager = MemoryManager::new(&project_path)
-        .map_err(|e| format!("创建记忆管理器失败: {}", e))?;
+    let manager = open_memory_manager(&project_path)?;
     
     let stats = manager.get_stats();
     Ok(MemoryStatsDto {
@@ -276,27 +292,30 @@ pub async fn get_memory_stats(project_path: String) -> Result<MemoryStatsDto, St
 /// 获取记忆配置
 #[tauri::command]
 pub async fn get_memory_config(project_path: String) -> Result<MemoryConfigDto, String> {
-    let manager = MemoryManager::new(&project_path)
-        .map_err(|e| format!("创建记忆管理器失败: {}", e))?;
+    let manager = open_memory_manager(&project_path)?;
     
     let config = manager.config();
     Ok(MemoryConfigDto {
         similarity_threshold: config.similarity_threshold,
         dedup_on_startup: config.dedup_on_startup,
         enable_dedup: config.enable_dedup,
+        recall_fuzzy_threshold: Some(config.recall_fuzzy_threshold),
     })
 }
 
 /// 保存记忆配置
 #[tauri::command]
 pub async fn save_memory_config(project_path: String, config: MemoryConfigDto) -> Result<(), String> {
-    let mut manager = MemoryManager::new(&project_path)
-        .map_err(|e| format!("创建记忆管理器失败: {}", e))?;
+    let mut manager = open_memory_manager(&project_path)?;
     
     let new_config = MemoryConfig {
         similarity_threshold: config.similarity_threshold.clamp(0.5, 0.95),
         dedup_on_startup: config.dedup_on_startup,
         enable_dedup: config.enable_dedup,
+        recall_fuzzy_threshold: config
+            .recall_fuzzy_threshold
+            .unwrap_or(manager.config().recall_fuzzy_threshold)
+            .clamp(0.3, 0.95),
     };
     
     manager.update_config(new_config)
@@ -309,8 +328,7 @@ pub async fn save_memory_config(project_path: String, config: MemoryConfigDto) -
 /// 执行去重整理
 #[tauri::command]
 pub async fn deduplicate_memories(project_path: String) -> Result<DedupResultDto, String> {
-    let mut manager = MemoryManager::new(&project_path)
-        .map_err(|e| format!("创建记忆管理器失败: {}", e))?;
+    let mut manager = open_memory_manager(&project_path)?;
     
     let stats = manager.deduplicate_with_stats()
         .map_err(|e| format!("去重失败: {}", e))?;
@@ -328,8 +346,7 @@ pub async fn deduplicate_memories(project_path: String) -> Result<DedupResultDto
 pub async fn preview_similarity(project_path: String, content: String) -> Result<SimilarityPreviewDto, String> {
     use crate::mcp::tools::memory::dedup::MemoryDeduplicator;
     
-    let manager = MemoryManager::new(&project_path)
-        .map_err(|e| format!("创建记忆管理器失败: {}", e))?;
+    let manager = open_memory_manager(&project_path)?;
     
     let threshold = manager.config().similarity_threshold;
     let dedup = MemoryDeduplicator::new(threshold);
@@ -348,8 +365,7 @@ pub async fn preview_similarity(project_path: String, content: String) -> Result
 /// 删除记忆
 #[tauri::command]
 pub async fn delete_memory(project_path: String, memory_id: String) -> Result<String, String> {
-    let mut manager = MemoryManager::new(&project_path)
-        .map_err(|e| format!("创建记忆管理器失败: {}", e))?;
+    let mut manager = open_memory_manager(&project_path)?;
     
     match manager.delete_memory(&memory_id) {
         Ok(Some(content)) => {
@@ -361,3 +377,61 @@ pub async fn delete_memory(project_path: String, memory_id: String) -> Result<St
     }
 }
 
+/// 批量删除记忆（单次加载/保存）
+#[tauri::command]
+pub async fn delete_memories(project_path: String, ids: Vec<String>) -> Result<BatchDeleteResultDto, String> {
+    let mut manager = open_memory_manager(&project_path)?;
+
+    match manager.delete_memories(&ids) {
+        Ok((deleted, not_found)) => {
+            log::info!("已批量删除记忆: {} 条，未找到 {} 条", deleted.len(), not_found.len());
+            Ok(BatchDeleteResultDto { deleted, not_found })
+        }
+        Err(e) => Err(format!("批量删除记忆失败: {}", e)),
+    }
+}
+
+/// 设置记忆置顶状态
+#[tauri::command]
+pub async fn set_memory_pinned(project_path: String, memory_id: String, pinned: bool) -> Result<(), String> {
+    let mut manager = open_memory_manager(&project_path)?;
+
+    match manager.set_pinned(&memory_id, pinned) {
+        Ok(true) => {
+            log::info!("记忆置顶状态已更新: {} -> {}", memory_id, pinned);
+            Ok(())
+        }
+        Ok(false) => Err(format!("未找到指定 ID 的记忆: {}", memory_id)),
+        Err(e) => Err(format!("更新置顶状态失败: {}", e)),
+    }
+}
+
+/// 修改记忆分类
+#[tauri::command]
+pub async fn update_memory_category(project_path: String, memory_id: String, category: String) -> Result<(), String> {
+    let category = MemoryCategory::parse(&category).ok_or_else(|| {
+        format!("未知的记忆分类: {}。支持的分类: rule | preference | pattern | context", category)
+    })?;
+    let mut manager = open_memory_manager(&project_path)?;
+
+    match manager.update_category(&memory_id, category) {
+        Ok(true) => {
+            log::info!("记忆分类已更新: {} -> {:?}", memory_id, category);
+            Ok(())
+        }
+        Ok(false) => Err(format!("未找到指定 ID 的记忆: {}", memory_id)),
+        Err(e) => Err(format!("更新记忆分类失败: {}", e)),
+    }
+}
+
+/// 列出所有项目的对话历史与 zhi 历史（跨项目浏览，孤立文件会被标记）
+#[tauri::command]
+pub async fn list_history_projects() -> Result<Vec<crate::mcp::utils::HistoryProjectSummary>, String> {
+    Ok(crate::mcp::utils::history_browser::list_history_projects())
+}
+
+/// 清空所有项目的对话历史与 zhi 历史（需传入确认口令 "CONFIRM"）
+#[tauri::command]
+pub async fn clear_all_history(confirm_token: String) -> Result<crate::mcp::utils::ClearAllHistoryResult, String> {
+    crate::mcp::utils::history_browser::clear_all_history(&confirm_token).map_err(|e| e.to_string())
+}
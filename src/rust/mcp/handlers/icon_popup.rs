@@ -2,18 +2,37 @@
 // 负责调用 GUI 进程打开图标选择界面
 
 use anyhow::Result;
-use std::process::Command;
-use std::time::Instant;
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
 
+use crate::constants::mcp::{DEFAULT_ICON_POPUP_TIMEOUT_SECS, ICON_POPUP_TIMEOUT_ENV};
 use crate::mcp::types::{IconSaveResponse, TuRequest};
 use crate::mcp::utils::safe_truncate_clean;
 use crate::{log_important, log_debug};
 
+use super::popup::{output_with_timeout, timeout_from_env, PopupSpawnError, PopupTimeoutError};
+use super::ui_command::resolve_ui_command;
+
+/// 读取图标弹窗最长等待时间（环境变量优先，非法值回退默认值）
+pub fn icon_popup_timeout() -> Duration {
+    timeout_from_env(ICON_POPUP_TIMEOUT_ENV, DEFAULT_ICON_POPUP_TIMEOUT_SECS)
+}
+
 /// 创建图标选择弹窗
 /// 
 /// 调用 "等一下" GUI 进程，进入图标搜索模式
 /// 用户可以搜索、预览、选择并保存图标
 pub fn create_icon_popup(request: &TuRequest) -> Result<IconSaveResponse> {
+    run_icon_popup(request, resolve_ui_command, icon_popup_timeout())
+}
+
+/// 以指定的 UI 命令解析方式与等待时间运行图标弹窗
+///
+/// 进程启动失败时重新解析 UI 命令并重试一次；超时则终止进程并按取消处理
+pub fn run_icon_popup<F>(request: &TuRequest, resolve: F, timeout: Duration) -> Result<IconSaveResponse>
+where
+    F: Fn() -> Result<String>,
+{
     let start = Instant::now();
 
     log_important!(
@@ -25,34 +44,15 @@ pub fn create_icon_popup(request: &TuRequest) -> Result<IconSaveResponse> {
         request.project_root.as_deref().map(|s| safe_truncate_clean(s, 120))
     );
 
-    // 构建命令行参数
-    let mut cmd = Command::new(find_ui_command()?);
-    cmd.arg("--icon-search");
-    
-    // 添加可选参数
-    if let Some(query) = &request.query {
-        if !query.is_empty() {
-            cmd.arg("--query").arg(query);
-        }
-    }
-    if let Some(style) = &request.style {
-        if !style.is_empty() {
-            cmd.arg("--style").arg(style);
-        }
-    }
-    if let Some(path) = &request.save_path {
-        if !path.is_empty() {
-            cmd.arg("--save-path").arg(path);
+    let output = match run_icon_command(request, &resolve, timeout) {
+        Ok(output) => output,
+        Err(e) if e.downcast_ref::<PopupTimeoutError>().is_some() => {
+            log_important!(warn, "[icon_popup] {}，按取消处理", e);
+            return Ok(cancelled_response());
         }
-    }
-    if let Some(root) = &request.project_root {
-        if !root.is_empty() {
-            cmd.arg("--project-root").arg(root);
-        }
-    }
+        Err(e) => return Err(e),
+    };
     
-    // 执行命令并等待结果
-    let output = cmd.output()?;
     let elapsed_ms = start.elapsed().as_millis();
     let exit_code = output.status.code();
     let stdout_len = output.stdout.len();
@@ -72,12 +72,7 @@ pub fn create_icon_popup(request: &TuRequest) -> Result<IconSaveResponse> {
         
         if response_str.is_empty() {
             // 用户取消了操作
-            return Ok(IconSaveResponse {
-                saved_count: 0,
-                save_path: String::new(),
-                saved_names: vec![],
-                cancelled: true,
-            });
+            return Ok(cancelled_response());
         }
         
         // 解析 JSON 响应
@@ -109,59 +104,140 @@ pub fn create_icon_popup(request: &TuRequest) -> Result<IconSaveResponse> {
     }
 }
 
-/// 查找 UI 命令路径
-/// 
-/// 复用 popup.rs 中的逻辑
-fn find_ui_command() -> Result<String> {
-    // 1. 优先尝试与当前 MCP 服务器同目录的等一下命令
-    if let Ok(current_exe) = std::env::current_exe() {
-        if let Some(exe_dir) = current_exe.parent() {
-            let local_ui_path = exe_dir.join("等一下");
-            if local_ui_path.exists() && is_executable(&local_ui_path) {
-                return Ok(local_ui_path.to_string_lossy().to_string());
-            }
+/// 启动 GUI 进程并等待结束；仅在进程无法启动时（如 UI 命令刚被移动）重新解析并重试一次，
+/// 已启动后的等待 / 读取错误直接返回，避免重复弹窗
+fn run_icon_command<F>(request: &TuRequest, resolve: &F, timeout: Duration) -> Result<Output>
+where
+    F: Fn() -> Result<String>,
+{
+    let program = resolve()?;
+    match output_with_timeout(build_icon_command(&program, request), timeout, None) {
+        Err(e) if e.downcast_ref::<PopupSpawnError>().is_some() => {
+            log_important!(warn, "[icon_popup] GUI进程启动失败，重新定位UI命令后重试: program={}, error={}", program, e);
+            let program = resolve()?;
+            output_with_timeout(build_icon_command(&program, request), timeout, None)
         }
+        result => result,
     }
+}
 
-    // 2. 尝试全局命令
-    if test_command_available("等一下") {
-        return Ok("等一下".to_string());
-    }
+/// 构建图标搜索模式的命令行
+fn build_icon_command(program: &str, request: &TuRequest) -> Command {
+    let mut cmd = Command::new(program);
+    cmd.arg("--icon-search");
 
-    // 3. 返回错误
-    anyhow::bail!(
-        "找不到等一下 UI 命令。请确保：\n\
-         1. 已编译项目：cargo build --release\n\
-         2. 或已全局安装：./install.sh\n\
-         3. 或等一下命令在同目录下"
-    )
+    // 添加可选参数
+    if let Some(query) = &request.query {
+        if !query.is_empty() {
+            cmd.arg("--query").arg(query);
+        }
+    }
+    if let Some(style) = &request.style {
+        if !style.is_empty() {
+            cmd.arg("--style").arg(style);
+        }
+    }
+    if let Some(path) = &request.save_path {
+        if !path.is_empty() {
+            cmd.arg("--save-path").arg(path);
+        }
+    }
+    if let Some(root) = &request.project_root {
+        if !root.is_empty() {
+            cmd.arg("--project-root").arg(root);
+        }
+    }
+    cmd
 }
 
-/// 测试命令是否可用
-fn test_command_available(command: &str) -> bool {
-    Command::new(command)
-        .arg("--version")
-        .output()
-        .map(|output| output.status.success())
-        .unwrap_or(false)
+/// 用户取消（或等待超时）时的响应
+fn cancelled_response() -> IconSaveResponse {
+    IconSaveResponse {
+        saved_count: 0,
+        save_path: String::new(),
+        saved_names: vec![],
+        cancelled: true,
+    }
 }
 
-/// 检查文件是否可执行
-fn is_executable(path: &std::path::Path) -> bool {
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn sample_request() -> TuRequest {
+        TuRequest {
+            query: Some("home".to_string()),
+            style: None,
+            save_path: None,
+            project_root: None,
+        }
+    }
+
     #[cfg(unix)]
-    {
+    fn fake_ui_command(dir: &Path, name: &str, body: &str) -> String {
         use std::os::unix::fs::PermissionsExt;
-        path.metadata()
-            .map(|metadata| metadata.permissions().mode() & 0o111 != 0)
-            .unwrap_or(false)
+
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_long_running_icon_command_is_killed_and_cancelled() {
+        let temp_dir = TempDir::new().unwrap();
+        let program = fake_ui_command(temp_dir.path(), "slow-ui", "sleep 30");
+
+        let start = Instant::now();
+        let response = run_icon_popup(&sample_request(), || Ok(program.clone()), Duration::from_millis(300)).unwrap();
+
+        assert!(response.cancelled);
+        assert_eq!(response.saved_count, 0);
+        assert!(start.elapsed() < Duration::from_secs(5));
     }
 
-    #[cfg(windows)]
-    {
-        // Windows 上检查文件扩展名
-        path.extension()
-            .and_then(|ext| ext.to_str())
-            .map(|ext| ext.eq_ignore_ascii_case("exe"))
-            .unwrap_or(false)
+    #[cfg(unix)]
+    #[test]
+    fn test_spawn_failure_retries_with_re_resolved_command() {
+        let temp_dir = TempDir::new().unwrap();
+        let program = fake_ui_command(
+            temp_dir.path(),
+            "icon-ui",
+            r#"echo '{"saved_count":1,"save_path":"/tmp/icons","saved_names":["home"],"cancelled":false}'"#,
+        );
+        let missing = temp_dir.path().join("missing-ui").to_string_lossy().to_string();
+
+        let calls = Cell::new(0);
+        let resolve = || {
+            calls.set(calls.get() + 1);
+            Ok(if calls.get() == 1 { missing.clone() } else { program.clone() })
+        };
+        let response = run_icon_popup(&sample_request(), resolve, Duration::from_secs(10)).unwrap();
+
+        assert_eq!(calls.get(), 2);
+        assert!(!response.cancelled);
+        assert_eq!(response.saved_names, vec!["home"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failed_icon_command_is_not_retried() {
+        let temp_dir = TempDir::new().unwrap();
+        let program = fake_ui_command(temp_dir.path(), "failing-ui", "echo boom >&2; exit 3");
+
+        let calls = Cell::new(0);
+        let resolve = || {
+            calls.set(calls.get() + 1);
+            Ok(program.clone())
+        };
+        let err = run_icon_popup(&sample_request(), resolve, Duration::from_secs(10)).unwrap_err();
+
+        assert_eq!(calls.get(), 1);
+        assert!(err.to_string().contains("boom"), "{}", err);
     }
 }
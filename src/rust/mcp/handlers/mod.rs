@@ -1,7 +1,9 @@
 pub mod popup;
 pub mod response;
 pub mod icon_popup;
+pub mod ui_command;
 
 pub use popup::*;
 pub use response::*;
 pub use icon_popup::*;
+pub use ui_command::*;
@@ -1,30 +1,156 @@
 use anyhow::Result;
-use std::process::Command;
+use base64::{Engine as _, engine::general_purpose};
+use std::io::{Read, Write};
+use std::process::{Command, Output, Stdio};
 use std::fs;
-use std::path::Path;
-use std::time::Instant;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
-use crate::mcp::types::PopupRequest;
+use crate::constants::mcp::{DEFAULT_POPUP_TIMEOUT_SECS, POPUP_TIMEOUT_ENV, POPUP_TIMEOUT_GRACE_SECS};
+use crate::mcp::shutdown::ShutdownCoordinator;
+use crate::mcp::types::{build_send_response, PopupRequest};
 use crate::mcp::utils::safe_truncate_clean;
 use crate::{log_important, log_debug};
 
+use super::ui_command::resolve_ui_command;
+
+/// 弹窗请求传递方式环境变量：file | stdin | arg（默认 file）
+pub const POPUP_TRANSPORT_ENV: &str = "SANSHU_POPUP_TRANSPORT";
+
+/// `--mcp-request` 参数取该值时表示从 stdin 读取请求
+pub const MCP_REQUEST_STDIN_ARG: &str = "-";
+
+/// `--mcp-request` 参数以该前缀开头时表示内联 base64 请求
+pub const MCP_REQUEST_BASE64_PREFIX: &str = "base64:";
+
+/// arg 方式允许的最大参数长度（超出时改用 stdin，避免超过系统命令行长度限制）
+const MAX_ARG_TRANSPORT_LEN: usize = 16 * 1024;
+
+/// GUI 进程等待超时错误（便于上层区分超时与其他失败）
+#[derive(Debug)]
+pub struct PopupTimeoutError {
+    pub timeout: Duration,
+}
+
+impl std::fmt::Display for PopupTimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "UI进程等待超时（{}秒），已终止进程", self.timeout.as_secs_f32())
+    }
+}
+
+impl std::error::Error for PopupTimeoutError {}
+
+/// GUI 进程无法启动错误（命令不存在、无执行权限等，便于上层决定是否重试）
+#[derive(Debug)]
+pub struct PopupSpawnError {
+    pub source: std::io::Error,
+}
+
+impl std::fmt::Display for PopupSpawnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "UI进程启动失败: {}", self.source)
+    }
+}
+
+impl std::error::Error for PopupSpawnError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// 弹窗请求传递方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopupTransport {
+    /// 写入临时文件并传递文件路径
+    File,
+    /// 通过子进程 stdin 传递
+    Stdin,
+    /// 通过 base64 命令行参数传递
+    Arg,
+}
+
+impl PopupTransport {
+    /// 从环境变量读取传递方式（未设置或非法值时使用 file）
+    pub fn from_env() -> Self {
+        match std::env::var(POPUP_TRANSPORT_ENV)
+            .map(|v| v.trim().to_lowercase())
+            .as_deref()
+        {
+            Ok("stdin") => Self::Stdin,
+            Ok("arg") => Self::Arg,
+            _ => Self::File,
+        }
+    }
+}
+
+/// 读取 `--mcp-request` 参数指向的请求内容
+///
+/// 支持 `-`（stdin）、`base64:<data>`（内联）与文件路径三种形式
+pub fn read_request_source<R: Read>(source: &str, mut stdin: R) -> Result<String> {
+    if source == MCP_REQUEST_STDIN_ARG {
+        let mut content = String::new();
+        stdin.read_to_string(&mut content)?;
+        return Ok(content);
+    }
+    if let Some(encoded) = source.strip_prefix(MCP_REQUEST_BASE64_PREFIX) {
+        let bytes = general_purpose::STANDARD.decode(encoded.trim())?;
+        return Ok(String::from_utf8(bytes)?);
+    }
+    Ok(fs::read_to_string(source)?)
+}
+
 /// 创建 Tauri 弹窗
 ///
 /// 优先调用与 MCP 服务器同目录的 UI 命令，找不到时使用全局版本
 pub fn create_tauri_popup(request: &PopupRequest) -> Result<String> {
     let start = Instant::now();
-
-    // 创建临时请求文件 - 跨平台适配
-    let temp_dir = std::env::temp_dir();
-    let temp_file = temp_dir.join(format!("mcp_request_{}.json", request.id));
     let request_json = serde_json::to_string_pretty(request)?;
-    fs::write(&temp_file, request_json)?;
+
+    // 根据传递方式准备 --mcp-request 参数与 stdin 内容
+    let requested_transport = PopupTransport::from_env();
+    let mut transport = requested_transport;
+    let mut temp_file = None;
+    let mut stdin_data = None;
+    let request_arg = match requested_transport {
+        PopupTransport::File => {
+            // 创建临时请求文件 - 跨平台适配
+            let path = std::env::temp_dir().join(format!("mcp_request_{}.json", request.id));
+            fs::write(&path, &request_json)?;
+            // 登记临时文件，服务器被信号中断时统一清理
+            ShutdownCoordinator::global().register_temp_file(&path);
+            let arg = path.to_string_lossy().to_string();
+            temp_file = Some(path);
+            arg
+        }
+        PopupTransport::Arg => {
+            let encoded = general_purpose::STANDARD.encode(request_json.as_bytes());
+            if encoded.len() <= MAX_ARG_TRANSPORT_LEN {
+                format!("{}{}", MCP_REQUEST_BASE64_PREFIX, encoded)
+            } else {
+                log_debug!("[popup] 请求过大，arg 方式回退为 stdin: request_id={}, encoded_len={}", request.id, encoded.len());
+                transport = PopupTransport::Stdin;
+                stdin_data = Some(request_json.clone().into_bytes());
+                MCP_REQUEST_STDIN_ARG.to_string()
+            }
+        }
+        PopupTransport::Stdin => {
+            stdin_data = Some(request_json.clone().into_bytes());
+            MCP_REQUEST_STDIN_ARG.to_string()
+        }
+    };
+    let cleanup = |temp_file: &Option<std::path::PathBuf>| {
+        if let Some(path) = temp_file {
+            let _ = fs::remove_file(path);
+            ShutdownCoordinator::global().unregister_temp_file(path);
+        }
+    };
 
     log_important!(
         info,
-        "[popup] 已写入MCP请求文件: request_id={}, file={}, message_len={}, message_preview={}, options_len={}, project={:?}, markdown={}",
+        "[popup] 已准备MCP请求: request_id={}, transport={:?}, file={:?}, message_len={}, message_preview={}, options_len={}, project={:?}, markdown={}",
         request.id,
-        temp_file.display(),
+        transport,
+        temp_file.as_ref().map(|p| p.display().to_string()),
         request.message.len(),
         safe_truncate_clean(&request.message, 200),
         request.predefined_options.as_ref().map(|v| v.len()).unwrap_or(0),
@@ -33,22 +159,56 @@ pub fn create_tauri_popup(request: &PopupRequest) -> Result<String> {
     );
 
     // 尝试找到等一下命令的路径
-    let command_path = find_ui_command()?;
+    let command_path = match resolve_ui_command() {
+        Ok(path) => path,
+        Err(e) => {
+            cleanup(&temp_file);
+            return Err(e);
+        }
+    };
+    // 请求自带超时时以其为准（额外留出宽限期让 GUI 自行提交默认回复）
+    let timeout = match request.timeout_secs {
+        Some(secs) => Duration::from_secs(secs.saturating_add(POPUP_TIMEOUT_GRACE_SECS)),
+        None => popup_timeout(),
+    };
 
     log_debug!(
-        "[popup] 准备调用GUI进程: request_id={}, command_path={}",
+        "[popup] 准备调用GUI进程: request_id={}, command_path={}, timeout_secs={}",
         request.id,
-        command_path
+        command_path,
+        timeout.as_secs()
     );
 
-    // 调用等一下命令
-    let output = Command::new(&command_path)
-        .arg("--mcp-request")
-        .arg(temp_file.to_string_lossy().to_string())
-        .output()?;
+    // 调用等一下命令（超时后终止进程，避免 MCP 调用被永久阻塞）
+    let mut cmd = Command::new(&command_path);
+    cmd.arg("--mcp-request").arg(&request_arg);
+    let output = output_with_timeout(cmd, timeout, stdin_data);
+
+    // 清理临时文件（超时或启动失败时同样清理）
+    cleanup(&temp_file);
 
-    // 清理临时文件
-    let _ = fs::remove_file(&temp_file);
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            if let Some(response) = timeout_fallback_response(request, &e) {
+                log_important!(
+                    info,
+                    "[popup] 用户未在限定时间内回复，使用超时默认回复: request_id={}, has_default={}",
+                    request.id,
+                    request.default_on_timeout.is_some()
+                );
+                return Ok(response);
+            }
+            log_important!(
+                error,
+                "[popup] GUI进程未正常结束: request_id={}, error={}, elapsed_ms={}",
+                request.id,
+                e,
+                start.elapsed().as_millis()
+            );
+            return Err(e);
+        }
+    };
 
     let elapsed_ms = start.elapsed().as_millis();
     let exit_code = output.status.code();
@@ -89,59 +249,233 @@ pub fn create_tauri_popup(request: &PopupRequest) -> Result<String> {
     }
 }
 
-/// 查找等一下 UI 命令的路径
+/// 请求设置了 timeout_secs 且 GUI 进程超时时，返回兜底回复
 ///
-/// 按优先级查找：同目录 -> 全局版本 -> 开发环境
-fn find_ui_command() -> Result<String> {
-    // 1. 优先尝试与当前 MCP 服务器同目录的等一下命令
-    if let Ok(current_exe) = std::env::current_exe() {
-        if let Some(exe_dir) = current_exe.parent() {
-            let local_ui_path = exe_dir.join("等一下");
-            if local_ui_path.exists() && is_executable(&local_ui_path) {
-                return Ok(local_ui_path.to_string_lossy().to_string());
-            }
-        }
-    }
+/// 提供 default_on_timeout 时按用户提交该默认回复处理，否则视为取消；其他错误返回 None
+pub fn timeout_fallback_response(request: &PopupRequest, err: &anyhow::Error) -> Option<String> {
+    request.timeout_secs?;
+    err.downcast_ref::<PopupTimeoutError>()?;
+    Some(match request.default_on_timeout.as_deref() {
+        Some(default) => build_send_response(
+            Some(default.to_string()),
+            vec![],
+            vec![],
+            Some(request.id.clone()),
+            "popup_timeout",
+        ),
+        None => "用户取消了操作".to_string(),
+    })
+}
 
-    // 2. 尝试全局命令（最常见的部署方式）
-    if test_command_available("等一下") {
-        return Ok("等一下".to_string());
+/// 读取 zhi 弹窗最长等待时间（环境变量优先，非法值回退默认值）
+pub fn popup_timeout() -> Duration {
+    timeout_from_env(POPUP_TIMEOUT_ENV, DEFAULT_POPUP_TIMEOUT_SECS)
+}
+
+/// 从环境变量读取等待秒数，未设置、非数字或为 0 时回退 `default_secs`
+pub fn timeout_from_env(name: &str, default_secs: u64) -> Duration {
+    let secs = std::env::var(name)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(default_secs);
+    Duration::from_secs(secs)
+}
+
+/// 执行命令并等待结束，超过 `timeout` 时终止子进程并返回错误
+///
+/// 与 `Command::output()` 一致捕获 stdout/stderr；提供 `stdin_data` 时写入子进程 stdin，否则置空
+pub fn output_with_timeout(mut cmd: Command, timeout: Duration, stdin_data: Option<Vec<u8>>) -> Result<Output> {
+    let mut child = cmd
+        .stdin(if stdin_data.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|source| PopupSpawnError { source })?;
+
+    // 中文注释：写入完成后关闭 stdin，子进程才能读到 EOF
+    if let (Some(data), Some(mut stdin)) = (stdin_data, child.stdin.take()) {
+        std::thread::spawn(move || {
+            let _ = stdin.write_all(&data);
+        });
     }
 
-    // 3. 如果都找不到，返回详细错误信息
-    anyhow::bail!(
-        "找不到等一下 UI 命令。请确保：\n\
-         1. 已编译项目：cargo build --release\n\
-         2. 或已全局安装：./install.sh\n\
-         3. 或等一下命令在同目录下"
-    )
+    // 中文注释：在独立线程中读取输出，避免管道写满导致子进程阻塞
+    let stdout_reader = spawn_pipe_reader(child.stdout.take());
+    let stderr_reader = spawn_pipe_reader(child.stderr.take());
+
+    // 中文注释：超大超时无法表示为 Instant 时视为不限时
+    let deadline = Instant::now().checked_add(timeout);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(PopupTimeoutError { timeout }.into());
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    };
+
+    let stdout = stdout_reader.join().unwrap_or_default();
+    let stderr = stderr_reader.join().unwrap_or_default();
+    Ok(Output { status, stdout, stderr })
 }
 
-/// 测试命令是否可用
-fn test_command_available(command: &str) -> bool {
-    Command::new(command)
-        .arg("--version")
-        .output()
-        .map(|output| output.status.success())
-        .unwrap_or(false)
+/// 在后台线程中读取管道全部内容
+fn spawn_pipe_reader<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
 }
 
-/// 检查文件是否可执行
-fn is_executable(path: &Path) -> bool {
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_with_timeout_kills_long_running_command() {
+        let mut cmd = Command::new("sleep");
+        cmd.arg("30");
+
+        let start = Instant::now();
+        let result = output_with_timeout(cmd, Duration::from_millis(300), None);
+
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("超时"));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_with_timeout_captures_stdout() {
+        let mut cmd = Command::new("echo");
+        cmd.arg("done");
+
+        let output = output_with_timeout(cmd, Duration::from_secs(10), None).unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "done");
+    }
+
     #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        path.metadata()
-            .map(|metadata| metadata.permissions().mode() & 0o111 != 0)
-            .unwrap_or(false)
-    }
-
-    #[cfg(windows)]
-    {
-        // Windows 上检查文件扩展名
-        path.extension()
-            .and_then(|ext| ext.to_str())
-            .map(|ext| ext.eq_ignore_ascii_case("exe"))
-            .unwrap_or(false)
+    #[test]
+    fn test_output_with_timeout_accepts_huge_timeout() {
+        let mut cmd = Command::new("echo");
+        cmd.arg("done");
+
+        // 超出 Instant 表示范围的超时不应 panic
+        let timeout = Duration::from_secs(u64::MAX.saturating_add(POPUP_TIMEOUT_GRACE_SECS));
+        let output = output_with_timeout(cmd, timeout, None).unwrap();
+        assert!(output.status.success());
+    }
+
+    #[test]
+    fn test_missing_command_reports_spawn_error() {
+        let cmd = Command::new("/nonexistent/sanshu-ui");
+        let err = output_with_timeout(cmd, Duration::from_secs(1), None).unwrap_err();
+        let spawn = err.downcast_ref::<PopupSpawnError>().expect("应为启动失败错误");
+        assert_eq!(spawn.source.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    fn sample_request() -> PopupRequest {
+        PopupRequest {
+            id: "req-317".to_string(),
+            message: "## 确认\n是否继续？".to_string(),
+            predefined_options: Some(vec!["继续".to_string(), "取消".to_string()]),
+            is_markdown: true,
+            project_root_path: Some("/tmp/project".to_string()),
+            uiux_intent: None,
+            uiux_context_policy: Some("auto".to_string()),
+            uiux_reason: None,
+            default_input: None,
+            timeout_secs: None,
+            default_on_timeout: None,
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timed_out_popup_resolves_to_default_response() {
+        let mut cmd = Command::new("sleep");
+        cmd.arg("30");
+        let err = output_with_timeout(cmd, Duration::from_millis(200), None).unwrap_err();
+
+        let request = PopupRequest {
+            timeout_secs: Some(1),
+            default_on_timeout: Some("按默认方案继续".to_string()),
+            ..sample_request()
+        };
+        let response = timeout_fallback_response(&request, &err).expect("超时应返回默认回复");
+        let parsed: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(parsed["user_input"], "按默认方案继续");
+        assert_eq!(parsed["metadata"]["request_id"], "req-317");
+        assert_eq!(parsed["metadata"]["source"], "popup_timeout");
+
+        // 未提供默认回复时视为取消；未设置 timeout_secs 时保持原有报错
+        let cancel = PopupRequest { timeout_secs: Some(1), ..sample_request() };
+        assert_eq!(timeout_fallback_response(&cancel, &err).as_deref(), Some("用户取消了操作"));
+        assert!(timeout_fallback_response(&sample_request(), &err).is_none());
+        assert!(timeout_fallback_response(&request, &anyhow::anyhow!("UI进程失败")).is_none());
+    }
+
+    #[test]
+    fn test_default_input_serialized_only_when_provided() {
+        let without = serde_json::to_value(sample_request()).unwrap();
+        assert!(without.get("default_input").is_none());
+
+        let request = PopupRequest {
+            default_input: Some("建议：先拆分模块".to_string()),
+            ..sample_request()
+        };
+        let with = serde_json::to_value(&request).unwrap();
+        assert_eq!(with["default_input"], "建议：先拆分模块");
+
+        // 旧版请求 JSON 缺少该字段时仍可解析
+        let parsed: PopupRequest = serde_json::from_value(without).unwrap();
+        assert!(parsed.default_input.is_none());
+    }
+
+    #[test]
+    fn test_stdin_transport_roundtrip() {
+        let request = sample_request();
+        let json = serde_json::to_string_pretty(&request).unwrap();
+
+        let content = read_request_source(MCP_REQUEST_STDIN_ARG, std::io::Cursor::new(json.into_bytes())).unwrap();
+        let parsed: PopupRequest = serde_json::from_str(&content).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap(),
+            serde_json::to_value(&request).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_base64_arg_transport_roundtrip() {
+        let request = sample_request();
+        let json = serde_json::to_string(&request).unwrap();
+        let arg = format!("{}{}", MCP_REQUEST_BASE64_PREFIX, general_purpose::STANDARD.encode(json.as_bytes()));
+
+        let content = read_request_source(&arg, std::io::empty()).unwrap();
+        let parsed: PopupRequest = serde_json::from_str(&content).unwrap();
+
+        assert_eq!(parsed.id, request.id);
+        assert_eq!(parsed.message, request.message);
+        assert_eq!(parsed.predefined_options, request.predefined_options);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_with_timeout_writes_stdin() {
+        let cmd = Command::new("cat");
+        let output = output_with_timeout(cmd, Duration::from_secs(10), Some(b"hello".to_vec())).unwrap();
+
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "hello");
     }
 }
@@ -5,6 +5,7 @@ use std::path::PathBuf;
 use base64::{Engine as _, engine::general_purpose};
 use serde_json::json;
 
+use crate::constants::mcp::DEFAULT_MAX_RESPONSE_IMAGES;
 use crate::mcp::types::{McpResponse, McpResponseContent};
 use crate::log_debug;
 
@@ -12,6 +13,61 @@ use crate::log_debug;
 ///
 /// 支持新的结构化格式和旧格式的兼容性，并生成适当的 Content 对象
 pub fn parse_mcp_response(response: &str) -> Result<Vec<Content>, McpError> {
+    parse_mcp_response_with_limit(response, max_response_images())
+}
+
+/// 读取单次回复的图片上限（配置优先，非法值回退默认值）
+fn max_response_images() -> usize {
+    crate::config::load_standalone_config()
+        .ok()
+        .and_then(|c| c.mcp_config.zhi_max_images)
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_MAX_RESPONSE_IMAGES)
+}
+
+/// 保存图片前是否移除元数据（默认开启）
+fn strip_metadata_enabled() -> bool {
+    crate::config::load_standalone_config()
+        .ok()
+        .and_then(|c| c.mcp_config.zhi_strip_image_metadata)
+        .unwrap_or(true)
+}
+
+/// 强制指定图片返回方式的环境变量：`inline`（base64 内联）或 `file`（保存到临时目录返回路径）
+pub const IMAGE_MODE_ENV: &str = "MCP_IMAGE_MODE";
+
+/// 解析图片返回方式，无法识别的值返回 None（按自动判断处理）
+fn parse_image_mode(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "file" => Some(true),
+        "inline" => Some(false),
+        _ => None,
+    }
+}
+
+/// 图片是否以临时文件路径返回
+///
+/// 优先级：环境变量 `MCP_IMAGE_MODE` > 配置 `zhi_image_mode` > 客户端自动判断（Augment 使用文件）
+fn use_file_image_mode() -> bool {
+    std::env::var(IMAGE_MODE_ENV)
+        .ok()
+        .and_then(|v| parse_image_mode(&v))
+        .or_else(|| {
+            crate::config::load_standalone_config()
+                .ok()
+                .and_then(|c| c.mcp_config.zhi_image_mode)
+                .and_then(|v| parse_image_mode(&v))
+        })
+        .unwrap_or_else(is_augment_client)
+}
+
+/// 超出图片上限时附加到文本中的说明
+fn images_omitted_note(omitted: usize, max_images: usize) -> String {
+    format!("⚠️ 另有 {} 张图片已省略（单次回复最多 {} 张）", omitted, max_images)
+}
+
+/// 按指定图片上限解析 MCP 响应，超出上限的图片被丢弃并附加说明
+pub fn parse_mcp_response_with_limit(response: &str, max_images: usize) -> Result<Vec<Content>, McpError> {
     if response.trim() == "CANCELLED" || response.trim() == "用户取消了操作" {
         log_debug!("[parse_mcp_response] 收到取消信号");
         return Ok(vec![Content::text("用户取消了操作".to_string())]);
@@ -26,7 +82,7 @@ pub fn parse_mcp_response(response: &str) -> Result<Vec<Content>, McpError> {
             structured_response.metadata.request_id.as_deref(),
             structured_response.metadata.source.as_deref()
         );
-        return parse_structured_response(structured_response);
+        return parse_structured_response(structured_response, max_images);
     }
 
     // 回退到旧格式兼容性解析
@@ -36,13 +92,14 @@ pub fn parse_mcp_response(response: &str) -> Result<Vec<Content>, McpError> {
             let mut result = Vec::new();
             let mut image_count = 0;
 
-            // 检查是否为 Augment 客户端
-            let is_augment = is_augment_client();
+            // 文件模式（Augment 客户端或显式指定）：图片保存到临时目录
+            let file_mode = use_file_image_mode();
 
             // 分别收集用户文本和图片信息
             let mut user_text_parts = Vec::new();
             let mut image_info_parts = Vec::new();
             let mut collected_images = Vec::new(); // 收集图片数据用于 Augment 客户端
+            let mut omitted_images = 0;
 
             for content in content_array {
                 match content.content_type.as_str() {
@@ -54,9 +111,13 @@ pub fn parse_mcp_response(response: &str) -> Result<Vec<Content>, McpError> {
                     "image" => {
                         if let Some(source) = content.source {
                             if source.source_type == "base64" {
+                                if image_count >= max_images {
+                                    omitted_images += 1;
+                                    continue;
+                                }
                                 image_count += 1;
 
-                                if is_augment {
+                                if file_mode {
                                     // Augment 客户端：收集图片信息，稍后统一处理
                                     collected_images.push((source.data.clone(), source.media_type.clone()));
                                 } else {
@@ -65,7 +126,7 @@ pub fn parse_mcp_response(response: &str) -> Result<Vec<Content>, McpError> {
                                 }
 
                                 // 根据客户端类型决定是否添加详细信息
-                                if !is_augment {
+                                if !file_mode {
                                     // 非 Augment 客户端：添加详细图片信息
                                     let base64_len = source.data.len();
                                     let preview = if base64_len > 50 {
@@ -102,6 +163,11 @@ pub fn parse_mcp_response(response: &str) -> Result<Vec<Content>, McpError> {
                 }
             }
 
+            if omitted_images > 0 {
+                log_debug!("[parse_mcp_response] 图片超出上限: kept={}, omitted={}", image_count, omitted_images);
+                user_text_parts.push(images_omitted_note(omitted_images, max_images));
+            }
+
             // 构建文本内容
             let mut all_text_parts = Vec::new();
 
@@ -111,12 +177,13 @@ pub fn parse_mcp_response(response: &str) -> Result<Vec<Content>, McpError> {
             }
 
             // 2. 根据客户端类型处理图片
-            if is_augment && !collected_images.is_empty() {
+            if file_mode && !collected_images.is_empty() {
                 // Augment 客户端：保存图片到临时目录并返回特殊格式
                 let mut saved_images = Vec::new();
+                let strip_metadata = strip_metadata_enabled();
 
                 for (index, (data, media_type)) in collected_images.iter().enumerate() {
-                    match save_image_to_temp(data, media_type, index) {
+                    match save_image_to_temp(data, media_type, index, strip_metadata) {
                         Ok(file_path) => {
                             let image_type = media_type
                                 .strip_prefix("image/")
@@ -151,7 +218,7 @@ pub fn parse_mcp_response(response: &str) -> Result<Vec<Content>, McpError> {
 
                     return Ok(result);
                 }
-            } else if !is_augment {
+            } else if !file_mode {
                 // 非 Augment 客户端：添加详细图片信息
                 if !image_info_parts.is_empty() {
                     all_text_parts.extend(image_info_parts);
@@ -191,13 +258,61 @@ pub fn parse_mcp_response(response: &str) -> Result<Vec<Content>, McpError> {
     }
 }
 
+/// 提取用户回复的文本摘要（不包含图片原始数据）
+///
+/// 用于写入 zhi 交互历史；用户取消时返回 None
+pub fn summarize_mcp_response(response: &str) -> Option<String> {
+    let trimmed = response.trim();
+    if trimmed.is_empty() || trimmed == "CANCELLED" || trimmed == "用户取消了操作" {
+        return None;
+    }
+
+    let mut parts = Vec::new();
+    if let Ok(structured) = serde_json::from_str::<McpResponse>(trimmed) {
+        if let Some(input) = structured.user_input.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            parts.push(format!("用户输入: {}", input));
+        }
+        if !structured.selected_options.is_empty() {
+            parts.push(format!("选项: {}", structured.selected_options.join(", ")));
+        }
+        if !structured.images.is_empty() {
+            parts.push(format!("图片数量: {}", structured.images.len()));
+        }
+    } else if let Ok(content_array) = serde_json::from_str::<Vec<McpResponseContent>>(trimmed) {
+        let texts: Vec<String> = content_array
+            .iter()
+            .filter(|c| c.content_type != "image")
+            .filter_map(|c| c.text.as_deref().map(str::trim).filter(|s| !s.is_empty()))
+            .map(|s| s.to_string())
+            .collect();
+        let image_count = content_array.iter().filter(|c| c.content_type == "image").count();
+        if !texts.is_empty() {
+            parts.push(format!("用户输入: {}", texts.join("\n")));
+        }
+        if image_count > 0 {
+            parts.push(format!("图片数量: {}", image_count));
+        }
+    } else {
+        parts.push(format!("用户输入: {}", trimmed));
+    }
+
+    if parts.is_empty() {
+        parts.push("用户输入: 用户确认继续".to_string());
+    }
+    Some(parts.join("\n"))
+}
+
 /// 解析新的结构化响应格式
-fn parse_structured_response(response: McpResponse) -> Result<Vec<Content>, McpError> {
+fn parse_structured_response(mut response: McpResponse, max_images: usize) -> Result<Vec<Content>, McpError> {
     let mut result = Vec::new();
     let mut text_parts = Vec::new();
 
-    // 检查是否为 Augment 客户端
-    let is_augment = is_augment_client();
+    // 超出上限的图片直接丢弃，避免 MCP 响应过大
+    let omitted_images = response.images.len().saturating_sub(max_images);
+    response.images.truncate(max_images);
+
+    // 文件模式（Augment 客户端或显式指定）：图片保存到临时目录
+    let file_mode = use_file_image_mode();
 
     // 1. 处理选择的选项
     if !response.selected_options.is_empty() {
@@ -211,14 +326,20 @@ fn parse_structured_response(response: McpResponse) -> Result<Vec<Content>, McpE
         }
     }
 
+    if omitted_images > 0 {
+        log_debug!("[parse_mcp_response] 图片超出上限: kept={}, omitted={}", response.images.len(), omitted_images);
+        text_parts.push(images_omitted_note(omitted_images, max_images));
+    }
+
     // 3. 处理图片附件
     if !response.images.is_empty() {
-        if is_augment {
+        if file_mode {
             // Augment 客户端：保存图片到临时目录并返回特殊格式
             let mut saved_images = Vec::new();
+            let strip_metadata = strip_metadata_enabled();
 
             for (index, image) in response.images.iter().enumerate() {
-                match save_image_to_temp(&image.data, &image.media_type, index) {
+                match save_image_to_temp(&image.data, &image.media_type, index, strip_metadata) {
                     Ok(file_path) => {
                         // 从 media_type 提取文件类型
                         let image_type = image.media_type
@@ -333,22 +454,50 @@ fn generate_image_filename(index: usize, extension: &str) -> String {
     format!("augment_image_{}_{}.{}", index + 1, random_suffix, extension)
 }
 
-/// 保存图片到临时目录并返回路径
-fn save_image_to_temp(base64_data: &str, media_type: &str, index: usize) -> Result<String, String> {
-    // 解码 Base64 数据
-    let image_data = general_purpose::STANDARD
-        .decode(base64_data)
-        .map_err(|e| format!("Base64 解码失败: {}", e))?;
-
-    // 根据 media_type 确定文件扩展名
-    let extension = match media_type {
+/// 根据声明的 media_type 确定文件扩展名
+fn extension_for_media_type(media_type: &str) -> &'static str {
+    match media_type {
         "image/png" => "png",
         "image/jpeg" | "image/jpg" => "jpg",
         "image/gif" => "gif",
         "image/webp" => "webp",
         "image/bmp" => "bmp",
         _ => "png", // 默认使用 png
-    };
+    }
+}
+
+/// 根据文件头（magic bytes）识别图片格式，返回对应扩展名
+fn detect_image_extension(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("png")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("jpg")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("gif")
+    } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("webp")
+    } else if data.starts_with(b"BM") {
+        Some("bmp")
+    } else {
+        None
+    }
+}
+
+/// 保存图片到临时目录并返回路径
+///
+/// `strip_metadata` 为 true 时写入前移除 EXIF/XMP 等元数据（避免 GPS 等信息落盘）
+fn save_image_to_temp(base64_data: &str, media_type: &str, index: usize, strip_metadata: bool) -> Result<String, String> {
+    // 解码 Base64 数据
+    let mut image_data = general_purpose::STANDARD
+        .decode(base64_data)
+        .map_err(|e| format!("Base64 解码失败: {}", e))?;
+    if strip_metadata {
+        image_data = crate::utils::strip_image_metadata(&image_data);
+    }
+
+    // 优先按文件头识别真实格式，无法识别时再使用声明的 media_type
+    let extension = detect_image_extension(&image_data)
+        .unwrap_or_else(|| extension_for_media_type(media_type));
 
     // 生成文件名和完整路径
     let filename = generate_image_filename(index, extension);
@@ -365,3 +514,95 @@ fn save_image_to_temp(base64_data: &str, media_type: &str, index: usize) -> Resu
         .ok_or_else(|| "路径转换失败".to_string())
         .map(|s| s.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image_count(contents: &[Content]) -> usize {
+        contents.iter().filter(|c| c.raw.as_image().is_some()).count()
+    }
+
+    fn joined_text(contents: &[Content]) -> String {
+        contents
+            .iter()
+            .filter_map(|c| c.raw.as_text().map(|t| t.text.clone()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_png_labeled_as_jpeg_is_saved_as_png() {
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR";
+        let path = save_image_to_temp(&general_purpose::STANDARD.encode(png), "image/jpeg", 0, true).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert!(path.ends_with(".png"), "path: {}", path);
+    }
+
+    #[test]
+    fn test_saved_jpeg_has_exif_removed() {
+        let mut jpeg = vec![0xFF, 0xD8];
+        // APP0 (JFIF)
+        jpeg.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x07]);
+        jpeg.extend_from_slice(b"JFIF\0");
+        // APP1 (EXIF，含 GPS 信息)
+        jpeg.extend_from_slice(&[0xFF, 0xE1, 0x00, 0x0C]);
+        jpeg.extend_from_slice(b"Exif\0\0GPS\0\0\0");
+        // SOS + 扫描数据 + EOI
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x04, 0x01, 0x02, 0xAB, 0xCD, 0xFF, 0xD9]);
+
+        let path = save_image_to_temp(&general_purpose::STANDARD.encode(&jpeg), "image/jpeg", 0, true).unwrap();
+        let saved = fs::read(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert!(!saved.windows(4).any(|w| w == b"Exif"));
+        assert!(saved.windows(4).any(|w| w == b"JFIF"));
+        assert!(saved.ends_with(&[0xFF, 0xDA, 0x00, 0x04, 0x01, 0x02, 0xAB, 0xCD, 0xFF, 0xD9]));
+    }
+
+    #[test]
+    fn test_detects_webp_container_and_falls_back_to_declared_type() {
+        let mut webp = b"RIFF\x24\x00\x00\x00WEBPVP8 ".to_vec();
+        webp.extend_from_slice(&[0u8; 8]);
+
+        assert_eq!(detect_image_extension(&webp), Some("webp"));
+        // RIFF 容器但不是 WebP（如 WAV），不应误判
+        assert_eq!(detect_image_extension(b"RIFF\x24\x00\x00\x00WAVEfmt "), None);
+        assert_eq!(detect_image_extension(b"unknown"), None);
+        assert_eq!(extension_for_media_type("image/gif"), "gif");
+    }
+
+    #[test]
+    fn test_images_beyond_cap_are_omitted_with_note() {
+        let images: Vec<_> = (0..5)
+            .map(|i| json!({ "data": format!("aW1n{}", i), "media_type": "image/png", "filename": null }))
+            .collect();
+        let structured = json!({
+            "user_input": "看看这些截图",
+            "selected_options": [],
+            "images": images,
+            "metadata": { "timestamp": null, "request_id": "req-355", "source": "popup" }
+        })
+        .to_string();
+
+        let contents = parse_mcp_response_with_limit(&structured, 3).unwrap();
+        assert_eq!(image_count(&contents), 3);
+        assert!(joined_text(&contents).contains("另有 2 张图片已省略（单次回复最多 3 张）"));
+
+        // 旧格式解析路径同样生效
+        let mut legacy = vec![json!({ "type": "text", "text": "旧格式" })];
+        legacy.extend((0..5).map(|i| json!({
+            "type": "image",
+            "source": { "type": "base64", "media_type": "image/png", "data": format!("aW1n{}", i) }
+        })));
+        let contents = parse_mcp_response_with_limit(&serde_json::Value::Array(legacy).to_string(), 3).unwrap();
+        assert_eq!(image_count(&contents), 3);
+        assert!(joined_text(&contents).contains("另有 2 张图片已省略"));
+
+        // 未超出上限时不附加说明
+        let contents = parse_mcp_response_with_limit(&structured, 8).unwrap();
+        assert_eq!(image_count(&contents), 5);
+        assert!(!joined_text(&contents).contains("已省略"));
+    }
+}
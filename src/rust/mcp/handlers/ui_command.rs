@@ -0,0 +1,181 @@
+// 等一下 UI 命令定位
+// popup 与 icon_popup 共用，按优先级查找 GUI 可执行文件
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::log_debug;
+
+/// 指定 UI 命令位置的环境变量（可为可执行文件或其所在目录）
+pub const UI_PATH_ENV: &str = "SANSHU_UI_PATH";
+
+/// UI 命令名称
+const UI_COMMAND_NAME: &str = "等一下";
+
+/// 查找等一下 UI 命令的路径
+///
+/// 按优先级查找：SANSHU_UI_PATH -> 同目录 -> 上级目录 -> 平台安装位置 -> 全局命令
+pub fn resolve_ui_command() -> Result<String> {
+    let env_override = std::env::var(UI_PATH_ENV).ok();
+    resolve_ui_command_with(env_override.as_deref())
+}
+
+/// 以给定的路径覆盖值（即 SANSHU_UI_PATH 的取值）查找 UI 命令
+fn resolve_ui_command_with(env_override: Option<&str>) -> Result<String> {
+    let current_exe = std::env::current_exe().ok();
+    let candidates = ui_command_candidates(env_override, current_exe.as_deref());
+
+    for candidate in &candidates {
+        if candidate.is_file() && is_executable(candidate) {
+            log_debug!("[ui_command] 已找到UI命令: {}", candidate.display());
+            return Ok(candidate.to_string_lossy().to_string());
+        }
+    }
+
+    // 尝试全局命令（最常见的部署方式）
+    if test_command_available(UI_COMMAND_NAME) {
+        return Ok(UI_COMMAND_NAME.to_string());
+    }
+
+    let tried = candidates
+        .iter()
+        .map(|p| format!("  - {}", p.display()))
+        .chain(std::iter::once(format!("  - {} (PATH)", UI_COMMAND_NAME)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    anyhow::bail!(
+        "找不到等一下 UI 命令。已尝试以下位置：\n{}\n请确保：\n\
+         1. 已编译项目：cargo build --release\n\
+         2. 或已全局安装：./install.sh\n\
+         3. 或等一下命令在同目录下\n\
+         4. 或通过 {} 环境变量指定路径",
+        tried,
+        UI_PATH_ENV
+    )
+}
+
+/// 生成候选路径列表（按优先级排序）
+fn ui_command_candidates(env_override: Option<&str>, current_exe: Option<&Path>) -> Vec<PathBuf> {
+    let file_name = format!("{}{}", UI_COMMAND_NAME, std::env::consts::EXE_SUFFIX);
+    let mut candidates = Vec::new();
+
+    // 1. 环境变量指定（目录或可执行文件）
+    if let Some(value) = env_override.map(str::trim).filter(|v| !v.is_empty()) {
+        let path = PathBuf::from(value);
+        if path.is_dir() {
+            candidates.push(path.join(&file_name));
+        } else {
+            candidates.push(path);
+        }
+    }
+
+    if let Some(exe_dir) = current_exe.and_then(|exe| exe.parent()) {
+        // 2. 与当前 MCP 服务器同目录
+        candidates.push(exe_dir.join(&file_name));
+        // 3. 上级目录（例如服务器位于 bin/ 子目录）
+        if let Some(parent) = exe_dir.parent() {
+            candidates.push(parent.join(&file_name));
+        }
+        // 4. macOS 应用包内布局（服务器位于 Contents/Resources 等目录）
+        if cfg!(target_os = "macos") {
+            if let Some(contents) = exe_dir.parent() {
+                candidates.push(contents.join("MacOS").join(&file_name));
+            }
+        }
+    }
+
+    // 5. 平台默认安装位置
+    candidates.extend(platform_install_candidates(&file_name));
+
+    // 去重（保持优先级顺序）
+    let mut seen = std::collections::HashSet::new();
+    candidates.retain(|p| seen.insert(p.clone()));
+    candidates
+}
+
+/// 平台相关的默认安装位置
+fn platform_install_candidates(file_name: &str) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    if cfg!(target_os = "macos") {
+        let bundle_bin = Path::new("三术.app").join("Contents").join("MacOS").join(file_name);
+        out.push(Path::new("/Applications").join(&bundle_bin));
+        if let Some(home) = dirs::home_dir() {
+            out.push(home.join("Applications").join(&bundle_bin));
+        }
+    } else if cfg!(windows) {
+        if let Some(local) = dirs::data_local_dir() {
+            out.push(local.join("Programs").join("三术").join(file_name));
+            out.push(local.join("三术").join(file_name));
+        }
+    } else if let Some(home) = dirs::home_dir() {
+        out.push(home.join(".local").join("bin").join(file_name));
+    }
+    out
+}
+
+/// 测试命令是否可用
+fn test_command_available(command: &str) -> bool {
+    Command::new(command)
+        .arg("--version")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// 检查文件是否可执行
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .map(|metadata| metadata.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+
+    #[cfg(windows)]
+    {
+        // Windows 上检查文件扩展名
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.eq_ignore_ascii_case("exe"))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_env_override_wins_when_executable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let ui_path = temp_dir.path().join(UI_COMMAND_NAME);
+        fs::write(&ui_path, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&ui_path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        // 目录形式
+        let from_dir = resolve_ui_command_with(Some(&temp_dir.path().to_string_lossy()));
+        // 文件形式
+        let from_file = resolve_ui_command_with(Some(&ui_path.to_string_lossy()));
+
+        let expected = ui_path.to_string_lossy().to_string();
+        assert_eq!(from_dir.unwrap(), expected);
+        assert_eq!(from_file.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_candidates_include_exe_dir_and_parent() {
+        let exe = Path::new("/opt/sanshu/bin/三术");
+        let candidates = ui_command_candidates(None, Some(exe));
+        let file_name = format!("{}{}", UI_COMMAND_NAME, std::env::consts::EXE_SUFFIX);
+
+        assert_eq!(candidates[0], Path::new("/opt/sanshu/bin").join(&file_name));
+        assert_eq!(candidates[1], Path::new("/opt/sanshu").join(&file_name));
+    }
+}
@@ -1,5 +1,6 @@
 pub mod commands;
 pub mod server;
+pub mod shutdown;
 pub mod tools;
 pub mod types;
 pub mod handlers;
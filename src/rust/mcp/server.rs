@@ -9,13 +9,18 @@ use rmcp::model::*;
 use std::collections::HashMap;
 use std::time::Instant;
 
+use super::shutdown::{wait_for_shutdown_signal, ShutdownCoordinator, DEFAULT_SHUTDOWN_GRACE};
+
 use super::tools::{InteractionTool, MemoryTool, AcemcpTool, Context7Tool, IconTool, SkillsTool, UiuxTool, EnhanceTool};
 use super::types::{ZhiRequest, JiyiRequest, TuRequest, SkillRunRequest};
 use crate::mcp::tools::enhance::mcp::EnhanceMcpRequest;
+use crate::mcp::tools::uiux::mcp::UIUX_TOOL_NAMES;
 use crate::mcp::tools::context7::types::Context7Request;
 use crate::config::load_standalone_config;
+use crate::constants::mcp::{LIST_TOOLS_PAGE_SIZE, MAX_ZHI_TIMEOUT_SECS};
 use crate::mcp::utils::safe_truncate_clean;
 use crate::mcp::utils::generate_request_id;
+use crate::utils::with_request_id;
 use crate::{log_important, log_debug};
 
 #[derive(Clone)]
@@ -87,7 +92,7 @@ impl ServerHandler for ZhiServer {
 
     async fn list_tools(
         &self,
-        _request: Option<PaginatedRequestParam>,
+        request: Option<PaginatedRequestParam>,
         _context: RequestContext<RoleServer>,
     ) -> Result<ListToolsResult, McpError> {
         use std::sync::Arc;
@@ -115,6 +120,20 @@ impl ServerHandler for ZhiServer {
                 "project_root_path": {
                     "type": "string",
                     "description": "项目根目录绝对路径（必填）"
+                },
+                "default_input": {
+                    "type": "string",
+                    "description": "输入框预填的默认文本（可选，用户可编辑后提交）"
+                },
+                "timeout_secs": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "maximum": MAX_ZHI_TIMEOUT_SECS,
+                    "description": "等待用户回复的超时秒数（可选，适用于无人值守场景）"
+                },
+                "default_on_timeout": {
+                    "type": "string",
+                    "description": "超时后自动提交的默认回复（可选，未提供时超时视为取消）"
                 }
             },
             "required": ["message", "project_root_path"]
@@ -140,7 +159,7 @@ impl ServerHandler for ZhiServer {
                 "properties": {
                     "action": {
                         "type": "string",
-                        "description": "操作类型：记忆(添加) | 回忆(查询) | 整理(去重) | 列表(全部记忆) | 预览相似(检测相似度) | 配置(获取/更新) | 删除(移除记忆)"
+                        "description": "操作类型：记忆(添加) | 回忆(查询) | 整理(去重) | 列表(全部记忆) | 预览相似(检测相似度) | 配置(获取/更新) | 删除(移除记忆) | 置顶(保护记忆不被去重) | 取消置顶 | 重新分类(修改记忆分类) | 批量删除(一次移除多条记忆)"
                     },
                     "project_path": {
                         "type": "string",
@@ -148,11 +167,11 @@ impl ServerHandler for ZhiServer {
                     },
                     "content": {
                         "type": "string",
-                        "description": "记忆内容（记忆/预览相似操作时必需）"
+                        "description": "记忆内容（记忆/预览相似操作时必需；回忆操作时作为可选查询）"
                     },
                     "category": {
                         "type": "string",
-                        "description": "记忆分类：rule(规范规则), preference(用户偏好), pattern(最佳实践), context(项目上下文)"
+                        "description": "记忆分类：rule(规范规则), preference(用户偏好), pattern(最佳实践), context(项目上下文)；重新分类时为目标分类"
                     },
                     "config": {
                         "type": "object",
@@ -169,12 +188,26 @@ impl ServerHandler for ZhiServer {
                             "enable_dedup": {
                                 "type": "boolean",
                                 "description": "启用去重检测"
+                            },
+                            "recall_fuzzy_threshold": {
+                                "type": "number",
+                                "description": "回忆模糊匹配阈值 (0.3~0.95)，低于此值的模糊结果被忽略"
                             }
                         }
                     },
                     "memory_id": {
                         "type": "string",
-                        "description": "记忆ID（删除操作时必需）"
+                        "description": "记忆ID（删除/置顶/取消置顶/重新分类操作时必需）"
+                    },
+                    "memory_ids": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "记忆ID列表（批量删除操作时必需）"
+                    },
+                    "order": {
+                        "type": "string",
+                        "enum": ["default", "recent", "frequent"],
+                        "description": "回忆结果排序：default(创建顺序) | recent(最近访问优先) | frequent(常用优先)"
                     }
                 },
                 "required": ["action", "project_path"]
@@ -219,15 +252,18 @@ impl ServerHandler for ZhiServer {
             tools.push(EnhanceTool::get_tool_definition());
         }
 
-        // 技能运行时工具 - 动态发现 skills 并追加工具
+        // 技能运行时工具 - 动态发现 skills 并追加工具（按名称排序，保证分页游标稳定）
         let project_root = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
         tools.extend(SkillsTool::list_dynamic_tools(&project_root));
 
-        log_debug!("返回给客户端的工具列表: {:?}", tools.iter().map(|t| &t.name).collect::<Vec<_>>());
+        let cursor = request.and_then(|r| r.cursor);
+        let (tools, next_cursor) = paginate_tools(tools, cursor.as_deref(), LIST_TOOLS_PAGE_SIZE)?;
+
+        log_debug!("返回给客户端的工具列表: {:?}, next_cursor={:?}", tools.iter().map(|t| &t.name).collect::<Vec<_>>(), next_cursor);
 
         Ok(ListToolsResult {
             meta: None,
-            next_cursor: None,
+            next_cursor,
             tools,
         })
     }
@@ -238,9 +274,27 @@ impl ServerHandler for ZhiServer {
         _context: RequestContext<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
         let call_id = generate_request_id();
+        // 在请求 ID 作用域内处理，作用域内的日志自动附带 rid 字段
+        with_request_id(call_id.clone(), self.dispatch_tool_call(call_id, request)).await
+    }
+}
+
+impl ZhiServer {
+    /// 分发工具调用（在 call_tool 设置的请求 ID 作用域内执行）
+    async fn dispatch_tool_call(
+        &self,
+        call_id: String,
+        request: CallToolRequestParam,
+    ) -> Result<CallToolResult, McpError> {
         let start = Instant::now();
 
         let tool_name = request.name.to_string();
+
+        // 关闭流程开始后不再接收新调用
+        let Some(_in_flight) = ShutdownCoordinator::global().try_enter() else {
+            log_important!(warn, "[MCP] 服务器正在关闭，拒绝调用: call_id={}, tool={}", call_id, tool_name);
+            return Err(McpError::internal_error("MCP 服务器正在关闭".to_string(), None));
+        };
         let arg_keys: Vec<String> = request
             .arguments
             .as_ref()
@@ -372,14 +426,18 @@ impl ServerHandler for ZhiServer {
                     }
                 }
             }
-            "uiux" => {
+            name if UIUX_TOOL_NAMES.contains(&name) => {
                 if !self.is_tool_enabled("uiux") {
-                    log_important!(warn, "[MCP] 工具已禁用: call_id={}, tool=uiux", call_id);
+                    log_important!(warn, "[MCP] 工具已禁用: call_id={}, tool={}", call_id, name);
                     Err(McpError::internal_error("UI/UX 工具已被禁用".to_string(), None))
                 } else {
-                    UiuxTool::call_tool("uiux", arguments_value).await
+                    UiuxTool::call_tool(name, arguments_value).await
                 }
             }
+            "skill_list" => {
+                let project_root = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
+                SkillsTool::list_skills(&project_root)
+            }
             name if name == "skill_run" || name.starts_with("skill_") => {
                 match serde_json::from_value::<SkillRunRequest>(arguments_value) {
                     Ok(skill_request) => {
@@ -453,6 +511,29 @@ impl ServerHandler for ZhiServer {
 
 
 
+/// 按游标分页工具列表
+///
+/// 游标为下一页起始下标；返回当前页与下一页游标（无剩余时为 None）
+fn paginate_tools(
+    tools: Vec<Tool>,
+    cursor: Option<&str>,
+    page_size: usize,
+) -> Result<(Vec<Tool>, Option<String>), McpError> {
+    let start = match cursor.map(str::trim).filter(|c| !c.is_empty()) {
+        Some(c) => c
+            .parse::<usize>()
+            .map_err(|_| McpError::invalid_params(format!("无效的分页游标: {}", c), None))?,
+        None => 0,
+    };
+    let page_size = page_size.max(1);
+    let total = tools.len();
+
+    let page: Vec<Tool> = tools.into_iter().skip(start).take(page_size).collect();
+    let end = start.saturating_add(page.len());
+    let next_cursor = if end < total { Some(end.to_string()) } else { None };
+    Ok((page, next_cursor))
+}
+
 /// 启动MCP服务器
 pub async fn run_server() -> Result<(), Box<dyn std::error::Error>> {
     // 创建并运行服务器
@@ -474,7 +555,68 @@ pub async fn run_server() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
-    // 等待服务器关闭
-    service.waiting().await?;
+    // 等待服务器关闭或退出信号
+    let cancel_token = service.cancellation_token();
+    let waiting = service.waiting();
+    tokio::pin!(waiting);
+
+    tokio::select! {
+        result = &mut waiting => {
+            result?;
+        }
+        signal = wait_for_shutdown_signal() => {
+            log_important!(info, "[shutdown] 收到退出信号: {}，开始优雅关闭", signal);
+            let report = ShutdownCoordinator::global().shutdown(DEFAULT_SHUTDOWN_GRACE).await;
+            cancel_token.cancel();
+            let _ = waiting.await;
+            log_important!(
+                info,
+                "[shutdown] MCP 服务器已关闭: drained={}, remaining={}, cleaned_files={}",
+                report.drained,
+                report.remaining,
+                report.cleaned_files
+            );
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+    use std::sync::Arc;
+
+    fn tool(name: &str) -> Tool {
+        Tool {
+            name: Cow::Owned(name.to_string()),
+            description: None,
+            input_schema: Arc::new(serde_json::Map::new()),
+            annotations: None,
+            icons: None,
+            meta: None,
+            output_schema: None,
+            title: None,
+        }
+    }
+
+    #[test]
+    fn test_list_tools_pages_cover_all_without_overlap() {
+        let names = ["zhi", "ji", "skill_run", "skill_list", "skill_alpha"];
+        let all: Vec<Tool> = names.iter().map(|n| tool(n)).collect();
+
+        let (page1, cursor) = paginate_tools(all.clone(), None, 3).unwrap();
+        let cursor = cursor.expect("第一页后应有 next_cursor");
+        let (page2, end_cursor) = paginate_tools(all, Some(&cursor), 3).unwrap();
+
+        let page1: Vec<String> = page1.iter().map(|t| t.name.to_string()).collect();
+        let page2: Vec<String> = page2.iter().map(|t| t.name.to_string()).collect();
+        assert_eq!(page1, vec!["zhi", "ji", "skill_run"]);
+        assert_eq!(page2, vec!["skill_list", "skill_alpha"]);
+        assert!(end_cursor.is_none());
+        assert!(page1.iter().all(|n| !page2.contains(n)));
+        assert_eq!(page1.len() + page2.len(), names.len());
+
+        assert!(paginate_tools(vec![tool("zhi")], Some("abc"), 3).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+// MCP 服务器优雅关闭
+// 跟踪进行中的工具调用与临时文件，收到退出信号后停止接收新调用并等待其完成
+
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::{log_debug, log_important};
+
+/// 默认等待进行中调用完成的时间
+pub const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+static GLOBAL_COORDINATOR: Lazy<ShutdownCoordinator> = Lazy::new(ShutdownCoordinator::new);
+
+/// 关闭协调器
+pub struct ShutdownCoordinator {
+    /// 是否已进入关闭流程（不再接收新调用）
+    shutting_down: AtomicBool,
+    /// 进行中的调用数量
+    in_flight: AtomicUsize,
+    /// 进行中调用归零时通知
+    idle: Notify,
+    /// 需要在关闭时清理的临时文件
+    temp_files: Mutex<HashSet<PathBuf>>,
+}
+
+/// 进行中调用的计数守卫（Drop 时自动减少计数）
+pub struct InFlightGuard<'a> {
+    coordinator: &'a ShutdownCoordinator,
+}
+
+/// 关闭结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// 进行中调用是否在等待时间内全部完成
+    pub drained: bool,
+    /// 超时后仍未完成的调用数量
+    pub remaining: usize,
+    /// 清理的临时文件数量
+    pub cleaned_files: usize,
+}
+
+impl Default for ShutdownCoordinator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShutdownCoordinator {
+    pub fn new() -> Self {
+        Self {
+            shutting_down: AtomicBool::new(false),
+            in_flight: AtomicUsize::new(0),
+            idle: Notify::new(),
+            temp_files: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// 进程级全局协调器
+    pub fn global() -> &'static ShutdownCoordinator {
+        &GLOBAL_COORDINATOR
+    }
+
+    /// 是否已进入关闭流程
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// 当前进行中的调用数量
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// 登记一次进行中的调用；关闭流程开始后返回 None
+    pub fn try_enter(&self) -> Option<InFlightGuard<'_>> {
+        // 先计数再检查，避免与 shutdown 设置标记之间出现竞态
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = InFlightGuard { coordinator: self };
+        if self.is_shutting_down() {
+            return None;
+        }
+        Some(guard)
+    }
+
+    /// 登记需要在关闭时清理的临时文件
+    pub fn register_temp_file(&self, path: &Path) {
+        if let Ok(mut files) = self.temp_files.lock() {
+            files.insert(path.to_path_buf());
+        }
+    }
+
+    /// 取消登记（文件已被正常清理）
+    pub fn unregister_temp_file(&self, path: &Path) {
+        if let Ok(mut files) = self.temp_files.lock() {
+            files.remove(path);
+        }
+    }
+
+    /// 执行优雅关闭：停止接收新调用 -> 等待进行中调用完成（最多 `grace`）-> 清理临时文件
+    pub async fn shutdown(&self, grace: Duration) -> ShutdownReport {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        log_important!(
+            info,
+            "[shutdown] 停止接收新调用，等待进行中调用完成: in_flight={}, grace_ms={}",
+            self.in_flight(),
+            grace.as_millis()
+        );
+
+        let drained = tokio::time::timeout(grace, async {
+            loop {
+                let notified = self.idle.notified();
+                if self.in_flight() == 0 {
+                    break;
+                }
+                notified.await;
+            }
+        })
+        .await
+        .is_ok();
+
+        let remaining = self.in_flight();
+        if drained {
+            log_important!(info, "[shutdown] 进行中调用已全部完成");
+        } else {
+            log_important!(warn, "[shutdown] 等待超时，仍有 {} 个调用未完成", remaining);
+        }
+
+        let cleaned_files = self.cleanup_temp_files();
+        log_important!(info, "[shutdown] 临时文件清理完成: count={}", cleaned_files);
+
+        ShutdownReport {
+            drained,
+            remaining,
+            cleaned_files,
+        }
+    }
+
+    /// 删除所有已登记的临时文件
+    fn cleanup_temp_files(&self) -> usize {
+        let files: Vec<PathBuf> = match self.temp_files.lock() {
+            Ok(mut files) => files.drain().collect(),
+            Err(_) => return 0,
+        };
+
+        files
+            .iter()
+            .filter(|path| match std::fs::remove_file(path) {
+                Ok(()) => true,
+                Err(e) => {
+                    log_debug!("[shutdown] 删除临时文件失败: path={}, error={}", path.display(), e);
+                    false
+                }
+            })
+            .count()
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        if self.coordinator.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.coordinator.idle.notify_waiters();
+        }
+    }
+}
+
+/// 等待退出信号（Unix: SIGINT/SIGTERM；其他平台: Ctrl-C），返回信号名称
+pub async fn wait_for_shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = wait_for_ctrl_c() => "SIGINT",
+                    _ = term.recv() => "SIGTERM",
+                }
+            }
+            Err(e) => {
+                log_debug!("[shutdown] 注册 SIGTERM 失败，仅监听 SIGINT: {}", e);
+                wait_for_ctrl_c().await;
+                "SIGINT"
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        wait_for_ctrl_c().await;
+        "Ctrl-C"
+    }
+}
+
+/// 等待 Ctrl-C（注册失败时永不返回，避免误触发关闭）
+async fn wait_for_ctrl_c() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        log_debug!("[shutdown] 监听 Ctrl-C 失败: {}", e);
+        std::future::pending::<()>().await;
+    }
+}
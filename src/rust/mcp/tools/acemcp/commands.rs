@@ -1283,7 +1283,7 @@ fn purge_project_index_records(project_root_path: &str, stop_watching: bool) ->
                 if needs_write {
                     let new_data = serde_json::to_string_pretty(&projects)
                         .map_err(|e| format!("序列化 projects.json 失败: {} (路径: {})", e, projects_path.display()))?;
-                    fs::write(&projects_path, new_data)
+                    crate::utils::write_atomic(&projects_path, new_data)
                         .map_err(|e| format!("写入 projects.json 失败: {} (路径: {})", e, projects_path.display()))?;
                 }
             }
@@ -1327,7 +1327,7 @@ fn purge_project_index_records(project_root_path: &str, stop_watching: bool) ->
                 if needs_write {
                     let new_data = serde_json::to_string_pretty(&status)
                         .map_err(|e| format!("序列化 projects_status.json 失败: {} (路径: {})", e, status_path.display()))?;
-                    fs::write(&status_path, new_data)
+                    crate::utils::write_atomic(&status_path, new_data)
                         .map_err(|e| format!("写入 projects_status.json 失败: {} (路径: {})", e, status_path.display()))?;
                 }
             }
@@ -30,6 +30,7 @@ use super::types::{
 };
 use crate::log_debug;
 use crate::log_important;
+use crate::mcp::utils::to_mcp_error;
 // 代理模块（在 create_acemcp_client 中使用）
 
 /// Acemcp工具实现
@@ -54,7 +55,7 @@ impl AcemcpTool {
         // 读取配置
         let mut acemcp_config = Self::get_acemcp_config()
             .await
-            .map_err(|e| McpError::internal_error(format!("获取acemcp配置失败: {}", e), None))?;
+            .map_err(|e| to_mcp_error("获取acemcp配置失败", e))?;
 
         // 规范化 base_url（缺协议时补 http://），并去除末尾斜杠
         if let Some(base) = &acemcp_config.base_url {
@@ -149,7 +150,7 @@ impl AcemcpTool {
         // 读取配置
         let mut acemcp_config = Self::get_acemcp_config()
             .await
-            .map_err(|e| McpError::internal_error(format!("获取acemcp配置失败: {}", e), None))?;
+            .map_err(|e| to_mcp_error("获取acemcp配置失败", e))?;
 
         // 规范化 base_url（缺协议时补 http://），并去除末尾斜杠
         if let Some(base) = &acemcp_config.base_url {
@@ -892,7 +893,7 @@ fn load_projects_status() -> ProjectsIndexStatus {
 fn save_projects_status(status: &ProjectsIndexStatus) -> Result<()> {
     let status_path = home_projects_status_file();
     let data = serde_json::to_string_pretty(status)?;
-    fs::write(status_path, data)?;
+    crate::utils::write_atomic(&status_path, data)?;
     Ok(())
 }
 
@@ -1538,7 +1539,7 @@ pub(crate) async fn update_index(config: &AcemcpConfig, project_root_path: &str)
     // 只保留当前项目中仍然存在的 blob 的哈希值（自动删除已删除的 blob）
     let all_blob_names: Vec<String> = existing_hashes.into_iter().chain(uploaded_names.into_iter()).collect();
     projects.0.insert(normalized_root.clone(), all_blob_names.clone());
-    if let Ok(s) = serde_json::to_string_pretty(&projects) { let _ = fs::write(projects_path, s); }
+    if let Ok(s) = serde_json::to_string_pretty(&projects) { let _ = crate::utils::write_atomic(&projects_path, s); }
 
     // 使用合并后的 blob_names（与 Python 版本保持一致）
     let blob_names = all_blob_names;
@@ -1765,8 +1766,11 @@ fn create_acemcp_client(config: &AcemcpConfig) -> anyhow::Result<Client> {
     let mut client_builder = Client::builder()
         .timeout(Duration::from_secs(60));
     
-    // 检查是否启用代理
-    if config.proxy_enabled.unwrap_or(false) {
+    // 检查是否启用代理（强制直连模式下忽略代理配置）
+    if crate::network::force_direct_enabled() {
+        log_debug!("强制直连模式，忽略 acemcp 代理配置");
+        client_builder = client_builder.no_proxy();
+    } else if config.proxy_enabled.unwrap_or(false) {
         let host = config.proxy_host.clone().unwrap_or_else(|| "127.0.0.1".to_string());
         let port = config.proxy_port.unwrap_or(7890);
         let proxy_type = config.proxy_type.clone().unwrap_or_else(|| "http".to_string());
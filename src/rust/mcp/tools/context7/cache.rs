@@ -0,0 +1,246 @@
+// Context7 文档磁盘缓存
+// 按 (library, topic, version, page) 缓存查询结果，TTL 内命中时跳过网络请求
+
+use anyhow::Result;
+use ring::digest::{Context as ShaContext, SHA256};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::types::{Context7CacheStats, Context7ClearCacheResult, Context7Request};
+use crate::log_debug;
+
+/// 默认缓存过期时间（24 小时）
+pub const DEFAULT_CACHE_TTL_MINUTES: u64 = 24 * 60;
+
+/// 缓存文件内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheFile {
+    library: String,
+    topic: Option<String>,
+    version: Option<String>,
+    page: Option<u32>,
+    /// 写入时间（Unix 秒）
+    cached_at: u64,
+    content: String,
+}
+
+/// Context7 磁盘缓存
+pub struct Context7Cache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl Context7Cache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self { dir, ttl }
+    }
+
+    /// 使用默认目录与配置中的 TTL
+    pub fn from_config() -> Self {
+        let ttl_minutes = crate::config::load_standalone_config()
+            .ok()
+            .and_then(|c| c.mcp_config.context7_cache_ttl_minutes)
+            .unwrap_or(DEFAULT_CACHE_TTL_MINUTES);
+        Self::new(Self::default_dir(), Duration::from_secs(ttl_minutes * 60))
+    }
+
+    /// 默认缓存目录（<数据目录>/context7_cache）
+    pub fn default_dir() -> PathBuf {
+        crate::paths::data_subdir("context7_cache")
+    }
+
+    /// 生成缓存键（未指定版本时视为 latest）
+    fn cache_key(request: &Context7Request) -> String {
+        let mut ctx = ShaContext::new(&SHA256);
+        ctx.update(request.library.trim().to_lowercase().as_bytes());
+        ctx.update(b"\0");
+        ctx.update(request.topic.as_deref().unwrap_or("").trim().to_lowercase().as_bytes());
+        ctx.update(b"\0");
+        ctx.update(request.version.as_deref().unwrap_or("latest").trim().as_bytes());
+        ctx.update(b"\0");
+        ctx.update(request.page.unwrap_or(1).to_string().as_bytes());
+        hex::encode(&ctx.finish().as_ref()[..16])
+    }
+
+    fn entry_path(&self, request: &Context7Request) -> PathBuf {
+        self.dir.join(format!("{}.json", Self::cache_key(request)))
+    }
+
+    fn now_secs() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    }
+
+    fn is_expired(&self, cached_at: u64) -> bool {
+        Self::now_secs().saturating_sub(cached_at) >= self.ttl.as_secs()
+    }
+
+    fn read_entry(path: &PathBuf) -> Option<CacheFile> {
+        let content = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// 读取未过期的缓存
+    pub fn get(&self, request: &Context7Request) -> Option<String> {
+        let entry = Self::read_entry(&self.entry_path(request))?;
+        if self.is_expired(entry.cached_at) {
+            return None;
+        }
+        Some(entry.content)
+    }
+
+    /// 写入缓存
+    pub fn put(&self, request: &Context7Request, content: &str) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let entry = CacheFile {
+            library: request.library.clone(),
+            topic: request.topic.clone(),
+            version: request.version.clone(),
+            page: request.page,
+            cached_at: Self::now_secs(),
+            content: content.to_string(),
+        };
+        crate::utils::write_atomic(&self.entry_path(request), serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+
+    /// 命中缓存则直接返回，否则调用 `fetch` 获取并写入缓存
+    pub async fn get_or_fetch<F, Fut>(&self, request: &Context7Request, fetch: F) -> Result<String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<String>>,
+    {
+        if let Some(cached) = self.get(request) {
+            log_debug!(
+                "Context7 命中缓存: library={}, topic={:?}, version={:?}",
+                request.library,
+                request.topic,
+                request.version
+            );
+            return Ok(cached);
+        }
+
+        let content = fetch().await?;
+        if let Err(e) = self.put(request, &content) {
+            log_debug!("Context7 写入缓存失败: {}", e);
+        }
+        Ok(content)
+    }
+
+    fn list_entries(&self) -> Vec<(PathBuf, Option<CacheFile>)> {
+        let Ok(read_dir) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        read_dir
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|ext| ext == "json").unwrap_or(false))
+            .map(|p| {
+                let entry = Self::read_entry(&p);
+                (p, entry)
+            })
+            .collect()
+    }
+
+    /// 缓存统计
+    pub fn stats(&self) -> Context7CacheStats {
+        let entries = self.list_entries();
+        let mut valid = 0;
+        let mut disk_usage = 0u64;
+        for (path, entry) in &entries {
+            disk_usage += fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            if entry.as_ref().map(|e| !self.is_expired(e.cached_at)).unwrap_or(false) {
+                valid += 1;
+            }
+        }
+
+        Context7CacheStats {
+            total_entries: entries.len(),
+            valid_entries: valid,
+            expired_entries: entries.len() - valid,
+            cache_ttl_minutes: self.ttl.as_secs() / 60,
+            disk_usage_bytes: disk_usage,
+        }
+    }
+
+    /// 清空缓存（`expired_only` 为 true 时仅清理已过期或损坏的条目）
+    pub fn clear(&self, expired_only: bool) -> Context7ClearCacheResult {
+        let entries = self.list_entries();
+        let total = entries.len();
+        let mut cleared = 0;
+        for (path, entry) in entries {
+            let should_remove = !expired_only
+                || entry.map(|e| self.is_expired(e.cached_at)).unwrap_or(true);
+            if should_remove && fs::remove_file(&path).is_ok() {
+                cleared += 1;
+            }
+        }
+
+        Context7ClearCacheResult {
+            cleared_count: cleared,
+            remaining_count: total - cleared,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::TempDir;
+
+    fn pinned_request() -> Context7Request {
+        Context7Request {
+            library: "vercel/next.js".to_string(),
+            topic: Some("routing".to_string()),
+            version: Some("v15.1.8".to_string()),
+            page: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_identical_pinned_queries_hit_network_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = Context7Cache::new(temp_dir.path().to_path_buf(), Duration::from_secs(3600));
+        let counter = AtomicUsize::new(0);
+        let calls = &counter;
+
+        // 桩客户端：记录调用次数
+        let stub_fetch = move || async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, anyhow::Error>("# next.js routing docs".to_string())
+        };
+
+        let first = cache.get_or_fetch(&pinned_request(), stub_fetch).await.unwrap();
+        let second = cache.get_or_fetch(&pinned_request(), stub_fetch).await.unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // 不同版本不共享缓存
+        let mut other_version = pinned_request();
+        other_version.version = Some("v14.0.0".to_string());
+        cache.get_or_fetch(&other_version, stub_fetch).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.stats().valid_entries, 2);
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_refetch_and_clear() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = Context7Cache::new(temp_dir.path().to_path_buf(), Duration::ZERO);
+        cache.put(&pinned_request(), "stale").unwrap();
+
+        assert!(cache.get(&pinned_request()).is_none());
+        assert_eq!(cache.stats().expired_entries, 1);
+
+        let result = cache.clear(true);
+        assert_eq!(result.cleared_count, 1);
+        assert_eq!(result.remaining_count, 0);
+    }
+}
@@ -1,6 +1,10 @@
 use tauri::State;
 use crate::config::AppState;
-use super::types::{Context7Request, Context7Config, TestConnectionResponse};
+use super::cache::Context7Cache;
+use super::types::{
+    Context7CacheStats, Context7ClearCacheResult, Context7Config, Context7Request,
+    TestConnectionResponse,
+};
 
 /// 测试 Context7 连接
 #[tauri::command]
@@ -18,6 +22,10 @@ pub async fn test_context7_connection(
         Context7Config {
             api_key: config.mcp_config.context7_api_key.clone(),
             base_url: "https://context7.com/api/v2".to_string(),
+            timeout_secs: config
+                .mcp_config
+                .context7_timeout_secs
+                .unwrap_or(crate::constants::network::DEFAULT_TOOL_REQUEST_TIMEOUT_SECS),
         }
     }; // config 在这里自动 drop
 
@@ -61,8 +69,8 @@ async fn execute_test_query(
     use reqwest::Client;
     use std::time::Duration;
 
-    let client = Client::builder()
-        .timeout(Duration::from_secs(30))
+    let client = crate::network::apply_force_direct(Client::builder())
+        .timeout(Duration::from_secs(config.timeout_secs))
         .build()
         .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))?;
 
@@ -87,7 +95,13 @@ async fn execute_test_query(
 
     // 发送请求
     let response = req_builder.send().await
-        .map_err(|e| format!("请求失败: {}", e))?;
+        .map_err(|e| {
+            if e.is_timeout() {
+                format!("请求超时（{} 秒内未响应）", config.timeout_secs)
+            } else {
+                format!("请求失败: {}", e)
+            }
+        })?;
 
     let status = response.status();
 
@@ -181,3 +195,15 @@ pub async fn save_context7_config(
     Ok(())
 }
 
+/// 获取 Context7 文档缓存统计
+#[tauri::command]
+pub fn get_context7_cache_stats() -> Context7CacheStats {
+    Context7Cache::from_config().stats()
+}
+
+/// 清空 Context7 文档缓存
+#[tauri::command]
+pub fn clear_context7_cache(expired_only: Option<bool>) -> Context7ClearCacheResult {
+    crate::log_debug!("清空 Context7 缓存: expired_only={:?}", expired_only);
+    Context7Cache::from_config().clear(expired_only.unwrap_or(false))
+}
@@ -7,9 +7,12 @@ use std::borrow::Cow;
 use std::sync::Arc;
 use std::time::Duration;
 
+use super::cache::Context7Cache;
 use super::types::{Context7Request, Context7Config, SearchResponse, SearchResult};
+use crate::constants::network::DEFAULT_TOOL_REQUEST_TIMEOUT_SECS;
 use crate::log_debug;
 use crate::log_important;
+use crate::mcp::utils::{timeout_error, to_mcp_error};
 
 /// Context7 工具实现
 pub struct Context7Tool;
@@ -25,10 +28,11 @@ impl Context7Tool {
         // 读取配置
         let config = Self::get_config()
             .await
-            .map_err(|e| McpError::internal_error(format!("获取 Context7 配置失败: {}", e), None))?;
+            .map_err(|e| to_mcp_error("获取 Context7 配置失败", e))?;
 
-        // 执行查询
-        match Self::fetch_docs(&config, &request).await {
+        // 执行查询（TTL 内命中磁盘缓存时跳过网络请求）
+        let cache = Context7Cache::from_config();
+        match cache.get_or_fetch(&request, || Self::fetch_docs(&config, &request)).await {
             Ok(result) => {
                 log_important!(info, "Context7 查询成功");
                 Ok(CallToolResult {
@@ -66,7 +70,7 @@ impl Context7Tool {
                 },
                 "version": {
                     "type": "string",
-                    "description": "版本号 (可选，例如: v15.1.8)"
+                    "description": "版本号 (可选，例如: v15.1.8)。指定版本可固定文档结果，并按版本缓存"
                 },
                 "page": {
                     "type": "integer",
@@ -103,13 +107,23 @@ impl Context7Tool {
         Ok(Context7Config {
             api_key: config.mcp_config.context7_api_key,
             base_url: "https://context7.com/api/v2".to_string(),
+            timeout_secs: config.mcp_config.context7_timeout_secs.unwrap_or(DEFAULT_TOOL_REQUEST_TIMEOUT_SECS),
         })
     }
 
+    /// 转换请求错误（超时转换为明确的超时错误）
+    fn map_request_error(config: &Context7Config, e: reqwest::Error) -> anyhow::Error {
+        if e.is_timeout() {
+            timeout_error("Context7", config.timeout_secs).into()
+        } else {
+            e.into()
+        }
+    }
+
     /// 执行 HTTP 请求获取文档
     async fn fetch_docs(config: &Context7Config, request: &Context7Request) -> Result<String> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
+        let client = crate::network::apply_force_direct(Client::builder())
+            .timeout(Duration::from_secs(config.timeout_secs))
             .build()?;
 
         // 构建 URL
@@ -139,7 +153,10 @@ impl Context7Tool {
         }
 
         // 发送请求
-        let response = req_builder.send().await?;
+        let response = req_builder
+            .send()
+            .await
+            .map_err(|e| Self::map_request_error(config, e))?;
         let status = response.status();
 
         log_debug!("Context7 响应状态: {}", status);
@@ -162,7 +179,10 @@ impl Context7Tool {
         }
 
         // 读取响应文本 (Context7 API 返回纯文本 Markdown，不是 JSON)
-        let response_text = response.text().await?;
+        let response_text = response
+            .text()
+            .await
+            .map_err(|e| Self::map_request_error(config, e))?;
 
         // 如果响应为空
         if response_text.trim().is_empty() {
@@ -245,8 +265,8 @@ impl Context7Tool {
 
     /// 搜索库
     async fn search_libraries(config: &Context7Config, query: &str) -> Result<Vec<SearchResult>> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(15))
+        let client = crate::network::apply_force_direct(Client::builder())
+            .timeout(Duration::from_secs(config.timeout_secs))
             .build()?;
 
         let url = format!("{}/search", config.base_url);
@@ -259,7 +279,10 @@ impl Context7Tool {
             req_builder = req_builder.header(AUTHORIZATION, format!("Bearer {}", api_key));
         }
 
-        let response = req_builder.send().await?;
+        let response = req_builder
+            .send()
+            .await
+            .map_err(|e| Self::map_request_error(config, e))?;
         let status = response.status();
 
         if !status.is_success() {
@@ -358,3 +381,46 @@ impl Context7Tool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::utils::is_timeout_error;
+    use std::net::TcpListener;
+    use std::time::Instant;
+
+    /// 启动一个接受连接但从不响应的本地服务器
+    fn start_stalling_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut held = Vec::new();
+            for stream in listener.incoming().flatten() {
+                held.push(stream);
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_stalled_upstream_returns_timeout_error() {
+        let config = Context7Config {
+            api_key: None,
+            base_url: start_stalling_server(),
+            timeout_secs: 1,
+        };
+        let request = Context7Request {
+            library: "vercel/next.js".to_string(),
+            topic: None,
+            version: None,
+            page: None,
+        };
+
+        let start = Instant::now();
+        let err = Context7Tool::fetch_docs(&config, &request).await.unwrap_err();
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(is_timeout_error(&err), "unexpected error: {}", err);
+        assert!(err.to_string().contains("超时"));
+    }
+}
@@ -1,8 +1,13 @@
 pub mod types;
+pub mod cache;
 pub mod mcp;
 pub mod commands;
 
 pub use mcp::Context7Tool;
-pub use types::{Context7Request, Context7Config};
-pub use commands::{test_context7_connection, get_context7_config, save_context7_config};
+pub use cache::Context7Cache;
+pub use types::{Context7Request, Context7Config, Context7CacheStats, Context7ClearCacheResult};
+pub use commands::{
+    test_context7_connection, get_context7_config, save_context7_config,
+    get_context7_cache_stats, clear_context7_cache,
+};
 
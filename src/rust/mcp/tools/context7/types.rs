@@ -10,8 +10,8 @@ pub struct Context7Request {
     #[schemars(description = "查询主题 (可选，例如: routing, authentication)")]
     #[serde(default)]
     pub topic: Option<String>,
-    /// 版本号 (可选，例如: "v15.1.8")
-    #[schemars(description = "版本号 (可选，例如: v15.1.8)")]
+    /// 版本号 (可选，例如: "v15.1.8")；指定后结果可复现，并按版本缓存
+    #[schemars(description = "版本号 (可选，例如: v15.1.8)。指定版本可固定文档结果")]
     #[serde(default)]
     pub version: Option<String>,
     /// 分页页码 (可选，默认1，最大10)
@@ -27,6 +27,13 @@ pub struct Context7Config {
     pub api_key: Option<String>,
     /// API 基础 URL
     pub base_url: String,
+    /// 单次请求超时（秒）
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    crate::constants::network::DEFAULT_TOOL_REQUEST_TIMEOUT_SECS
 }
 
 impl Default for Context7Config {
@@ -34,6 +41,7 @@ impl Default for Context7Config {
         Self {
             api_key: None,
             base_url: "https://context7.com/api/v2".to_string(),
+            timeout_secs: default_timeout_secs(),
         }
     }
 }
@@ -69,6 +77,30 @@ pub struct PaginationInfo {
     pub has_next: bool,
 }
 
+/// Context7 缓存统计信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Context7CacheStats {
+    /// 缓存条目总数
+    pub total_entries: usize,
+    /// 有效条目数（未过期）
+    pub valid_entries: usize,
+    /// 已过期条目数
+    pub expired_entries: usize,
+    /// 缓存过期时间（分钟）
+    pub cache_ttl_minutes: u64,
+    /// 磁盘占用（字节）
+    pub disk_usage_bytes: u64,
+}
+
+/// Context7 清空缓存结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Context7ClearCacheResult {
+    /// 清空的缓存条目数
+    pub cleared_count: usize,
+    /// 剩余缓存条目数
+    pub remaining_count: usize,
+}
+
 /// 测试连接响应
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TestConnectionResponse {
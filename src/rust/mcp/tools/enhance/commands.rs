@@ -2,40 +2,94 @@
 // 将提示词增强功能暴露给前端调用
 
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
+use std::sync::{Arc, Mutex, MutexGuard, atomic::{AtomicBool, Ordering}};
 use once_cell::sync::Lazy;
 use tauri::{AppHandle, Emitter};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use super::types::*;
 use super::core::PromptEnhancer;
-use super::history::ChatHistoryManager;
+use super::history::{ChatHistoryManager, ChatHistoryResult};
 use crate::log_important;
+use crate::mcp::utils::{
+    configured_request_id_format, next_request_id, normalize_and_validate_project_root,
+    normalize_optional_project_root, RequestIdFormat,
+};
+use crate::utils::with_request_id;
+
+/// 规范化项目路径后创建对话历史管理器
+fn open_history(project_root_path: &str) -> Result<ChatHistoryManager, String> {
+    let project_root_path = normalize_and_validate_project_root(project_root_path).map_err(|e| e.to_string())?;
+    ChatHistoryManager::new(&project_root_path).map_err(|e| format!("创建历史管理器失败: {}", e))
+}
+
+/// 前端未传 request_id 时按配置的格式生成（与增强器内部的 ID 来源一致）
+fn resolve_request_id(request_id: Option<String>, format: RequestIdFormat) -> String {
+    request_id.unwrap_or_else(|| next_request_id(format))
+}
 
 // 中文注释：保存增强请求的取消标记，用于前端主动取消
 static ENHANCE_CANCEL_FLAGS: Lazy<Mutex<HashMap<String, Arc<AtomicBool>>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
+/// 获取取消标记表（锁中毒时继续使用内部数据，避免单次 panic 导致无法取消任何请求）
+fn cancel_flags() -> MutexGuard<'static, HashMap<String, Arc<AtomicBool>>> {
+    ENHANCE_CANCEL_FLAGS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
 fn register_cancel_flag(request_id: &str) -> Arc<AtomicBool> {
     let flag = Arc::new(AtomicBool::new(false));
-    if let Ok(mut map) = ENHANCE_CANCEL_FLAGS.lock() {
-        map.insert(request_id.to_string(), flag.clone());
-    }
+    cancel_flags().insert(request_id.to_string(), flag.clone());
     flag
 }
 
 fn remove_cancel_flag(request_id: &str) {
-    if let Ok(mut map) = ENHANCE_CANCEL_FLAGS.lock() {
-        map.remove(request_id);
-    }
+    cancel_flags().remove(request_id);
 }
 
 fn cancel_request(request_id: &str) -> bool {
-    if let Ok(map) = ENHANCE_CANCEL_FLAGS.lock() {
-        if let Some(flag) = map.get(request_id) {
+    match cancel_flags().get(request_id) {
+        Some(flag) => {
             flag.store(true, Ordering::Relaxed);
-            return true;
+            true
         }
+        None => false,
+    }
+}
+
+/// 列出进行中的增强请求 ID（已注册取消标记且尚未结束，按 ID 排序）
+pub fn list_active_requests() -> Vec<String> {
+    let mut ids: Vec<String> = cancel_flags().keys().cloned().collect();
+    ids.sort();
+    ids
+}
+
+/// 取消全部进行中的增强请求，返回标记为取消的请求数
+fn cancel_all_requests() -> usize {
+    let flags = cancel_flags();
+    for flag in flags.values() {
+        flag.store(true, Ordering::Relaxed);
     }
-    false
+    flags.len()
+}
+
+/// 默认同时进行的流式增强请求上限
+const DEFAULT_MAX_CONCURRENT_STREAMS: usize = 3;
+
+// 中文注释：限制并发的流式增强请求数，避免前端连续点击打开大量 SSE 连接（上限在首次使用时读取配置）
+static ENHANCE_STREAM_SEMAPHORE: Lazy<Arc<Semaphore>> = Lazy::new(|| {
+    let limit = crate::config::load_standalone_config()
+        .ok()
+        .and_then(|c| c.mcp_config.enhance_max_concurrent_streams)
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_MAX_CONCURRENT_STREAMS);
+    Arc::new(Semaphore::new(limit))
+});
+
+/// 尝试获取流式增强许可；已达上限时返回繁忙错误。许可随返回值析构释放，覆盖所有退出路径
+fn try_acquire_stream_permit(semaphore: &Arc<Semaphore>) -> Result<OwnedSemaphorePermit, String> {
+    semaphore.clone().try_acquire_owned().map_err(|_| {
+        "当前进行中的增强请求过多，请等待已有请求完成或取消后重试".to_string()
+    })
 }
 
 /// 流式增强提示词（主要入口）
@@ -50,9 +104,28 @@ pub async fn enhance_prompt_stream(
     current_file_path: Option<String>,
     include_history: Option<bool>,
     selected_history_ids: Option<Vec<String>>,
+    selected_zhi_ids: Option<Vec<String>>,
+    regenerate: Option<bool>,
+    // 中文注释：显式指定的 blob 列表（可选，为空时从 projects.json 自动加载）
+    blob_names: Option<Vec<String>>,
     request_id: Option<String>,
+    // 中文注释：多轮迭代增强时回传上次响应的 conversation_id
+    conversation_id: Option<String>,
+    // 中文注释：历史注入方式（raw | summary，默认 raw）
+    history_mode: Option<HistoryMode>,
+    // 中文注释：为 true 时响应附带注入上下文明细（injected_context）
+    verbose: Option<bool>,
+    // 中文注释：本次请求注入的对话历史 / zhi 历史条数（可选，覆盖配置，上限 20）
+    history_limit: Option<usize>,
+    zhi_history_limit: Option<usize>,
 ) -> Result<EnhanceResponse, String> {
-    let request_id = request_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let request_id = resolve_request_id(request_id, configured_request_id_format());
+    let project_root_path = normalize_optional_project_root(project_root_path.as_deref())
+        .map_err(|e| e.to_string())?;
+    let _permit = try_acquire_stream_permit(&ENHANCE_STREAM_SEMAPHORE).map_err(|e| {
+        log_important!(warn, "拒绝增强请求（并发已达上限）: request_id={}", request_id);
+        e
+    })?;
     let cancel_flag = register_cancel_flag(&request_id);
 
     log_important!(info, "收到增强请求: request_id={}, prompt_len={}, project={:?}", 
@@ -77,26 +150,38 @@ pub async fn enhance_prompt_stream(
         current_file_path,
         include_history: include_history.unwrap_or(true),
         selected_history_ids,
+        selected_zhi_ids,
+        regenerate: regenerate.unwrap_or(false),
+        blob_names,
         request_id: Some(request_id.clone()),
+        conversation_id,
+        history_limit,
+        zhi_history_limit,
+        history_mode: history_mode.unwrap_or_default(),
+        persona_type: None,
+        mode: None,
+        dry_run: false,
+        verbose: verbose.unwrap_or(false),
+        record_to: None,
         cancel_flag: Some(cancel_flag.clone()),
     };
 
     // 使用流式增强
     let app = app_handle.clone();
-    let result = enhancer.enhance_stream(request, move |event| {
+    let result = with_request_id(request_id.clone(), enhancer.enhance_stream(request, move |event| {
         // 通过 Tauri Event 推送给前端
         if let Err(e) = app.emit("enhance-stream", &event) {
             log_important!(warn, "推送增强事件失败: {}", e);
         }
-    }).await;
+    })).await;
 
     // 中文注释：请求结束后释放取消标记，避免内存泄漏
     remove_cancel_flag(&request_id);
 
     match result {
         Ok(response) => {
-            // 如果增强成功，记录到对话历史
-            if response.success {
+            // 如果增强成功，记录到对话历史（离线兜底结果不写入历史）
+            if response.success && !response.fallback_used {
                 if let Some(ref path) = project_root_path {
                     if let Ok(manager) = ChatHistoryManager::new(path) {
                         // 中文注释：优先记录“原始用户输入”，避免把规则/上下文拼接写入历史
@@ -125,9 +210,24 @@ pub async fn enhance_prompt(
     current_file_path: Option<String>,
     include_history: Option<bool>,
     selected_history_ids: Option<Vec<String>>,
+    selected_zhi_ids: Option<Vec<String>>,
+    regenerate: Option<bool>,
+    // 中文注释：显式指定的 blob 列表（可选，为空时从 projects.json 自动加载）
+    blob_names: Option<Vec<String>>,
     request_id: Option<String>,
+    // 中文注释：多轮迭代增强时回传上次响应的 conversation_id
+    conversation_id: Option<String>,
+    // 中文注释：历史注入方式（raw | summary，默认 raw）
+    history_mode: Option<HistoryMode>,
+    // 中文注释：为 true 时响应附带注入上下文明细（injected_context）
+    verbose: Option<bool>,
+    // 中文注释：本次请求注入的对话历史 / zhi 历史条数（可选，覆盖配置，上限 20）
+    history_limit: Option<usize>,
+    zhi_history_limit: Option<usize>,
 ) -> Result<EnhanceResponse, String> {
-    let request_id = request_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let request_id = resolve_request_id(request_id, configured_request_id_format());
+    let project_root_path = normalize_optional_project_root(project_root_path.as_deref())
+        .map_err(|e| e.to_string())?;
 
     log_important!(info, "收到同步增强请求: request_id={}, prompt_len={}", request_id, prompt.len());
 
@@ -147,15 +247,47 @@ pub async fn enhance_prompt(
         current_file_path,
         include_history: include_history.unwrap_or(true),
         selected_history_ids,
-        request_id: Some(request_id),
+        selected_zhi_ids,
+        regenerate: regenerate.unwrap_or(false),
+        blob_names,
+        request_id: Some(request_id.clone()),
+        conversation_id,
+        history_limit,
+        zhi_history_limit,
+        history_mode: history_mode.unwrap_or_default(),
+        persona_type: None,
+        mode: None,
+        dry_run: false,
+        verbose: verbose.unwrap_or(false),
+        record_to: None,
         cancel_flag: None,
     };
 
-    enhancer.enhance(request)
+    with_request_id(request_id, enhancer.enhance(request))
         .await
         .map_err(|e| format!("增强失败: {}", e))
 }
 
+/// 校验增强配置（不发送提示词），`probe` 为 true 时探测 base_url 连通性
+#[tauri::command]
+pub async fn validate_enhance_config(probe: Option<bool>) -> Result<EnhanceConfigCheck, String> {
+    Ok(PromptEnhancer::validate_acemcp_config(probe.unwrap_or(true)).await)
+}
+
+/// 获取增强将使用的 blob 信息（自动加载结果）
+#[tauri::command]
+pub async fn get_enhance_blobs(
+    project_root_path: String,
+) -> Result<EnhanceBlobsInfo, String> {
+    let project_root_path = normalize_and_validate_project_root(&project_root_path).map_err(|e| e.to_string())?;
+    let (blob_names, blob_source_root) = PromptEnhancer::load_project_blobs(&project_root_path);
+    Ok(EnhanceBlobsInfo {
+        blob_count: blob_names.len(),
+        blob_names,
+        blob_source_root,
+    })
+}
+
 /// 添加对话历史记录
 #[tauri::command]
 pub async fn add_chat_history(
@@ -164,8 +296,7 @@ pub async fn add_chat_history(
     ai_response: String,
     source: Option<String>,
 ) -> Result<String, String> {
-    let manager = ChatHistoryManager::new(&project_root_path)
-        .map_err(|e| format!("创建历史管理器失败: {}", e))?;
+    let manager = open_history(&project_root_path)?;
     
     manager.add_entry(
         &user_input,
@@ -174,17 +305,20 @@ pub async fn add_chat_history(
     ).map_err(|e| format!("添加历史记录失败: {}", e))
 }
 
-/// 获取对话历史
+/// 获取对话历史（附带读取状态，便于前端区分“暂无历史 / 读取失败”）
 #[tauri::command]
 pub async fn get_chat_history(
     project_root_path: String,
     count: Option<usize>,
-) -> Result<Vec<super::history::ChatEntry>, String> {
-    let manager = ChatHistoryManager::new(&project_root_path)
-        .map_err(|e| format!("创建历史管理器失败: {}", e))?;
-    
-    manager.get_recent_entries(count.unwrap_or(20))
-        .map_err(|e| format!("读取历史记录失败: {}", e))
+    // 中文注释：按来源过滤（popup/mcp/telegram/enhance），为空时返回全部
+    source: Option<String>,
+) -> Result<ChatHistoryResult, String> {
+    let manager = match open_history(&project_root_path) {
+        Ok(manager) => manager,
+        Err(e) => return Ok(ChatHistoryResult::failed(e)),
+    };
+
+    Ok(manager.get_recent_with_status(count.unwrap_or(20), source.as_deref()))
 }
 
 /// 清空对话历史
@@ -192,8 +326,7 @@ pub async fn get_chat_history(
 pub async fn clear_chat_history(
     project_root_path: String,
 ) -> Result<(), String> {
-    let manager = ChatHistoryManager::new(&project_root_path)
-        .map_err(|e| format!("创建历史管理器失败: {}", e))?;
+    let manager = open_history(&project_root_path)?;
     
     manager.clear()
         .map_err(|e| format!("清空历史失败: {}", e))
@@ -206,3 +339,63 @@ pub async fn cancel_enhance_request(
 ) -> Result<bool, String> {
     Ok(cancel_request(&request_id))
 }
+
+/// 列出进行中的增强请求 ID
+#[tauri::command]
+pub async fn list_active_enhance_requests() -> Result<Vec<String>, String> {
+    Ok(list_active_requests())
+}
+
+/// 取消全部进行中的增强请求，返回取消的请求数
+#[tauri::command]
+pub async fn cancel_all_enhance_requests() -> Result<usize, String> {
+    let count = cancel_all_requests();
+    if count > 0 {
+        log_important!(info, "已取消全部增强请求: count={}", count);
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_permit_rejects_beyond_limit_and_releases_on_drop() {
+        let semaphore = Arc::new(Semaphore::new(3));
+        let mut permits: Vec<_> = (0..3).map(|_| try_acquire_stream_permit(&semaphore).unwrap()).collect();
+
+        assert!(try_acquire_stream_permit(&semaphore).is_err());
+
+        // 任一请求结束（含取消）后许可归还，新请求可以进入
+        permits.pop();
+        let _fourth = try_acquire_stream_permit(&semaphore).unwrap();
+        assert!(try_acquire_stream_permit(&semaphore).is_err());
+    }
+
+    #[test]
+    fn test_missing_request_id_follows_configured_format() {
+        let id = resolve_request_id(None, RequestIdFormat::Prefixed);
+        assert!(id.starts_with("sanshu-"), "{}", id);
+        assert!(uuid::Uuid::parse_str(&resolve_request_id(None, RequestIdFormat::Uuid)).is_ok());
+        assert_eq!(resolve_request_id(Some("req-ui".to_string()), RequestIdFormat::Prefixed), "req-ui");
+    }
+
+    #[test]
+    fn test_list_and_cancel_all_active_requests() {
+        let first = register_cancel_flag("req-399-a");
+        let second = register_cancel_flag("req-399-b");
+
+        let active = list_active_requests();
+        assert!(active.contains(&"req-399-a".to_string()));
+        assert!(active.contains(&"req-399-b".to_string()));
+
+        assert!(cancel_all_requests() >= 2);
+        assert!(first.load(Ordering::Relaxed));
+        assert!(second.load(Ordering::Relaxed));
+
+        remove_cancel_flag("req-399-a");
+        remove_cancel_flag("req-399-b");
+        assert!(!list_active_requests().contains(&"req-399-a".to_string()));
+    }
+}
@@ -1,22 +1,131 @@
 // 提示词增强核心逻辑
 // 调用 Augment chat-stream API 实现流式提示词增强
 
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use std::sync::{Arc, Mutex};
 use std::sync::atomic::Ordering;
 use anyhow::Result;
+use once_cell::sync::Lazy;
 use reqwest::{Client, header::{AUTHORIZATION, CONTENT_TYPE}};
 use serde_json::json;
 use regex::Regex;
 use futures_util::StreamExt;
 
 use super::types::*;
-use super::history::ChatHistoryManager;
+use super::history::{ChatHistoryManager, MAX_API_HISTORY_ENTRIES};
+use super::offline::offline_rewrite;
+use super::postprocess::{apply_post_process, parse_post_process, PostProcessStep};
+use super::trace::{record_raw_sse, resolve_record_path, trace_enabled, write_enhance_trace};
 use crate::mcp::tools::interaction::ZhiHistoryManager;
+use crate::mcp::tools::memory::TextSimilarity;
 use crate::mcp::tools::acemcp::mcp::ProjectsFile;
+use crate::mcp::utils::{
+    configured_request_id_format, request_id_source, HistoryStore, RequestIdSource, SanshuError,
+    UuidRequestIdSource,
+};
 use crate::{log_debug, log_important};
 
+/// projects.json 解析缓存（按文件路径缓存，修改时间或大小变化时重新解析）
+///
+/// 大型项目的 projects.json 可能达数 MB，每次增强都完整反序列化会明显拖慢响应
+static PROJECTS_CACHE: Lazy<Mutex<HashMap<PathBuf, CachedProjects>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+struct CachedProjects {
+    modified: Option<SystemTime>,
+    len: u64,
+    projects: Arc<ProjectsFile>,
+}
+
+/// 读取并解析 projects.json（命中缓存时不再读取文件内容）
+fn read_projects_file(path: &Path) -> Option<Arc<ProjectsFile>> {
+    let metadata = match fs::metadata(path) {
+        Ok(m) => m,
+        Err(_) => {
+            log_debug!("projects.json 不存在，跳过 blob 加载: {:?}", path);
+            return None;
+        }
+    };
+    let modified = metadata.modified().ok();
+    let len = metadata.len();
+
+    if let Ok(cache) = PROJECTS_CACHE.lock() {
+        if let Some(cached) = cache.get(path) {
+            // 中文注释：部分文件系统不提供修改时间，此时无法判断是否变化，不使用缓存
+            if cached.modified.is_some() && cached.modified == modified && cached.len == len {
+                return Some(cached.projects.clone());
+            }
+        }
+    }
+
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            log_debug!("读取 projects.json 失败: {}", e);
+            return None;
+        }
+    };
+    let projects: ProjectsFile = match serde_json::from_str(&content) {
+        Ok(p) => p,
+        Err(e) => {
+            log_debug!("解析 projects.json 失败: {}", e);
+            return None;
+        }
+    };
+
+    let projects = Arc::new(projects);
+    if let Ok(mut cache) = PROJECTS_CACHE.lock() {
+        cache.insert(
+            path.to_path_buf(),
+            CachedProjects { modified, len, projects: projects.clone() },
+        );
+    }
+    Some(projects)
+}
+
+/// 请求体默认 persona_type 与 mode（Augment 聊天模式）
+const DEFAULT_PERSONA_TYPE: u32 = 1;
+const DEFAULT_PAYLOAD_MODE: &str = "CHAT";
+
+/// 允许的 persona_type 取值
+pub const PERSONA_TYPES: &[u32] = &[0, 1, 2, 3];
+
+/// 允许的 mode 取值
+pub const PAYLOAD_MODES: &[&str] = &["CHAT", "AGENT"];
+
+/// 校验 persona_type 与 mode，返回规范化后的值（mode 统一为大写）
+pub fn validate_payload_options(
+    persona_type: Option<u32>,
+    mode: Option<&str>,
+) -> Result<(Option<u32>, Option<String>)> {
+    if let Some(persona) = persona_type {
+        if !PERSONA_TYPES.contains(&persona) {
+            anyhow::bail!("无效的 persona_type: {}，可选值: {:?}", persona, PERSONA_TYPES);
+        }
+    }
+    let mode = match mode.map(str::trim).filter(|m| !m.is_empty()) {
+        Some(m) => {
+            let upper = m.to_uppercase();
+            if !PAYLOAD_MODES.contains(&upper.as_str()) {
+                anyhow::bail!("无效的 mode: {}，可选值: {}", m, PAYLOAD_MODES.join(" | "));
+            }
+            Some(upper)
+        }
+        None => None,
+    };
+    Ok((persona_type, mode))
+}
+
+/// 增强结果起止标签
+const ENHANCED_PROMPT_OPEN_TAG: &str = "<augment-enhanced-prompt>";
+const ENHANCED_PROMPT_CLOSE_TAG: &str = "</augment-enhanced-prompt>";
+
+/// 增强结果被截断时的错误信息
+const TRUNCATED_OUTPUT_MESSAGE: &str = "增强结果不完整：上游响应在结果结束前中断，可重试";
+
 /// 增强系统提示词模板
 const ENHANCE_SYSTEM_PROMPT: &str = r#"⚠️ NO TOOLS ALLOWED ⚠️
 
@@ -32,12 +141,46 @@ Here is my original instruction:
 
 "#;
 
-/// zhi 历史摘要最大条数
-const MAX_ZHI_HISTORY_ENTRIES: usize = 5;
+/// 严格版提示词的 NO TOOLS 警示头
+const NO_TOOLS_HEADER: &str = "⚠️ NO TOOLS ALLOWED ⚠️\n\n";
+/// 严格版提示词中禁止调用工具的指令，宽松版替换为 `RELAXED_REPLY_INSTRUCTION`
+const NO_TOOLS_INSTRUCTION: &str = "Do not use any tools: reply immediately with your answer";
+const RELAXED_REPLY_INSTRUCTION: &str = "Reply directly with your answer";
+
+/// 宽松版系统提示词：由严格版去掉 NO TOOLS 警示与禁用指令得到，保留响应格式约定与 `<augment-enhanced-prompt>` 标记
+static ENHANCE_SYSTEM_PROMPT_RELAXED: Lazy<String> = Lazy::new(|| {
+    ENHANCE_SYSTEM_PROMPT
+        .trim_start_matches(NO_TOOLS_HEADER)
+        .replacen(NO_TOOLS_INSTRUCTION, RELAXED_REPLY_INSTRUCTION, 1)
+});
+
+/// 重新生成模式追加的指令（要求给出与上次不同的增强结果）
+const REGENERATE_INSTRUCTION: &str = "\n\nThe previous enhanced version of this instruction was not satisfactory. Provide a distinctly different alternative: vary the structure, emphasis and wording instead of repeating the earlier result.";
+
+/// 默认注入的对话历史条数
+pub const DEFAULT_HISTORY_ENTRIES: usize = 5;
+/// 默认注入的 zhi 历史摘要条数
+pub const DEFAULT_ZHI_HISTORY_ENTRIES: usize = 5;
 /// 单条摘要最大字符数（避免提示词过长）
 const MAX_ZHI_HISTORY_TEXT_LEN: usize = 200;
+/// 摘要模式下单条要点最大字符数
+const MAX_SUMMARY_LINE_LEN: usize = 160;
+/// 摘要模式下两条要点视为重复的相似度阈值
+const SUMMARY_DEDUP_SIMILARITY: f64 = 0.85;
 /// 历史兜底文本最大字符数（避免重复注入导致提示词过长）
 const MAX_FALLBACK_HISTORY_TEXT_LEN: usize = 500;
+/// 默认请求总长度上限（消息 + 注入历史，按字符计）
+pub const DEFAULT_MAX_PROMPT_CHARS: usize = 100_000;
+/// SSE 单行（未换行缓冲）最大字节数
+const DEFAULT_MAX_SSE_LINE_BYTES: usize = 1024 * 1024;
+/// 流式响应累积文本最大字节数
+const DEFAULT_MAX_RESPONSE_BYTES: usize = 16 * 1024 * 1024;
+/// 配置校验时连通性探测的超时时间
+const CONFIG_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// 增强 API 地址覆盖环境变量（优先于 acemcp 配置，便于脚本与测试注入）
+pub const ENHANCE_BASE_URL_ENV: &str = "SANSHU_ENHANCE_BASE_URL";
+/// 增强 API token 覆盖环境变量
+pub const ENHANCE_TOKEN_ENV: &str = "SANSHU_ENHANCE_TOKEN";
 
 #[derive(Debug, Clone, Default)]
 struct HistoryBuildDiagnostics {
@@ -47,11 +190,27 @@ struct HistoryBuildDiagnostics {
     load_error: Option<String>,
     /// 是否启用了“历史为空兜底”（即使 loaded_count 为 0，也会提供临时上下文）
     fallback_used: bool,
+    /// 实际注入的对话历史条目 ID（仅 verbose 请求收集）
+    injected_ids: Vec<String>,
+    /// 注入的 zhi 交互历史条数
+    zhi_count: usize,
 }
 
 struct BuildPayloadResult {
     payload: serde_json::Value,
     history_diag: HistoryBuildDiagnostics,
+    prompt_trim: Option<PromptTrimReport>,
+    /// 本次请求使用的会话 ID
+    conversation_id: String,
+}
+
+/// 发送前的请求上下文（同步与流式增强共用）
+struct PreparedRequest {
+    request_id: String,
+    url: String,
+    payload: serde_json::Value,
+    /// 以请求上下文填充的响应基准，各返回分支在此基础上补充结果
+    base: EnhanceResponse,
 }
 
 /// 提示词增强器
@@ -64,6 +223,32 @@ pub struct PromptEnhancer {
     client: Client,
     /// 项目根路径
     project_root: Option<String>,
+    /// API 不可达时是否使用离线兜底（默认关闭，保持失败返回）
+    offline_fallback: bool,
+    /// request_id / conversation_id 来源（测试可注入确定性序列）
+    id_source: Arc<dyn RequestIdSource>,
+    /// 请求总长度上限（字符数）
+    max_prompt_chars: usize,
+    /// SSE 单行最大字节数（上游始终不换行时中止）
+    max_sse_line_bytes: usize,
+    /// 累积响应文本最大字节数
+    max_response_bytes: usize,
+    /// 系统提示词是否使用严格的 NO TOOLS 警示（默认开启）
+    strict_no_tools: bool,
+    /// 注入的对话历史条数
+    history_entries: usize,
+    /// 注入的 zhi 历史摘要条数
+    zhi_history_entries: usize,
+    /// 增强结果后处理步骤（默认为空，结果原样返回）
+    post_process: Vec<PostProcessStep>,
+    /// 请求体 persona_type 默认值
+    persona_type: u32,
+    /// 请求体 mode 默认值
+    payload_mode: String,
+    /// 对话历史存储（None 时使用默认落盘位置）
+    chat_history_store: Option<Arc<dyn HistoryStore>>,
+    /// zhi 历史存储（None 时使用默认落盘位置）
+    zhi_history_store: Option<Arc<dyn HistoryStore>>,
 }
 
 impl PromptEnhancer {
@@ -96,7 +281,7 @@ impl PromptEnhancer {
 
     /// 创建增强器实例
     pub fn new(base_url: &str, token: &str) -> Result<Self> {
-        let client = Client::builder()
+        let client = crate::network::apply_force_direct(Client::builder())
             .timeout(Duration::from_secs(120))
             .build()?;
 
@@ -105,6 +290,19 @@ impl PromptEnhancer {
             token: token.to_string(),
             client,
             project_root: None,
+            offline_fallback: false,
+            id_source: Arc::new(UuidRequestIdSource),
+            max_prompt_chars: DEFAULT_MAX_PROMPT_CHARS,
+            max_sse_line_bytes: DEFAULT_MAX_SSE_LINE_BYTES,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            strict_no_tools: true,
+            history_entries: DEFAULT_HISTORY_ENTRIES,
+            zhi_history_entries: DEFAULT_ZHI_HISTORY_ENTRIES,
+            post_process: Vec::new(),
+            persona_type: DEFAULT_PERSONA_TYPE,
+            payload_mode: DEFAULT_PAYLOAD_MODE.to_string(),
+            chat_history_store: None,
+            zhi_history_store: None,
         })
     }
 
@@ -114,30 +312,297 @@ impl PromptEnhancer {
         self
     }
 
-    /// 从 acemcp 配置创建增强器
-    pub async fn from_acemcp_config() -> Result<Self> {
+    /// 设置 ID 来源
+    pub fn with_id_source(mut self, source: Arc<dyn RequestIdSource>) -> Self {
+        self.id_source = source;
+        self
+    }
+
+    /// 设置请求总长度上限
+    pub fn with_max_prompt_chars(mut self, max_chars: usize) -> Self {
+        self.max_prompt_chars = max_chars;
+        self
+    }
+
+    /// 设置流式响应的单行与总量上限（字节）
+    pub fn with_stream_limits(mut self, max_line_bytes: usize, max_total_bytes: usize) -> Self {
+        self.max_sse_line_bytes = max_line_bytes;
+        self.max_response_bytes = max_total_bytes;
+        self
+    }
+
+    /// 设置是否启用离线兜底
+    pub fn with_offline_fallback(mut self, enabled: bool) -> Self {
+        self.offline_fallback = enabled;
+        self
+    }
+
+    /// 设置系统提示词是否使用严格的 NO TOOLS 警示
+    pub fn with_strict_no_tools(mut self, strict: bool) -> Self {
+        self.strict_no_tools = strict;
+        self
+    }
+
+    /// 设置注入的对话历史与 zhi 历史条数（超过上限时截断为 20）
+    pub fn with_history_entries(mut self, chat: usize, zhi: usize) -> Self {
+        self.history_entries = chat.min(MAX_API_HISTORY_ENTRIES);
+        self.zhi_history_entries = zhi.min(MAX_API_HISTORY_ENTRIES);
+        self
+    }
+
+    /// 设置增强结果后处理步骤
+    pub fn with_post_process(mut self, steps: Vec<PostProcessStep>) -> Self {
+        self.post_process = steps;
+        self
+    }
+
+    /// 设置请求体 persona_type / mode 默认值（需先经 `validate_payload_options` 校验）
+    pub fn with_payload_options(mut self, persona_type: Option<u32>, mode: Option<String>) -> Self {
+        if let Some(persona) = persona_type {
+            self.persona_type = persona;
+        }
+        if let Some(mode) = mode {
+            self.payload_mode = mode;
+        }
+        self
+    }
+
+    /// 设置对话历史与 zhi 历史的存储（测试可注入内存实现）
+    pub fn with_history_stores(mut self, chat: Arc<dyn HistoryStore>, zhi: Arc<dyn HistoryStore>) -> Self {
+        self.chat_history_store = Some(chat);
+        self.zhi_history_store = Some(zhi);
+        self
+    }
+
+    /// 创建当前项目的对话历史管理器
+    fn chat_history_manager(&self, project_root: &str) -> Result<ChatHistoryManager> {
+        match &self.chat_history_store {
+            Some(store) => ChatHistoryManager::with_store(project_root, store.clone()),
+            None => ChatHistoryManager::new(project_root),
+        }
+    }
+
+    /// 创建当前项目的 zhi 历史管理器
+    fn zhi_history_manager(&self, project_root: &str) -> Result<ZhiHistoryManager> {
+        match &self.zhi_history_store {
+            Some(store) => ZhiHistoryManager::with_store(project_root, store.clone()),
+            None => ZhiHistoryManager::new(project_root),
+        }
+    }
+
+    /// 解析增强使用的 base_url 与 token（环境变量优先，其次 acemcp 配置，均未配置时按地区映射）
+    async fn configured_credentials() -> Result<(Option<String>, Option<String>)> {
         use crate::mcp::tools::acemcp::AcemcpTool;
-        
+
         let config = AcemcpTool::get_acemcp_config().await?;
-        let base_url = config.base_url
-            .ok_or_else(|| anyhow::anyhow!("未配置 Acemcp base_url"))?;
-        let token = config.token
-            .ok_or_else(|| anyhow::anyhow!("未配置 Acemcp token"))?;
+        let env_override = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let base_url = match env_override(ENHANCE_BASE_URL_ENV).or(config.base_url) {
+            Some(url) => Some(url),
+            None => {
+                let endpoints = crate::config::load_standalone_config()
+                    .ok()
+                    .and_then(|c| c.mcp_config.enhance_region_endpoints)
+                    .unwrap_or_default();
+                Self::regional_base_url(&endpoints).await
+            }
+        };
+        let token = env_override(ENHANCE_TOKEN_ENV).or(config.token);
+        Ok((base_url, token))
+    }
+
+    /// 从 acemcp 配置创建增强器
+    pub async fn from_acemcp_config() -> Result<Self> {
+        let (base_url, token) = Self::configured_credentials().await?;
+        let base_url = base_url.ok_or_else(|| anyhow::anyhow!("未配置 Acemcp base_url"))?;
+        let token = token.ok_or_else(|| anyhow::anyhow!("未配置 Acemcp token"))?;
+        let mcp_config = crate::config::load_standalone_config()
+            .ok()
+            .map(|c| c.mcp_config);
+
+        let offline_fallback = mcp_config.as_ref()
+            .and_then(|c| c.enhance_offline_fallback)
+            .unwrap_or(false);
+        let max_prompt_chars = mcp_config.as_ref()
+            .and_then(|c| c.enhance_max_prompt_chars)
+            .unwrap_or(DEFAULT_MAX_PROMPT_CHARS);
+        let strict_no_tools = mcp_config.as_ref()
+            .and_then(|c| c.enhance_strict_no_tools)
+            .unwrap_or(true);
+        let history_entries = mcp_config.as_ref()
+            .and_then(|c| c.enhance_history_entries)
+            .unwrap_or(DEFAULT_HISTORY_ENTRIES);
+        let zhi_history_entries = mcp_config.as_ref()
+            .and_then(|c| c.enhance_zhi_history_entries)
+            .unwrap_or(DEFAULT_ZHI_HISTORY_ENTRIES);
+        let post_process = mcp_config.as_ref()
+            .and_then(|c| c.enhance_post_process.as_ref().map(|steps| {
+                parse_post_process(steps, c.enhance_prepend_template.as_deref())
+            }))
+            .unwrap_or_default();
+        // 中文注释：配置值无效时记录警告并沿用默认值，避免增强整体不可用
+        let (persona_type, payload_mode) = match validate_payload_options(
+            mcp_config.as_ref().and_then(|c| c.enhance_persona_type),
+            mcp_config.as_ref().and_then(|c| c.enhance_mode.as_deref()),
+        ) {
+            Ok(options) => options,
+            Err(e) => {
+                log_important!(warn, "增强请求体配置无效，使用默认值: {}", e);
+                (None, None)
+            }
+        };
+
+        Ok(Self::new(&base_url, &token)?
+            .with_offline_fallback(offline_fallback)
+            .with_max_prompt_chars(max_prompt_chars)
+            .with_strict_no_tools(strict_no_tools)
+            .with_history_entries(history_entries, zhi_history_entries)
+            .with_post_process(post_process)
+            .with_payload_options(persona_type, payload_mode)
+            .with_id_source(request_id_source(configured_request_id_format())))
+    }
+
+    /// 校验当前配置（不发送提示词），`probe` 为 true 时额外探测 base_url 连通性
+    pub async fn validate_acemcp_config(probe: bool) -> EnhanceConfigCheck {
+        match Self::configured_credentials().await {
+            Ok((base_url, token)) => {
+                Self::validate_config(base_url.as_deref(), token.as_deref(), probe, CONFIG_PROBE_TIMEOUT).await
+            }
+            Err(e) => EnhanceConfigCheck::invalid(None, e.to_string()),
+        }
+    }
+
+    /// 校验 base_url 与 token；探测时仅发送不带 token 的 HEAD 请求，任意 HTTP 响应都视为可连通
+    pub async fn validate_config(
+        base_url: Option<&str>,
+        token: Option<&str>,
+        probe: bool,
+        timeout: Duration,
+    ) -> EnhanceConfigCheck {
+        let raw = match base_url.map(str::trim).filter(|u| !u.is_empty()) {
+            Some(url) => url,
+            None => return EnhanceConfigCheck::invalid(None, "未配置 Acemcp base_url"),
+        };
+        let normalized = normalize_base_url(raw);
+        let valid_url = reqwest::Url::parse(&normalized)
+            .map(|u| u.host_str().is_some_and(|h| !h.is_empty()))
+            .unwrap_or(false);
+        if !valid_url {
+            return EnhanceConfigCheck::invalid(Some(normalized), format!("base_url 格式无效: {}", raw));
+        }
+        if token.map(str::trim).filter(|t| !t.is_empty()).is_none() {
+            return EnhanceConfigCheck::invalid(Some(normalized), "未配置 Acemcp token");
+        }
+        if !probe {
+            return EnhanceConfigCheck {
+                ok: true,
+                base_url: Some(normalized),
+                reachable: None,
+                message: "配置有效（未检测连通性）".to_string(),
+            };
+        }
+
+        let client = match crate::network::apply_force_direct(Client::builder()).timeout(timeout).build() {
+            Ok(client) => client,
+            Err(e) => return EnhanceConfigCheck::invalid(Some(normalized), format!("创建 HTTP 客户端失败: {}", e)),
+        };
+        match client.head(&normalized).send().await {
+            Ok(resp) => EnhanceConfigCheck {
+                ok: true,
+                message: format!("连接成功（HTTP {}）", resp.status().as_u16()),
+                base_url: Some(normalized),
+                reachable: Some(true),
+            },
+            Err(e) => {
+                log_debug!("增强配置连通性探测失败: {}", e);
+                let reason = if e.is_timeout() { "连接超时" } else { "无法建立连接" };
+                EnhanceConfigCheck {
+                    ok: false,
+                    message: format!("无法连接到 {}: {}", normalized, reason),
+                    base_url: Some(normalized),
+                    reachable: Some(false),
+                }
+            }
+        }
+    }
+
+    /// 按国家代码从地区映射中选择增强 API 地址（国家代码大小写不敏感）
+    pub fn select_regional_base_url(country: &str, endpoints: &HashMap<String, String>) -> Option<String> {
+        let country = country.trim();
+        if country.is_empty() || country.eq_ignore_ascii_case("UNKNOWN") {
+            return None;
+        }
+        endpoints
+            .iter()
+            .find(|(code, url)| code.trim().eq_ignore_ascii_case(country) && !url.trim().is_empty())
+            .map(|(_, url)| url.trim().to_string())
+    }
+
+    /// 根据当前 IP 所在地区选择增强 API 地址（未配置映射时不做地理检测）
+    async fn regional_base_url(endpoints: &HashMap<String, String>) -> Option<String> {
+        if endpoints.is_empty() {
+            return None;
+        }
+        let country = crate::network::detect_geo_location_cached().await;
+        let selected = Self::select_regional_base_url(&country, endpoints);
+        match &selected {
+            Some(url) => log_important!(info, "按地区选择增强 API 地址: country={}, base_url={}", country, url),
+            None => log_debug!("地区 {} 未配置增强 API 地址", country),
+        }
+        selected
+    }
+
+    /// 将失败响应转换为离线兜底结果（仅在启用离线兜底时调用）
+    fn into_offline_fallback(mut response: EnhanceResponse) -> EnhanceResponse {
+        let reason = response.error.take().unwrap_or_default();
+        response.error_kind = None;
+        log_important!(warn, "增强 API 不可达，使用离线兜底: {}", reason);
+        response.enhanced_prompt = offline_rewrite(&response.original_prompt);
+        response.success = true;
+        response.fallback_used = true;
+        response
+    }
+
+    /// 将非 2xx 响应转换为上游错误（附带响应体）
+    async fn upstream_error(response: reqwest::Response) -> SanshuError {
+        let status = response.status().as_u16();
+        let message = response.text().await.unwrap_or_default();
+        SanshuError::Upstream { status, message }
+    }
 
-        Self::new(&base_url, &token)
+    /// 构造网络失败时的响应（启用离线兜底时返回本地整理结果）
+    fn network_failure_response(&self, response: EnhanceResponse) -> EnhanceResponse {
+        if self.offline_fallback {
+            Self::into_offline_fallback(response)
+        } else {
+            response
+        }
+    }
+
+    /// 解析本次请求使用的 blob_names
+    ///
+    /// 显式传入时原样使用并跳过磁盘加载（此时没有匹配的项目根路径）
+    fn resolve_blob_names(&self, overrides: Option<&[String]>) -> (Vec<String>, Option<String>) {
+        if let Some(names) = overrides {
+            log_debug!("使用显式指定的 blob_names: count={}", names.len());
+            return (names.to_vec(), None);
+        }
+        self.load_blob_names()
     }
 
     /// 加载项目的 blob_names（返回匹配到的项目根路径）
     fn load_blob_names(&self) -> (Vec<String>, Option<String>) {
-        let project_root = match &self.project_root {
-            Some(path) => path.clone(),
-            None => return (Vec::new(), None),
-        };
+        match &self.project_root {
+            Some(path) => Self::load_project_blobs(path),
+            None => (Vec::new(), None),
+        }
+    }
 
+    /// 从 projects.json 加载指定项目的 blob_names（返回匹配到的项目根路径）
+    pub fn load_project_blobs(project_root: &str) -> (Vec<String>, Option<String>) {
         // 规范化项目路径（去除末尾斜杠，避免匹配失败）
-        let canonical_root = PathBuf::from(&project_root)
+        let canonical_root = PathBuf::from(project_root)
             .canonicalize()
-            .unwrap_or_else(|_| PathBuf::from(&project_root))
+            .unwrap_or_else(|_| PathBuf::from(project_root))
             .to_string_lossy()
             .to_string();
         let normalized_root = Self::clean_path_prefix_and_slashes(&canonical_root);
@@ -146,37 +611,26 @@ impl PromptEnhancer {
         let mut candidates = Vec::new();
         let acemcp_projects = crate::mcp::tools::acemcp::mcp::home_projects_file();
         candidates.push(acemcp_projects);
-        let legacy_projects = dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join(".sanshu")
-            .join("projects.json");
+        let legacy_projects = crate::paths::data_dir().join("projects.json");
         if !candidates.iter().any(|p| p == &legacy_projects) {
             candidates.push(legacy_projects);
         }
 
-        for projects_path in candidates {
-            if !projects_path.exists() {
-                log_debug!("projects.json 不存在，跳过 blob 加载: {:?}", projects_path);
-                continue;
-            }
-
-            let content = match fs::read_to_string(&projects_path) {
-                Ok(c) => c,
-                Err(e) => {
-                    log_debug!("读取 projects.json 失败: {}", e);
-                    continue;
-                }
-            };
+        Self::load_project_blobs_from(&candidates, &normalized_root)
+    }
 
-            let projects: ProjectsFile = match serde_json::from_str(&content) {
-                Ok(p) => p,
-                Err(e) => {
-                    log_debug!("解析 projects.json 失败: {}", e);
-                    continue;
-                }
+    /// 按顺序在候选 projects.json 中查找规范化后的项目根路径
+    fn load_project_blobs_from(
+        candidates: &[PathBuf],
+        normalized_root: &str,
+    ) -> (Vec<String>, Option<String>) {
+        for projects_path in candidates {
+            let projects = match read_projects_file(projects_path) {
+                Some(p) => p,
+                None => continue,
             };
 
-            if let Some((names, matched_root)) = Self::find_project_blobs(&projects, &normalized_root) {
+            if let Some((names, matched_root)) = Self::find_project_blobs(&projects, normalized_root) {
                 log_debug!(
                     "已加载 blob_names: count={}, source_root={}",
                     names.len(),
@@ -215,29 +669,56 @@ impl PromptEnhancer {
         None
     }
 
-    /// 加载对话历史
-    fn load_chat_history(&self, count: usize, selected_ids: Option<&[String]>) -> (Vec<ChatHistoryEntry>, Option<String>) {
+    /// 获取当前项目最近一次增强结果对应的历史 ID
+    fn last_enhance_id(&self) -> Option<String> {
+        let project_root = self.project_root.as_ref()?;
+        self.chat_history_manager(project_root).ok()?.last_enhance_id()
+    }
+
+    /// 加载对话历史（`exclude_id` 指定的条目不会被注入）
+    fn load_chat_history(
+        &self,
+        count: usize,
+        selected_ids: Option<&[String]>,
+        exclude_id: Option<&str>,
+    ) -> (Vec<ChatHistoryEntry>, Option<String>) {
         let project_root = match &self.project_root {
             Some(path) => path.clone(),
             None => return (Vec::new(), None),
         };
+        let keep = |entry: &ChatHistoryEntry| exclude_id != Some(entry.request_id.as_str());
 
-        match ChatHistoryManager::new(&project_root) {
+        match self.chat_history_manager(&project_root) {
             Ok(manager) => {
                 if let Some(ids) = selected_ids {
                     if ids.is_empty() {
                         return (Vec::new(), None);
                     }
                     return match manager.to_api_format_by_ids(ids) {
-                        Ok(v) => (v, None),
+                        Ok(v) => {
+                            // 中文注释：显式选择同样受条数上限约束，保留最近的条目
+                            let mut kept: Vec<ChatHistoryEntry> = v.into_iter().filter(keep).collect();
+                            if kept.len() > count {
+                                kept.drain(..kept.len() - count);
+                            }
+                            (kept, None)
+                        }
                         Err(e) => {
                             log_debug!("加载对话历史失败: {}", e);
                             (Vec::new(), Some(e.to_string()))
                         }
                     };
                 }
-                match manager.to_api_format(count) {
-                    Ok(v) => (v, None),
+                // 中文注释：需要排除条目时多取一条，保证注入条数不因排除而减少
+                let fetch_count = if exclude_id.is_some() { count + 1 } else { count };
+                match manager.to_api_format(fetch_count) {
+                    Ok(v) => {
+                        let mut kept: Vec<ChatHistoryEntry> = v.into_iter().filter(keep).collect();
+                        if kept.len() > count {
+                            kept.drain(..kept.len() - count);
+                        }
+                        (kept, None)
+                    }
                     Err(e) => {
                         log_debug!("加载对话历史失败: {}", e);
                         (Vec::new(), Some(e.to_string()))
@@ -252,7 +733,7 @@ impl PromptEnhancer {
     }
 
     /// 构造“历史为空兜底”的临时历史条目
-    fn build_fallback_history_entry(prompt: &str) -> Option<ChatHistoryEntry> {
+    fn build_fallback_history_entry(&self, prompt: &str) -> Option<ChatHistoryEntry> {
         let prompt = prompt.trim();
         if prompt.is_empty() {
             return None;
@@ -260,7 +741,7 @@ impl PromptEnhancer {
 
         // 中文注释：截断兜底内容，避免重复注入导致提示词过长
         let prompt = Self::truncate_text(prompt, MAX_FALLBACK_HISTORY_TEXT_LEN);
-        let request_id = format!("fallback_{}", uuid::Uuid::new_v4());
+        let request_id = format!("fallback_{}", self.id_source.next_id());
 
         Some(ChatHistoryEntry {
             request_message: prompt.clone(),
@@ -305,58 +786,106 @@ impl PromptEnhancer {
         truncated
     }
 
-    /// 构建 zhi 交互历史摘要（轻量补充上下文）
-    fn build_zhi_history_summary(&self, count: usize) -> (String, usize) {
+    /// 加载 zhi 交互历史问答对（已截断，跳过空条目）
+    ///
+    /// 指定 `selected_ids` 时按传入顺序使用对应条目，否则使用最近 `count` 条
+    fn load_zhi_history(&self, count: usize, selected_ids: Option<&[String]>) -> Vec<(String, String)> {
         let project_root = match &self.project_root {
             Some(path) => path.clone(),
-            None => return (String::new(), 0),
+            None => return Vec::new(),
         };
 
-        let manager = match ZhiHistoryManager::new(&project_root) {
+        let manager = match self.zhi_history_manager(&project_root) {
             Ok(manager) => manager,
             Err(e) => {
                 log_debug!("加载 zhi 历史失败: {}", e);
-                return (String::new(), 0);
+                return Vec::new();
             }
         };
 
-        let entries = manager.get_recent(count);
-        if entries.is_empty() {
-            return (String::new(), 0);
-        }
+        let entries = match selected_ids {
+            Some(ids) => manager.get_by_ids(&ids[..ids.len().min(count)]),
+            None => manager.get_recent(count),
+        };
+
+        entries
+            .iter()
+            .map(|entry| (
+                Self::truncate_text(&entry.prompt, MAX_ZHI_HISTORY_TEXT_LEN),
+                Self::truncate_text(&entry.user_reply, MAX_ZHI_HISTORY_TEXT_LEN),
+            ))
+            .filter(|(prompt, reply)| !prompt.is_empty() || !reply.is_empty())
+            .collect()
+    }
 
-        let mut lines = Vec::new();
-        for entry in entries {
-            let prompt = Self::truncate_text(&entry.prompt, MAX_ZHI_HISTORY_TEXT_LEN);
-            let reply = Self::truncate_text(&entry.user_reply, MAX_ZHI_HISTORY_TEXT_LEN);
-            if prompt.is_empty() && reply.is_empty() {
+    /// 构建 zhi 交互历史摘要（轻量补充上下文）
+    fn format_zhi_summary(items: &[(String, String)]) -> String {
+        items
+            .iter()
+            .map(|(prompt, reply)| format!("- Q: {}\n  A: {}", prompt, reply))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 摘要模式：将对话历史与 zhi 摘要合并为一段要点列表（截断 + 相似去重，不调用 API）
+    fn summarize_history(chat_history: &[ChatHistoryEntry], zhi_items: &[(String, String)]) -> String {
+        let chat_items = chat_history
+            .iter()
+            // 中文注释：兜底条目即当前输入本身，摘要中无需重复
+            .filter(|entry| !entry.request_id.starts_with("fallback_"))
+            .map(|entry| {
+                let reply = entry.response_nodes
+                    .iter()
+                    .filter_map(|node| node.content.as_deref())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                (entry.request_message.clone(), reply)
+            });
+
+        let mut lines: Vec<String> = Vec::new();
+        for (question, answer) in chat_items.chain(zhi_items.iter().cloned()) {
+            let text = match (question.trim(), answer.trim()) {
+                ("", "") => continue,
+                (q, "") => q.to_string(),
+                ("", a) => a.to_string(),
+                (q, a) => format!("{} → {}", q, a),
+            };
+            let line = Self::truncate_text(&text, MAX_SUMMARY_LINE_LEN);
+            if lines.iter().any(|existing| TextSimilarity::calculate_enhanced(existing, &line) >= SUMMARY_DEDUP_SIMILARITY) {
                 continue;
             }
-            lines.push(format!("- Q: {}\n  A: {}", prompt, reply));
-        }
-
-        if lines.is_empty() {
-            return (String::new(), 0);
+            lines.push(line);
         }
 
-        (lines.join("\n"), lines.len())
+        lines.iter().map(|line| format!("- {}", line)).collect::<Vec<_>>().join("\n")
     }
 
     /// 构建 chat-stream 请求体
-    fn build_request_payload(
-        &self,
-        prompt: &str,
-        original_prompt: Option<&str>,
-        current_file: Option<&str>,
-        include_history: bool,
-        selected_history_ids: Option<&[String]>,
-        blob_names: &[String],
-    ) -> BuildPayloadResult {
+    fn build_request_payload(&self, request: &EnhanceRequest, blob_names: &[String]) -> BuildPayloadResult {
+        let mut prompt = request.prompt.clone();
+        let original_prompt = request.original_prompt.as_deref();
+        let current_file = request.current_file_path.as_deref();
+        let include_history = request.include_history;
+        let selected_history_ids = request.selected_history_ids.as_deref();
+        let selected_zhi_ids = request.selected_zhi_ids.as_deref();
+        // 中文注释：请求级条数覆盖配置，两者均受上限约束，避免上下文膨胀
+        let history_limit = request.history_limit
+            .map_or(self.history_entries, |n| n.min(MAX_API_HISTORY_ENTRIES));
+        let zhi_history_limit = request.zhi_history_limit
+            .map_or(self.zhi_history_entries, |n| n.min(MAX_API_HISTORY_ENTRIES));
+
+        // 中文注释：重新生成时排除上一次增强结果，避免历史注入让输出趋同
+        let exclude_history_id = if request.regenerate {
+            self.last_enhance_id()
+        } else {
+            None
+        };
+
         // 支持按 ID 过滤对话历史，未指定则使用最近历史
         let history_enabled = include_history
             && selected_history_ids.map(|ids| !ids.is_empty()).unwrap_or(true);
         let (mut chat_history, history_load_error) = if history_enabled {
-            self.load_chat_history(5, selected_history_ids) // 最多5条历史
+            self.load_chat_history(history_limit, selected_history_ids, exclude_history_id.as_deref())
         } else {
             (Vec::new(), None)
         };
@@ -365,37 +894,76 @@ impl PromptEnhancer {
         // 中文注释：兜底——历史为空时，用当前输入构造 1 条临时历史，确保上下文链路不断
         let mut history_fallback_used = false;
         if history_enabled && chat_history.is_empty() {
-            let fallback_text = original_prompt.unwrap_or(prompt);
-            if let Some(entry) = Self::build_fallback_history_entry(fallback_text) {
+            let fallback_text = original_prompt.unwrap_or(&request.prompt);
+            if let Some(entry) = self.build_fallback_history_entry(fallback_text) {
                 chat_history.push(entry);
                 history_fallback_used = true;
             }
         }
 
-        let (zhi_summary, zhi_count) = if history_enabled {
-            self.build_zhi_history_summary(MAX_ZHI_HISTORY_ENTRIES)
+        // 中文注释：显式指定 zhi 历史时独立于对话历史的选择，仅受 include_history 控制
+        let zhi_enabled = match selected_zhi_ids {
+            Some(ids) => include_history && !ids.is_empty(),
+            None => history_enabled,
+        };
+        let zhi_items = if zhi_enabled {
+            self.load_zhi_history(zhi_history_limit, selected_zhi_ids)
         } else {
-            (String::new(), 0)
+            Vec::new()
         };
+        let zhi_count = zhi_items.len();
+        let mut zhi_summary = Self::format_zhi_summary(&zhi_items);
+
+        // 中文注释：verbose 模式记录注入的历史条目 ID（兜底条目不计入）
+        let mut injected_ids: Vec<String> = if request.verbose && !history_fallback_used {
+            chat_history.iter().map(|e| e.request_id.clone()).collect()
+        } else {
+            Vec::new()
+        };
+
+        if request.history_mode == HistoryMode::Summary {
+            zhi_summary = Self::summarize_history(&chat_history, &zhi_items);
+            chat_history.clear();
+        }
 
         log_important!(
             info,
-            "构建增强请求: blob_count={}, history_count={}, history_fallback_used={}, zhi_history_count={}",
+            "构建增强请求: blob_count={}, history_count={}, history_fallback_used={}, zhi_history_count={}, regenerate={}",
             blob_names.len(),
             loaded_history_count,
             history_fallback_used,
-            zhi_count
+            zhi_count,
+            request.regenerate
         );
 
-        // 构建完整消息（系统提示词 + 历史摘要 + 原始提示词）
-        let mut full_message = String::new();
-        full_message.push_str(ENHANCE_SYSTEM_PROMPT);
-        if !zhi_summary.is_empty() {
-            full_message.push_str("\n\n[最近交互摘要]\n");
-            full_message.push_str(&zhi_summary);
-            full_message.push_str("\n\n");
+        // 中文注释：超出长度上限时按 zhi 摘要 → 对话历史 → 提示词中段的顺序裁剪
+        let prompt_trim = self.fit_prompt_budget(
+            self.max_prompt_chars,
+            &mut zhi_summary,
+            &mut chat_history,
+            &mut prompt,
+            request.regenerate,
+        );
+        if let Some(ref trim) = prompt_trim {
+            log_important!(warn, "增强请求超出长度上限，已裁剪: {:?}", trim);
+            // 中文注释：裁剪从最早的历史开始移除，明细同步剔除
+            let dropped = trim.history_entries_dropped.min(injected_ids.len());
+            injected_ids.drain(..dropped);
         }
-        full_message.push_str(prompt);
+
+        let full_message = self.compose_message(&zhi_summary, &prompt, request.regenerate);
+        // 中文注释：多轮迭代时复用前端回传的会话 ID，否则每次生成新 ID
+        let conversation_id = request.conversation_id.as_deref()
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| self.id_source.next_id());
+        // 中文注释：请求级覆盖已在入口校验，这里直接使用
+        let persona_type = request.persona_type.unwrap_or(self.persona_type);
+        let payload_mode = request.mode.as_deref()
+            .map(|m| m.trim().to_uppercase())
+            .filter(|m| !m.is_empty())
+            .unwrap_or_else(|| self.payload_mode.clone());
 
         let payload = json!({
             "model": "claude-sonnet-4-5",
@@ -431,13 +999,13 @@ impl PromptEnhancer {
                     }
                 }
             ],
-            "mode": "CHAT",
+            "mode": payload_mode,
             "agent_memories": null,
-            "persona_type": 1,
+            "persona_type": persona_type,
             "rules": [],
             "silent": true,
             "third_party_override": null,
-            "conversation_id": uuid::Uuid::new_v4().to_string(),
+            "conversation_id": conversation_id,
             "canvas_id": null
         });
 
@@ -447,8 +1015,211 @@ impl PromptEnhancer {
                 loaded_count: loaded_history_count,
                 load_error: history_load_error,
                 fallback_used: history_fallback_used,
+                injected_ids,
+                zhi_count,
             },
+            prompt_trim,
+            conversation_id,
+        }
+    }
+
+    /// 汇总注入上下文明细（仅 verbose 请求返回）
+    fn injected_context_summary(
+        request: &EnhanceRequest,
+        diag: &HistoryBuildDiagnostics,
+        blob_source_root: Option<&str>,
+        blob_count: usize,
+    ) -> Option<InjectedContextSummary> {
+        if !request.verbose {
+            return None;
+        }
+        Some(InjectedContextSummary {
+            blob_source_root: blob_source_root.map(str::to_string),
+            blob_count,
+            history_ids: diag.injected_ids.clone(),
+            zhi_count: diag.zhi_count,
+            history_fallback_used: diag.fallback_used,
+            summary_mode: request.history_mode == HistoryMode::Summary,
+        })
+    }
+
+    /// 组装 dry_run 返回内容（请求头中的 token 已脱敏）
+    fn dry_run_payload(url: &str, payload: serde_json::Value) -> serde_json::Value {
+        json!({
+            "url": url,
+            "headers": {
+                "Authorization": "Bearer ***",
+                "Content-Type": "application/json"
+            },
+            "body": payload
+        })
+    }
+
+    /// 开启追踪时转储请求体与原始 SSE 响应（token 已脱敏）
+    fn trace(&self, request_id: &str, url: &str, payload: &serde_json::Value, raw_sse: Option<&str>) {
+        if let Some(raw) = raw_sse {
+            write_enhance_trace(request_id, url, payload, raw, &self.token);
+        }
+    }
+
+    /// 请求指定 record_to 或设置了录制目录时录制原始 SSE 响应；录制失败仅记录日志，不影响增强结果
+    fn record(record_to: Option<&Path>, raw_sse: Option<&str>) {
+        if let (Some(path), Some(raw)) = (record_to, raw_sse) {
+            match record_raw_sse(path, raw) {
+                Ok(()) => log_debug!("原始 SSE 响应已录制: {}", path.display()),
+                Err(e) => log_important!(warn, "录制原始 SSE 响应失败: {}", e),
+            }
+        }
+    }
+
+    /// 离线回放录制的 SSE 响应（不访问网络），按当前增强器的后处理步骤输出，用于将异常响应固化为回归用例
+    pub fn replay_recorded(&self, path: impl AsRef<Path>) -> Result<RecordedReplay> {
+        let raw = fs::read_to_string(path.as_ref())?;
+        Ok(Self::replay_sse(&raw, &self.post_process))
+    }
+
+    /// 按在线请求相同的拆行、累积与提取逻辑处理完整的原始 SSE 文本
+    fn replay_sse(raw: &str, post_process: &[PostProcessStep]) -> RecordedReplay {
+        let mut accumulated_text = String::new();
+        let mut usage: Option<TokenUsage> = None;
+        let mut sse_buffer = String::new();
+        let mut on_line = |line: &str| {
+            if let Some(json) = Self::parse_sse_json_line(line) {
+                if let Some(text_chunk) = json.get("text").and_then(|t| t.as_str()) {
+                    accumulated_text.push_str(text_chunk);
+                }
+                Self::collect_usage(&mut usage, &json);
+            }
+        };
+        Self::drain_sse_lines(&mut sse_buffer, raw, &mut on_line);
+        if !sse_buffer.trim().is_empty() {
+            on_line(&sse_buffer);
+        }
+
+        let enhanced_prompt = Self::extract_enhanced_prompt(&accumulated_text)
+            .map(|prompt| apply_post_process(&prompt, post_process))
+            .unwrap_or_default();
+        let (enhanced_prompt, truncated, error) = Self::classify_extraction(enhanced_prompt, &accumulated_text);
+        RecordedReplay {
+            enhanced_prompt,
+            accumulated_text,
+            truncated,
+            error,
+            usage,
+        }
+    }
+
+    /// 当前使用的系统提示词
+    fn system_prompt(&self) -> &'static str {
+        if self.strict_no_tools {
+            ENHANCE_SYSTEM_PROMPT
+        } else {
+            ENHANCE_SYSTEM_PROMPT_RELAXED.as_str()
+        }
+    }
+
+    /// 构建完整消息（系统提示词 + 历史摘要 + 原始提示词）
+    fn compose_message(&self, zhi_summary: &str, prompt: &str, regenerate: bool) -> String {
+        let mut full_message = String::new();
+        full_message.push_str(self.system_prompt());
+        if !zhi_summary.is_empty() {
+            full_message.push_str("\n\n[最近交互摘要]\n");
+            full_message.push_str(zhi_summary);
+            full_message.push_str("\n\n");
+        }
+        full_message.push_str(prompt);
+        if regenerate {
+            full_message.push_str(REGENERATE_INSTRUCTION);
         }
+        full_message
+    }
+
+    /// 单条历史计入长度预算的字符数
+    fn history_entry_chars(entry: &ChatHistoryEntry) -> usize {
+        entry.request_message.chars().count()
+            + entry.response_nodes
+                .iter()
+                .filter_map(|node| node.content.as_deref())
+                .map(|content| content.chars().count())
+                .sum::<usize>()
+    }
+
+    /// 将消息与注入历史控制在 `max_chars` 内，返回裁剪报告（未超限时为 None）
+    ///
+    /// 裁剪顺序：zhi 摘要（从尾部截断，不够则整段移除）→ 最早的对话历史 → 提示词中段（保留首尾）；
+    /// 固定模板本身已超限时不再截断提示词，并在报告中标记 `exceeds_budget`
+    fn fit_prompt_budget(
+        &self,
+        max_chars: usize,
+        zhi_summary: &mut String,
+        chat_history: &mut Vec<ChatHistoryEntry>,
+        prompt: &mut String,
+        regenerate: bool,
+    ) -> Option<PromptTrimReport> {
+        let total = |zhi: &str, history: &[ChatHistoryEntry], prompt: &str| {
+            self.compose_message(zhi, prompt, regenerate).chars().count()
+                + history.iter().map(Self::history_entry_chars).sum::<usize>()
+        };
+
+        let mut current = total(zhi_summary, chat_history, prompt);
+        if current <= max_chars {
+            return None;
+        }
+        let mut report = PromptTrimReport { max_chars, ..Default::default() };
+
+        if !zhi_summary.is_empty() {
+            let zhi_len = zhi_summary.chars().count();
+            let excess = current - max_chars;
+            if excess < zhi_len {
+                *zhi_summary = zhi_summary.chars().take(zhi_len - excess).collect();
+            } else {
+                zhi_summary.clear();
+            }
+            report.zhi_summary_chars_removed = zhi_len - zhi_summary.chars().count();
+            current = total(zhi_summary, chat_history, prompt);
+        }
+
+        while current > max_chars && !chat_history.is_empty() {
+            chat_history.remove(0);
+            report.history_entries_dropped += 1;
+            current = total(zhi_summary, chat_history, prompt);
+        }
+
+        // 中文注释：固定模板本身超限时，即使清空提示词也无法满足上限，保留提示词交由调用方报错
+        if current > max_chars && total(zhi_summary, chat_history, "") > max_chars {
+            report.exceeds_budget = true;
+            return Some(report);
+        }
+
+        if current > max_chars {
+            let prompt_len = prompt.chars().count();
+            let keep = prompt_len.saturating_sub(current - max_chars);
+            let (truncated, removed) = Self::truncate_middle(prompt, keep);
+            *prompt = truncated;
+            report.prompt_chars_removed = removed;
+        }
+
+        Some(report)
+    }
+
+    /// 截断文本中段（保留首尾），结果不超过 `max_chars` 个字符；返回截断后文本与移除的字符数
+    fn truncate_middle(text: &str, max_chars: usize) -> (String, usize) {
+        let chars: Vec<char> = text.chars().collect();
+        if chars.len() <= max_chars {
+            return (text.to_string(), 0);
+        }
+
+        let marker = |removed: usize| format!("\n...[已省略 {} 字符]...\n", removed);
+        // 以最大可能移除数估算标记长度，保证结果不超限
+        let keep = max_chars.saturating_sub(marker(chars.len()).chars().count());
+        let head = keep / 2;
+        let tail = keep - head;
+        let removed = chars.len() - keep;
+
+        let mut result: String = chars[..head].iter().collect();
+        result.push_str(&marker(removed));
+        result.extend(&chars[chars.len() - tail..]);
+        (result, removed)
     }
 
     /// 从响应文本中提取增强后的提示词
@@ -460,6 +1231,32 @@ impl PromptEnhancer {
             .map(|m| m.as_str().trim().to_string())
     }
 
+    /// 检测“已开始但未闭合”的增强结果（上游中途断开），返回已接收的部分内容
+    pub fn extract_truncated_prompt(text: &str) -> Option<String> {
+        let start = text.rfind(ENHANCED_PROMPT_OPEN_TAG)? + ENHANCED_PROMPT_OPEN_TAG.len();
+        let partial = &text[start..];
+        if partial.contains(ENHANCED_PROMPT_CLOSE_TAG) {
+            return None;
+        }
+        Some(partial.trim().to_string())
+    }
+
+    /// 根据提取结果确定返回的提示词、截断标记与错误信息
+    ///
+    /// 未提取到完整结果但存在未闭合标签时，返回部分内容并标记为截断，便于前端提示重试
+    fn classify_extraction(enhanced_prompt: String, accumulated_text: &str) -> (String, bool, Option<String>) {
+        if !enhanced_prompt.is_empty() {
+            return (enhanced_prompt, false, None);
+        }
+        match Self::extract_truncated_prompt(accumulated_text) {
+            Some(partial) => {
+                log_important!(warn, "增强结果被截断：已接收 {} 字符", partial.chars().count());
+                (partial, true, Some(TRUNCATED_OUTPUT_MESSAGE.to_string()))
+            }
+            None => (String::new(), false, Some("未能从响应中提取增强结果".to_string())),
+        }
+    }
+
     /// 解析 SSE 单行（兼容 data: 前缀）
     fn parse_sse_json_line(line: &str) -> Option<serde_json::Value> {
         let trimmed = line.trim();
@@ -472,6 +1269,13 @@ impl PromptEnhancer {
         serde_json::from_str::<serde_json::Value>(payload).ok()
     }
 
+    /// 累积 SSE 事件中的 token 用量（上游未发送用量时保持 None）
+    fn collect_usage(usage: &mut Option<TokenUsage>, event: &serde_json::Value) {
+        if let Some(parsed) = TokenUsage::from_sse_event(event) {
+            *usage = Some(usage.unwrap_or_default().merge(parsed));
+        }
+    }
+
     /// 处理跨分片的 SSE 行，保留尾部未完整行
     fn drain_sse_lines<F>(buffer: &mut String, chunk: &str, mut on_line: F)
     where
@@ -486,79 +1290,163 @@ impl PromptEnhancer {
         *buffer = remainder.to_string();
     }
 
-    /// 同步增强（等待完成后返回）
-    pub async fn enhance(&self, request: EnhanceRequest) -> Result<EnhanceResponse> {
+    /// 检查流式缓冲是否超限，超限时返回错误信息
+    fn check_stream_limits(&self, sse_buffer: &str, accumulated_text: &str) -> Option<String> {
+        if sse_buffer.len() > self.max_sse_line_bytes {
+            return Some(format!(
+                "流式响应单行超过 {} 字节仍未结束，已中止",
+                self.max_sse_line_bytes
+            ));
+        }
+        if accumulated_text.len() > self.max_response_bytes {
+            return Some(format!(
+                "流式响应超过 {} 字节上限，已中止",
+                self.max_response_bytes
+            ));
+        }
+        None
+    }
+
+    /// 上下文预检：请求了历史或自动加载 blob，却缺少项目根路径/项目未索引时返回警告，避免静默使用空上下文
+    pub fn context_preflight(
+        request: &EnhanceRequest,
+        project_root: Option<&str>,
+        blob_source_root: Option<&str>,
+    ) -> Option<ContextWarning> {
+        // 中文注释：显式指定 blob_names 时不依赖项目索引
+        let wants_project_blobs = request.blob_names.is_none();
+        let warning = match project_root {
+            None if request.include_history || wants_project_blobs => Some(ContextWarning::NoProjectRoot),
+            Some(_) if wants_project_blobs && blob_source_root.is_none() => Some(ContextWarning::ProjectNotIndexed),
+            _ => None,
+        };
+        if let Some(warning) = warning {
+            log_important!(warn, "增强上下文预检: {}", warning.message());
+        }
+        warning
+    }
+
+    /// 预处理请求：校验选项、生成 request_id、构建请求体，并以请求上下文构造响应基准
+    fn prepare_request(&self, request: &EnhanceRequest) -> Result<PreparedRequest> {
+        validate_payload_options(request.persona_type, request.mode.as_deref())?;
         // 中文注释：为每次请求生成稳定的 request_id，便于前后端关联
         let request_id = request.request_id.clone()
-            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+            .unwrap_or_else(|| self.id_source.next_id());
 
         // 预加载 blob 信息，便于返回给前端展示来源与数量
-        let (blob_names, blob_source_root) = self.load_blob_names();
+        let (blob_names, blob_source_root) = self.resolve_blob_names(request.blob_names.as_deref());
         let blob_count = blob_names.len();
         let project_root_path = request.project_root_path.clone().or(self.project_root.clone());
-
-        let build = self.build_request_payload(
-            &request.prompt,
-            request.original_prompt.as_deref(),
-            request.current_file_path.as_deref(),
-            request.include_history,
-            request.selected_history_ids.as_deref(),
-            &blob_names,
+        let context_warning = Self::context_preflight(request, project_root_path.as_deref(), blob_source_root.as_deref());
+
+        let build = self.build_request_payload(request, &blob_names);
+        if build.prompt_trim.as_ref().is_some_and(|trim| trim.exceeds_budget) {
+            anyhow::bail!(
+                "增强请求长度上限（{} 字符）小于固定模板长度，请调大 enhance_max_prompt_chars",
+                self.max_prompt_chars
+            );
+        }
+        let injected_context = Self::injected_context_summary(
+            request,
+            &build.history_diag,
+            blob_source_root.as_deref(),
+            blob_count,
         );
-        let history_count = build.history_diag.loaded_count;
-        let history_load_error = build.history_diag.load_error.clone();
-        let history_fallback_used = build.history_diag.fallback_used;
-        let payload = build.payload;
-        // 中文注释：返回给前端的“原始提示词”优先使用传入的 original_prompt
-        let response_original_prompt = request.original_prompt.clone()
-            .unwrap_or_else(|| request.prompt.clone());
-
-        let url = format!("{}/chat-stream", self.base_url);
+        let base = EnhanceResponse {
+            // 中文注释：返回给前端的“原始提示词”优先使用传入的 original_prompt
+            original_prompt: request.original_prompt.clone()
+                .unwrap_or_else(|| request.prompt.clone()),
+            blob_count,
+            history_count: build.history_diag.loaded_count,
+            history_load_error: build.history_diag.load_error.clone(),
+            history_fallback_used: build.history_diag.fallback_used,
+            project_root_path,
+            blob_source_root,
+            request_id: Some(request_id.clone()),
+            prompt_trim: build.prompt_trim,
+            conversation_id: Some(build.conversation_id),
+            context_warning,
+            injected_context,
+            ..Default::default()
+        };
+
+        Ok(PreparedRequest {
+            request_id,
+            url: join_endpoint(&self.base_url, "chat-stream"),
+            payload: build.payload,
+            base,
+        })
+    }
+
+    /// 同步增强（等待完成后返回）
+    pub async fn enhance(&self, request: EnhanceRequest) -> Result<EnhanceResponse> {
+        let PreparedRequest { request_id, url, payload, base } = self.prepare_request(&request)?;
+
+        if request.dry_run {
+            return Ok(base.with_dry_run_payload(Self::dry_run_payload(&url, payload)));
+        }
+
         log_important!(info, "发送增强请求: url={}", url);
 
-        let response = self.client
+        let send_result = self.client
             .post(&url)
             .header(AUTHORIZATION, format!("Bearer {}", self.token))
             .header(CONTENT_TYPE, "application/json")
             .json(&payload)
             .send()
-            .await?;
+            .await;
+
+        // 中文注释：网络不可达或服务端 5xx 时，按配置决定是否使用离线兜底
+        let failure = |error: SanshuError| base.clone().failed_with(error);
+
+        let response = match send_result {
+            Ok(response) => response,
+            Err(e) if self.offline_fallback => {
+                return Ok(self.network_failure_response(failure(SanshuError::from(e))));
+            }
+            Err(e) => return Err(SanshuError::from(e).into()),
+        };
 
         if !response.status().is_success() {
             let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Ok(EnhanceResponse {
-                enhanced_prompt: String::new(),
-                original_prompt: response_original_prompt.clone(),
-                success: false,
-                error: Some(format!("HTTP {} - {}", status, body)),
-                blob_count,
-                history_count,
-                history_load_error,
-                history_fallback_used,
-                project_root_path,
-                blob_source_root,
-                request_id: Some(request_id),
-            });
+            let failed = failure(Self::upstream_error(response).await);
+            if status.is_server_error() {
+                return Ok(self.network_failure_response(failed));
+            }
+            return Ok(failed);
         }
 
         // 处理 SSE 流式响应
         let mut accumulated_text = String::new();
         let mut stream = response.bytes_stream();
         let mut sse_buffer = String::new();
+        // 中文注释：仅在开启追踪或录制时保留原始 SSE 文本，避免常规请求的额外内存占用
+        let record_path = resolve_record_path(request.record_to.as_deref(), &request_id);
+        let mut raw_sse = (trace_enabled() || record_path.is_some()).then(String::new);
+        let mut usage: Option<TokenUsage> = None;
 
         while let Some(chunk_result) = stream.next().await {
             match chunk_result {
                 Ok(bytes) => {
                     let text = String::from_utf8_lossy(&bytes);
+                    if let Some(raw) = raw_sse.as_mut() {
+                        raw.push_str(&text);
+                    }
                     // 使用缓冲拆行，避免 JSON 跨分片丢失
                     Self::drain_sse_lines(&mut sse_buffer, &text, |line| {
                         if let Some(json) = Self::parse_sse_json_line(line) {
                             if let Some(text_chunk) = json.get("text").and_then(|t| t.as_str()) {
                                 accumulated_text.push_str(text_chunk);
                             }
+                            Self::collect_usage(&mut usage, &json);
                         }
                     });
+                    if let Some(error_msg) = self.check_stream_limits(&sse_buffer, &accumulated_text) {
+                        log_important!(warn, "{}", error_msg);
+                        self.trace(&request_id, &url, &payload, raw_sse.as_deref());
+                        Self::record(record_path.as_deref(), raw_sse.as_deref());
+                        return Ok(base.clone().failed(error_msg));
+                    }
                 }
                 Err(e) => {
                     log_debug!("读取流式响应失败: {}", e);
@@ -571,28 +1459,21 @@ impl PromptEnhancer {
                 if let Some(text_chunk) = json.get("text").and_then(|t| t.as_str()) {
                     accumulated_text.push_str(text_chunk);
                 }
+                Self::collect_usage(&mut usage, &json);
             }
         }
+        self.trace(&request_id, &url, &payload, raw_sse.as_deref());
+        Self::record(record_path.as_deref(), raw_sse.as_deref());
 
         // 提取增强后的提示词
         let enhanced_prompt = Self::extract_enhanced_prompt(&accumulated_text)
+            .map(|prompt| apply_post_process(&prompt, &self.post_process))
             .unwrap_or_default();
 
         let success = !enhanced_prompt.is_empty();
+        let (enhanced_prompt, truncated, error) = Self::classify_extraction(enhanced_prompt, &accumulated_text);
 
-        Ok(EnhanceResponse {
-            enhanced_prompt,
-            original_prompt: response_original_prompt,
-            success,
-            error: if success { None } else { Some("未能从响应中提取增强结果".to_string()) },
-            blob_count,
-            history_count,
-            history_load_error,
-            history_fallback_used,
-            project_root_path,
-            blob_source_root,
-            request_id: Some(request_id),
-        })
+        Ok(base.completed(enhanced_prompt, success, truncated, error, usage))
     }
 
     /// 流式增强（通过回调函数推送进度）
@@ -600,62 +1481,54 @@ impl PromptEnhancer {
     where
         F: FnMut(EnhanceStreamEvent) + Send,
     {
-        // 中文注释：为每次请求生成稳定的 request_id，便于前后端关联
-        let request_id = request.request_id.clone()
-            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let PreparedRequest { request_id, url, payload, base } = self.prepare_request(&request)?;
         let cancel_flag = request.cancel_flag.clone();
 
-        // 预加载 blob 信息，便于返回给前端展示来源与数量
-        let (blob_names, blob_source_root) = self.load_blob_names();
-        let blob_count = blob_names.len();
-        let project_root_path = request.project_root_path.clone().or(self.project_root.clone());
+        // 中文注释：dry_run 仅返回构建结果，不推送流式事件
+        if request.dry_run {
+            return Ok(base.with_dry_run_payload(Self::dry_run_payload(&url, payload)));
+        }
 
-        let build = self.build_request_payload(
-            &request.prompt,
-            request.original_prompt.as_deref(),
-            request.current_file_path.as_deref(),
-            request.include_history,
-            request.selected_history_ids.as_deref(),
-            &blob_names,
-        );
-        let history_count = build.history_diag.loaded_count;
-        let history_load_error = build.history_diag.load_error.clone();
-        let history_fallback_used = build.history_diag.fallback_used;
-        let payload = build.payload;
-        // 中文注释：返回给前端的“原始提示词”优先使用传入的 original_prompt
-        let response_original_prompt = request.original_prompt.clone()
-            .unwrap_or_else(|| request.prompt.clone());
-
-        let url = format!("{}/chat-stream", self.base_url);
         log_important!(info, "发送流式增强请求: url={}", url);
 
-        let response = self.client
+        let send_result = self.client
             .post(&url)
             .header(AUTHORIZATION, format!("Bearer {}", self.token))
             .header(CONTENT_TYPE, "application/json")
             .json(&payload)
             .send()
-            .await?;
+            .await;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            let error_msg = format!("HTTP {} - {}", status, body);
-            on_event(EnhanceStreamEvent::error(&request_id, &error_msg));
-            return Ok(EnhanceResponse {
-                enhanced_prompt: String::new(),
-                original_prompt: response_original_prompt.clone(),
-                success: false,
-                error: Some(error_msg),
-                blob_count,
-                history_count,
-                history_load_error,
-                history_fallback_used,
-                project_root_path,
-                blob_source_root,
-                request_id: Some(request_id),
-            });
-        }
+        // 中文注释：网络不可达或服务端 5xx 时，按配置决定是否使用离线兜底
+        let failure = |error: SanshuError| base.clone().failed_with(error);
+
+        let network_error = match send_result {
+            Ok(response) if response.status().is_success() => Ok(response),
+            Ok(response) => {
+                let server_error = response.status().is_server_error();
+                Err((Self::upstream_error(response).await, server_error))
+            }
+            Err(e) if self.offline_fallback => Err((SanshuError::from(e), true)),
+            Err(e) => return Err(SanshuError::from(e).into()),
+        };
+
+        let response = match network_error {
+            Ok(response) => response,
+            Err((error, unreachable)) => {
+                let failed = failure(error);
+                let result = if unreachable {
+                    self.network_failure_response(failed)
+                } else {
+                    failed
+                };
+                if result.success {
+                    on_event(EnhanceStreamEvent::complete(&request_id, &result.enhanced_prompt, &result.enhanced_prompt));
+                } else {
+                    on_event(EnhanceStreamEvent::error(&request_id, result.error.as_deref().unwrap_or_default()));
+                }
+                return Ok(result);
+            }
+        };
 
         // 处理 SSE 流式响应
         let mut accumulated_text = String::new();
@@ -665,6 +1538,9 @@ impl PromptEnhancer {
         let mut stream_failed = false;
         let mut stream_error: Option<String> = None;
         let mut cancelled = false;
+        let record_path = resolve_record_path(request.record_to.as_deref(), &request_id);
+        let mut raw_sse = (trace_enabled() || record_path.is_some()).then(String::new);
+        let mut usage: Option<TokenUsage> = None;
 
         while let Some(chunk_result) = stream.next().await {
             if let Some(flag) = &cancel_flag {
@@ -677,6 +1553,9 @@ impl PromptEnhancer {
             match chunk_result {
                 Ok(bytes) => {
                     let text = String::from_utf8_lossy(&bytes);
+                    if let Some(raw) = raw_sse.as_mut() {
+                        raw.push_str(&text);
+                    }
                     // 使用缓冲拆行，避免 JSON 跨分片丢失
                     Self::drain_sse_lines(&mut sse_buffer, &text, |line| {
                         if let Some(json) = Self::parse_sse_json_line(line) {
@@ -696,8 +1575,16 @@ impl PromptEnhancer {
                                     ));
                                 }
                             }
+                            Self::collect_usage(&mut usage, &json);
                         }
                     });
+                    if let Some(error_msg) = self.check_stream_limits(&sse_buffer, &accumulated_text) {
+                        log_important!(warn, "{}", error_msg);
+                        on_event(EnhanceStreamEvent::error(&request_id, &error_msg));
+                        stream_failed = true;
+                        stream_error = Some(error_msg);
+                        break;
+                    }
                 }
                 Err(e) => {
                     log_debug!("读取流式响应失败: {}", e);
@@ -710,38 +1597,15 @@ impl PromptEnhancer {
                 }
             }
         }
+        self.trace(&request_id, &url, &payload, raw_sse.as_deref());
+        Self::record(record_path.as_deref(), raw_sse.as_deref());
         // 中文注释：请求被取消时，停止后续解析与完成事件
         if cancelled {
-            let cancel_msg = "已取消增强请求".to_string();
-            on_event(EnhanceStreamEvent::error(&request_id, &cancel_msg));
-            return Ok(EnhanceResponse {
-                enhanced_prompt: String::new(),
-                original_prompt: response_original_prompt.clone(),
-                success: false,
-                error: Some(cancel_msg),
-                blob_count,
-                history_count,
-                history_load_error,
-                history_fallback_used,
-                project_root_path,
-                blob_source_root,
-                request_id: Some(request_id),
-            });
+            on_event(EnhanceStreamEvent::cancelled(&request_id));
+            return Ok(base.cancelled());
         }
         if stream_failed {
-            return Ok(EnhanceResponse {
-                enhanced_prompt: String::new(),
-                original_prompt: response_original_prompt.clone(),
-                success: false,
-                error: stream_error.or_else(|| Some("读取流式响应失败".to_string())),
-                blob_count,
-                history_count,
-                history_load_error,
-                history_fallback_used,
-                project_root_path,
-                blob_source_root,
-                request_id: Some(request_id),
-            });
+            return Ok(base.failed(stream_error.unwrap_or_else(|| "读取流式响应失败".to_string())));
         }
         // 处理最后残留的未换行片段
         if !sse_buffer.trim().is_empty() {
@@ -760,45 +1624,876 @@ impl PromptEnhancer {
                         ));
                     }
                 }
+                Self::collect_usage(&mut usage, &json);
             }
         }
 
         // 提取增强后的提示词
         let enhanced_prompt = Self::extract_enhanced_prompt(&accumulated_text)
+            .map(|prompt| apply_post_process(&prompt, &self.post_process))
             .unwrap_or_default();
 
         let success = !enhanced_prompt.is_empty();
+        let (enhanced_prompt, truncated, error) = Self::classify_extraction(enhanced_prompt, &accumulated_text);
 
-        if success {
-            on_event(EnhanceStreamEvent::complete(&request_id, &enhanced_prompt, &accumulated_text));
-        } else {
-            on_event(EnhanceStreamEvent::error(&request_id, "未能从响应中提取增强结果"));
+        match &error {
+            None => on_event(EnhanceStreamEvent::complete(&request_id, &enhanced_prompt, &accumulated_text).with_usage(usage)),
+            Some(e) => on_event(EnhanceStreamEvent::error(&request_id, e)),
         }
 
-        Ok(EnhanceResponse {
-            enhanced_prompt,
-            original_prompt: response_original_prompt,
-            success,
-            error: if success { None } else { Some("未能从响应中提取增强结果".to_string()) },
-            blob_count,
-            history_count,
-            history_load_error,
-            history_fallback_used,
-            project_root_path,
-            blob_source_root,
-            request_id: Some(request_id),
-        })
+        Ok(base.completed(enhanced_prompt, success, truncated, error, usage))
     }
 }
 
 /// 规范化 URL
+///
+/// 保留路径前缀（如 `https://host/api/v2`）与查询参数，仅去除片段与路径末尾斜杠
 fn normalize_base_url(input: &str) -> String {
     let mut url = input.trim().to_string();
     if !(url.starts_with("http://") || url.starts_with("https://")) {
         url = format!("https://{}", url);
     }
+    let mut query = None;
+    if let Ok(mut parsed) = reqwest::Url::parse(&url) {
+        query = parsed.query().filter(|q| !q.is_empty()).map(str::to_string);
+        parsed.set_query(None);
+        parsed.set_fragment(None);
+        url = parsed.to_string();
+    }
     while url.ends_with('/') {
         url.pop();
     }
-    url
+    match query {
+        Some(query) => format!("{}?{}", url, query),
+        None => url,
+    }
+}
+
+/// 在 base_url（可能带路径前缀与查询参数）后拼接接口路径，查询参数保留在末尾
+fn join_endpoint(base_url: &str, endpoint: &str) -> String {
+    let (base, query) = match base_url.split_once('?') {
+        Some((base, query)) => (base, Some(query)),
+        None => (base_url, None),
+    };
+    let joined = format!(
+        "{}/{}",
+        base.trim_end_matches('/'),
+        endpoint.trim_start_matches('/')
+    );
+    match query {
+        Some(query) => format!("{}?{}", joined, query),
+        None => joined,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::utils::{MemoryHistoryStore, SequentialRequestIdSource};
+    use crate::utils::http_mock::{MockResponse, MockServer};
+    use std::sync::atomic::AtomicBool;
+    use tempfile::TempDir;
+
+    fn test_request(prompt: &str, project_root: &str) -> EnhanceRequest {
+        EnhanceRequest {
+            prompt: prompt.to_string(),
+            original_prompt: None,
+            project_root_path: Some(project_root.to_string()),
+            current_file_path: None,
+            include_history: true,
+            selected_history_ids: None,
+            selected_zhi_ids: None,
+            regenerate: false,
+            blob_names: None,
+            request_id: None,
+            conversation_id: None,
+            history_limit: None,
+            zhi_history_limit: None,
+            history_mode: HistoryMode::Raw,
+            persona_type: None,
+            mode: None,
+            dry_run: false,
+            verbose: false,
+            record_to: None,
+            cancel_flag: None,
+        }
+    }
+
+    #[test]
+    fn test_country_code_selects_mapped_endpoint() {
+        let endpoints: HashMap<String, String> = [
+            ("CN".to_string(), "https://cn.example.com".to_string()),
+            ("us".to_string(), "https://us.example.com".to_string()),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            PromptEnhancer::select_regional_base_url("CN", &endpoints).as_deref(),
+            Some("https://cn.example.com")
+        );
+        assert_eq!(
+            PromptEnhancer::select_regional_base_url("US", &endpoints).as_deref(),
+            Some("https://us.example.com")
+        );
+        // 未知地区或未映射的国家回退到全局配置
+        assert!(PromptEnhancer::select_regional_base_url("UNKNOWN", &endpoints).is_none());
+        assert!(PromptEnhancer::select_regional_base_url("JP", &endpoints).is_none());
+    }
+
+    #[test]
+    fn test_regenerate_excludes_last_enhance_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+
+        let chat_store: Arc<dyn HistoryStore> = Arc::new(MemoryHistoryStore::new());
+        let manager = ChatHistoryManager::with_store(&project_root, chat_store.clone()).unwrap();
+        let popup_id = manager.add_entry("普通对话", "普通回复", "popup").unwrap();
+        let enhance_id = manager.add_entry("优化提示词", "上一次的增强结果", "enhance").unwrap();
+
+        let enhancer = PromptEnhancer::new("https://example.com", "token")
+            .unwrap()
+            .with_project_root(&project_root)
+            .with_history_stores(chat_store, Arc::new(MemoryHistoryStore::new()));
+
+        let mut request = test_request("优化提示词", &project_root);
+        let normal = enhancer.build_request_payload(&request, &[]);
+        request.regenerate = true;
+        let regenerated = enhancer.build_request_payload(&request, &[]);
+
+        let history_ids = |payload: &serde_json::Value| -> Vec<String> {
+            payload["chat_history"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|e| e["request_id"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(history_ids(&normal.payload), vec![popup_id.clone(), enhance_id.clone()]);
+        assert_eq!(history_ids(&regenerated.payload), vec![popup_id]);
+        assert!(regenerated.payload["message"].as_str().unwrap().ends_with(REGENERATE_INSTRUCTION));
+        assert!(!normal.payload["message"].as_str().unwrap().contains(REGENERATE_INSTRUCTION));
+    }
+
+    #[test]
+    fn test_summary_history_mode_injects_single_compact_block() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+
+        let chat_store: Arc<dyn HistoryStore> = Arc::new(MemoryHistoryStore::new());
+        let zhi_store: Arc<dyn HistoryStore> = Arc::new(MemoryHistoryStore::new());
+        let chat = ChatHistoryManager::with_store(&project_root, chat_store.clone()).unwrap();
+        chat.add_entry("实现登录页", "已完成登录表单", "popup").unwrap();
+        chat.add_entry("增加记住密码", "已添加复选框", "popup").unwrap();
+        chat.add_entry("修复样式", "已调整间距", "popup").unwrap();
+        let zhi = ZhiHistoryManager::with_store(&project_root, zhi_store.clone()).unwrap();
+        zhi.add_entry("req-1", "修复样式", "已调整间距", "popup").unwrap();
+
+        let enhancer = PromptEnhancer::new("https://example.com", "token")
+            .unwrap()
+            .with_project_root(&project_root)
+            .with_history_stores(chat_store, zhi_store);
+        let raw = enhancer.build_request_payload(&test_request("继续优化", &project_root), &[]);
+        let mut request = test_request("继续优化", &project_root);
+        request.history_mode = HistoryMode::Summary;
+        let summary = enhancer.build_request_payload(&request, &[]);
+
+        assert_eq!(raw.payload["chat_history"].as_array().unwrap().len(), 3);
+        assert!(summary.payload["chat_history"].as_array().unwrap().is_empty());
+        let message = summary.payload["message"].as_str().unwrap();
+        assert_eq!(message.matches("[最近交互摘要]").count(), 1);
+        // 三条对话历史 + 一条与之重复的 zhi 记录，去重后为三条要点
+        assert_eq!(message.matches("\n- ").count(), 3);
+        assert!(message.contains("- 修复样式 → 已调整间距"));
+        assert!(!message.contains("Q: "));
+    }
+
+    #[test]
+    fn test_configured_history_entries_limit_payload() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+
+        let chat_store: Arc<dyn HistoryStore> = Arc::new(MemoryHistoryStore::new());
+        let manager = ChatHistoryManager::with_store(&project_root, chat_store.clone()).unwrap();
+        for i in 0..6 {
+            manager.add_entry(&format!("问题{}", i), &format!("回答{}", i), "popup").unwrap();
+        }
+
+        let enhancer = PromptEnhancer::new("https://example.com", "token")
+            .unwrap()
+            .with_project_root(&project_root)
+            .with_history_stores(chat_store, Arc::new(MemoryHistoryStore::new()))
+            .with_history_entries(3, 3);
+        let configured = enhancer.build_request_payload(&test_request("优化提示词", &project_root), &[]);
+
+        // 请求级覆盖同样受上限约束
+        let mut request = test_request("优化提示词", &project_root);
+        request.history_limit = Some(100);
+        let overridden = enhancer.build_request_payload(&request, &[]);
+
+        assert_eq!(configured.history_diag.loaded_count, 3);
+        assert!(configured.payload["chat_history"].as_array().unwrap().len() <= 3);
+        assert_eq!(overridden.history_diag.loaded_count, 6);
+    }
+
+    /// 获取一个当前无人监听的本地地址（模拟 API 不可达）
+    fn unreachable_base_url() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+        format!("http://127.0.0.1:{}", port)
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_api_returns_labeled_offline_result() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        let base_url = unreachable_base_url();
+
+        let enhancer = PromptEnhancer::new(&base_url, "token")
+            .unwrap()
+            .with_offline_fallback(true);
+        let response = enhancer
+            .enhance(test_request("修复   登录 bug", &project_root))
+            .await
+            .unwrap();
+
+        assert!(response.success);
+        assert!(response.fallback_used);
+        assert!(response.error.is_none());
+        assert!(response.enhanced_prompt.starts_with("## 任务\n\n修复 登录 bug"));
+
+        // 未启用时保持原有失败行为，根因为网络错误
+        let strict = PromptEnhancer::new(&base_url, "token").unwrap();
+        let err = strict.enhance(test_request("修复登录 bug", &project_root)).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<SanshuError>(), Some(SanshuError::Network(_))));
+    }
+
+    #[tokio::test]
+    async fn test_upstream_status_reports_typed_failure() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        let server = MockServer::respond_with(MockResponse::status(401, "Unauthorized").with_body("bad token"));
+
+        let enhancer = PromptEnhancer::new(&server.url(""), "token").unwrap();
+        let mut request = test_request("优化提示词", &project_root);
+        request.include_history = false;
+        let response = enhancer.enhance(request).await.unwrap();
+
+        assert!(!response.success);
+        assert_eq!(response.error_kind.as_deref(), Some("upstream"));
+        let error = response.error.unwrap();
+        assert!(error.contains("401") && error.contains("bad token"), "{}", error);
+    }
+
+    /// 启动一个逐块推送原始响应体的本地服务器，块之间间隔一段时间
+    fn start_slow_sse_server(chunks: Vec<String>) -> String {
+        let response = MockResponse::status(200, "OK")
+            .with_header("Content-Type", "text/event-stream")
+            .with_chunks(chunks, std::time::Duration::from_millis(200));
+        MockServer::respond_with(response).url("")
+    }
+
+    /// 将 JSON 数据包装为 SSE 事件
+    fn sse_events(events: &[&str]) -> Vec<String> {
+        events.iter().map(|e| format!("data: {}\n\n", e)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_validate_config_reports_missing_token_and_reachability() {
+        let timeout = std::time::Duration::from_secs(5);
+
+        let missing = PromptEnhancer::validate_config(Some("api.example.com/"), Some("  "), true, timeout).await;
+        assert!(!missing.ok);
+        assert_eq!(missing.base_url.as_deref(), Some("https://api.example.com"));
+        assert_eq!(missing.reachable, None);
+        assert!(missing.message.contains("token"));
+        assert!(!PromptEnhancer::validate_config(None, Some("t"), false, timeout).await.ok);
+
+        let base_url = start_slow_sse_server(Vec::new());
+        let check = PromptEnhancer::validate_config(Some(&base_url), Some("secret-token"), true, timeout).await;
+        assert!(check.ok, "{}", check.message);
+        assert_eq!(check.reachable, Some(true));
+        assert!(!serde_json::to_string(&check).unwrap().contains("secret-token"));
+
+        let check = PromptEnhancer::validate_config(Some(&unreachable_base_url()), Some("secret-token"), true, timeout).await;
+        assert!(!check.ok);
+        assert_eq!(check.reachable, Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_mid_stream_reports_cancelled() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        let base_url = start_slow_sse_server(sse_events(&[
+            r#"{"text":"<augment-enhanced-prompt>第一段"}"#,
+            r#"{"text":"第二段"}"#,
+            r#"{"text":"</augment-enhanced-prompt>"}"#,
+        ]));
+
+        let flag = Arc::new(AtomicBool::new(false));
+        let mut request = test_request("优化提示词", &project_root);
+        request.include_history = false;
+        request.request_id = Some("req-cancel".to_string());
+        request.cancel_flag = Some(flag.clone());
+
+        let enhancer = PromptEnhancer::new(&base_url, "token").unwrap();
+        let mut event_types = Vec::new();
+        let response = enhancer
+            .enhance_stream(request, |event| {
+                // 收到第一块后模拟用户取消
+                if event.event_type == "chunk" {
+                    flag.store(true, Ordering::Relaxed);
+                }
+                event_types.push(event.event_type);
+            })
+            .await
+            .unwrap();
+
+        assert!(response.cancelled);
+        assert!(!response.success);
+        assert!(response.error.as_deref().map_or(true, |e| e.contains("取消")));
+        assert_eq!(event_types, vec!["chunk", "cancelled"]);
+    }
+
+    #[tokio::test]
+    async fn test_overlong_unterminated_sse_line_aborts_stream() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        // 上游持续输出却从不换行
+        let base_url = start_slow_sse_server(vec!["x".repeat(100), "x".repeat(100)]);
+
+        let enhancer = PromptEnhancer::new(&base_url, "token")
+            .unwrap()
+            .with_stream_limits(128, 1024);
+        let mut request = test_request("优化提示词", &project_root);
+        request.include_history = false;
+        let mut errors = Vec::new();
+        let response = enhancer
+            .enhance_stream(request, |event| {
+                if event.event_type == "error" {
+                    errors.push(event.error.unwrap_or_default());
+                }
+            })
+            .await
+            .unwrap();
+
+        assert!(!response.success);
+        assert!(response.error.as_deref().unwrap().contains("128"));
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_multi_chunk_stream_within_limits_succeeds() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        let base_url = start_slow_sse_server(sse_events(&[
+            r#"{"text":"<augment-enhanced-prompt>第一段"}"#,
+            r#"{"text":"，第二段"}"#,
+            r#"{"text":"</augment-enhanced-prompt>"}"#,
+        ]));
+
+        let enhancer = PromptEnhancer::new(&base_url, "token")
+            .unwrap()
+            .with_stream_limits(1024, 4096);
+        let mut request = test_request("优化提示词", &project_root);
+        request.include_history = false;
+        let response = enhancer.enhance_stream(request, |_| {}).await.unwrap();
+
+        assert!(response.success, "error: {:?}", response.error);
+        assert_eq!(response.enhanced_prompt, "第一段，第二段");
+        // 上游未发送用量时保持为空
+        assert_eq!(response.prompt_tokens, None);
+        assert_eq!(response.completion_tokens, None);
+    }
+
+    #[tokio::test]
+    async fn test_usage_event_populates_token_counts() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        let base_url = start_slow_sse_server(sse_events(&[
+            r#"{"text":"<augment-enhanced-prompt>结果</augment-enhanced-prompt>"}"#,
+            r#"{"text":"","nodes":[{"id":1,"type":0,"token_usage":{"input_tokens":1200,"output_tokens":85}}]}"#,
+        ]));
+
+        let enhancer = PromptEnhancer::new(&base_url, "token").unwrap();
+        let mut request = test_request("优化提示词", &project_root);
+        request.include_history = false;
+        let mut complete_usage = None;
+        let response = enhancer
+            .enhance_stream(request, |event| {
+                if event.event_type == "complete" {
+                    complete_usage = Some((event.prompt_tokens, event.completion_tokens));
+                }
+            })
+            .await
+            .unwrap();
+
+        assert!(response.success, "error: {:?}", response.error);
+        assert_eq!(response.prompt_tokens, Some(1200));
+        assert_eq!(response.completion_tokens, Some(85));
+        assert_eq!(complete_usage, Some((Some(1200), Some(85))));
+
+        let usage = TokenUsage::from_sse_event(&serde_json::json!({"usage": {"prompt_tokens": 7, "completion_tokens": 3}}));
+        assert_eq!(usage, Some(TokenUsage { prompt_tokens: Some(7), completion_tokens: Some(3) }));
+        assert_eq!(TokenUsage::from_sse_event(&serde_json::json!({"text": "x"})), None);
+    }
+
+    #[tokio::test]
+    async fn test_unclosed_marker_reports_truncated_partial_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        let base_url = start_slow_sse_server(sse_events(&[
+            r####"{"text":"### BEGIN RESPONSE ###\n<augment-enhanced-prompt>请重构登录"}"####,
+            r#"{"text":"模块并补充测试"}"#,
+        ]));
+
+        let enhancer = PromptEnhancer::new(&base_url, "token").unwrap();
+        let mut request = test_request("重构登录", &project_root);
+        request.include_history = false;
+        let mut error_event = None;
+        let response = enhancer
+            .enhance_stream(request, |event| {
+                if event.event_type == "error" {
+                    error_event = event.error.clone();
+                }
+            })
+            .await
+            .unwrap();
+
+        assert!(!response.success);
+        assert!(response.truncated);
+        assert_eq!(response.enhanced_prompt, "请重构登录模块并补充测试");
+        assert_eq!(response.error.as_deref(), Some(TRUNCATED_OUTPUT_MESSAGE));
+        assert_eq!(error_event.as_deref(), Some(TRUNCATED_OUTPUT_MESSAGE));
+
+        // 完整结果或无标签时不视为截断
+        assert!(PromptEnhancer::extract_truncated_prompt("<augment-enhanced-prompt>x</augment-enhanced-prompt>").is_none());
+        assert!(PromptEnhancer::extract_truncated_prompt("no marker").is_none());
+    }
+
+    #[test]
+    fn test_payload_persona_and_mode_overrides() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        let enhancer = PromptEnhancer::new("https://example.com", "token").unwrap();
+
+        let mut request = test_request("提示词", &project_root);
+        request.include_history = false;
+        let build = enhancer.build_request_payload(&request, &[]);
+        assert_eq!(build.payload["persona_type"], 1);
+        assert_eq!(build.payload["mode"], "CHAT");
+
+        request.persona_type = Some(0);
+        request.mode = Some("agent".to_string());
+        assert!(validate_payload_options(request.persona_type, request.mode.as_deref()).is_ok());
+        let build = enhancer.build_request_payload(&request, &[]);
+        assert_eq!(build.payload["persona_type"], 0);
+        assert_eq!(build.payload["mode"], "AGENT");
+
+        // 配置默认值在请求未覆盖时生效
+        let enhancer = enhancer.with_payload_options(Some(2), Some("AGENT".to_string()));
+        let build = enhancer.build_request_payload(&test_request("提示词", &project_root), &[]);
+        assert_eq!(build.payload["persona_type"], 2);
+        assert_eq!(build.payload["mode"], "AGENT");
+
+        assert!(validate_payload_options(Some(9), None).is_err());
+        let err = validate_payload_options(None, Some("linear")).unwrap_err();
+        assert!(err.to_string().contains("CHAT | AGENT"));
+    }
+
+    #[test]
+    fn test_injected_id_source_gives_stable_payload_ids() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+
+        let build_ids = || {
+            let enhancer = PromptEnhancer::new("https://example.com", "token")
+                .unwrap()
+                .with_project_root(&project_root)
+                .with_history_stores(Arc::new(MemoryHistoryStore::new()), Arc::new(MemoryHistoryStore::new()))
+                .with_id_source(Arc::new(SequentialRequestIdSource::new("test")));
+            let request = test_request("优化提示词", &project_root);
+            let payload = enhancer.build_request_payload(&request, &[]).payload;
+            (
+                payload["chat_history"][0]["request_id"].as_str().unwrap().to_string(),
+                payload["conversation_id"].as_str().unwrap().to_string(),
+            )
+        };
+
+        // 空历史触发兜底条目，随后生成 conversation_id
+        let expected = ("fallback_test-1".to_string(), "test-2".to_string());
+        assert_eq!(build_ids(), expected);
+        assert_eq!(build_ids(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_strict_and_relaxed_system_prompts_keep_extractable_format() {
+        let canned = "### BEGIN RESPONSE ###\nHere is an enhanced version of the original instruction that is more specific and clear:\n<augment-enhanced-prompt>重构登录模块并补充单元测试</augment-enhanced-prompt>\n\n### END RESPONSE ###";
+
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        for strict in [true, false] {
+            let enhancer = PromptEnhancer::new("https://example.com", "token")
+                .unwrap()
+                .with_strict_no_tools(strict);
+            let mut request = test_request("重构登录", &project_root);
+            request.include_history = false;
+            request.dry_run = true;
+            let payload = enhancer.enhance(request).await.unwrap().dry_run_payload.unwrap();
+            let message = payload["body"]["message"].as_str().unwrap().to_string();
+
+            assert_eq!(message.contains("NO TOOLS ALLOWED"), strict);
+            assert_eq!(message.contains("Do not use any tools"), strict);
+            assert_eq!(message.contains("Reply directly with your answer"), !strict);
+            assert!(message.contains("<augment-enhanced-prompt>enhanced prompt goes here</augment-enhanced-prompt>"));
+            assert!(message.contains("### BEGIN RESPONSE ###") && message.contains("### END RESPONSE ###"));
+            assert!(message.ends_with("重构登录"));
+            assert_eq!(
+                PromptEnhancer::extract_enhanced_prompt(canned).as_deref(),
+                Some("重构登录模块并补充单元测试")
+            );
+        }
+    }
+
+    #[test]
+    fn test_prompt_budget_trims_zhi_then_history_then_prompt_middle() {
+        let enhancer = PromptEnhancer::new("https://example.com", "token").unwrap();
+        let history_entry = |text: &str| enhancer.build_fallback_history_entry(text).unwrap();
+        let base_len = enhancer.compose_message("", "", false).chars().count();
+
+        // 仅 zhi 摘要超限：截断摘要，历史与提示词不变
+        let mut zhi = "z".repeat(300);
+        let mut history = vec![history_entry(&"h".repeat(200))];
+        let mut prompt = "p".repeat(200);
+        let max_chars = base_len + 600;
+        let report = enhancer.fit_prompt_budget(max_chars, &mut zhi, &mut history, &mut prompt, false).unwrap();
+        assert!(report.zhi_summary_chars_removed > 0);
+        assert_eq!(report.history_entries_dropped, 0);
+        assert_eq!(report.prompt_chars_removed, 0);
+        assert!(!zhi.is_empty());
+
+        // 摘要移除后仍超限：丢弃最早的历史，再截断提示词中段
+        let mut zhi = "z".repeat(300);
+        let mut history = vec![history_entry(&"a".repeat(200)), history_entry(&"b".repeat(200))];
+        let mut prompt = format!("HEAD{}TAIL", "p".repeat(2000));
+        let max_chars = base_len + 500;
+        let report = enhancer.fit_prompt_budget(max_chars, &mut zhi, &mut history, &mut prompt, false).unwrap();
+        assert_eq!(report.zhi_summary_chars_removed, 300);
+        assert_eq!(report.history_entries_dropped, 2);
+        assert!(report.prompt_chars_removed > 0);
+        assert!(zhi.is_empty() && history.is_empty());
+        assert!(prompt.starts_with("HEAD") && prompt.ends_with("TAIL"));
+        assert!(prompt.contains("已省略"));
+
+        let message = enhancer.compose_message(&zhi, &prompt, false);
+        assert!(message.chars().count() <= max_chars);
+
+        // 未超限时不裁剪
+        let mut prompt = "short".to_string();
+        assert!(enhancer.fit_prompt_budget(max_chars, &mut String::new(), &mut Vec::new(), &mut prompt, false).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_prompt_budget_below_fixed_template_is_rejected() {
+        let enhancer = PromptEnhancer::new("https://example.com", "token")
+            .unwrap()
+            .with_max_prompt_chars(10);
+
+        // 固定模板已超限：标记超限且保留提示词原文
+        let mut zhi = "z".repeat(50);
+        let mut prompt = "修复登录 bug".to_string();
+        let report = enhancer.fit_prompt_budget(10, &mut zhi, &mut Vec::new(), &mut prompt, false).unwrap();
+        assert!(report.exceeds_budget);
+        assert!(zhi.is_empty());
+        assert_eq!(prompt, "修复登录 bug");
+        assert_eq!(report.prompt_chars_removed, 0);
+
+        // 请求在发送前报错，提示调大上限
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        let mut request = test_request("修复登录 bug", &project_root);
+        request.include_history = false;
+        request.dry_run = true;
+        let err = enhancer.enhance(request).await.unwrap_err();
+        assert!(err.to_string().contains("enhance_max_prompt_chars"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_returns_payload_without_http_request() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        let enhancer = PromptEnhancer::new(&base_url, "secret-token").unwrap();
+        let mut request = test_request("优化提示词", &project_root);
+        request.include_history = false;
+        request.dry_run = true;
+        let response = enhancer.enhance(request).await.unwrap();
+
+        let payload = response.dry_run_payload.expect("dry_run 应返回请求体");
+        assert!(response.success);
+        assert!(payload["url"].as_str().unwrap().ends_with("/chat-stream"));
+        assert!(payload["body"]["message"].as_str().unwrap().ends_with("优化提示词"));
+        assert!(!payload.to_string().contains("secret-token"));
+
+        // 没有任何连接到达本地服务器
+        assert_eq!(
+            listener.accept().unwrap_err().kind(),
+            std::io::ErrorKind::WouldBlock
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verbose_request_reports_injected_context() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        let chat_store: Arc<dyn HistoryStore> = Arc::new(MemoryHistoryStore::new());
+        let zhi_store: Arc<dyn HistoryStore> = Arc::new(MemoryHistoryStore::new());
+        let chat = ChatHistoryManager::with_store(&project_root, chat_store.clone()).unwrap();
+        let first = chat.add_entry("实现登录页", "已完成", "popup").unwrap();
+        let second = chat.add_entry("增加记住密码", "已添加", "popup").unwrap();
+        let zhi = ZhiHistoryManager::with_store(&project_root, zhi_store.clone()).unwrap();
+        zhi.add_entry("req-1", "确认方案", "同意", "popup").unwrap();
+        zhi.add_entry("req-2", "继续", "好的", "popup").unwrap();
+
+        let enhancer = PromptEnhancer::new("https://example.com", "token")
+            .unwrap()
+            .with_project_root(&project_root)
+            .with_history_stores(chat_store, zhi_store);
+        let mut request = test_request("继续优化", &project_root);
+        request.dry_run = true;
+        request.blob_names = Some(vec!["blob-a".to_string(), "blob-b".to_string()]);
+
+        let quiet = enhancer.enhance(request.clone()).await.unwrap();
+        assert!(quiet.injected_context.is_none());
+
+        request.verbose = true;
+        let response = enhancer.enhance(request.clone()).await.unwrap();
+        let summary = response.injected_context.expect("verbose 请求应返回注入明细");
+        assert_eq!(
+            summary,
+            InjectedContextSummary {
+                blob_source_root: None,
+                blob_count: 2,
+                history_ids: vec![first.clone(), second.clone()],
+                zhi_count: 2,
+                history_fallback_used: false,
+                summary_mode: false,
+            }
+        );
+
+        // 摘要模式与 blob 来源路径同样体现在明细中
+        request.history_mode = HistoryMode::Summary;
+        let build = enhancer.build_request_payload(&request, &[]);
+        let summary = PromptEnhancer::injected_context_summary(&request, &build.history_diag, Some(&project_root), 0)
+            .unwrap();
+        assert!(summary.summary_mode);
+        assert_eq!(summary.history_ids, vec![first, second]);
+        assert_eq!(summary.blob_source_root.as_deref(), Some(project_root.as_str()));
+
+        chat.clear().unwrap();
+        let build = enhancer.build_request_payload(&request, &[]);
+        let summary = PromptEnhancer::injected_context_summary(&request, &build.history_diag, None, 0).unwrap();
+        assert!(summary.history_fallback_used);
+        assert!(summary.history_ids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_provided_conversation_id_is_reused_in_payload_and_response() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        let enhancer = PromptEnhancer::new("https://example.com", "token")
+            .unwrap()
+            .with_id_source(Arc::new(SequentialRequestIdSource::new("test")));
+
+        let mut request = test_request("继续细化", &project_root);
+        request.include_history = false;
+        request.dry_run = true;
+        request.conversation_id = Some("conv-42".to_string());
+        let response = enhancer.enhance(request).await.unwrap();
+
+        assert_eq!(response.conversation_id.as_deref(), Some("conv-42"));
+        assert_eq!(response.dry_run_payload.unwrap()["body"]["conversation_id"], "conv-42");
+
+        // 未指定时仍为每次请求生成新 ID
+        let mut request = test_request("新的提示词", &project_root);
+        request.include_history = false;
+        request.dry_run = true;
+        let response = enhancer.enhance(request).await.unwrap();
+        assert!(response.conversation_id.is_some_and(|id| id != "conv-42"));
+    }
+
+    #[tokio::test]
+    async fn test_context_warning_distinguishes_missing_root_and_unindexed_project() {
+        let enhancer = PromptEnhancer::new("https://example.com", "token").unwrap();
+
+        let mut request = test_request("优化登录页", "");
+        request.project_root_path = None;
+        request.dry_run = true;
+        let response = enhancer.enhance(request).await.unwrap();
+        assert_eq!(response.context_warning, Some(ContextWarning::NoProjectRoot));
+
+        // 项目路径存在但未建立索引
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        let warning = PromptEnhancer::context_preflight(&test_request("优化登录页", &project_root), Some(&project_root), None);
+        assert_eq!(warning, Some(ContextWarning::ProjectNotIndexed));
+
+        // 未请求历史且显式指定 blob 时无需项目路径
+        let mut request = test_request("优化登录页", "");
+        request.include_history = false;
+        request.blob_names = Some(vec!["blob-1".to_string()]);
+        assert_eq!(PromptEnhancer::context_preflight(&request, None, None), None);
+    }
+
+    #[test]
+    fn test_zhi_summary_uses_selected_ids_in_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+
+        let zhi_store: Arc<dyn HistoryStore> = Arc::new(MemoryHistoryStore::new());
+        let manager = ZhiHistoryManager::with_store(&project_root, zhi_store.clone()).unwrap();
+        let first = manager.add_entry("req-1", "问题一", "回答一", "popup").unwrap();
+        let _second = manager.add_entry("req-2", "问题二", "回答二", "popup").unwrap();
+        let third = manager.add_entry("req-3", "问题三", "回答三", "popup").unwrap();
+
+        let enhancer = PromptEnhancer::new("https://example.com", "token")
+            .unwrap()
+            .with_project_root(&project_root)
+            .with_history_stores(Arc::new(MemoryHistoryStore::new()), zhi_store);
+        let selected = vec![third.clone(), first.clone()];
+        let items = enhancer.load_zhi_history(DEFAULT_ZHI_HISTORY_ENTRIES, Some(&selected));
+        let summary = PromptEnhancer::format_zhi_summary(&items);
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(summary, "- Q: 问题三\n  A: 回答三\n- Q: 问题一\n  A: 回答一");
+    }
+
+    #[test]
+    fn test_explicit_blob_names_used_verbatim() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        let enhancer = PromptEnhancer::new("https://example.com", "token")
+            .unwrap()
+            .with_project_root(&project_root);
+
+        let explicit = vec!["blob-b".to_string(), "blob-a".to_string(), "blob-b".to_string()];
+        let (names, source_root) = enhancer.resolve_blob_names(Some(&explicit));
+        assert_eq!(names, explicit);
+        // 显式指定时不查找 projects.json，因此没有匹配的项目根路径
+        assert!(source_root.is_none());
+
+        let mut request = test_request("提示词", &project_root);
+        request.include_history = false;
+        request.blob_names = Some(explicit.clone());
+        let build = enhancer.build_request_payload(&request, &names);
+        let added: Vec<String> = serde_json::from_value(build.payload["blobs"]["added_blobs"].clone()).unwrap();
+        assert_eq!(added, explicit);
+    }
+
+    #[test]
+    fn test_chat_stream_url_bare_host() {
+        let base = normalize_base_url("api.example.com");
+        assert_eq!(base, "https://api.example.com");
+        assert_eq!(join_endpoint(&base, "chat-stream"), "https://api.example.com/chat-stream");
+    }
+
+    #[test]
+    fn test_chat_stream_url_trailing_slash() {
+        let base = normalize_base_url("https://api.example.com:8443/");
+        assert_eq!(base, "https://api.example.com:8443");
+        assert_eq!(join_endpoint(&base, "chat-stream"), "https://api.example.com:8443/chat-stream");
+    }
+
+    #[test]
+    fn test_chat_stream_url_keeps_path_prefix() {
+        let base = normalize_base_url(" https://host.example.com/api/v2/#section ");
+        assert_eq!(base, "https://host.example.com/api/v2");
+        assert_eq!(join_endpoint(&base, "/chat-stream"), "https://host.example.com/api/v2/chat-stream");
+    }
+
+    #[test]
+    fn test_chat_stream_url_keeps_query_string() {
+        let base = normalize_base_url(" https://host.example.com/api/v2/?region=cn&key=abc ");
+        assert_eq!(base, "https://host.example.com/api/v2?region=cn&key=abc");
+        assert_eq!(
+            join_endpoint(&base, "/chat-stream"),
+            "https://host.example.com/api/v2/chat-stream?region=cn&key=abc"
+        );
+    }
+
+    #[test]
+    fn test_large_projects_file_is_cached() {
+        let temp_dir = TempDir::new().unwrap();
+        let projects_path = temp_dir.path().join("projects.json");
+        let mut projects = HashMap::new();
+        for i in 0..20_000 {
+            let blobs = (0..5).map(|j| format!("blob-{}-{}", i, j)).collect::<Vec<_>>();
+            projects.insert(format!("/workspace/project-{}", i), blobs);
+        }
+        fs::write(&projects_path, serde_json::to_string(&projects).unwrap()).unwrap();
+        let candidates = vec![projects_path.clone()];
+
+        let (names, root) = PromptEnhancer::load_project_blobs_from(&candidates, "/workspace/project-12345");
+        assert_eq!(names, vec!["blob-12345-0", "blob-12345-1", "blob-12345-2", "blob-12345-3", "blob-12345-4"]);
+        assert_eq!(root.as_deref(), Some("/workspace/project-12345"));
+
+        let (cached_names, _) = PromptEnhancer::load_project_blobs_from(&candidates, "/workspace/project-12345");
+        assert_eq!(cached_names, names);
+        // 中文注释：以缓存条目的指针一致性判断命中，避免依赖耗时比较
+        assert!(Arc::ptr_eq(
+            &read_projects_file(&projects_path).unwrap(),
+            &read_projects_file(&projects_path).unwrap()
+        ));
+
+        // 文件变化（大小不同）后重新解析
+        fs::write(&projects_path, r#"{"/workspace/project-12345":["fresh"]}"#).unwrap();
+        let (names, _) = PromptEnhancer::load_project_blobs_from(&candidates, "/workspace/project-12345");
+        assert_eq!(names, vec!["fresh"]);
+    }
+
+    #[tokio::test]
+    async fn test_recorded_stream_replays_to_same_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_root = temp_dir.path().to_string_lossy().to_string();
+        // 中文注释：JSON 跨分片、末尾事件缺少换行，覆盖拆行缓冲与残留片段处理
+        let base_url = start_slow_sse_server(vec![
+            "data: {\"text\":\"<augment-enhanced-prompt>先复现\"}\n\ndata: {\"te".to_string(),
+            "xt\":\"，再修复\"}\n\n".to_string(),
+            "data: {\"text\":\"</augment-enhanced-prompt>\",\"usage\":{\"prompt_tokens\":12,\"completion_tokens\":5}}".to_string(),
+        ]);
+        let record_path = temp_dir.path().join("records").join("bad_response.sse");
+
+        let enhancer = PromptEnhancer::new(&base_url, "token").unwrap();
+        let mut request = test_request("优化提示词", &project_root);
+        request.include_history = false;
+        request.record_to = Some(record_path.clone());
+        let response = enhancer.enhance(request).await.unwrap();
+        assert!(response.success, "error: {:?}", response.error);
+        assert_eq!(response.enhanced_prompt, "先复现，再修复");
+
+        let replay = enhancer.replay_recorded(&record_path).unwrap();
+        assert_eq!(replay.enhanced_prompt, response.enhanced_prompt);
+        assert_eq!(replay.error, response.error);
+        assert!(!replay.truncated);
+        assert_eq!(replay.usage.and_then(|u| u.prompt_tokens), response.prompt_tokens);
+        assert_eq!(replay.usage.and_then(|u| u.completion_tokens), response.completion_tokens);
+
+        // 回放沿用增强器配置的后处理步骤
+        let wrapped = PromptEnhancer::new(&base_url, "token")
+            .unwrap()
+            .with_post_process(vec![PostProcessStep::PrependTemplate("<task>{prompt}</task>".to_string())]);
+        assert_eq!(wrapped.replay_recorded(&record_path).unwrap().enhanced_prompt, "<task>先复现，再修复</task>");
+
+        // 截断的录制同样可以离线复现
+        let truncated_path = temp_dir.path().join("truncated.sse");
+        fs::write(&truncated_path, "data: {\"text\":\"<augment-enhanced-prompt>半截\"}\n\n").unwrap();
+        let replay = enhancer.replay_recorded(&truncated_path).unwrap();
+        assert!(replay.truncated);
+        assert_eq!(replay.enhanced_prompt, "半截");
+        assert!(enhancer.replay_recorded(temp_dir.path().join("missing.sse")).is_err());
+    }
 }
@@ -2,15 +2,18 @@
 // 持久化存储用户与弹窗的交互历史，供提示词增强时使用
 
 use std::collections::{HashMap, HashSet, VecDeque};
-use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
+use std::sync::Arc;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use ring::digest::{Context as ShaContext, SHA256};
 
 use crate::{log_debug, log_important};
-use crate::mcp::utils::safe_truncate;
+use crate::mcp::utils::{project_root_aliases, safe_truncate, FileLockGuard, FsHistoryStore, HistoryStore, SanshuError};
+
+/// 单次增强请求最多注入的历史条数（对话历史与 zhi 历史共用，避免上下文膨胀）
+pub const MAX_API_HISTORY_ENTRIES: usize = 20;
 
 /// 对话历史管理器
 pub struct ChatHistoryManager {
@@ -22,6 +25,12 @@ pub struct ChatHistoryManager {
     project_path: String,
     /// 最大历史条数
     max_entries: usize,
+    /// AI 响应摘要保存的最大字符数
+    summary_max_chars: usize,
+    /// 历史文件损坏时是否隔离并重建
+    repair_on_load: bool,
+    /// 历史存储（默认文件系统）
+    store: Arc<dyn HistoryStore>,
 }
 
 /// 单条对话历史
@@ -31,7 +40,7 @@ pub struct ChatEntry {
     pub id: String,
     /// 用户输入
     pub user_input: String,
-    /// AI响应摘要（仅保存前500字符）
+    /// AI响应摘要（默认仅保存前500字符，可通过配置调整）
     pub ai_response_summary: String,
     /// 时间戳
     pub timestamp: DateTime<Utc>,
@@ -40,6 +49,35 @@ pub struct ChatEntry {
     pub source: String,
 }
 
+/// 历史读取状态（供 GUI 区分“暂无历史 / 读取失败 / 已加载 N 条”）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum HistoryStatus {
+    /// 无历史文件或没有匹配的条目
+    Empty,
+    /// 成功读取的条目数
+    Loaded(usize),
+    /// 历史文件存在但读取/解析失败（附错误信息）
+    Failed(String),
+}
+
+/// 带读取状态的历史查询结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatHistoryResult {
+    pub status: HistoryStatus,
+    pub entries: Vec<ChatEntry>,
+}
+
+impl ChatHistoryResult {
+    /// 构造读取失败的结果
+    pub fn failed(message: impl Into<String>) -> Self {
+        Self {
+            status: HistoryStatus::Failed(message.into()),
+            entries: Vec::new(),
+        }
+    }
+}
+
 /// 历史文件结构
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 struct ChatHistoryFile {
@@ -52,24 +90,70 @@ struct ChatHistoryFile {
     /// 最后更新时间
     #[serde(default)]
     last_updated: Option<DateTime<Utc>>,
+    /// 最近一次增强结果对应的条目 ID（用于“重新生成”时排除）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_enhance_id: Option<String>,
+}
+
+impl ChatEntry {
+    /// 条目来源；旧版本写入的空来源视为 "popup"
+    pub fn effective_source(&self) -> &str {
+        if self.source.trim().is_empty() {
+            "popup"
+        } else {
+            &self.source
+        }
+    }
 }
 
 impl ChatHistoryManager {
     /// 最大历史条数默认值
     const DEFAULT_MAX_ENTRIES: usize = 20;
+    /// AI 响应摘要长度默认值（字符数）
+    pub const DEFAULT_SUMMARY_MAX_CHARS: usize = 500;
+    /// 注入 chat-stream 时单条响应的最大字符数（与保存长度相互独立）
+    const API_SUMMARY_MAX_CHARS: usize = 500;
 
-    /// 创建对话历史管理器
+    /// 创建对话历史管理器（存储于 <数据目录>/chat_history）
     pub fn new(project_path: &str) -> Result<Self> {
+        let mcp_config = crate::config::load_standalone_config().ok().map(|c| c.mcp_config);
+        let summary_max_chars = mcp_config
+            .as_ref()
+            .and_then(|c| c.enhance_history_summary_chars)
+            .unwrap_or(Self::DEFAULT_SUMMARY_MAX_CHARS);
+        let repair_on_load = mcp_config
+            .as_ref()
+            .and_then(|c| c.enhance_history_repair_on_load)
+            .unwrap_or(false);
+        Ok(Self::with_store(project_path, Arc::new(FsHistoryStore::new(Self::history_dir())))?
+            .with_summary_max_chars(summary_max_chars)
+            .with_repair_on_load(repair_on_load))
+    }
+
+    /// 使用指定存储创建对话历史管理器
+    pub fn with_store(project_path: &str, store: Arc<dyn HistoryStore>) -> Result<Self> {
         // 中文注释：新 hash 规则会清理 Windows 长路径前缀与末尾斜杠，避免同一项目出现多个 hash 文件
         let project_hash = Self::hash_path_v2(project_path);
 
         // 中文注释：兼容旧 hash 规则（历史文件可能已经以旧规则落盘）
-        let legacy_hashes = Self::legacy_hashes(project_path, &project_hash);
+        let mut legacy_hashes = Self::legacy_hashes(project_path, &project_hash);
+        // 中文注释：兼容规范化前以原始路径（如符号链接）计算的 hash
+        for alias in project_root_aliases(project_path) {
+            let alias_hash = Self::hash_path_v2(&alias);
+            for hash in std::iter::once(alias_hash.clone()).chain(Self::legacy_hashes(&alias, &alias_hash)) {
+                if hash != project_hash && !legacy_hashes.contains(&hash) {
+                    legacy_hashes.push(hash);
+                }
+            }
+        }
         Ok(Self {
             project_hash,
             legacy_hashes,
             project_path: project_path.to_string(),
             max_entries: Self::DEFAULT_MAX_ENTRIES,
+            summary_max_chars: Self::DEFAULT_SUMMARY_MAX_CHARS,
+            repair_on_load: false,
+            store,
         })
     }
 
@@ -79,6 +163,18 @@ impl ChatHistoryManager {
         self
     }
 
+    /// 设置 AI 响应摘要保存的最大字符数
+    pub fn with_summary_max_chars(mut self, max: usize) -> Self {
+        self.summary_max_chars = max;
+        self
+    }
+
+    /// 设置历史文件损坏时是否隔离并重建（默认关闭，保持严格报错）
+    pub fn with_repair_on_load(mut self, repair: bool) -> Self {
+        self.repair_on_load = repair;
+        self
+    }
+
     /// 旧规则：仅 trim + 小写 + 反斜杠转正斜杠
     fn normalize_path_v1(path: &str) -> String {
         path.trim().to_lowercase().replace('\\', "/")
@@ -163,31 +259,13 @@ impl ChatHistoryManager {
 
     /// 获取历史目录
     fn history_dir() -> PathBuf {
-        let data_dir = dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join(".sanshu")
-            .join("chat_history");
-        // 确保目录存在
-        let _ = fs::create_dir_all(&data_dir);
-        data_dir
+        crate::paths::data_subdir("chat_history")
     }
 
-    fn history_file_path_for_hash(hash: &str) -> PathBuf {
-        Self::history_dir().join(format!("{}.json", hash))
-    }
-
-    /// 新规则文件路径（v2 hash）
-    fn primary_history_file_path(&self) -> PathBuf {
-        Self::history_file_path_for_hash(&self.project_hash)
-    }
-
-    /// 返回所有可能的历史文件路径（新规则优先）
-    fn history_file_paths(&self) -> Vec<PathBuf> {
-        let mut out = Vec::new();
-        out.push(Self::history_file_path_for_hash(&self.project_hash));
-        for h in &self.legacy_hashes {
-            out.push(Self::history_file_path_for_hash(h));
-        }
+    /// 返回所有可能的历史存储键（新规则优先）
+    fn history_keys(&self) -> Vec<&str> {
+        let mut out = vec![self.project_hash.as_str()];
+        out.extend(self.legacy_hashes.iter().map(String::as_str));
         out
     }
 
@@ -196,16 +274,36 @@ impl ChatHistoryManager {
             project_path: self.project_path.clone(),
             entries: VecDeque::new(),
             last_updated: None,
+            last_enhance_id: None,
         }
     }
 
     /// 加载单个历史文件（失败时返回 Err，便于上层区分“空/失败”）
-    fn load_history_from_path(&self, path: &Path) -> Result<ChatHistoryFile> {
-        let content = fs::read_to_string(path)
-            .with_context(|| format!("读取对话历史文件失败: {}", path.display()))?;
-        let parsed: ChatHistoryFile = serde_json::from_str(&content)
-            .with_context(|| format!("解析对话历史文件失败: {}", path.display()))?;
-        Ok(parsed)
+    fn load_history_for_key(&self, key: &str) -> Result<ChatHistoryFile> {
+        let content = self.store.read(key)
+            .with_context(|| format!("读取对话历史文件失败: {}", self.store.describe(key)))?;
+        match serde_json::from_str::<ChatHistoryFile>(&content) {
+            Ok(parsed) => Ok(parsed),
+            Err(e) if self.repair_on_load => self.repair_history_for_key(key, &e),
+            Err(e) => Err(SanshuError::from(e))
+                .with_context(|| format!("解析对话历史文件失败: {}", self.store.describe(key))),
+        }
+    }
+
+    /// 隔离损坏的历史文件并写入空历史，避免用户被永久卡在“读取失败”
+    fn repair_history_for_key(&self, key: &str, err: &serde_json::Error) -> Result<ChatHistoryFile> {
+        let timestamp = Utc::now().format("%Y%m%d%H%M%S").to_string();
+        let moved_to = self.store.quarantine(key, &timestamp)?;
+        log_important!(
+            warn,
+            "对话历史文件损坏，已隔离到 {} 并重建空历史: {} ({})",
+            moved_to,
+            self.store.describe(key),
+            err
+        );
+        let fresh = self.empty_history();
+        self.save_history_for_key(key, &fresh)?;
+        Ok(fresh)
     }
 
     /// 加载并合并历史（兼容旧 hash 文件）
@@ -216,18 +314,18 @@ impl ChatHistoryManager {
     fn load_history_merged(&self) -> Result<ChatHistoryFile> {
         let mut found_any_file = false;
         let mut loaded_files: Vec<ChatHistoryFile> = Vec::new();
-        let mut errors: Vec<String> = Vec::new();
+        let mut errors: Vec<anyhow::Error> = Vec::new();
 
-        for path in self.history_file_paths() {
-            if !path.exists() {
+        for key in self.history_keys() {
+            if !self.store.exists(key) {
                 continue;
             }
             found_any_file = true;
-            match self.load_history_from_path(&path) {
+            match self.load_history_for_key(key) {
                 Ok(file) => loaded_files.push(file),
                 Err(e) => {
                     log_debug!("{}", e);
-                    errors.push(e.to_string());
+                    errors.push(e);
                 }
             }
         }
@@ -236,14 +334,18 @@ impl ChatHistoryManager {
             if !found_any_file {
                 return Ok(self.empty_history());
             }
-            // 有文件但都失败：显式返回 Err，便于前端区分“空/失败”
-            let msg = errors
-                .first()
-                .cloned()
-                .unwrap_or_else(|| "对话历史文件读取/解析失败".to_string());
-            return Err(anyhow::anyhow!(msg));
+            // 有文件但都失败：显式返回 Err（保留首个错误的根因），便于前端区分“空/失败”
+            return Err(errors
+                .into_iter()
+                .next()
+                .unwrap_or_else(|| anyhow::anyhow!("对话历史文件读取/解析失败")));
         }
 
+        // 中文注释：last_enhance_id 以新规则文件为准（history_file_paths 新规则优先）
+        let last_enhance_id = loaded_files
+            .iter()
+            .find_map(|file| file.last_enhance_id.clone());
+
         // 合并 entries（按 id 去重，按 timestamp 排序）
         let mut map: HashMap<String, ChatEntry> = HashMap::new();
         for file in loaded_files {
@@ -259,30 +361,37 @@ impl ChatHistoryManager {
             project_path: self.project_path.clone(),
             entries: VecDeque::from(entries),
             last_updated: Some(Utc::now()),
+            last_enhance_id,
         })
     }
 
-    /// 保存历史文件到指定路径
-    fn save_history_to_path(&self, path: &Path, history: &ChatHistoryFile) -> Result<()> {
+    /// 保存历史文件到指定存储键
+    fn save_history_for_key(&self, key: &str, history: &ChatHistoryFile) -> Result<()> {
         let content = serde_json::to_string_pretty(history)?;
-        fs::write(path, content)
-            .with_context(|| format!("写入对话历史文件失败: {}", path.display()))?;
-        log_debug!("对话历史已保存: {}", path.display());
+        self.store.write(key, &content)
+            .with_context(|| format!("写入对话历史文件失败: {}", self.store.describe(key)))?;
+        log_debug!("对话历史已保存: {}", self.store.describe(key));
         Ok(())
     }
 
-    /// 保存历史文件到 v2 hash 路径
+    /// 保存历史文件到 v2 hash 键
     fn save_history_v2(&self, history: &ChatHistoryFile) -> Result<()> {
-        let path = self.primary_history_file_path();
-        self.save_history_to_path(&path, history)
+        self.save_history_for_key(&self.project_hash, history)
+    }
+
+    /// 获取项目历史的独占锁（新旧 hash 文件共用 v2 键的锁）
+    fn lock_history(&self) -> Result<Option<FileLockGuard>> {
+        self.store.lock(&self.project_hash)
+            .with_context(|| format!("锁定对话历史失败: {}", self.store.describe(&self.project_hash)))
     }
 
     /// 添加一条对话记录
     pub fn add_entry(&self, user_input: &str, ai_response: &str, source: &str) -> Result<String> {
+        // 中文注释：加载-修改-保存需在锁内完成，避免 MCP 与 GUI 并发写入时丢失记录
+        let _lock = self.lock_history()?;
         // 中文注释：写入时只维护 v2 文件；读取时会合并展示（兼容旧文件）
-        let primary_path = self.primary_history_file_path();
-        let mut history = if primary_path.exists() {
-            match self.load_history_from_path(&primary_path) {
+        let mut history = if self.store.exists(&self.project_hash) {
+            match self.load_history_for_key(&self.project_hash) {
                 Ok(h) => h,
                 Err(e) => {
                     log_debug!("加载对话历史失败，将创建新历史文件: {}", e);
@@ -299,9 +408,9 @@ impl ChatHistoryManager {
             fastrand::u32(..)
         );
 
-        // 截取AI响应摘要（最多500字符）
+        // 截取AI响应摘要（长度由配置决定，默认500字符）
         // 使用 safe_truncate 确保在 UTF-8 字符边界安全截断，避免多字节字符被截断导致 panic
-        let ai_summary = safe_truncate(ai_response, 500);
+        let ai_summary = safe_truncate(ai_response, self.summary_max_chars);
 
         let entry = ChatEntry {
             id: id.clone(),
@@ -312,6 +421,9 @@ impl ChatHistoryManager {
         };
 
         history.entries.push_back(entry);
+        if Self::is_enhance_source(source) {
+            history.last_enhance_id = Some(id.clone());
+        }
         
         // 保持历史条数在限制内
         while history.entries.len() > self.max_entries {
@@ -325,10 +437,29 @@ impl ChatHistoryManager {
         Ok(id)
     }
 
+    /// 是否为增强结果来源（GUI 增强或 MCP 增强）
+    fn is_enhance_source(source: &str) -> bool {
+        matches!(source, "enhance" | "mcp")
+    }
+
+    /// 获取最近一次增强结果对应的条目 ID
+    pub fn last_enhance_id(&self) -> Option<String> {
+        self.load_history_merged().ok()?.last_enhance_id
+    }
+
     /// 获取最近N条对话历史
     pub fn get_recent(&self, count: usize) -> Result<Vec<ChatEntry>> {
-        let history = self.load_history_merged()?;
-        let entries: Vec<ChatEntry> = history.entries.into_iter().collect();
+        self.get_recent_by_source(count, None)
+    }
+
+    /// 获取最近N条对话历史（别名，便于外部调用语义统一）
+    pub fn get_recent_entries(&self, count: usize) -> Result<Vec<ChatEntry>> {
+        self.get_recent(count)
+    }
+
+    /// 获取最近N条指定来源的对话历史（`source` 为空时不过滤）
+    pub fn get_recent_by_source(&self, count: usize, source: Option<&str>) -> Result<Vec<ChatEntry>> {
+        let entries = self.get_all_by_source(source)?;
         if entries.len() <= count {
             return Ok(entries);
         }
@@ -342,15 +473,34 @@ impl ChatHistoryManager {
             .collect())
     }
 
-    /// 获取最近N条对话历史（别名，便于外部调用语义统一）
-    pub fn get_recent_entries(&self, count: usize) -> Result<Vec<ChatEntry>> {
-        self.get_recent(count)
+    /// 获取最近N条对话历史，并附带读取状态（读取失败不返回 Err，而是体现在状态中）
+    pub fn get_recent_with_status(&self, count: usize, source: Option<&str>) -> ChatHistoryResult {
+        match self.get_recent_by_source(count, source) {
+            Ok(entries) => ChatHistoryResult {
+                status: if entries.is_empty() {
+                    HistoryStatus::Empty
+                } else {
+                    HistoryStatus::Loaded(entries.len())
+                },
+                entries,
+            },
+            Err(e) => ChatHistoryResult::failed(format!("读取历史记录失败: {:#}", e)),
+        }
     }
 
     /// 获取所有对话历史
     pub fn get_all(&self) -> Result<Vec<ChatEntry>> {
+        self.get_all_by_source(None)
+    }
+
+    /// 获取所有指定来源的对话历史（`source` 为空时不过滤）
+    pub fn get_all_by_source(&self, source: Option<&str>) -> Result<Vec<ChatEntry>> {
         let history = self.load_history_merged()?;
-        Ok(history.entries.into_iter().collect())
+        let source = source.map(str::trim).filter(|s| !s.is_empty());
+        Ok(history.entries
+            .into_iter()
+            .filter(|e| source.map_or(true, |s| e.effective_source() == s))
+            .collect())
     }
 
     /// 根据 ID 列表获取历史（保持传入顺序）
@@ -372,17 +522,19 @@ impl ChatHistoryManager {
 
     /// 清空对话历史
     pub fn clear(&self) -> Result<()> {
+        let _lock = self.lock_history()?;
         let history = ChatHistoryFile {
             project_path: self.project_path.clone(),
             entries: VecDeque::new(),
             last_updated: Some(Utc::now()),
+            last_enhance_id: None,
         };
 
         // 中文注释：清空所有可能的历史文件（新旧 hash），避免“清空后仍然有历史”
         let mut wrote_any = false;
-        for path in self.history_file_paths() {
-            if path.exists() {
-                self.save_history_to_path(&path, &history)?;
+        for key in self.history_keys() {
+            if self.store.exists(key) {
+                self.save_history_for_key(key, &history)?;
                 wrote_any = true;
             }
         }
@@ -396,20 +548,24 @@ impl ChatHistoryManager {
 
     /// 删除指定ID的历史条目
     pub fn remove_entry(&self, entry_id: &str) -> Result<bool> {
+        let _lock = self.lock_history()?;
         let mut removed_any = false;
 
         // 中文注释：尽量从所有可能的历史文件中删除，避免旧文件残留导致“删除后又出现”
-        for path in self.history_file_paths() {
-            if !path.exists() {
+        for key in self.history_keys() {
+            if !self.store.exists(key) {
                 continue;
             }
-            match self.load_history_from_path(&path) {
+            match self.load_history_for_key(key) {
                 Ok(mut history) => {
                     let original_len = history.entries.len();
                     history.entries.retain(|e| e.id != entry_id);
                     if history.entries.len() < original_len {
+                        if history.last_enhance_id.as_deref() == Some(entry_id) {
+                            history.last_enhance_id = None;
+                        }
                         history.last_updated = Some(Utc::now());
-                        self.save_history_to_path(&path, &history)?;
+                        self.save_history_for_key(key, &history)?;
                         removed_any = true;
                     }
                 }
@@ -425,7 +581,7 @@ impl ChatHistoryManager {
 
     /// 转换为 chat-stream API 所需的格式
     pub fn to_api_format(&self, count: usize) -> Result<Vec<super::types::ChatHistoryEntry>> {
-        let entries = self.get_recent(count)?;
+        let entries = self.get_recent(count.min(MAX_API_HISTORY_ENTRIES))?;
 
         Ok(entries.into_iter().map(|entry| {
             super::types::ChatHistoryEntry {
@@ -444,7 +600,8 @@ impl ChatHistoryManager {
                     super::types::ChatHistoryResponseNode {
                         id: 1,
                         node_type: 0,
-                        content: Some(entry.ai_response_summary),
+                        // 中文注释：保存长度可配置，注入请求时仍单独限长，避免历史挤占上下文
+                        content: Some(safe_truncate(&entry.ai_response_summary, Self::API_SUMMARY_MAX_CHARS)),
                         tool_use: None,
                         thinking: None,
                         billing_metadata: None,
@@ -477,7 +634,8 @@ impl ChatHistoryManager {
                     super::types::ChatHistoryResponseNode {
                         id: 1,
                         node_type: 0,
-                        content: Some(entry.ai_response_summary),
+                        // 中文注释：保存长度可配置，注入请求时仍单独限长，避免历史挤占上下文
+                        content: Some(safe_truncate(&entry.ai_response_summary, Self::API_SUMMARY_MAX_CHARS)),
                         tool_use: None,
                         thinking: None,
                         billing_metadata: None,
@@ -489,3 +647,221 @@ impl ChatHistoryManager {
         }).collect())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::utils::MemoryHistoryStore;
+
+    fn memory_manager() -> (Arc<MemoryHistoryStore>, ChatHistoryManager) {
+        let store = Arc::new(MemoryHistoryStore::new());
+        let manager = ChatHistoryManager::with_store("/tmp/sanshu-memory-project", store.clone()).unwrap();
+        (store, manager)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_root_keeps_history_written_before_normalization() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let real = temp_dir.path().join("real");
+        std::fs::create_dir(&real).unwrap();
+        let link = temp_dir.path().join("link");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+        let link_path = link.to_string_lossy().to_string();
+
+        let store = Arc::new(MemoryHistoryStore::new());
+        ChatHistoryManager::with_store(&link_path, store.clone())
+            .unwrap()
+            .add_entry("旧提问", "旧回复", "popup")
+            .unwrap();
+
+        let canonical = crate::mcp::utils::normalize_and_validate_project_root(&link_path).unwrap();
+        assert_ne!(canonical, link_path);
+        let manager = ChatHistoryManager::with_store(&canonical, store).unwrap();
+        let entries = manager.get_all().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].user_input, "旧提问");
+    }
+
+    #[test]
+    fn test_filter_by_source_isolates_entries() {
+        let (_store, manager) = memory_manager();
+        manager.add_entry("弹窗1", "回复1", "popup").unwrap();
+        manager.add_entry("增强1", "结果1", "enhance").unwrap();
+        // 中文注释：旧版本条目可能没有来源，过滤时按 popup 处理
+        manager.add_entry("旧条目", "回复2", "").unwrap();
+        manager.add_entry("电报1", "回复3", "telegram").unwrap();
+        manager.add_entry("增强2", "结果2", "enhance").unwrap();
+
+        let inputs = |entries: Vec<ChatEntry>| entries.into_iter().map(|e| e.user_input).collect::<Vec<_>>();
+
+        assert_eq!(inputs(manager.get_all_by_source(Some("enhance")).unwrap()), vec!["增强1", "增强2"]);
+        assert_eq!(inputs(manager.get_all_by_source(Some("popup")).unwrap()), vec!["弹窗1", "旧条目"]);
+        assert_eq!(inputs(manager.get_recent_by_source(1, Some("popup")).unwrap()), vec!["旧条目"]);
+        assert_eq!(inputs(manager.get_recent_by_source(5, Some("telegram")).unwrap()), vec!["电报1"]);
+        assert!(manager.get_all_by_source(Some("mcp")).unwrap().is_empty());
+        // 空来源等同于不过滤
+        assert_eq!(manager.get_all_by_source(Some("")).unwrap().len(), 5);
+        assert_eq!(manager.get_recent(2).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_summary_length_follows_config() {
+        let long_response = "增强".repeat(1000);
+
+        let (_store, manager) = memory_manager();
+        let manager = manager.with_summary_max_chars(2000);
+        manager.add_entry("长回复", &long_response, "enhance").unwrap();
+        let entry = manager.get_all().unwrap().remove(0);
+        assert_eq!(entry.ai_response_summary, long_response);
+        // 注入 API 时仍按独立上限截断
+        let api_entries = manager.to_api_format(1).unwrap();
+        let injected = api_entries[0].response_nodes[0].content.clone().unwrap();
+        assert_eq!(injected.chars().count(), 500 + "...".len());
+
+        let (_store, manager) = memory_manager();
+        let manager = manager.with_summary_max_chars(100);
+        // 中文注释：ASCII 与多字节字符混排，截断位置落在多字节字符之间
+        let mixed = format!("a{}", "增强".repeat(100));
+        manager.add_entry("短回复", &mixed, "enhance").unwrap();
+        let summary = manager.get_all().unwrap().remove(0).ai_response_summary;
+        let expected: String = mixed.chars().take(100).collect();
+        assert_eq!(summary, format!("{}...", expected));
+        assert!(summary.ends_with("增..."));
+    }
+
+    #[test]
+    fn test_memory_store_add_get_clear() {
+        let (store, manager) = memory_manager();
+        assert!(manager.get_all().unwrap().is_empty());
+
+        let first = manager.add_entry("问题1", "回答1", "popup").unwrap();
+        let second = manager.add_entry("问题2", "回答2", "enhance").unwrap();
+
+        let all = manager.get_all().unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(manager.get_recent(1).unwrap()[0].id, second);
+        assert_eq!(manager.get_by_ids(&[first.clone()]).unwrap()[0].user_input, "问题1");
+        assert_eq!(manager.last_enhance_id(), Some(second));
+        assert_eq!(store.len(), 1);
+
+        manager.clear().unwrap();
+        assert!(manager.get_all().unwrap().is_empty());
+        assert_eq!(manager.last_enhance_id(), None);
+    }
+
+    #[test]
+    fn test_memory_store_respects_max_entries_and_remove() {
+        let (_store, manager) = memory_manager();
+        let manager = manager.with_max_entries(2);
+        let first = manager.add_entry("a", "1", "popup").unwrap();
+        let second = manager.add_entry("b", "2", "popup").unwrap();
+        let third = manager.add_entry("c", "3", "popup").unwrap();
+
+        let ids: Vec<String> = manager.get_all().unwrap().into_iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![second.clone(), third]);
+        assert!(!manager.remove_entry(&first).unwrap());
+        assert!(manager.remove_entry(&second).unwrap());
+        assert_eq!(manager.get_all().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_memory_store_reports_corrupt_history() {
+        let (store, manager) = memory_manager();
+        store.write(&manager.project_hash, "not json").unwrap();
+        let err = manager.get_all().unwrap_err();
+        assert!(matches!(err.downcast_ref::<SanshuError>(), Some(SanshuError::Parse(_))));
+    }
+
+    #[test]
+    fn test_history_status_reflects_on_disk_state() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = Arc::new(FsHistoryStore::new(temp_dir.path().to_path_buf()));
+        let manager = ChatHistoryManager::with_store("/tmp/sanshu-status-project", store.clone()).unwrap();
+
+        // 无历史文件
+        let result = manager.get_recent_with_status(20, None);
+        assert_eq!(result.status, HistoryStatus::Empty);
+        assert!(result.entries.is_empty());
+
+        // 有文件但没有条目
+        manager.clear().unwrap();
+        assert!(store.exists(&manager.project_hash));
+        assert_eq!(manager.get_recent_with_status(20, None).status, HistoryStatus::Empty);
+
+        // 正常读取
+        for i in 0..5 {
+            manager.add_entry(&format!("问题{}", i), "回答", "popup").unwrap();
+        }
+        let result = manager.get_recent_with_status(20, None);
+        assert_eq!(result.status, HistoryStatus::Loaded(5));
+        assert_eq!(result.entries.len(), 5);
+        assert_eq!(manager.get_recent_with_status(3, None).status, HistoryStatus::Loaded(3));
+        assert_eq!(manager.get_recent_with_status(20, Some("enhance")).status, HistoryStatus::Empty);
+
+        // 文件损坏
+        store.write(&manager.project_hash, "{ broken").unwrap();
+        let result = manager.get_recent_with_status(20, None);
+        match result.status {
+            HistoryStatus::Failed(message) => assert!(message.contains("解析对话历史文件失败"), "{}", message),
+            other => panic!("损坏的历史文件应返回 Failed，实际为 {:?}", other),
+        }
+        assert!(result.entries.is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_fs_writers_do_not_lose_entries() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path().to_path_buf();
+
+        let handles: Vec<_> = (0..2)
+            .map(|worker| {
+                let dir = dir.clone();
+                std::thread::spawn(move || {
+                    // 中文注释：每个线程使用独立的存储实例，模拟 MCP 与 GUI 两个进程
+                    let store = Arc::new(FsHistoryStore::new(dir));
+                    let manager = ChatHistoryManager::with_store("/tmp/sanshu-lock-project", store)
+                        .unwrap()
+                        .with_max_entries(100);
+                    for i in 0..10 {
+                        manager.add_entry(&format!("w{}-{}", worker, i), "ok", "popup").unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let manager = ChatHistoryManager::with_store("/tmp/sanshu-lock-project", Arc::new(FsHistoryStore::new(dir)))
+            .unwrap()
+            .with_max_entries(100);
+        assert_eq!(manager.get_all().unwrap().len(), 20);
+    }
+
+    #[test]
+    fn test_repair_on_load_quarantines_corrupt_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = Arc::new(FsHistoryStore::new(temp_dir.path().to_path_buf()));
+        let manager = ChatHistoryManager::with_store("/tmp/sanshu-repair-project", store.clone())
+            .unwrap()
+            .with_repair_on_load(true);
+        store.write(&manager.project_hash, "{ not json").unwrap();
+
+        assert!(manager.get_all().unwrap().is_empty());
+
+        // 损坏文件被隔离保留，原位置重建为可解析的空历史
+        let quarantined: Vec<_> = std::fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.to_string_lossy().contains(".json.corrupt."))
+            .collect();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(std::fs::read_to_string(&quarantined[0]).unwrap(), "{ not json");
+        let rebuilt: ChatHistoryFile = serde_json::from_str(&store.read(&manager.project_hash).unwrap()).unwrap();
+        assert!(rebuilt.entries.is_empty());
+
+        manager.add_entry("hi", "ok", "popup").unwrap();
+        assert_eq!(manager.get_all().unwrap().len(), 1);
+    }
+}
@@ -10,6 +10,7 @@ use super::types::*;
 use super::core::PromptEnhancer;
 use super::history::ChatHistoryManager;
 use crate::log_important;
+use crate::mcp::utils::{normalize_optional_project_root, project_path_error, to_mcp_error};
 
 /// MCP 增强工具请求参数
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +26,27 @@ pub struct EnhanceMcpRequest {
     /// 指定参与增强的历史记录 ID（可选）
     #[serde(default)]
     pub selected_history_ids: Option<Vec<String>>,
+    /// 指定参与增强的 zhi 交互历史 ID（可选）
+    #[serde(default)]
+    pub selected_zhi_ids: Option<Vec<String>>,
+    /// 重新生成模式（可选，默认 false）
+    #[serde(default)]
+    pub regenerate: Option<bool>,
+    /// 复用的会话 ID（可选，多轮迭代增强时传入上次结果中的会话 ID）
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// 历史注入方式（可选，默认 raw）
+    #[serde(default)]
+    pub history_mode: Option<HistoryMode>,
+    /// 注入的对话历史条数（可选，覆盖配置，上限 20）
+    #[serde(default)]
+    pub history_limit: Option<usize>,
+    /// 注入的 zhi 交互历史条数（可选，覆盖配置，上限 20）
+    #[serde(default)]
+    pub zhi_history_limit: Option<usize>,
+    /// 仅返回构建的请求体，不调用增强 API（可选，默认 false）
+    #[serde(default)]
+    pub dry_run: Option<bool>,
 }
 
 /// 提示词增强 MCP 工具
@@ -47,6 +69,45 @@ impl EnhanceTool {
                 "include_history": {
                     "type": "boolean",
                     "description": "是否包含对话历史（可选，默认 true）。"
+                },
+                "selected_history_ids": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "指定参与增强的对话历史 ID（可选）。提供时仅注入这些历史记录，不传时按时间取最近的记录。"
+                },
+                "selected_zhi_ids": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "指定参与增强的 zhi 交互历史 ID（可选）。提供时仅注入这些交互记录，不传时按时间取最近的记录。"
+                },
+                "regenerate": {
+                    "type": "boolean",
+                    "description": "重新生成模式（可选，默认 false）。对上次结果不满意时使用，将给出不同的增强版本。"
+                },
+                "conversation_id": {
+                    "type": "string",
+                    "description": "复用的会话 ID（可选）。多轮迭代优化同一提示词时传入上次结果中的会话 ID，保持上下文连续；不传时开启新会话。"
+                },
+                "history_mode": {
+                    "type": "string",
+                    "enum": ["raw", "summary"],
+                    "description": "历史注入方式（可选，默认 raw）。summary 将对话历史与交互记录在本地压缩为一段要点摘要，避免历史挤占提示词。"
+                },
+                "history_limit": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "maximum": 20,
+                    "description": "本次注入的对话历史条数（可选，默认使用配置值）。"
+                },
+                "zhi_history_limit": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "maximum": 20,
+                    "description": "本次注入的 zhi 交互历史条数（可选，默认使用配置值）。"
+                },
+                "dry_run": {
+                    "type": "boolean",
+                    "description": "仅构建并返回请求体（历史、代码上下文、zhi 摘要，token 已脱敏），不调用增强 API（可选，默认 false）。用于排查增强效果不佳的原因。"
                 }
             },
             "required": ["prompt"]
@@ -74,23 +135,22 @@ impl EnhanceTool {
     pub async fn enhance(request: EnhanceMcpRequest) -> Result<CallToolResult, McpError> {
         log_important!(info, "MCP enhance 工具被调用: prompt_len={}", request.prompt.len());
 
+        let project_root_path = normalize_optional_project_root(request.project_root_path.as_deref())
+            .map_err(|e| McpError::from(project_path_error(format!("项目路径无效: {}", e))))?;
+
         // 创建增强器
         let enhancer = match PromptEnhancer::from_acemcp_config().await {
             Ok(mut e) => {
-                if let Some(ref path) = request.project_root_path {
+                if let Some(ref path) = project_root_path {
                     e = e.with_project_root(path);
                 }
                 e
             }
             Err(e) => {
-                return Err(McpError::internal_error(
-                    format!("初始化增强器失败: {}", e),
-                    None
-                ));
+                return Err(to_mcp_error("初始化增强器失败", e));
             }
         };
 
-        let project_root_path = request.project_root_path.clone();
         let include_history = request.include_history.unwrap_or(true);
 
         let enhance_request = EnhanceRequest {
@@ -101,16 +161,36 @@ impl EnhanceTool {
             current_file_path: None,
             include_history,
             selected_history_ids: request.selected_history_ids.clone(),
+            selected_zhi_ids: request.selected_zhi_ids.clone(),
+            regenerate: request.regenerate.unwrap_or(false),
+            blob_names: None,
             // 中文注释：MCP 调用无需前端取消与请求关联，保持为空
             request_id: None,
+            conversation_id: request.conversation_id.clone(),
+            history_limit: request.history_limit,
+            zhi_history_limit: request.zhi_history_limit,
+            history_mode: request.history_mode.unwrap_or_default(),
+            persona_type: None,
+            mode: None,
+            dry_run: request.dry_run.unwrap_or(false),
+            verbose: false,
+            record_to: None,
             cancel_flag: None,
         };
 
         match enhancer.enhance(enhance_request).await {
             Ok(response) => {
+                if let Some(payload) = response.dry_run_payload {
+                    // dry_run：仅返回构建的请求，不写入历史
+                    let payload_text = serde_json::to_string_pretty(&payload).unwrap_or_default();
+                    return Ok(CallToolResult::success(vec![Content::text(format!(
+                        "## 增强请求预览（dry run，未调用 API）\n\n```json\n{}\n```",
+                        payload_text
+                    ))]));
+                }
                 if response.success {
-                    // 记录对话历史（仅在提供项目路径时）
-                    if let Some(ref path) = project_root_path {
+                    // 记录对话历史（仅在提供项目路径时；离线兜底结果不写入历史）
+                    if let (Some(path), false) = (project_root_path.as_ref(), response.fallback_used) {
                         if let Ok(manager) = ChatHistoryManager::new(path) {
                             let _ = manager.add_entry(
                                 &request.prompt,
@@ -120,28 +200,38 @@ impl EnhanceTool {
                         }
                     }
                     // 成功：返回增强后的提示词
-                    let result_text = format!(
-                        "## 增强后的提示词\n\n{}\n\n---\n*使用了 {} 个代码上下文块，{} 条对话历史*",
-                        response.enhanced_prompt,
-                        response.blob_count,
-                        response.history_count
-                    );
+                    let result_text = if response.fallback_used {
+                        format!(
+                            "## 增强后的提示词（离线兜底）\n\n{}\n\n---\n*增强服务不可达，以上为本地整理结果*",
+                            response.enhanced_prompt
+                        )
+                    } else {
+                        format!(
+                            "## 增强后的提示词\n\n{}\n\n---\n*使用了 {} 个代码上下文块，{} 条对话历史*\n*会话 ID: {}*",
+                            response.enhanced_prompt,
+                            response.blob_count,
+                            response.history_count,
+                            response.conversation_id.as_deref().unwrap_or_default()
+                        )
+                    };
+                    let result_text = match response.context_warning {
+                        Some(warning) => format!("{}\n\n⚠️ {}", result_text, warning.message()),
+                        None => result_text,
+                    };
                     Ok(CallToolResult::success(vec![Content::text(result_text)]))
                 } else {
                     // 失败：返回错误信息
-                    let error_text = format!(
+                    let mut error_text = format!(
                         "增强失败: {}",
                         response.error.unwrap_or_else(|| "未知错误".to_string())
                     );
+                    if response.truncated && !response.enhanced_prompt.is_empty() {
+                        error_text.push_str(&format!("\n\n已接收的部分结果：\n{}", response.enhanced_prompt));
+                    }
                     Ok(CallToolResult::success(vec![Content::text(error_text)]))
                 }
             }
-            Err(e) => {
-                Err(McpError::internal_error(
-                    format!("增强执行失败: {}", e),
-                    None
-                ))
-            }
+            Err(e) => Err(to_mcp_error("增强执行失败", e)),
         }
     }
 }
@@ -4,6 +4,9 @@
 pub mod types;
 pub mod core;
 pub mod history;
+pub mod offline;
+pub mod postprocess;
+pub mod trace;
 pub mod commands;
 pub mod mcp;
 
@@ -12,3 +15,4 @@ pub use mcp::EnhanceTool;
 pub use types::*;
 pub use core::*;
 pub use history::ChatHistoryManager;
+pub use trace::{ENHANCE_RECORD_DIR_ENV, ENHANCE_TRACE_ENV};
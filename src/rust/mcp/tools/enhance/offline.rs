@@ -0,0 +1,125 @@
+// 离线增强兜底
+// Augment API 不可达时，对原始提示词做确定性的本地整理，避免用户被阻塞
+
+/// 离线整理后追加的通用要求
+const OFFLINE_REQUIREMENTS: &[&str] = &[
+    "先理解上述任务的上下文与目标，不确定时先提问确认",
+    "保持现有代码风格，仅做必要的修改",
+    "完成后说明改动内容与验证方式",
+];
+
+/// 对提示词做确定性的本地整理
+///
+/// - 去除首尾空白，合并行内多余空白与连续空行
+/// - 将未包裹的缩进代码块包裹为 ``` 代码块（已有代码块内容保持不变）
+/// - 添加「任务 / 要求」结构标题
+pub fn offline_rewrite(prompt: &str) -> String {
+    let body = normalize_body(prompt.trim());
+
+    let mut output = String::from("## 任务\n\n");
+    output.push_str(&body);
+    output.push_str("\n\n## 要求\n\n");
+    for item in OFFLINE_REQUIREMENTS {
+        output.push_str("- ");
+        output.push_str(item);
+        output.push('\n');
+    }
+    output.trim_end().to_string()
+}
+
+fn is_indented_code(line: &str) -> bool {
+    line.starts_with("    ") || line.starts_with('\t')
+}
+
+/// 合并行内连续空白（保留行首缩进）
+fn collapse_inline_whitespace(line: &str) -> String {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    let words: Vec<&str> = trimmed.split_whitespace().collect();
+    format!("{}{}", indent, words.join(" "))
+}
+
+fn normalize_body(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_fence = false;
+    let mut in_indented_code = false;
+    let mut blank_run = 0;
+
+    for raw in text.lines() {
+        let line = raw.trim_end();
+
+        if line.trim_start().starts_with("```") {
+            if in_indented_code {
+                lines.push("```".to_string());
+                in_indented_code = false;
+            }
+            in_fence = !in_fence;
+            blank_run = 0;
+            lines.push(line.trim_start().to_string());
+            continue;
+        }
+
+        // 已有代码块内容保持原样
+        if in_fence {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        if line.is_empty() {
+            blank_run += 1;
+            if blank_run == 1 && !in_indented_code {
+                lines.push(String::new());
+            }
+            continue;
+        }
+
+        if is_indented_code(line) {
+            if !in_indented_code {
+                lines.push("```".to_string());
+                in_indented_code = true;
+            }
+            lines.push(line.to_string());
+            blank_run = 0;
+            continue;
+        }
+
+        if in_indented_code {
+            lines.push("```".to_string());
+            in_indented_code = false;
+            if blank_run > 0 {
+                lines.push(String::new());
+            }
+        }
+        blank_run = 0;
+        lines.push(collapse_inline_whitespace(line));
+    }
+
+    if in_indented_code || in_fence {
+        lines.push("```".to_string());
+    }
+
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offline_rewrite_is_structured_and_deterministic() {
+        let prompt = "  修复   登录 bug\n\n\n\n代码如下:\n    fn login() {}\n    // TODO\n结束  ";
+        let first = offline_rewrite(prompt);
+        assert_eq!(first, offline_rewrite(prompt));
+
+        assert!(first.starts_with("## 任务\n\n修复 登录 bug\n\n代码如下:\n```\n    fn login() {}\n    // TODO\n```\n结束"));
+        assert!(first.contains("## 要求"));
+        assert!(!first.contains("\n\n\n"));
+    }
+
+    #[test]
+    fn test_existing_fences_are_untouched() {
+        let prompt = "看看这个\n```rust\nlet  x  =  1;\n```";
+        let rewritten = offline_rewrite(prompt);
+        assert!(rewritten.contains("```rust\nlet  x  =  1;\n```"));
+    }
+}
@@ -0,0 +1,129 @@
+// 增强结果后处理
+// 对提取出的增强提示词按配置顺序执行整理步骤（默认不做任何处理）
+
+use crate::log_important;
+
+/// 模板中代表增强结果的占位符
+const PROMPT_PLACEHOLDER: &str = "{prompt}";
+
+/// 后处理步骤
+#[derive(Debug, Clone, PartialEq)]
+pub enum PostProcessStep {
+    /// 去除首尾空白与每行行尾空白
+    Trim,
+    /// 去除 Markdown 标题标记（`## 标题` → `标题`，代码块内保持不变）
+    StripMarkdownHeaders,
+    /// 在结果前添加模板；模板包含 `{prompt}` 时替换占位符
+    PrependTemplate(String),
+}
+
+impl PostProcessStep {
+    /// 按配置名称解析步骤（`prepend_template` 需要配置模板，否则跳过）
+    pub fn parse(name: &str, template: Option<&str>) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "trim" => Some(Self::Trim),
+            "strip_markdown_headers" => Some(Self::StripMarkdownHeaders),
+            "prepend_template" => template
+                .filter(|t| !t.trim().is_empty())
+                .map(|t| Self::PrependTemplate(t.to_string())),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, text: &str) -> String {
+        match self {
+            Self::Trim => text
+                .lines()
+                .map(str::trim_end)
+                .collect::<Vec<_>>()
+                .join("\n")
+                .trim()
+                .to_string(),
+            Self::StripMarkdownHeaders => strip_markdown_headers(text),
+            Self::PrependTemplate(template) if template.contains(PROMPT_PLACEHOLDER) => {
+                template.replace(PROMPT_PLACEHOLDER, text)
+            }
+            Self::PrependTemplate(template) => format!("{}{}", template, text),
+        }
+    }
+}
+
+/// 从配置构建后处理流水线（未知步骤记录警告后忽略）
+pub fn parse_post_process(names: &[String], template: Option<&str>) -> Vec<PostProcessStep> {
+    names
+        .iter()
+        .filter_map(|name| {
+            let step = PostProcessStep::parse(name, template);
+            if step.is_none() {
+                log_important!(warn, "忽略无效的增强后处理步骤: {}（prepend_template 需同时配置模板）", name);
+            }
+            step
+        })
+        .collect()
+}
+
+/// 依次执行后处理步骤
+pub fn apply_post_process(text: &str, steps: &[PostProcessStep]) -> String {
+    steps
+        .iter()
+        .fold(text.to_string(), |acc, step| step.apply(&acc))
+}
+
+fn strip_markdown_headers(text: &str) -> String {
+    let mut in_fence = false;
+    text.lines()
+        .map(|line| {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+                return line;
+            }
+            if in_fence {
+                return line;
+            }
+            let trimmed = line.trim_start();
+            let level = trimmed.chars().take_while(|c| *c == '#').count();
+            if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+                trimmed[level..].trim_start()
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_pipeline_is_identity() {
+        let text = "## 目标\n内容  \n";
+        assert_eq!(apply_post_process(text, &[]), text);
+    }
+
+    #[test]
+    fn test_prepend_template_then_trim() {
+        let steps = parse_post_process(
+            &["prepend_template".to_string(), "trim".to_string(), "unknown".to_string()],
+            Some("【项目规则】遵循 AGENTS.md\n\n"),
+        );
+        assert_eq!(steps.len(), 2);
+
+        let output = apply_post_process("重构登录模块  \n\n\n", &steps);
+        assert_eq!(output, "【项目规则】遵循 AGENTS.md\n\n重构登录模块");
+
+        let wrapped = apply_post_process("内容", &[PostProcessStep::PrependTemplate("<task>{prompt}</task>".to_string())]);
+        assert_eq!(wrapped, "<task>内容</task>");
+    }
+
+    #[test]
+    fn test_strip_markdown_headers_keeps_code_blocks() {
+        let text = "## 任务\n说明\n```bash\n# 安装依赖\n```\n#标签";
+        assert_eq!(
+            apply_post_process(text, &[PostProcessStep::StripMarkdownHeaders]),
+            "任务\n说明\n```bash\n# 安装依赖\n```\n#标签"
+        );
+        assert!(PostProcessStep::parse("prepend_template", None).is_none());
+    }
+}
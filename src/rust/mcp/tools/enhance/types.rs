@@ -1,8 +1,11 @@
 // 提示词增强模块的类型定义
 
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::sync::{Arc, atomic::AtomicBool};
 
+use crate::mcp::utils::SanshuError;
+
 /// 增强请求参数
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnhanceRequest {
@@ -24,9 +27,45 @@ pub struct EnhanceRequest {
     /// 指定参与增强的历史记录 ID（为空时使用默认最近历史）
     #[serde(default)]
     pub selected_history_ids: Option<Vec<String>>,
+    /// 指定参与增强的 zhi 交互历史 ID（为空时使用最近的 zhi 历史）
+    #[serde(default)]
+    pub selected_zhi_ids: Option<Vec<String>>,
+    /// 重新生成模式：要求给出不同的结果，并排除上一次增强结果的历史注入
+    #[serde(default)]
+    pub regenerate: bool,
+    /// 显式指定的 blob_names（提供时跳过 projects.json 自动加载）
+    #[serde(default)]
+    pub blob_names: Option<Vec<String>>,
     /// 请求 ID（用于前后端与流式事件关联）
     #[serde(default)]
     pub request_id: Option<String>,
+    /// 复用的会话 ID（多轮迭代增强时传入上次响应的 conversation_id，为空时生成新 ID）
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// 本次注入的对话历史条数（覆盖配置，上限 20）
+    #[serde(default)]
+    pub history_limit: Option<usize>,
+    /// 本次注入的 zhi 交互历史条数（覆盖配置，上限 20）
+    #[serde(default)]
+    pub zhi_history_limit: Option<usize>,
+    /// 历史注入方式：原样注入（raw）或本地压缩为单段上下文摘要（summary）
+    #[serde(default)]
+    pub history_mode: HistoryMode,
+    /// 请求体 persona_type 覆盖（未指定时使用配置默认值，缺省为 1）
+    #[serde(default)]
+    pub persona_type: Option<u32>,
+    /// 请求体 mode 覆盖：CHAT | AGENT（未指定时使用配置默认值，缺省为 CHAT）
+    #[serde(default)]
+    pub mode: Option<String>,
+    /// 仅构建请求体并返回，不调用 API（用于排查注入的上下文）
+    #[serde(default)]
+    pub dry_run: bool,
+    /// 在响应中附带注入上下文的明细（injected_context），默认关闭
+    #[serde(default)]
+    pub verbose: bool,
+    /// 将原始 SSE 响应录制到该文件，便于通过 `PromptEnhancer::replay_recorded` 离线复现异常结果
+    #[serde(default)]
+    pub record_to: Option<PathBuf>,
     /// 取消标记（仅后端内部使用，前端不可见）
     #[serde(skip)]
     pub cancel_flag: Option<Arc<AtomicBool>>,
@@ -36,8 +75,19 @@ fn default_include_history() -> bool {
     true
 }
 
+/// 历史注入方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryMode {
+    /// 对话历史作为 chat_history 原样注入，zhi 历史作为摘要附加到消息
+    #[default]
+    Raw,
+    /// 合并对话历史与 zhi 历史，本地截断去重后作为单段要点列表注入（不额外调用 API）
+    Summary,
+}
+
 /// 增强响应结果
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct EnhanceResponse {
     /// 增强后的提示词
     pub enhanced_prompt: String,
@@ -48,6 +98,9 @@ pub struct EnhanceResponse {
     /// 错误信息（如有）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// 网络 / 上游失败的错误种类（与 `SanshuError::kind` 一致，如 network、upstream）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<String>,
     /// 使用的 blob 数量
     #[serde(default)]
     pub blob_count: usize,
@@ -69,6 +122,235 @@ pub struct EnhanceResponse {
     /// 请求 ID（用于前后端关联）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub request_id: Option<String>,
+    /// 是否使用了离线兜底（API 不可达时的本地整理结果，前端需标注）
+    #[serde(default)]
+    pub fallback_used: bool,
+    /// 是否被用户取消（区别于服务端失败，前端展示中性状态）
+    #[serde(default)]
+    pub cancelled: bool,
+    /// 增强结果被截断（有起始标签但无结束标签），此时 enhanced_prompt 为已接收的部分内容
+    #[serde(default)]
+    pub truncated: bool,
+    /// 超出长度上限时的裁剪情况（未裁剪时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_trim: Option<PromptTrimReport>,
+    /// dry_run 模式下构建的请求（URL、脱敏请求头与请求体）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dry_run_payload: Option<serde_json::Value>,
+    /// 实际使用的会话 ID（前端下一轮增强时回传以保持多轮连续）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conversation_id: Option<String>,
+    /// 上下文预检警告：请求了历史/代码上下文但无法提供（未传项目路径或项目未索引）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_warning: Option<ContextWarning>,
+    /// 输入 token 数（上游流未返回用量时为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens: Option<u64>,
+    /// 输出 token 数（上游流未返回用量时为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_tokens: Option<u64>,
+    /// 注入上下文明细（仅 verbose 请求返回）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub injected_context: Option<InjectedContextSummary>,
+}
+
+impl EnhanceResponse {
+    /// 标记为失败并附带错误信息
+    pub fn failed(mut self, error: impl Into<String>) -> Self {
+        self.success = false;
+        self.error = Some(error.into());
+        self
+    }
+
+    /// 标记为失败，错误信息与种类取自 `SanshuError`
+    pub fn failed_with(self, error: SanshuError) -> Self {
+        let mut response = self.failed(error.to_string());
+        response.error_kind = Some(error.kind().to_string());
+        response
+    }
+
+    /// 标记为用户取消
+    pub fn cancelled(self) -> Self {
+        let mut response = self.failed("已取消增强请求");
+        response.cancelled = true;
+        response
+    }
+
+    /// dry_run 结果：附带构建的请求，不调用 API
+    pub fn with_dry_run_payload(mut self, payload: serde_json::Value) -> Self {
+        self.success = true;
+        self.dry_run_payload = Some(payload);
+        self
+    }
+
+    /// 填充从流式响应中提取的结果与 token 用量
+    pub fn completed(
+        mut self,
+        enhanced_prompt: String,
+        success: bool,
+        truncated: bool,
+        error: Option<String>,
+        usage: Option<TokenUsage>,
+    ) -> Self {
+        self.enhanced_prompt = enhanced_prompt;
+        self.success = success;
+        self.truncated = truncated;
+        self.error = error;
+        self.prompt_tokens = usage.and_then(|u| u.prompt_tokens);
+        self.completion_tokens = usage.and_then(|u| u.completion_tokens);
+        self
+    }
+}
+
+/// 本次增强实际注入的上下文明细
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InjectedContextSummary {
+    /// blob 来源的项目根路径（未使用代码上下文时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob_source_root: Option<String>,
+    /// 注入的 blob 数量
+    pub blob_count: usize,
+    /// 注入的对话历史条目 ID（按时间顺序，不含兜底条目与被裁剪的条目）
+    pub history_ids: Vec<String>,
+    /// 注入的 zhi 交互历史条数
+    pub zhi_count: usize,
+    /// 是否使用了“历史为空兜底”
+    pub history_fallback_used: bool,
+    /// 是否以摘要方式注入历史（history_mode = summary）
+    pub summary_mode: bool,
+}
+
+/// 增强配置校验结果（用于“测试连接”，不包含 token）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnhanceConfigCheck {
+    /// 配置可用（已探测时还要求可连通）
+    pub ok: bool,
+    /// 规范化后的 base_url（未配置时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// 连通性探测结果（未探测时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reachable: Option<bool>,
+    /// 面向用户的说明
+    pub message: String,
+}
+
+impl EnhanceConfigCheck {
+    /// 构造校验失败的结果
+    pub fn invalid(base_url: Option<String>, message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            base_url,
+            reachable: None,
+            message: message.into(),
+        }
+    }
+}
+
+/// 录制的 SSE 响应离线回放结果（与在线请求使用相同的拆行与提取逻辑）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordedReplay {
+    /// 提取并后处理后的增强提示词（截断时为已接收的部分内容）
+    pub enhanced_prompt: String,
+    /// 所有 SSE 事件中 text 字段拼接后的原始文本
+    pub accumulated_text: String,
+    /// 增强结果是否被截断
+    pub truncated: bool,
+    /// 提取失败或截断时的错误信息
+    pub error: Option<String>,
+    /// 流中携带的 token 用量
+    pub usage: Option<TokenUsage>,
+}
+
+/// 流式响应中的 token 用量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>,
+}
+
+impl TokenUsage {
+    /// 从单个 SSE JSON 事件中提取用量（兼容顶层 `token_usage`/`usage` 与 `nodes[].token_usage`）
+    pub fn from_sse_event(event: &serde_json::Value) -> Option<Self> {
+        let nodes = event
+            .get("nodes")
+            .and_then(|n| n.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|node| node.get("token_usage"));
+        let mut found: Option<Self> = None;
+        for usage in ["token_usage", "usage"].iter().filter_map(|k| event.get(*k)).chain(nodes) {
+            let count = |keys: &[&str]| keys.iter().find_map(|k| usage.get(*k).and_then(|v| v.as_u64()));
+            let parsed = Self {
+                prompt_tokens: count(&["prompt_tokens", "input_tokens"]),
+                completion_tokens: count(&["completion_tokens", "output_tokens"]),
+            };
+            if parsed.prompt_tokens.is_some() || parsed.completion_tokens.is_some() {
+                found = Some(found.unwrap_or_default().merge(parsed));
+            }
+        }
+        found
+    }
+
+    /// 合并用量：新事件中出现的字段覆盖旧值（上游通常发送累计值）
+    pub fn merge(self, newer: Self) -> Self {
+        Self {
+            prompt_tokens: newer.prompt_tokens.or(self.prompt_tokens),
+            completion_tokens: newer.completion_tokens.or(self.completion_tokens),
+        }
+    }
+}
+
+/// 上下文预检警告类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextWarning {
+    /// 未提供项目根路径，对话历史与代码上下文均不可用
+    NoProjectRoot,
+    /// 提供了项目根路径，但 projects.json 中没有该项目的索引（无代码上下文）
+    ProjectNotIndexed,
+}
+
+impl ContextWarning {
+    /// 面向用户的提示（包含处理建议）
+    pub fn message(&self) -> &'static str {
+        match self {
+            ContextWarning::NoProjectRoot => {
+                "未提供项目根路径（project_root_path），本次增强未使用对话历史与代码上下文；请传入项目根目录后重试"
+            }
+            ContextWarning::ProjectNotIndexed => {
+                "项目尚未建立代码索引，本次增强未使用代码上下文；请先通过 sou 工具或设置页触发索引"
+            }
+        }
+    }
+}
+
+/// 提示词长度裁剪报告（按 zhi 摘要 → 对话历史 → 用户提示词中段的顺序裁剪）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PromptTrimReport {
+    /// 长度上限（字符数）
+    pub max_chars: usize,
+    /// zhi 摘要被移除的字符数
+    pub zhi_summary_chars_removed: usize,
+    /// 被丢弃的最早对话历史条数
+    pub history_entries_dropped: usize,
+    /// 用户提示词中段被移除的字符数
+    pub prompt_chars_removed: usize,
+    /// 固定模板（系统提示与指令）本身已超出上限，裁剪无法满足（此时不截断提示词）
+    #[serde(default)]
+    pub exceeds_budget: bool,
+}
+
+/// 增强使用的 blob 信息（供前端展示与编辑）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnhanceBlobsInfo {
+    /// 解析到的 blob 列表
+    pub blob_names: Vec<String>,
+    /// blob 数量
+    pub blob_count: usize,
+    /// 实际匹配到的项目根路径（未匹配时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob_source_root: Option<String>,
 }
 
 /// 流式增强事件（通过 Tauri Event 推送给前端）
@@ -76,7 +358,7 @@ pub struct EnhanceResponse {
 pub struct EnhanceStreamEvent {
     /// 请求 ID（用于并发请求关联）
     pub request_id: String,
-    /// 事件类型: "chunk" | "complete" | "error"
+    /// 事件类型: "chunk" | "complete" | "error" | "cancelled"
     pub event_type: String,
     /// 流式文本块（仅 chunk 类型有值）
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -93,6 +375,12 @@ pub struct EnhanceStreamEvent {
     /// 进度百分比（0-100）
     #[serde(default)]
     pub progress: u8,
+    /// 输入 token 数（仅 complete 类型且上游返回用量时有值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens: Option<u64>,
+    /// 输出 token 数（仅 complete 类型且上游返回用量时有值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_tokens: Option<u64>,
 }
 
 impl EnhanceStreamEvent {
@@ -106,6 +394,8 @@ impl EnhanceStreamEvent {
             enhanced_prompt: None,
             error: None,
             progress,
+            prompt_tokens: None,
+            completion_tokens: None,
         }
     }
 
@@ -119,7 +409,18 @@ impl EnhanceStreamEvent {
             enhanced_prompt: Some(enhanced_prompt.to_string()),
             error: None,
             progress: 100,
+            prompt_tokens: None,
+            completion_tokens: None,
+        }
+    }
+
+    /// 附加 token 用量（用于完成事件）
+    pub fn with_usage(mut self, usage: Option<TokenUsage>) -> Self {
+        if let Some(usage) = usage {
+            self.prompt_tokens = usage.prompt_tokens;
+            self.completion_tokens = usage.completion_tokens;
         }
+        self
     }
 
     /// 创建错误事件
@@ -132,6 +433,23 @@ impl EnhanceStreamEvent {
             enhanced_prompt: None,
             error: Some(message.to_string()),
             progress: 0,
+            prompt_tokens: None,
+            completion_tokens: None,
+        }
+    }
+
+    /// 创建取消事件（用户主动取消，不携带错误信息）
+    pub fn cancelled(request_id: &str) -> Self {
+        Self {
+            request_id: request_id.to_string(),
+            event_type: "cancelled".to_string(),
+            chunk: None,
+            accumulated_text: None,
+            enhanced_prompt: None,
+            error: None,
+            progress: 0,
+            prompt_tokens: None,
+            completion_tokens: None,
         }
     }
 }
@@ -8,26 +8,65 @@ use std::collections::HashMap;
 use std::sync::RwLock;
 use std::time::{Duration, Instant};
 
+use super::favorites::IconFavorites;
 use super::types::{
-    IconItem, IconSearchRequest, IconSearchResult,
+    IconError, IconItem, IconSearchRequest, IconSearchResult,
     IconfontApiResponse, IconfontIcon,
 };
 use crate::log_debug;
+use crate::mcp::utils::{is_timeout_error, timeout_error, SanshuError};
 
 // ============ 常量定义 ============
 
 /// Iconfont 搜索 API 端点
 const ICONFONT_SEARCH_API: &str = "https://www.iconfont.cn/api/icon/search.json";
 
+/// 覆盖 Iconfont 搜索 API 地址的环境变量（便于脚本与测试指向模拟服务）
+pub const ICON_SEARCH_API_ENV: &str = "SANSHU_ICON_SEARCH_API";
+
+/// Iconfont 图标详情端点
+pub(crate) const ICONFONT_DETAIL_API: &str = "https://www.iconfont.cn/api/icon/detail.json";
+
+/// 缩略图尺寸（像素）
+const PREVIEW_THUMBNAIL_SIZE: u32 = 64;
+
 /// 默认缓存过期时间（30分钟）
 const DEFAULT_CACHE_EXPIRY_SECS: u64 = 30 * 60;
 
-/// HTTP 请求超时时间
-const REQUEST_TIMEOUT_SECS: u64 = 30;
-
 /// 最大重试次数
 const MAX_RETRIES: usize = 3;
 
+/// Iconfont 成功响应 code
+const SUCCESS_CODE: i32 = 200;
+
+/// 已知的限流 code
+const RATE_LIMIT_CODES: &[i32] = &[429];
+
+/// 限流响应 message 中的关键字（部分限流以普通错误 code 返回）
+const RATE_LIMIT_KEYWORDS: &[&str] = &["频繁", "too many", "rate limit"];
+
+/// 图标风格可选值（每项为：标准值 + 常见同义词）
+const STYLE_OPTIONS: &[(&str, &[&str])] = &[
+    ("line", &["outline", "stroke", "线性"]),
+    ("fill", &["filled", "solid", "面性"]),
+    ("flat", &["扁平"]),
+    ("all", &["any", "全部"]),
+];
+
+/// 填充类型可选值
+const FILLS_OPTIONS: &[(&str, &[&str])] = &[
+    ("single", &["mono", "monochrome", "单色"]),
+    ("multi", &["multicolor", "color", "多色"]),
+    ("all", &["any", "全部"]),
+];
+
+/// 排序方式可选值
+const SORT_OPTIONS: &[(&str, &[&str])] = &[
+    ("relate", &["relevance", "relevant", "相关度"]),
+    ("new", &["newest", "latest", "最新"]),
+    ("hot", &["popular", "最热"]),
+];
+
 // ============ 缓存结构 ============
 
 /// 缓存条目
@@ -52,9 +91,9 @@ static CACHE_EXPIRY_SECS: Lazy<RwLock<u64>> =
 /// 
 /// 注意：iconfont.cn 是国内网站，不需要代理
 /// 显式禁用代理以避免用户系统代理设置（用于翻墙）干扰
-fn create_http_client() -> Result<Client> {
+fn create_http_client(timeout_secs: u64) -> Result<Client> {
     Client::builder()
-        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .timeout(Duration::from_secs(timeout_secs))
         .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
         .no_proxy()  // 禁用代理，直连国内网站
         .build()
@@ -165,16 +204,59 @@ pub fn set_cache_expiry_minutes(minutes: u64) {
     }
 }
 
+// ============ 参数校验 ============
+
+/// 将筛选参数规范化为 Iconfont 接受的取值（大小写与同义词兼容）
+fn normalize_option(
+    field: &str,
+    value: Option<String>,
+    options: &[(&str, &[&str])],
+) -> Result<Option<String>> {
+    let raw = match value {
+        Some(v) if !v.trim().is_empty() => v,
+        _ => return Ok(None),
+    };
+    let key = raw.trim().to_lowercase();
+    options
+        .iter()
+        .find(|(canonical, synonyms)| *canonical == key || synonyms.contains(&key.as_str()))
+        .map(|(canonical, _)| Some(canonical.to_string()))
+        .ok_or_else(|| {
+            let valid: Vec<&str> = options.iter().map(|(canonical, _)| *canonical).collect();
+            anyhow!("无效的 {} 参数: {}，可选值: {}", field, raw, valid.join(" | "))
+        })
+}
+
+/// 校验并规范化搜索请求的 style / fills / sort_type
+pub fn normalize_search_request(mut request: IconSearchRequest) -> Result<IconSearchRequest> {
+    request.style = normalize_option("style", request.style, STYLE_OPTIONS)?;
+    request.fills = normalize_option("fills", request.fills, FILLS_OPTIONS)?;
+    request.sort_type = normalize_option("sort_type", request.sort_type, SORT_OPTIONS)?;
+    Ok(request)
+}
+
 // ============ API 调用 ============
 
 /// 搜索图标
 /// 
 /// 调用 Iconfont API 搜索图标，支持缓存
-pub async fn search_icons(request: IconSearchRequest) -> Result<IconSearchResult> {
+pub async fn search_icons(request: IconSearchRequest, timeout_secs: u64) -> Result<IconSearchResult> {
+    // 中文注释：收藏夹搜索在本地完成，允许空关键词（返回全部收藏）
+    if request.from_favorites.unwrap_or(false) {
+        return IconFavorites::global()
+            .search_page(
+                &request.query,
+                request.page.unwrap_or(1),
+                request.page_size.unwrap_or(50),
+            )
+            .map_err(|e| anyhow!("搜索收藏夹失败: {}", e));
+    }
+
     // 参数验证
     if request.query.trim().is_empty() {
         return Err(anyhow!("搜索关键词不能为空"));
     }
+    let request = normalize_search_request(request)?;
     
     // 检查缓存
     let cache_key = generate_cache_key(&request);
@@ -210,7 +292,7 @@ pub async fn search_icons(request: IconSearchRequest) -> Result<IconSearchResult
     }
     
     // 执行请求（带重试）
-    let result = retry_search_request(&params).await?;
+    let result = retry_search_request(&search_api_url(), &params, timeout_secs).await?;
     
     // 解析响应
     let search_result = parse_search_response(result, page, page_size)?;
@@ -221,9 +303,24 @@ pub async fn search_icons(request: IconSearchRequest) -> Result<IconSearchResult
     Ok(search_result)
 }
 
+/// 搜索 API 地址（环境变量优先）
+fn search_api_url() -> String {
+    std::env::var(ICON_SEARCH_API_ENV)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| ICONFONT_SEARCH_API.to_string())
+}
+
 /// 带重试的搜索请求
-async fn retry_search_request(params: &HashMap<&str, String>) -> Result<IconfontApiResponse> {
-    let client = create_http_client()?;
+///
+/// 超时与限流错误不再重试，避免慢上游让调用耗时成倍增长、限流时加重负载
+async fn retry_search_request(
+    url: &str,
+    params: &HashMap<&str, String>,
+    timeout_secs: u64,
+) -> Result<IconfontApiResponse> {
+    let client = create_http_client(timeout_secs)?;
     let mut last_error = None;
     
     for attempt in 0..MAX_RETRIES {
@@ -234,8 +331,9 @@ async fn retry_search_request(params: &HashMap<&str, String>) -> Result<Iconfont
             log_debug!("图标搜索重试第 {} 次", attempt + 1);
         }
         
-        match execute_search_request(&client, params).await {
+        match execute_search_request(&client, url, params, timeout_secs).await {
             Ok(response) => return Ok(response),
+            Err(e) if is_timeout_error(&e) || is_rate_limited(&e) => return Err(e),
             Err(e) => {
                 last_error = Some(e);
             }
@@ -248,43 +346,80 @@ async fn retry_search_request(params: &HashMap<&str, String>) -> Result<Iconfont
 /// 执行单次搜索请求
 async fn execute_search_request(
     client: &Client,
+    url: &str,
     params: &HashMap<&str, String>,
+    timeout_secs: u64,
 ) -> Result<IconfontApiResponse> {
     let response = client
-        .post(ICONFONT_SEARCH_API)
+        .post(url)
         .form(params)
         .send()
         .await
         .map_err(|e| {
             if e.is_timeout() {
-                anyhow!("请求超时")
+                anyhow::Error::from(timeout_error("Iconfont", timeout_secs))
             } else if e.is_connect() {
-                anyhow!("网络连接失败")
+                SanshuError::Network("无法连接 Iconfont".to_string()).into()
             } else {
-                anyhow!("请求失败: {}", e)
+                SanshuError::Network(e.to_string()).into()
             }
         })?;
     
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok());
+        return Err(IconError::RateLimited { retry_after }.into());
+    }
+    
     if !response.status().is_success() {
-        return Err(anyhow!("API 返回错误状态码: {}", response.status()));
+        return Err(SanshuError::Upstream {
+            status: response.status().as_u16(),
+            message: "Iconfont 搜索 API".to_string(),
+        }
+        .into());
     }
     
     let api_response: IconfontApiResponse = response
         .json()
         .await
-        .map_err(|e| anyhow!("解析响应 JSON 失败: {}", e))?;
+        .map_err(|e| {
+            if e.is_timeout() {
+                anyhow::Error::from(timeout_error("Iconfont", timeout_secs))
+            } else {
+                SanshuError::Parse(format!("Iconfont 响应 JSON: {}", e)).into()
+            }
+        })?;
     
-    if api_response.code != 200 {
-        return Err(anyhow!(
-            "API 返回错误: code={}, message={}",
-            api_response.code,
-            api_response.message.as_deref().unwrap_or("未知错误")
-        ));
-    }
+    check_response_code(&api_response)?;
     
     Ok(api_response)
 }
 
+/// 判断错误是否为限流
+fn is_rate_limited(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<IconError>(), Some(IconError::RateLimited { .. }))
+}
+
+/// 校验响应 code，限流时返回 `IconError::RateLimited`
+fn check_response_code(response: &IconfontApiResponse) -> Result<(), IconError> {
+    if response.code == SUCCESS_CODE {
+        return Ok(());
+    }
+    
+    let message = response.message.clone().unwrap_or_else(|| "未知错误".to_string());
+    let lower = message.to_lowercase();
+    if RATE_LIMIT_CODES.contains(&response.code)
+        || RATE_LIMIT_KEYWORDS.iter().any(|k| lower.contains(k))
+    {
+        return Err(IconError::RateLimited { retry_after: None });
+    }
+    
+    Err(IconError::Api { code: response.code, message })
+}
+
 /// 解析搜索响应
 fn parse_search_response(
     response: IconfontApiResponse,
@@ -296,6 +431,10 @@ fn parse_search_response(
     let icons: Vec<IconItem> = data.icons
         .into_iter()
         .map(IconItem::from)
+        .map(|mut icon| {
+            icon.preview_url = build_preview_url(&icon);
+            icon
+        })
         .collect();
     
     let total = data.count;
@@ -313,7 +452,7 @@ fn parse_search_response(
 /// 获取图标 SVG 内容
 /// 
 /// 根据图标 ID 获取 SVG 内容（如果搜索结果中已包含则直接返回）
-pub async fn get_icon_svg(id: u64, cached_svg: Option<String>) -> Result<String> {
+pub async fn get_icon_svg(id: u64, cached_svg: Option<String>, timeout_secs: u64) -> Result<String> {
     // 如果已有缓存的 SVG 内容，直接返回
     if let Some(svg) = cached_svg {
         if !svg.is_empty() {
@@ -324,20 +463,39 @@ pub async fn get_icon_svg(id: u64, cached_svg: Option<String>) -> Result<String>
     // 否则需要单独请求（Iconfont 的图标详情 API）
     // 注意：Iconfont 的搜索结果通常已包含 show_svg 字段，
     // 这里提供备用方案
-    let svg_url = format!(
-        "https://www.iconfont.cn/api/icon/detail.json?id={}",
-        id
-    );
-    
-    let client = create_http_client()?;
+    fetch_icon_detail(ICONFONT_DETAIL_API, id, timeout_secs)
+        .await?
+        .show_svg
+        .ok_or_else(|| SanshuError::NotFound(format!("图标 {} 的 SVG 内容", id)).into())
+}
+
+/// 请求图标详情 API，返回图标原始信息
+pub(crate) async fn fetch_icon_detail(
+    detail_api: &str,
+    id: u64,
+    timeout_secs: u64,
+) -> Result<IconfontIcon> {
+    let svg_url = format!("{}?id={}", detail_api, id);
+    
+    let client = create_http_client(timeout_secs)?;
     let response = client
         .get(&svg_url)
         .send()
         .await
-        .map_err(|e| anyhow!("获取图标详情失败: {}", e))?;
+        .map_err(|e| {
+            if e.is_timeout() {
+                anyhow::Error::from(timeout_error("Iconfont", timeout_secs))
+            } else {
+                SanshuError::Network(format!("获取图标详情失败: {}", e)).into()
+            }
+        })?;
     
     if !response.status().is_success() {
-        return Err(anyhow!("获取图标详情失败: {}", response.status()));
+        return Err(SanshuError::Upstream {
+            status: response.status().as_u16(),
+            message: "Iconfont 图标详情 API".to_string(),
+        }
+        .into());
     }
     
     // 解析详情响应
@@ -355,7 +513,7 @@ pub async fn get_icon_svg(id: u64, cached_svg: Option<String>) -> Result<String>
     let detail: DetailResponse = response
         .json()
         .await
-        .map_err(|e| anyhow!("解析图标详情失败: {}", e))?;
+        .map_err(|e| SanshuError::Parse(format!("图标详情: {}", e)))?;
     
     if detail.code != 200 {
         return Err(anyhow!("获取图标详情失败: API 返回错误"));
@@ -363,19 +521,21 @@ pub async fn get_icon_svg(id: u64, cached_svg: Option<String>) -> Result<String>
     
     detail.data
         .and_then(|d| d.icon)
-        .and_then(|i| i.show_svg)
-        .ok_or_else(|| anyhow!("图标 {} 没有 SVG 内容", id))
+        .ok_or_else(|| SanshuError::NotFound(format!("图标 {}", id)).into())
 }
 
 // ============ 辅助函数 ============
 
-/// 构建图标预览 URL
-pub fn build_preview_url(id: u64) -> String {
-    // Iconfont 的图标预览 URL 格式
-    format!(
-        "https://at.alicdn.com/t/c/font_{}_preview.svg",
-        id
-    )
+/// 构建图标缩略图 URL
+///
+/// Iconfont 没有公开的独立缩略图地址，这里复用下载接口的小尺寸 PNG（与 `build_download_url` 同源）。
+/// 该接口只按全站唯一的图标 ID 定位图标，图标库 ID（`repository_id`）不参与寻址，
+/// 因此缺少图标库 ID 的图标同样可以生成缩略图；唯一的必需字段是图标 ID，缺失（为 0）时返回 None
+pub fn build_preview_url(icon: &IconItem) -> Option<String> {
+    if icon.id == 0 {
+        return None;
+    }
+    Some(build_download_url(icon.id, "png", Some(PREVIEW_THUMBNAIL_SIZE)))
 }
 
 /// 构建图标下载 URL
@@ -396,3 +556,161 @@ pub fn build_download_url(id: u64, format: &str, size: Option<u32>) -> String {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::http_mock::{MockResponse, MockServer};
+    use std::net::TcpListener;
+
+    /// 启动一个接受连接但从不响应的本地服务器
+    fn start_stalling_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut held = Vec::new();
+            for stream in listener.incoming().flatten() {
+                held.push(stream);
+            }
+        });
+        format!("http://{}/api/icon/search.json", addr)
+    }
+
+    #[tokio::test]
+    async fn test_stalled_upstream_returns_timeout_error_without_retry() {
+        let url = start_stalling_server();
+        let mut params = HashMap::new();
+        params.insert("q", "home".to_string());
+
+        let start = Instant::now();
+        let err = retry_search_request(&url, &params, 1).await.unwrap_err();
+
+        assert!(start.elapsed() < Duration::from_secs(3));
+        assert!(is_timeout_error(&err), "unexpected error: {}", err);
+        assert!(err.to_string().contains("超时"));
+    }
+
+    /// 启动 HTTP 服务器，返回指定状态码与 JSON 响应体
+    fn start_search_server(status: u16, reason: &str, body: &'static str) -> String {
+        MockServer::respond_with(MockResponse::json(body).with_status(status, reason)).url("/api/icon/search.json")
+    }
+
+    #[tokio::test]
+    async fn test_failure_paths_produce_typed_errors() {
+        let client = create_http_client(5).unwrap();
+        let params = HashMap::new();
+
+        let url = start_search_server(503, "Service Unavailable", "");
+        let err = execute_search_request(&client, &url, &params, 5).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SanshuError>(),
+            Some(SanshuError::Upstream { status: 503, .. })
+        ));
+
+        let url = start_search_server(200, "OK", "not json");
+        let err = execute_search_request(&client, &url, &params, 5).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<SanshuError>(), Some(SanshuError::Parse(_))));
+
+        // 端口已释放，连接被拒绝
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let url = format!("http://{}/api/icon/search.json", closed);
+        let err = execute_search_request(&client, &url, &params, 5).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<SanshuError>(), Some(SanshuError::Network(_))));
+
+        let url = start_search_server(200, "OK", r#"{"code":200,"data":{}}"#);
+        let err = fetch_icon_detail(&url, 42, 5).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<SanshuError>(), Some(SanshuError::NotFound(_))));
+    }
+
+    fn api_response(json: &str) -> IconfontApiResponse {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_rate_limited_code_returns_typed_error() {
+        let response = api_response(r#"{"code":429,"message":"请求过于频繁"}"#);
+        assert!(matches!(
+            check_response_code(&response),
+            Err(IconError::RateLimited { retry_after: None })
+        ));
+
+        // 普通错误 code 保留 message
+        let response = api_response(r#"{"code":500,"message":"服务异常"}"#);
+        let err = check_response_code(&response).unwrap_err();
+        assert!(matches!(err, IconError::Api { code: 500, .. }));
+        assert!(err.to_string().contains("服务异常"));
+    }
+
+    #[test]
+    fn test_success_code_parses_normally() {
+        let response = api_response(
+            r#"{"code":200,"data":{"icons":[{"id":1,"name":"home","font_class":"home","repository":{"id":7}}],"count":1}}"#,
+        );
+        assert!(check_response_code(&response).is_ok());
+
+        let result = parse_search_response(response, 1, 50).unwrap();
+        assert_eq!(result.total, 1);
+        assert_eq!(result.icons[0].name, "home");
+        assert!(result.icons[0].preview_url.is_some());
+        assert!(!result.has_more);
+    }
+
+    #[test]
+    fn test_preview_url_uses_download_thumbnail() {
+        let mut icon: IconItem = serde_json::from_str(
+            r#"{"id":123456,"name":"home","font_class":"home","repository_id":789}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            build_preview_url(&icon).as_deref(),
+            Some("https://www.iconfont.cn/api/icon/downloadIcon?id=123456&type=png&size=64")
+        );
+
+        // 下载接口不按图标库寻址：缺少图标库 ID 时生成相同的地址
+        icon.repository_id = None;
+        assert_eq!(
+            build_preview_url(&icon).as_deref(),
+            Some("https://www.iconfont.cn/api/icon/downloadIcon?id=123456&type=png&size=64")
+        );
+
+        // 必需字段（图标 ID）缺失时不生成
+        icon.id = 0;
+        assert!(build_preview_url(&icon).is_none());
+    }
+
+    fn search_request(style: &str, fills: &str, sort_type: &str) -> IconSearchRequest {
+        IconSearchRequest {
+            query: "home".to_string(),
+            style: Some(style.to_string()),
+            fills: Some(fills.to_string()),
+            sort_type: Some(sort_type.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_valid_search_options_pass_through() {
+        let request = normalize_search_request(search_request("line", "multi", "hot")).unwrap();
+        assert_eq!(request.style.as_deref(), Some("line"));
+        assert_eq!(request.fills.as_deref(), Some("multi"));
+        assert_eq!(request.sort_type.as_deref(), Some("hot"));
+    }
+
+    #[test]
+    fn test_invalid_search_option_lists_valid_values() {
+        let err = normalize_search_request(search_request("bold", "all", "relate")).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("style"), "{}", message);
+        assert!(message.contains("line | fill | flat | all"), "{}", message);
+
+        assert!(normalize_search_request(search_request("all", "gradient", "relate")).is_err());
+    }
+
+    #[test]
+    fn test_search_option_synonyms_are_normalized() {
+        let request = normalize_search_request(search_request(" Outline ", "单色", "Newest")).unwrap();
+        assert_eq!(request.style.as_deref(), Some("line"));
+        assert_eq!(request.fills.as_deref(), Some("single"));
+        assert_eq!(request.sort_type.as_deref(), Some("new"));
+    }
+}
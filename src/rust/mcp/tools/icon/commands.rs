@@ -2,30 +2,50 @@
 // 提供前端可调用的图标搜索、下载、保存等功能
 
 use anyhow::Result;
+use futures_util::stream::{self, StreamExt};
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
 use usvg::TreeParsing;
 
 use super::api;
+use super::favorites::IconFavorites;
+use super::manifest;
 use super::types::{
     ClearCacheRequest, ClearCacheResult, IconCacheStats, IconConfig,
     IconContentRequest, IconContentResult, IconFormat, IconItem,
-    IconSaveItem, IconSaveRequest, IconSaveResult, IconSearchRequest,
+    IconSaveItem, IconSaveProgress, IconSaveRequest, IconSaveResult, IconSearchRequest,
     IconSearchResult,
 };
+use crate::constants::network::DEFAULT_TOOL_REQUEST_TIMEOUT_SECS;
 use crate::log_debug;
 use crate::log_important;
 
+/// 读取图标 API 请求超时（秒）
+fn request_timeout_secs(state: &crate::config::AppState) -> u64 {
+    state
+        .config
+        .lock()
+        .ok()
+        .and_then(|config| config.mcp_config.icon_request_timeout_secs)
+        .unwrap_or(DEFAULT_TOOL_REQUEST_TIMEOUT_SECS)
+}
+
 // ============ 搜索命令 ============
 
 /// 搜索图标
 /// 
 /// 根据关键词和筛选条件搜索 Iconfont 图标库
 #[tauri::command]
-pub async fn search_icons(request: IconSearchRequest) -> Result<IconSearchResult, String> {
+pub async fn search_icons(
+    state: tauri::State<'_, crate::config::AppState>,
+    request: IconSearchRequest,
+) -> Result<IconSearchResult, String> {
     log_debug!("搜索图标: query={}, page={:?}", request.query, request.page);
     
-    api::search_icons(request)
+    api::search_icons(request, request_timeout_secs(&state))
         .await
         .map_err(|e| {
             log_important!(error, "图标搜索失败: {}", e);
@@ -39,45 +59,179 @@ pub async fn search_icons(request: IconSearchRequest) -> Result<IconSearchResult
 /// 
 /// 获取指定图标的 SVG 或 PNG 内容
 #[tauri::command]
-pub async fn get_icon_content(request: IconContentRequest) -> Result<IconContentResult, String> {
+pub async fn get_icon_content(
+    state: tauri::State<'_, crate::config::AppState>,
+    request: IconContentRequest,
+) -> Result<IconContentResult, String> {
     log_debug!("获取图标内容: id={}, format={:?}", request.id, request.format);
     
+    let background = parse_png_background(request.png_background.as_deref())?;
+    
     // 获取 SVG 内容
-    let svg_content = api::get_icon_svg(request.id, None)
+    let svg_content = api::get_icon_svg(request.id, None, request_timeout_secs(&state))
         .await
         .map_err(|e| format!("获取图标内容失败: {}", e))?;
     
-    match request.format {
-        IconFormat::Svg | IconFormat::Both => {
-            Ok(IconContentResult {
-                id: request.id,
-                name: format!("icon_{}", request.id),
-                svg_content: Some(svg_content),
-                png_base64: None, // PNG 转换暂未实现
-                mime_type: "image/svg+xml".to_string(),
-            })
+    let result = build_content_result(
+        request.id,
+        format!("icon_{}", request.id),
+        svg_content,
+        &request.format,
+        request.png_size,
+        background,
+    );
+    match result.error {
+        Some(e) => Err(e),
+        None => Ok(result),
+    }
+}
+
+/// 批量获取的最大并发数
+const FETCH_CONCURRENCY: usize = 6;
+
+/// 批量并发获取图标内容
+///
+/// 按传入顺序返回结果，单个图标失败记录在对应结果的 `error` 中，不影响其他图标
+pub async fn fetch_contents(
+    ids: Vec<u64>,
+    format: IconFormat,
+    png_size: Option<u32>,
+    timeout_secs: u64,
+) -> Vec<IconContentResult> {
+    fetch_contents_from(api::ICONFONT_DETAIL_API, ids, format, png_size, timeout_secs).await
+}
+
+async fn fetch_contents_from(
+    detail_api: &str,
+    ids: Vec<u64>,
+    format: IconFormat,
+    png_size: Option<u32>,
+    timeout_secs: u64,
+) -> Vec<IconContentResult> {
+    let format = &format;
+    stream::iter(ids)
+        .map(|id| async move {
+            match api::fetch_icon_detail(detail_api, id, timeout_secs).await {
+                Ok(icon) => {
+                    let name = icon.name.clone().unwrap_or_else(|| format!("icon_{}", id));
+                    match icon.show_svg {
+                        Some(svg) if !svg.is_empty() => {
+                            build_content_result(id, name, svg, format, png_size, None)
+                        }
+                        _ => failed_content_result(id, format!("图标 {} 没有 SVG 内容", id)),
+                    }
+                }
+                Err(e) => failed_content_result(id, format!("获取图标内容失败: {}", e)),
+            }
+        })
+        .buffered(FETCH_CONCURRENCY)
+        .collect()
+        .await
+}
+
+/// 按请求格式构造图标内容（PNG 由 SVG 本地渲染）
+fn build_content_result(
+    id: u64,
+    name: String,
+    svg: String,
+    format: &IconFormat,
+    png_size: Option<u32>,
+    background: Option<tiny_skia::Color>,
+) -> IconContentResult {
+    use base64::{engine::general_purpose, Engine as _};
+
+    let png_base64 = if *format == IconFormat::Svg {
+        None
+    } else {
+        match render_svg_to_png(&svg, png_size.unwrap_or(64), background) {
+            Ok(png) => Some(general_purpose::STANDARD.encode(png)),
+            Err(e) => return failed_content_result(id, format!("PNG 转换失败: {}", e)),
         }
-        IconFormat::Png => {
-            // PNG 格式需要服务端转换，暂返回 SVG
-            log_debug!("PNG 格式暂不支持，返回 SVG");
-            Ok(IconContentResult {
-                id: request.id,
-                name: format!("icon_{}", request.id),
-                svg_content: Some(svg_content),
-                png_base64: None,
-                mime_type: "image/svg+xml".to_string(),
-            })
+    };
+    let (svg_content, mime_type) = match format {
+        IconFormat::Png => (None, "image/png"),
+        IconFormat::Svg | IconFormat::Both => (Some(svg), "image/svg+xml"),
+    };
+    IconContentResult {
+        id,
+        name,
+        svg_content,
+        png_base64,
+        mime_type: mime_type.to_string(),
+        error: None,
+    }
+}
+
+fn failed_content_result(id: u64, error: String) -> IconContentResult {
+    IconContentResult {
+        id,
+        name: format!("icon_{}", id),
+        svg_content: None,
+        png_base64: None,
+        mime_type: String::new(),
+        error: Some(error),
+    }
+}
+
+/// 为缺少 SVG 内容的图标批量预取内容
+///
+/// 获取失败的图标保持原样，由保存阶段重新获取并记录错误
+async fn prefetch_missing_svgs(mut icons: Vec<IconItem>, timeout_secs: u64) -> Vec<IconItem> {
+    let missing: Vec<u64> = icons
+        .iter()
+        .filter(|icon| !matches!(icon.svg_content.as_deref(), Some(svg) if !svg.is_empty()))
+        .map(|icon| icon.id)
+        .collect();
+    if missing.is_empty() {
+        return icons;
+    }
+
+    log_debug!("批量预取图标 SVG: count={}", missing.len());
+    let fetched = fetch_contents(missing, IconFormat::Svg, None, timeout_secs).await;
+    for result in fetched {
+        if let Some(svg) = result.svg_content {
+            for icon in icons.iter_mut().filter(|icon| icon.id == result.id) {
+                icon.svg_content = Some(svg.clone());
+            }
         }
     }
+    icons
 }
 
 // ============ 保存命令 ============
 
+/// 批量保存的最大并发数
+const SAVE_CONCURRENCY: usize = 6;
+
 /// 保存图标到本地
 /// 
-/// 将选中的图标保存到指定目录
+/// 将选中的图标并发保存到指定目录，每完成一个通过 `icon-save-progress` 事件推送进度
 #[tauri::command]
-pub async fn save_icons(request: IconSaveRequest) -> Result<IconSaveResult, String> {
+pub async fn save_icons(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, crate::config::AppState>,
+    request: IconSaveRequest,
+) -> Result<IconSaveResult, String> {
+    use tauri::Emitter;
+
+    let timeout_secs = request_timeout_secs(&state);
+    save_icons_with_progress(request, timeout_secs, move |progress| {
+        if let Err(e) = app_handle.emit("icon-save-progress", &progress) {
+            log_important!(warn, "推送图标保存进度失败: {}", e);
+        }
+    })
+    .await
+}
+
+/// 并发保存图标，每完成一个图标回调一次进度
+async fn save_icons_with_progress<F>(
+    request: IconSaveRequest,
+    timeout_secs: u64,
+    on_progress: F,
+) -> Result<IconSaveResult, String>
+where
+    F: Fn(IconSaveProgress),
+{
     log_debug!(
         "保存图标: count={}, path={}, format={:?}",
         request.icons.len(),
@@ -85,6 +239,8 @@ pub async fn save_icons(request: IconSaveRequest) -> Result<IconSaveResult, Stri
         request.format
     );
     
+    let background = parse_png_background(request.png_background.as_deref())?;
+    
     // 确保目录存在
     let save_dir = PathBuf::from(&request.save_path);
     if !save_dir.exists() {
@@ -92,33 +248,67 @@ pub async fn save_icons(request: IconSaveRequest) -> Result<IconSaveResult, Stri
             .map_err(|e| format!("创建目录失败: {}", e))?;
     }
     
-    let mut items = Vec::new();
-    let mut success_count = 0;
-    let mut failed_count = 0;
-    
-    for icon in &request.icons {
-        match save_single_icon(icon, &save_dir, &request.format, request.png_size).await {
-            Ok(saved_paths) => {
-                items.push(IconSaveItem {
+    // 本批次已占用的文件名，避免并发写入时同名图标互相覆盖
+    let reserved = Mutex::new(HashSet::new());
+    let total = request.icons.len() as u32;
+    let png_size = request.png_size;
+    let completed = AtomicU32::new(0);
+    let icons = prefetch_missing_svgs(request.icons.clone(), timeout_secs).await;
+    
+    let mut results: Vec<(usize, IconSaveItem)> = stream::iter(
+        icons.iter().enumerate(),
+    )
+    .map(|(index, icon)| {
+        let save_dir = &save_dir;
+        let reserved = &reserved;
+        let format = &request.format;
+        let completed = &completed;
+        let on_progress = &on_progress;
+        async move {
+            let item = match save_single_icon(icon, save_dir, reserved, format, png_size, background, timeout_secs).await {
+                Ok(saved_paths) => IconSaveItem {
                     id: icon.id,
                     name: icon.name.clone(),
                     success: true,
                     saved_paths,
                     error: None,
-                });
-                success_count += 1;
-            }
-            Err(e) => {
-                log_important!(error, "保存图标 {} 失败: {}", icon.id, e);
-                items.push(IconSaveItem {
-                    id: icon.id,
-                    name: icon.name.clone(),
-                    success: false,
-                    saved_paths: vec![],
-                    error: Some(e),
-                });
-                failed_count += 1;
-            }
+                },
+                Err(e) => {
+                    log_important!(error, "保存图标 {} 失败: {}", icon.id, e);
+                    IconSaveItem {
+                        id: icon.id,
+                        name: icon.name.clone(),
+                        success: false,
+                        saved_paths: vec![],
+                        error: Some(e),
+                    }
+                }
+            };
+            
+            let saved = completed.fetch_add(1, Ordering::Relaxed) + 1;
+            on_progress(IconSaveProgress {
+                saved,
+                total,
+                id: icon.id,
+                name: icon.name.clone(),
+                success: item.success,
+            });
+            (index, item)
+        }
+    })
+    .buffer_unordered(SAVE_CONCURRENCY)
+    .collect()
+    .await;
+    
+    // 按请求顺序返回结果
+    results.sort_by_key(|(index, _)| *index);
+    let items: Vec<IconSaveItem> = results.into_iter().map(|(_, item)| item).collect();
+    let success_count = items.iter().filter(|item| item.success).count() as u32;
+    let failed_count = items.len() as u32 - success_count;
+    
+    if request.write_manifest && success_count > 0 {
+        if let Err(e) = manifest::write_manifest(&save_dir, &request.icons, &items) {
+            log_important!(warn, "写入图标清单失败: {}", e);
         }
     }
     
@@ -132,49 +322,130 @@ pub async fn save_icons(request: IconSaveRequest) -> Result<IconSaveResult, Stri
     })
 }
 
+/// 选择不冲突的文件名
+///
+/// 目标文件已存在且内容不同时依次尝试 `-1`、`-2` 后缀；内容完全相同则直接复用。
+/// 所有格式共用同一个文件名，保证 SVG 与 PNG 成对出现
+fn resolve_file_stem(
+    save_dir: &Path,
+    base: &str,
+    contents: &[(&str, &[u8])],
+    reserved: &Mutex<HashSet<String>>,
+) -> String {
+    let mut reserved = reserved.lock().unwrap_or_else(|e| e.into_inner());
+    let mut suffix = 0;
+    loop {
+        let stem = if suffix == 0 {
+            base.to_string()
+        } else {
+            format!("{}-{}", base, suffix)
+        };
+        suffix += 1;
+        
+        if reserved.contains(&stem) {
+            continue;
+        }
+        let available = contents.iter().all(|(ext, bytes)| {
+            match fs::read(save_dir.join(format!("{}.{}", stem, ext))) {
+                Ok(existing) => existing == *bytes,
+                Err(_) => true,
+            }
+        });
+        if available {
+            reserved.insert(stem.clone());
+            return stem;
+        }
+    }
+}
+
 /// 保存单个图标
 async fn save_single_icon(
     icon: &IconItem,
-    save_dir: &PathBuf,
+    save_dir: &Path,
+    reserved: &Mutex<HashSet<String>>,
     format: &IconFormat,
     png_size: Option<u32>,
+    background: Option<tiny_skia::Color>,
+    timeout_secs: u64,
 ) -> Result<Vec<String>, String> {
-    let mut saved_paths = Vec::new();
-    
     // 获取 SVG 内容
-    let svg_content = api::get_icon_svg(icon.id, icon.svg_content.clone())
+    let svg_content = api::get_icon_svg(icon.id, icon.svg_content.clone(), timeout_secs)
         .await
         .map_err(|e| format!("获取 SVG 失败: {}", e))?;
     
-    // 生成安全的文件名
-    let safe_name = sanitize_filename(&icon.name);
-    
-    // 保存 SVG（清洗后）
+    // 先生成所有格式的内容，再统一选择文件名
+    let mut contents: Vec<(&str, Vec<u8>)> = Vec::new();
     if *format == IconFormat::Svg || *format == IconFormat::Both {
-        let svg_path = save_dir.join(format!("{}.svg", safe_name));
         // 清洗 SVG：移除 Iconfont 添加的内联样式限制
-        let cleaned_svg = clean_svg_for_save(&svg_content);
-        fs::write(&svg_path, &cleaned_svg)
-            .map_err(|e| format!("写入 SVG 文件失败: {}", e))?;
-        saved_paths.push(svg_path.to_string_lossy().to_string());
+        contents.push(("svg", clean_svg_for_save(&svg_content).into_bytes()));
     }
-    
-    // 保存 PNG（使用 resvg 转换）
     if *format == IconFormat::Png || *format == IconFormat::Both {
-        let size = png_size.unwrap_or(64);
-        let png_path = save_dir.join(format!("{}.png", safe_name));
-        
-        convert_svg_to_png(&svg_content, &png_path, size)
+        // 使用 resvg 转换
+        let png = render_svg_to_png(&svg_content, png_size.unwrap_or(64), background)
             .map_err(|e| format!("PNG 转换失败: {}", e))?;
-        
-        saved_paths.push(png_path.to_string_lossy().to_string());
+        contents.push(("png", png));
+    }
+    
+    let borrowed: Vec<(&str, &[u8])> = contents
+        .iter()
+        .map(|(ext, bytes)| (*ext, bytes.as_slice()))
+        .collect();
+    let file_stem = resolve_file_stem(save_dir, &sanitize_filename(&icon.name), &borrowed, reserved);
+    
+    let mut saved_paths = Vec::new();
+    for (ext, bytes) in &contents {
+        let path = save_dir.join(format!("{}.{}", file_stem, ext));
+        // 内容相同的已有文件直接复用
+        if fs::read(&path).map(|existing| existing != *bytes).unwrap_or(true) {
+            fs::write(&path, bytes)
+                .map_err(|e| format!("写入 {} 文件失败: {}", ext.to_uppercase(), e))?;
+        }
+        saved_paths.push(path.to_string_lossy().to_string());
     }
     
     Ok(saved_paths)
 }
 
-/// SVG 转 PNG（使用 resvg）
-fn convert_svg_to_png(svg_content: &str, output_path: &PathBuf, size: u32) -> Result<(), String> {
+/// 解析 PNG 背景色（未设置或 transparent 时返回 None，表示透明背景）
+fn parse_png_background(value: Option<&str>) -> Result<Option<tiny_skia::Color>, String> {
+    let value = match value.map(str::trim) {
+        None | Some("") => return Ok(None),
+        Some(v) if v.eq_ignore_ascii_case("transparent") => return Ok(None),
+        Some(v) => v,
+    };
+    let invalid = || format!("无效的 PNG 背景色: {}，需要 #RGB、#RRGGBB、#RRGGBBAA 或 transparent", value);
+    
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let expanded: String = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 | 8 => hex.to_string(),
+        _ => return Err(invalid()),
+    };
+    let channel = |i: usize| u8::from_str_radix(&expanded[i..i + 2], 16).map_err(|_| invalid());
+    let alpha = if expanded.len() == 8 { channel(6)? } else { 255 };
+    Ok(Some(tiny_skia::Color::from_rgba8(channel(0)?, channel(2)?, channel(4)?, alpha)))
+}
+
+/// SVG 转 PNG（使用 resvg），返回 PNG 编码后的字节
+fn render_svg_to_png(
+    svg_content: &str,
+    size: u32,
+    background: Option<tiny_skia::Color>,
+) -> Result<Vec<u8>, String> {
+    render_svg_to_pixmap(svg_content, size, background)?
+        .encode_png()
+        .map_err(|e| format!("PNG 编码失败: {}", e))
+}
+
+/// SVG 渲染到画布（指定背景色时先填充背景）
+fn render_svg_to_pixmap(
+    svg_content: &str,
+    size: u32,
+    background: Option<tiny_skia::Color>,
+) -> Result<tiny_skia::Pixmap, String> {
     // 解析 SVG
     let tree = usvg::Tree::from_str(svg_content, &usvg::Options::default())
         .map_err(|e| format!("SVG 解析失败: {}", e))?;
@@ -184,6 +455,9 @@ fn convert_svg_to_png(svg_content: &str, output_path: &PathBuf, size: u32) -> Re
         .ok_or("无效的尺寸")?;
     let mut pixmap = tiny_skia::Pixmap::new(pixmap_size.width(), pixmap_size.height())
         .ok_or("创建画布失败")?;
+    if let Some(color) = background {
+        pixmap.fill(color);
+    }
     
     // 计算缩放变换（保持比例居中）
     let svg_size = tree.size;
@@ -193,11 +467,7 @@ fn convert_svg_to_png(svg_content: &str, output_path: &PathBuf, size: u32) -> Re
     // 渲染 SVG 到画布
     resvg::render(&tree, transform, &mut pixmap.as_mut());
     
-    // 保存为 PNG
-    pixmap.save_png(output_path)
-        .map_err(|e| format!("PNG 保存失败: {}", e))?;
-    
-    Ok(())
+    Ok(pixmap)
 }
 
 /// 清理文件名中的非法字符
@@ -274,6 +544,34 @@ pub fn clear_icon_cache(request: ClearCacheRequest) -> ClearCacheResult {
     api::clear_cache(request.expired_only)
 }
 
+// ============ 收藏夹命令 ============
+
+/// 列出收藏的图标
+#[tauri::command]
+pub fn list_icon_favorites() -> Result<Vec<IconItem>, String> {
+    IconFavorites::global()
+        .list()
+        .map_err(|e| format!("读取收藏夹失败: {}", e))
+}
+
+/// 收藏图标（已收藏时更新内容），返回是否为新增
+#[tauri::command]
+pub fn add_icon_favorite(icon: IconItem) -> Result<bool, String> {
+    log_debug!("收藏图标: id={}, name={}", icon.id, icon.name);
+    IconFavorites::global()
+        .add(icon)
+        .map_err(|e| format!("收藏图标失败: {}", e))
+}
+
+/// 取消收藏图标，返回该图标此前是否在收藏夹中
+#[tauri::command]
+pub fn remove_icon_favorite(id: u64) -> Result<bool, String> {
+    log_debug!("取消收藏图标: id={}", id);
+    IconFavorites::global()
+        .remove(id)
+        .map_err(|e| format!("取消收藏失败: {}", e))
+}
+
 // ============ 配置管理命令 ============
 
 /// 获取图标工坊配置（从主配置系统读取）
@@ -297,6 +595,7 @@ pub fn get_icon_config(
             .unwrap_or(IconFormat::Svg),
         default_png_size: mcp.icon_default_png_size.or(Some(64)),
         cache_expiry_minutes: mcp.icon_cache_expiry_minutes.or(Some(30)),
+        request_timeout_secs: mcp.icon_request_timeout_secs.or(Some(DEFAULT_TOOL_REQUEST_TIMEOUT_SECS)),
     })
 }
 
@@ -326,6 +625,10 @@ pub async fn set_icon_config(
         });
         mcp.icon_default_png_size = config.default_png_size;
         mcp.icon_cache_expiry_minutes = config.cache_expiry_minutes;
+        // 前端未传入时保留原配置
+        if config.request_timeout_secs.is_some() {
+            mcp.icon_request_timeout_secs = config.request_timeout_secs;
+        }
     }
     
     // 持久化到配置文件
@@ -342,6 +645,7 @@ pub async fn set_icon_config(
 #[tauri::command]
 pub async fn copy_icon_to_clipboard(
     app_handle: tauri::AppHandle,
+    state: tauri::State<'_, crate::config::AppState>,
     icon: IconItem,
 ) -> Result<(), String> {
     use tauri_plugin_clipboard_manager::ClipboardExt;
@@ -349,7 +653,7 @@ pub async fn copy_icon_to_clipboard(
     log_debug!("复制图标到剪贴板: id={}", icon.id);
     
     // 获取 SVG 内容
-    let svg_content = api::get_icon_svg(icon.id, icon.svg_content)
+    let svg_content = api::get_icon_svg(icon.id, icon.svg_content, request_timeout_secs(&state))
         .await
         .map_err(|e| format!("获取图标内容失败: {}", e))?;
     
@@ -398,3 +702,230 @@ pub async fn select_icon_save_directory(
     
     Ok(result.map(|path| path.to_string()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::http_mock::{MockResponse, MockServer};
+    use tempfile::TempDir;
+
+    fn test_icon(id: u64, name: &str) -> IconItem {
+        IconItem {
+            id,
+            name: name.to_string(),
+            font_class: format!("icon-{}", id),
+            unicode: None,
+            svg_content: Some(format!(r#"<svg xmlns="http://www.w3.org/2000/svg" class="icon" viewBox="0 0 1024 1024"><path d="M{} 0L1024 1024"/></svg>"#, id)),
+            preview_url: None,
+            author: None,
+            repository_name: None,
+            repository_id: None,
+            created_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_save_accounts_for_every_icon() {
+        let temp_dir = TempDir::new().unwrap();
+        // 前 5 个图标同名（内容不同），验证并发写入时按后缀去重
+        let icons: Vec<IconItem> = (1..=10)
+            .map(|id| test_icon(id, if id <= 5 { "home" } else { "user" }))
+            .collect();
+        let request = IconSaveRequest {
+            icons,
+            save_path: temp_dir.path().to_string_lossy().to_string(),
+            format: IconFormat::Svg,
+            png_size: None,
+            write_manifest: false,
+            png_background: None,
+        };
+
+        let progress = Mutex::new(Vec::new());
+        let result = save_icons_with_progress(request, 1, |p| {
+            progress.lock().unwrap().push(p.saved);
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(result.items.len(), 10);
+        assert_eq!(result.success_count, 10);
+        assert_eq!(result.failed_count, 0);
+        assert_eq!(
+            result.items.iter().map(|item| item.id).collect::<Vec<_>>(),
+            (1..=10).collect::<Vec<_>>()
+        );
+
+        let paths: HashSet<_> = result.items.iter().flat_map(|item| item.saved_paths.clone()).collect();
+        assert_eq!(paths.len(), 10);
+        assert!(temp_dir.path().join("home-4.svg").exists());
+        assert!(temp_dir.path().join("user-4.svg").exists());
+
+        let mut saved = progress.into_inner().unwrap();
+        saved.sort_unstable();
+        assert_eq!(saved, (1..=10).collect::<Vec<u32>>());
+    }
+
+    fn save_request(icons: Vec<IconItem>, dir: &TempDir, format: IconFormat) -> IconSaveRequest {
+        IconSaveRequest {
+            icons,
+            save_path: dir.path().to_string_lossy().to_string(),
+            format,
+            png_size: Some(16),
+            write_manifest: false,
+            png_background: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_same_name_icons_saved_to_distinct_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let request = save_request(
+            vec![test_icon(1, "arrow"), test_icon(2, "arrow")],
+            &temp_dir,
+            IconFormat::Both,
+        );
+
+        let result = save_icons_with_progress(request, 1, |_| {}).await.unwrap();
+
+        assert_eq!(result.success_count, 2);
+        for stem in ["arrow", "arrow-1"] {
+            assert!(temp_dir.path().join(format!("{}.svg", stem)).exists());
+            assert!(temp_dir.path().join(format!("{}.png", stem)).exists());
+        }
+        let mut paths: Vec<_> = result.items.iter().flat_map(|item| item.saved_paths.clone()).collect();
+        paths.sort();
+        paths.dedup();
+        assert_eq!(paths.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_identical_existing_file_is_reused() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = save_icons_with_progress(
+            save_request(vec![test_icon(1, "star")], &temp_dir, IconFormat::Svg),
+            1,
+            |_| {},
+        )
+        .await
+        .unwrap();
+        let second = save_icons_with_progress(
+            save_request(vec![test_icon(1, "star"), test_icon(2, "star")], &temp_dir, IconFormat::Svg),
+            1,
+            |_| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(second.items[0].saved_paths, first.items[0].saved_paths);
+        assert!(second.items[1].saved_paths[0].ends_with("star-1.svg"));
+    }
+
+    #[tokio::test]
+    async fn test_manifest_merges_with_existing_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join(manifest::MANIFEST_JSON),
+            r#"{"icons":{"logo":{"name":"logo","font_class":"brand-logo","files":["logo.svg"]}}}"#,
+        )
+        .unwrap();
+
+        let mut request = save_request(
+            vec![test_icon(1, "home"), test_icon(2, "user")],
+            &temp_dir,
+            IconFormat::Svg,
+        );
+        request.write_manifest = true;
+        save_icons_with_progress(request, 1, |_| {}).await.unwrap();
+
+        let saved = manifest::IconManifest::load(temp_dir.path()).unwrap();
+        assert_eq!(saved.icons.len(), 3);
+        assert_eq!(saved.icons["logo"].font_class, "brand-logo");
+        assert_eq!(saved.icons["home"].files, vec!["home.svg"]);
+        assert_eq!(saved.icons["user"].font_class, "icon-2");
+
+        let index_ts = std::fs::read_to_string(temp_dir.path().join(manifest::MANIFEST_TS)).unwrap();
+        assert!(index_ts.contains("\"logo\""));
+        assert!(index_ts.contains("\"home.svg\""));
+    }
+
+    /// 启动模拟图标详情 API：id 为 404 时返回错误 code
+    fn start_detail_server() -> (MockServer, String) {
+        let server = MockServer::start(|request| {
+            let id: u64 = request.query_param("id").and_then(|v| v.parse().ok()).unwrap_or(0);
+            let body = if id == 404 {
+                r#"{"code":500}"#.to_string()
+            } else {
+                serde_json::json!({
+                    "code": 200,
+                    "data": { "icon": {
+                        "id": id,
+                        "name": format!("icon-{}", id),
+                        "show_svg": format!(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 16 16"><path d="M{} 0L16 16"/></svg>"#, id % 16),
+                    }}
+                })
+                .to_string()
+            };
+            // 模拟慢接口，让并发请求在服务端重叠
+            MockResponse::json(body).with_delay(std::time::Duration::from_millis(200))
+        });
+        let url = server.url("/api/icon/detail.json");
+        (server, url)
+    }
+
+    #[tokio::test]
+    async fn test_fetch_contents_resolves_ids_concurrently() {
+        let (server, url) = start_detail_server();
+        let ids = vec![11, 12, 13, 14, 15];
+
+        let results = fetch_contents_from(&url, ids.clone(), IconFormat::Svg, None, 5).await;
+
+        // 以服务端同时处理的请求数判断并发，且不超过并发上限
+        let peak = server.peak_connections();
+        assert!(peak > 1 && peak <= 5, "peak={}", peak);
+        assert_eq!(results.iter().map(|r| r.id).collect::<Vec<_>>(), ids);
+        for result in &results {
+            assert!(result.error.is_none(), "{:?}", result.error);
+            assert_eq!(result.name, format!("icon-{}", result.id));
+            assert!(result.svg_content.as_deref().unwrap().starts_with("<svg"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_contents_records_per_id_errors() {
+        let (_server, url) = start_detail_server();
+        let results = fetch_contents_from(&url, vec![1, 404], IconFormat::Png, Some(16), 5).await;
+
+        assert!(results[0].error.is_none());
+        assert!(results[0].png_base64.is_some());
+        assert_eq!(results[0].mime_type, "image/png");
+        assert!(results[1].error.is_some());
+        assert!(results[1].svg_content.is_none());
+    }
+
+    #[test]
+    fn test_png_background_fills_transparent_corners() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 16 16"><circle cx="8" cy="8" r="4" fill="black"/></svg>"#;
+
+        let white = parse_png_background(Some("#fff")).unwrap();
+        let pixmap = render_svg_to_pixmap(svg, 16, white).unwrap();
+        for (x, y) in [(0, 0), (15, 0), (0, 15), (15, 15)] {
+            let pixel = pixmap.pixel(x, y).unwrap();
+            assert_eq!(
+                (pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()),
+                (255, 255, 255, 255)
+            );
+        }
+
+        // 默认透明背景
+        let pixmap = render_svg_to_pixmap(svg, 16, parse_png_background(None).unwrap()).unwrap();
+        assert_eq!(pixmap.pixel(0, 0).unwrap().alpha(), 0);
+    }
+
+    #[test]
+    fn test_invalid_png_background_is_rejected() {
+        assert!(parse_png_background(Some("transparent")).unwrap().is_none());
+        assert!(parse_png_background(Some("#12345")).is_err());
+        assert!(parse_png_background(Some("white")).is_err());
+        assert!(parse_png_background(Some("#ff000080")).unwrap().is_some());
+    }
+}
@@ -0,0 +1,207 @@
+// 图标收藏夹
+// 全局保存常用图标（~/.sanshu/icon_favorites.json），跨项目复用
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+
+use super::types::{IconItem, IconSearchResult};
+use crate::mcp::utils::{lock_exclusive, FileLockGuard};
+
+/// 收藏夹文件名（位于数据目录下）
+pub const FAVORITES_FILE: &str = "icon_favorites.json";
+
+/// 图标收藏夹存储
+pub struct IconFavorites {
+    path: PathBuf,
+}
+
+impl IconFavorites {
+    /// 使用指定文件路径创建收藏夹
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// 全局收藏夹（数据目录下的 icon_favorites.json）
+    pub fn global() -> Self {
+        Self::new(crate::paths::data_dir().join(FAVORITES_FILE))
+    }
+
+    /// 收藏夹文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 列出全部收藏（按收藏顺序，最新的在后）
+    pub fn list(&self) -> Result<Vec<IconItem>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow!("解析图标收藏夹 {} 失败: {}", self.path.display(), e))
+    }
+
+    /// 添加收藏（同 ID 已存在时更新为最新内容），返回是否为新增
+    pub fn add(&self, icon: IconItem) -> Result<bool> {
+        let _lock = self.lock()?;
+        let mut icons = self.list()?;
+        let added = match icons.iter_mut().find(|i| i.id == icon.id) {
+            Some(existing) => {
+                *existing = icon;
+                false
+            }
+            None => {
+                icons.push(icon);
+                true
+            }
+        };
+        self.save(&icons)?;
+        Ok(added)
+    }
+
+    /// 移除收藏，返回是否存在该图标
+    pub fn remove(&self, id: u64) -> Result<bool> {
+        let _lock = self.lock()?;
+        let mut icons = self.list()?;
+        let before = icons.len();
+        icons.retain(|i| i.id != id);
+        if icons.len() == before {
+            return Ok(false);
+        }
+        self.save(&icons)?;
+        Ok(true)
+    }
+
+    /// 按关键词过滤收藏（名称或 CSS 类名包含关键词，忽略大小写；空关键词返回全部）
+    pub fn search(&self, query: &str) -> Result<Vec<IconItem>> {
+        let query = query.trim().to_lowercase();
+        let icons = self.list()?;
+        if query.is_empty() {
+            return Ok(icons);
+        }
+        Ok(icons
+            .into_iter()
+            .filter(|i| {
+                i.name.to_lowercase().contains(&query) || i.font_class.to_lowercase().contains(&query)
+            })
+            .collect())
+    }
+
+    /// 以搜索结果的形式分页返回匹配的收藏
+    pub fn search_page(&self, query: &str, page: u32, page_size: u32) -> Result<IconSearchResult> {
+        let matched = self.search(query)?;
+        let page = page.max(1);
+        let page_size = page_size.max(1);
+        let total = u32::try_from(matched.len()).unwrap_or(u32::MAX);
+        // 中文注释：页码或页大小极大时避免乘法溢出
+        let skip = (page as usize - 1).saturating_mul(page_size as usize);
+        let icons = matched
+            .into_iter()
+            .skip(skip)
+            .take(page_size as usize)
+            .collect();
+        Ok(IconSearchResult {
+            icons,
+            total,
+            page,
+            page_size,
+            has_more: page.saturating_mul(page_size) < total,
+        })
+    }
+
+    /// 获取收藏夹文件的独占锁，保护「读取-修改-保存」临界区（跨进程）
+    fn lock(&self) -> Result<FileLockGuard> {
+        let mut lock_path = self.path.clone().into_os_string();
+        lock_path.push(".lock");
+        lock_exclusive(Path::new(&lock_path))
+    }
+
+    fn save(&self, icons: &[IconItem]) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        crate::utils::write_atomic(&self.path, serde_json::to_string_pretty(icons)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn icon(id: u64, name: &str) -> IconItem {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": name,
+            "font_class": format!("icon-{}", name),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_add_remove_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let favorites = IconFavorites::new(dir.path().join(FAVORITES_FILE));
+
+        assert!(favorites.add(icon(1, "home")).unwrap());
+        assert!(favorites.add(icon(2, "user")).unwrap());
+        // 重复收藏只更新，不新增
+        assert!(!favorites.add(icon(1, "home-filled")).unwrap());
+
+        let icons = favorites.list().unwrap();
+        assert_eq!(icons.len(), 2);
+        assert_eq!(icons[0].name, "home-filled");
+
+        assert!(favorites.remove(1).unwrap());
+        assert!(!favorites.remove(1).unwrap());
+        let ids: Vec<u64> = favorites.list().unwrap().iter().map(|i| i.id).collect();
+        assert_eq!(ids, vec![2]);
+    }
+
+    #[test]
+    fn test_search_filters_by_query_substring() {
+        let dir = TempDir::new().unwrap();
+        let favorites = IconFavorites::new(dir.path().join(FAVORITES_FILE));
+        for (id, name) in [(1, "arrow-left"), (2, "Arrow-Right"), (3, "search")] {
+            favorites.add(icon(id, name)).unwrap();
+        }
+
+        let names: Vec<String> = favorites.search("ARROW").unwrap().into_iter().map(|i| i.name).collect();
+        assert_eq!(names, vec!["arrow-left", "Arrow-Right"]);
+        assert_eq!(favorites.search("  ").unwrap().len(), 3);
+
+        let page = favorites.search_page("arrow", 2, 1).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.icons[0].id, 2);
+        assert!(!page.has_more);
+
+        // 极大的页码与页大小不应溢出
+        let page = favorites.search_page("arrow", u32::MAX, u32::MAX).unwrap();
+        assert!(page.icons.is_empty());
+        assert!(!page.has_more);
+    }
+
+    #[test]
+    fn test_concurrent_adds_are_not_lost() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(FAVORITES_FILE);
+
+        let handles: Vec<_> = (1..=8u64)
+            .map(|id| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    IconFavorites::new(path).add(icon(id, &format!("icon-{}", id))).unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let mut ids: Vec<u64> = IconFavorites::new(&path).list().unwrap().iter().map(|i| i.id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, (1..=8).collect::<Vec<_>>());
+    }
+}
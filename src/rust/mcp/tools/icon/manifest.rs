@@ -0,0 +1,105 @@
+// 图标索引清单
+// 批量保存后在保存目录生成 icons.json 与 index.ts，便于前端项目直接导入
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use super::types::{IconItem, IconSaveItem};
+
+/// 清单 JSON 文件名
+pub const MANIFEST_JSON: &str = "icons.json";
+
+/// 清单 TypeScript 索引文件名
+pub const MANIFEST_TS: &str = "index.ts";
+
+/// 清单中的单个图标条目
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// 图标名称
+    pub name: String,
+    /// 图标标识（CSS 类名）
+    #[serde(default)]
+    pub font_class: String,
+    /// Unicode 编码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unicode: Option<String>,
+    /// 相对于保存目录的文件列表
+    #[serde(default)]
+    pub files: Vec<String>,
+}
+
+/// 图标清单（按文件名主干索引，合并时只覆盖同名条目）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IconManifest {
+    #[serde(default)]
+    pub icons: BTreeMap<String, ManifestEntry>,
+}
+
+impl IconManifest {
+    /// 读取已有清单（不存在时返回空清单；解析失败时报错，避免覆盖用户文件）
+    pub fn load(save_dir: &Path) -> Result<Self> {
+        let path = save_dir.join(MANIFEST_JSON);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow!("解析已有清单 {} 失败: {}", path.display(), e))
+    }
+
+    /// 合并本次保存成功的图标
+    pub fn merge_saved(&mut self, icons: &[IconItem], items: &[IconSaveItem]) {
+        for (icon, item) in icons.iter().zip(items) {
+            if !item.success {
+                continue;
+            }
+            let files: Vec<String> = item
+                .saved_paths
+                .iter()
+                .filter_map(|p| Path::new(p).file_name())
+                .map(|f| f.to_string_lossy().to_string())
+                .collect();
+            let Some(key) = files
+                .first()
+                .and_then(|f| Path::new(f).file_stem())
+                .map(|s| s.to_string_lossy().to_string())
+            else {
+                continue;
+            };
+            self.icons.insert(
+                key,
+                ManifestEntry {
+                    name: icon.name.clone(),
+                    font_class: icon.font_class.clone(),
+                    unicode: icon.unicode.clone(),
+                    files,
+                },
+            );
+        }
+    }
+
+    /// 写入 icons.json 与 index.ts
+    pub fn write(&self, save_dir: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        crate::utils::write_atomic(&save_dir.join(MANIFEST_JSON), &json)?;
+
+        let icons_json = serde_json::to_string_pretty(&self.icons)?;
+        let ts = format!(
+            "// 由 sanshu 图标工坊自动生成，请勿手动修改\n\nexport const icons = {} as const\n\nexport type IconName = keyof typeof icons\n",
+            icons_json
+        );
+        crate::utils::write_atomic(&save_dir.join(MANIFEST_TS), ts)?;
+        Ok(())
+    }
+}
+
+/// 将本次保存结果合并进保存目录的清单
+pub fn write_manifest(save_dir: &Path, icons: &[IconItem], items: &[IconSaveItem]) -> Result<()> {
+    let mut manifest = IconManifest::load(save_dir)?;
+    manifest.merge_saved(icons, items);
+    manifest.write(save_dir)
+}
@@ -7,6 +7,7 @@ use std::sync::Arc;
 
 use crate::mcp::types::TuRequest;
 use crate::mcp::handlers::create_icon_popup;
+use crate::mcp::utils::to_mcp_error;
 
 /// 图标工坊 MCP 工具
 /// 
@@ -90,12 +91,7 @@ impl IconTool {
                     ]))
                 }
             }
-            Err(e) => {
-                Err(McpError::internal_error(
-                    format!("图标选择失败: {}", e),
-                    None
-                ))
-            }
+            Err(e) => Err(to_mcp_error("图标选择失败", e)),
         }
     }
 
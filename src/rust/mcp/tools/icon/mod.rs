@@ -3,6 +3,8 @@
 
 pub mod api;
 pub mod commands;
+pub mod favorites;
+pub mod manifest;
 pub mod mcp;
 pub mod types;
 
@@ -25,6 +25,9 @@ pub struct IconSearchRequest {
     /// 是否仅从精选集搜索
     #[serde(default)]
     pub from_collection: Option<bool>,
+    /// 是否仅从本地收藏夹搜索（按关键词过滤收藏的图标）
+    #[serde(default)]
+    pub from_favorites: Option<bool>,
 }
 
 impl Default for IconSearchRequest {
@@ -37,6 +40,7 @@ impl Default for IconSearchRequest {
             page: Some(1),
             page_size: Some(50),
             from_collection: None,
+            from_favorites: None,
         }
     }
 }
@@ -133,6 +137,12 @@ pub struct IconSaveRequest {
     /// PNG 尺寸（仅当 format 包含 PNG 时有效）
     #[serde(default)]
     pub png_size: Option<u32>,
+    /// 保存后在目录中生成/合并 icons.json 与 index.ts 清单
+    #[serde(default)]
+    pub write_manifest: bool,
+    /// PNG 背景色: #RGB | #RRGGBB | #RRGGBBAA | transparent（默认透明）
+    #[serde(default)]
+    pub png_background: Option<String>,
 }
 
 /// 单个图标保存结果
@@ -164,6 +174,21 @@ pub struct IconSaveResult {
     pub save_path: String,
 }
 
+/// 批量保存进度（通过 `icon-save-progress` 事件推送给前端）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IconSaveProgress {
+    /// 已完成数量（含失败）
+    pub saved: u32,
+    /// 总数量
+    pub total: u32,
+    /// 刚完成的图标 ID
+    pub id: u64,
+    /// 刚完成的图标名称
+    pub name: String,
+    /// 该图标是否保存成功
+    pub success: bool,
+}
+
 // ============ 图标内容获取相关类型 ============
 
 /// 获取图标内容请求
@@ -177,6 +202,9 @@ pub struct IconContentRequest {
     /// PNG 尺寸（仅 PNG 格式有效）
     #[serde(default)]
     pub png_size: Option<u32>,
+    /// PNG 背景色: #RGB | #RRGGBB | #RRGGBBAA | transparent（默认透明）
+    #[serde(default)]
+    pub png_background: Option<String>,
 }
 
 /// 图标内容响应
@@ -194,6 +222,9 @@ pub struct IconContentResult {
     pub png_base64: Option<String>,
     /// 文件 MIME 类型
     pub mime_type: String,
+    /// 获取失败时的错误信息（批量获取时按图标单独记录）
+    #[serde(default)]
+    pub error: Option<String>,
 }
 
 // ============ 缓存管理相关类型 ============
@@ -248,6 +279,9 @@ pub struct IconConfig {
     /// 缓存过期时间（分钟）
     #[serde(default)]
     pub cache_expiry_minutes: Option<u64>,
+    /// 单次请求超时（秒）
+    #[serde(default)]
+    pub request_timeout_secs: Option<u64>,
 }
 
 impl Default for IconConfig {
@@ -257,12 +291,32 @@ impl Default for IconConfig {
             default_format: IconFormat::Svg,
             default_png_size: Some(64),
             cache_expiry_minutes: Some(30),
+            request_timeout_secs: Some(crate::constants::network::DEFAULT_TOOL_REQUEST_TIMEOUT_SECS),
         }
     }
 }
 
 // ============ Iconfont API 响应类型（内部使用）============
 
+/// Iconfont API 错误
+#[derive(Debug, thiserror::Error)]
+pub enum IconError {
+    /// 触发限流，调用方应退避后再试
+    #[error("Iconfont 请求过于频繁，{}", retry_hint(.retry_after))]
+    RateLimited { retry_after: Option<u64> },
+
+    /// API 返回非成功 code
+    #[error("API 返回错误: code={code}, message={message}")]
+    Api { code: i32, message: String },
+}
+
+fn retry_hint(retry_after: &Option<u64>) -> String {
+    match retry_after {
+        Some(secs) => format!("请 {} 秒后重试", secs),
+        None => "请稍后重试".to_string(),
+    }
+}
+
 /// Iconfont API 原始响应结构
 #[derive(Debug, Clone, Deserialize)]
 pub(crate) struct IconfontApiResponse {
@@ -328,7 +382,7 @@ impl From<IconfontIcon> for IconItem {
             font_class: icon.font_class.unwrap_or_default(),
             unicode: icon.unicode,
             svg_content: icon.show_svg,
-            preview_url: None, // 需要单独构建
+            preview_url: None, // 由 api::build_preview_url 在解析搜索结果时填充
             author: icon.user.and_then(|u| u.nickname),
             repository_name: icon.repository.as_ref().and_then(|r| r.name.clone()),
             repository_id: icon.repository.and_then(|r| r.id),
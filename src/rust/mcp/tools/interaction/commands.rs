@@ -1,7 +1,15 @@
 // zhi 弹窗交互历史相关命令
-// 提供添加、查询、清空历史的 Tauri 接口
+// 提供添加、查询、搜索、删除、清空历史的 Tauri 接口，以及消息渲染预览
 
+use super::mcp::{preview_zhi_message as build_message_preview, ZhiMessagePreview};
 use super::zhi_history::{ZhiHistoryEntry, ZhiHistoryManager};
+use crate::mcp::utils::normalize_and_validate_project_root;
+
+/// 规范化项目路径后创建 zhi 历史管理器
+fn open_history(project_root_path: &str) -> Result<ZhiHistoryManager, String> {
+    let project_root_path = normalize_and_validate_project_root(project_root_path).map_err(|e| e.to_string())?;
+    ZhiHistoryManager::new(&project_root_path).map_err(|e| format!("创建历史管理器失败: {}", e))
+}
 
 /// 添加 zhi 交互历史
 #[tauri::command]
@@ -12,8 +20,7 @@ pub async fn add_zhi_history(
     user_reply: String,
     source: Option<String>,
 ) -> Result<String, String> {
-    let manager = ZhiHistoryManager::new(&project_root_path)
-        .map_err(|e| format!("创建历史管理器失败: {}", e))?;
+    let manager = open_history(&project_root_path)?;
 
     manager.add_entry(
         &request_id,
@@ -29,19 +36,58 @@ pub async fn get_zhi_history(
     project_root_path: String,
     count: Option<usize>,
 ) -> Result<Vec<ZhiHistoryEntry>, String> {
-    let manager = ZhiHistoryManager::new(&project_root_path)
-        .map_err(|e| format!("创建历史管理器失败: {}", e))?;
+    let manager = open_history(&project_root_path)?;
 
     Ok(manager.get_recent(count.unwrap_or(20)))
 }
 
+/// 按 ID 获取 zhi 交互历史（保持传入顺序）
+#[tauri::command]
+pub async fn get_zhi_history_by_ids(
+    project_root_path: String,
+    ids: Vec<String>,
+) -> Result<Vec<ZhiHistoryEntry>, String> {
+    let manager = open_history(&project_root_path)?;
+
+    Ok(manager.get_by_ids(&ids))
+}
+
+/// 搜索 zhi 交互历史
+#[tauri::command]
+pub async fn search_zhi_history(
+    project_root_path: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<ZhiHistoryEntry>, String> {
+    let manager = open_history(&project_root_path)?;
+
+    Ok(manager.search(&query, limit.unwrap_or(20)))
+}
+
+/// 删除单条 zhi 交互历史
+#[tauri::command]
+pub async fn remove_zhi_history_entry(
+    project_root_path: String,
+    entry_id: String,
+) -> Result<bool, String> {
+    let manager = open_history(&project_root_path)?;
+
+    manager.remove_entry(&entry_id)
+        .map_err(|e| format!("删除历史记录失败: {}", e))
+}
+
 /// 清空 zhi 交互历史
 #[tauri::command]
 pub async fn clear_zhi_history(
     project_root_path: String,
 ) -> Result<(), String> {
-    let manager = ZhiHistoryManager::new(&project_root_path)
-        .map_err(|e| format!("创建历史管理器失败: {}", e))?;
+    let manager = open_history(&project_root_path)?;
 
     manager.clear().map_err(|e| format!("清空历史失败: {}", e))
 }
+
+/// 预览 zhi 消息（清理后的消息与警告列表），不打开弹窗
+#[tauri::command]
+pub fn preview_zhi_message(message: String, is_markdown: Option<bool>) -> ZhiMessagePreview {
+    build_message_preview(&message, is_markdown.unwrap_or(true))
+}
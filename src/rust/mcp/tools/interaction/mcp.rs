@@ -1,11 +1,117 @@
 use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
 use rmcp::model::{ErrorData as McpError, CallToolResult};
+use std::sync::Arc;
 
+use crate::constants::mcp::{MAX_ZHI_MESSAGE_CHARS, MAX_ZHI_TIMEOUT_SECS};
 use crate::mcp::{ZhiRequest, PopupRequest};
-use crate::mcp::handlers::{create_tauri_popup, parse_mcp_response};
-use crate::mcp::utils::{generate_request_id, popup_error};
+use crate::mcp::handlers::{create_tauri_popup, parse_mcp_response, summarize_mcp_response};
+use crate::mcp::utils::{generate_request_id, normalize_optional_project_root, popup_error, HistoryStore};
 use crate::mcp::utils::safe_truncate_clean;
 use crate::{log_important, log_debug};
+use crate::utils::with_request_id;
+
+use super::zhi_history::ZhiHistoryManager;
+
+/// 链接目标中的危险协议（markdown 行内链接、引用式链接定义、自动链接、HTML href/src 属性）
+static UNSAFE_LINK_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)(\]\(\s*<?\s*|^\s*\[[^\]]+\]:\s*<?\s*|<\s*|(?:href|src)\s*=\s*["']?\s*)(javascript|vbscript|data)\s*:"#)
+        .expect("unsafe link regex")
+});
+
+/// markdown 清理结果：清理后的文本与处理过程中发现的问题
+#[derive(Debug, Clone)]
+pub struct SanitizedMarkdown {
+    pub text: String,
+    pub warnings: Vec<String>,
+}
+
+/// 清理发送给 GUI 的 markdown 消息
+///
+/// - 将 `javascript:`/`vbscript:`/`data:` 链接改写为页内锚点，避免被点击执行
+/// - 代码块（``` 围栏）内的内容保持原样
+/// - 超过 `max_chars` 时截断并附加标记；截断点位于代码块内时补上闭合围栏
+/// - 截断、改写链接、未闭合围栏都会记录到 `warnings`，供预览直接展示
+pub fn sanitize_markdown(message: &str, max_chars: usize) -> SanitizedMarkdown {
+    let mut warnings = Vec::new();
+    let total_chars = message.chars().count();
+    let truncated = total_chars > max_chars;
+    let mut text: String = if truncated {
+        warnings.push(format!(
+            "消息过长：{} 字符，超过上限 {}，将被截断",
+            total_chars, max_chars
+        ));
+        message.chars().take(max_chars).collect()
+    } else {
+        message.to_string()
+    };
+
+    let mut out = String::with_capacity(text.len());
+    let mut in_fence = false;
+    let mut unsafe_links = 0;
+    for line in text.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            out.push_str(line);
+        } else if in_fence {
+            out.push_str(line);
+        } else {
+            unsafe_links += UNSAFE_LINK_RE.find_iter(line).count();
+            out.push_str(&UNSAFE_LINK_RE.replace_all(line, "${1}#blocked-${2}:"));
+        }
+    }
+    text = out;
+
+    if truncated {
+        if in_fence {
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.push_str("```");
+        }
+        text.push_str(&format!("\n\n…[消息过长，已截断，原长度 {} 字符]", total_chars));
+    } else if in_fence {
+        warnings.push("代码块围栏 ``` 未闭合，后续内容将全部按代码显示".to_string());
+    }
+    if unsafe_links > 0 {
+        warnings.push(format!(
+            "包含 {} 个不安全链接（javascript:/vbscript:/data:），将被改写为页内锚点",
+            unsafe_links
+        ));
+    }
+
+    SanitizedMarkdown { text, warnings }
+}
+
+/// 按 zhi 弹窗的规则处理消息：markdown 消息清理危险链接；所有消息都限制长度
+pub fn normalize_zhi_message(message: &str, is_markdown: bool) -> String {
+    preview_zhi_message(message, is_markdown).message
+}
+
+/// zhi 消息预览结果：实际发送给弹窗的消息与检测到的问题
+#[derive(Debug, Clone, Serialize)]
+pub struct ZhiMessagePreview {
+    pub message: String,
+    pub warnings: Vec<String>,
+}
+
+/// 预览 zhi 消息渲染结果，不弹窗；用于发送前校验 markdown
+pub fn preview_zhi_message(message: &str, is_markdown: bool) -> ZhiMessagePreview {
+    if is_markdown || message.chars().count() > MAX_ZHI_MESSAGE_CHARS {
+        let sanitized = sanitize_markdown(message, MAX_ZHI_MESSAGE_CHARS);
+        ZhiMessagePreview {
+            message: sanitized.text,
+            warnings: sanitized.warnings,
+        }
+    } else {
+        ZhiMessagePreview {
+            message: message.to_string(),
+            warnings: Vec::new(),
+        }
+    }
+}
 
 /// 智能代码审查交互工具
 ///
@@ -19,7 +125,7 @@ impl InteractionTool {
     ) -> Result<CallToolResult, McpError> {
         // 默认生成 request_id（MCP server 会优先使用其 call_id 注入到 zhi_with_request_id）
         let request_id = generate_request_id();
-        Self::zhi_with_request_id(request, request_id).await
+        with_request_id(request_id.clone(), Self::zhi_with_request_id(request, request_id)).await
     }
 
     /// 带 request_id 的 zhi 调用入口
@@ -39,6 +145,15 @@ impl InteractionTool {
             );
         }
 
+        // 规范化项目路径，避免引号、末尾斜杠等导致历史 hash 不一致
+        let project_root_path = match normalize_optional_project_root(request.project_root_path.as_deref()) {
+            Ok(path) => path,
+            Err(e) => {
+                log_important!(warn, "[zhi] 项目路径无效，忽略: {}", e);
+                None
+            }
+        };
+
         log_important!(
             info,
             "[zhi] 弹窗请求: request_id={}, message_len={}, message_preview={}, options_len={}, project={:?}",
@@ -46,23 +161,30 @@ impl InteractionTool {
             request.message.len(),
             safe_truncate_clean(&request.message, 200),
             request.predefined_options.len(),
-            request.project_root_path.as_deref()
+            project_root_path.as_deref()
         );
 
+        let message = normalize_zhi_message(&request.message, request.is_markdown);
+
         let popup_request = PopupRequest {
             id: request_id.clone(),
-            message: request.message,
+            message,
             predefined_options: if request.predefined_options.is_empty() {
                 None
             } else {
                 Some(request.predefined_options)
             },
             is_markdown: request.is_markdown,
-            project_root_path: request.project_root_path,
+            project_root_path,
             // 透传 UI/UX 上下文控制信号
             uiux_intent: request.uiux_intent,
             uiux_context_policy: request.uiux_context_policy,
             uiux_reason: request.uiux_reason,
+            default_input: request.default_input,
+            timeout_secs: request.timeout_secs
+                .filter(|secs| *secs > 0)
+                .map(|secs| secs.min(MAX_ZHI_TIMEOUT_SECS)),
+            default_on_timeout: request.default_on_timeout,
         };
 
         match create_tauri_popup(&popup_request) {
@@ -74,6 +196,13 @@ impl InteractionTool {
                 );
                 // 解析响应内容，支持文本和图片
                 let content = parse_mcp_response(&response)?;
+                Self::record_zhi_history(
+                    None,
+                    popup_request.project_root_path.as_deref(),
+                    &request_id,
+                    &popup_request.message,
+                    &response,
+                );
                 Ok(CallToolResult::success(content))
             }
             Err(e) => {
@@ -82,4 +211,131 @@ impl InteractionTool {
             }
         }
     }
+
+    /// 将弹窗回复写入 zhi 交互历史（失败不影响主流程）
+    ///
+    /// 返回是否新写入了一条记录；取消、无项目路径或前端已记录时跳过。
+    /// `store` 为空时写入数据目录下的历史文件（测试注入内存存储）
+    fn record_zhi_history(
+        store: Option<Arc<dyn HistoryStore>>,
+        project_root_path: Option<&str>,
+        request_id: &str,
+        message: &str,
+        response: &str,
+    ) -> bool {
+        let Some(project_root) = project_root_path.filter(|p| !p.trim().is_empty()) else {
+            return false;
+        };
+        let Some(reply_summary) = summarize_mcp_response(response) else {
+            log_debug!("[zhi] 用户取消，跳过历史记录: request_id={}", request_id);
+            return false;
+        };
+
+        let manager = match store {
+            Some(store) => ZhiHistoryManager::with_store(project_root, store),
+            None => ZhiHistoryManager::new(project_root),
+        };
+        let manager = match manager {
+            Ok(manager) => manager,
+            Err(e) => {
+                log_debug!("[zhi] 创建历史管理器失败: {}", e);
+                return false;
+            }
+        };
+
+        // 中文注释：前端提交时可能已通过 add_zhi_history 记录过同一 request_id，避免重复写入
+        if manager.get_all().iter().any(|e| e.request_id == request_id) {
+            return false;
+        }
+
+        match manager.add_entry(request_id, message, &reply_summary, "popup") {
+            Ok(_) => true,
+            Err(e) => {
+                log_important!(warn, "[zhi] 记录交互历史失败: request_id={}, error={}", request_id, e);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::utils::MemoryHistoryStore;
+
+    #[test]
+    fn test_popup_reply_records_zhi_history() {
+        let store: Arc<dyn HistoryStore> = Arc::new(MemoryHistoryStore::new());
+        let project_root = "/tmp/sanshu-zhi-record-project";
+        let response = r#"{"user_input":"继续","selected_options":["方案A"],"images":[],"metadata":{"timestamp":null,"request_id":"req-311","source":"popup"}}"#;
+        let record = |request_id: &str, response: &str| {
+            InteractionTool::record_zhi_history(Some(store.clone()), Some(project_root), request_id, "选择方案", response)
+        };
+
+        let recorded = record("req-311", response);
+        // 重复记录同一 request_id 时应跳过
+        let recorded_again = record("req-311", response);
+        let cancelled = record("req-cancel", "CANCELLED");
+
+        let entries = ZhiHistoryManager::with_store(project_root, store.clone()).unwrap().get_all();
+
+        assert!(recorded);
+        assert!(!recorded_again);
+        assert!(!cancelled);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].request_id, "req-311");
+        assert_eq!(entries[0].prompt, "选择方案");
+        assert_eq!(entries[0].user_reply, "用户输入: 继续\n选项: 方案A");
+        assert_eq!(entries[0].source, "popup");
+    }
+
+    #[test]
+    fn test_sanitize_markdown_neutralizes_javascript_link() {
+        let message = "点击[这里](javascript:alert(1))查看\n```\n[示例](javascript:void(0))\n```";
+        let sanitized = sanitize_markdown(message, MAX_ZHI_MESSAGE_CHARS).text;
+
+        assert!(sanitized.starts_with("点击[这里](#blocked-javascript:alert(1))查看"));
+        // 代码块内的内容保持原样
+        assert!(sanitized.contains("```\n[示例](javascript:void(0))\n```"));
+    }
+
+    #[test]
+    fn test_sanitize_markdown_keeps_normal_message_unchanged() {
+        let message = "## 方案\n- 参考 [文档](https://example.com)\n```rust\nfn main() {}\n```";
+        let sanitized = sanitize_markdown(message, MAX_ZHI_MESSAGE_CHARS);
+        assert_eq!(sanitized.text, message);
+        assert!(sanitized.warnings.is_empty());
+
+        let truncated = sanitize_markdown("```\nabcdef", 6);
+        assert!(truncated.text.starts_with("```\nab\n```"));
+        assert!(truncated.text.contains("已截断"));
+        assert_eq!(truncated.warnings.len(), 1);
+        assert!(truncated.warnings[0].contains("消息过长"));
+    }
+
+    #[test]
+    fn test_sanitize_markdown_neutralizes_reference_style_link() {
+        let message = "见[说明][1]\n\n[1]: javascript:alert(1)\n  [doc]: <data:text/html,x>\n[ok]: https://example.com";
+        let sanitized = sanitize_markdown(message, MAX_ZHI_MESSAGE_CHARS);
+
+        assert!(sanitized.text.contains("[1]: #blocked-javascript:alert(1)"));
+        assert!(sanitized.text.contains("  [doc]: <#blocked-data:text/html,x>"));
+        assert!(sanitized.text.contains("[ok]: https://example.com"));
+        assert_eq!(sanitized.warnings.len(), 1);
+        assert!(sanitized.warnings[0].contains("2 个不安全链接"));
+    }
+
+    #[test]
+    fn test_preview_warns_on_unbalanced_fence_and_unsafe_link() {
+        let preview = preview_zhi_message("[点我](javascript:alert(1))\n```rust\nfn main() {}", true);
+
+        assert_eq!(preview.warnings.len(), 2);
+        assert!(preview.warnings[0].contains("未闭合"));
+        assert!(preview.warnings[1].contains("不安全链接"));
+        assert!(preview.message.starts_with("[点我](#blocked-javascript:alert(1))"));
+
+        let clean = preview_zhi_message("## 方案\n```\ncode\n```", true);
+        assert!(clean.warnings.is_empty());
+        assert_eq!(clean.message, "## 方案\n```\ncode\n```");
+    }
 }
@@ -1,24 +1,29 @@
 // zhi 弹窗交互历史管理
 // 仅保存最小必要信息（文本摘要与时间），不记录图片原始数据
 
-use std::collections::VecDeque;
-use std::fs;
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
+use std::sync::Arc;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use ring::digest::{Context, SHA256};
 
 use crate::{log_debug, log_important};
+use crate::mcp::utils::{project_root_aliases, FileLockGuard, FsHistoryStore, HistoryStore};
 
 /// zhi 交互历史管理器
 pub struct ZhiHistoryManager {
     /// 项目根路径的哈希值（用于文件名）
     project_hash: String,
+    /// 规范化前原始路径的哈希值（仅在新哈希文件不存在时读取）
+    legacy_hashes: Vec<String>,
     /// 原始项目路径
     project_path: String,
     /// 最大历史条数
     max_entries: usize,
+    /// 历史存储（默认文件系统）
+    store: Arc<dyn HistoryStore>,
 }
 
 /// 单条 zhi 交互历史
@@ -53,13 +58,28 @@ impl ZhiHistoryManager {
     /// 最大历史条数默认值
     const DEFAULT_MAX_ENTRIES: usize = 20;
 
-    /// 创建 zhi 历史管理器
+    /// 创建 zhi 历史管理器（存储于 <数据目录>/zhi_history）
     pub fn new(project_path: &str) -> Result<Self> {
+        Self::with_store(project_path, Arc::new(FsHistoryStore::new(Self::history_dir())))
+    }
+
+    /// 使用指定存储创建 zhi 历史管理器
+    pub fn with_store(project_path: &str, store: Arc<dyn HistoryStore>) -> Result<Self> {
         let project_hash = Self::hash_path(project_path);
+        // 中文注释：兼容规范化前以原始路径（如符号链接）计算哈希的历史文件
+        let mut legacy_hashes: Vec<String> = Vec::new();
+        for alias in project_root_aliases(project_path) {
+            let hash = Self::hash_path(&alias);
+            if hash != project_hash && !legacy_hashes.contains(&hash) {
+                legacy_hashes.push(hash);
+            }
+        }
         Ok(Self {
             project_hash,
+            legacy_hashes,
             project_path: project_path.to_string(),
             max_entries: Self::DEFAULT_MAX_ENTRIES,
+            store,
         })
     }
 
@@ -81,31 +101,33 @@ impl ZhiHistoryManager {
         hex::encode(&digest.as_ref()[..8]) // 取前8字节作为短哈希
     }
 
-    /// 获取历史文件路径
-    fn history_file_path(&self) -> PathBuf {
-        let data_dir = dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join(".sanshu")
-            .join("zhi_history");
-
-        // 确保目录存在
-        let _ = fs::create_dir_all(&data_dir);
+    /// 获取历史目录
+    fn history_dir() -> PathBuf {
+        crate::paths::data_subdir("zhi_history")
+    }
 
-        data_dir.join(format!("{}.json", self.project_hash))
+    /// 读取时使用的存储键：新哈希文件不存在时回退到旧哈希文件（保存时写入新哈希，完成迁移）
+    fn read_key(&self) -> Option<&str> {
+        std::iter::once(&self.project_hash)
+            .chain(self.legacy_hashes.iter())
+            .map(String::as_str)
+            .find(|key| self.store.exists(key))
     }
 
     /// 加载历史文件
     fn load_history(&self) -> ZhiHistoryFile {
-        let path = self.history_file_path();
-        if !path.exists() {
-            return ZhiHistoryFile {
-                project_path: self.project_path.clone(),
-                entries: VecDeque::new(),
-                last_updated: None,
-            };
-        }
+        let key = match self.read_key() {
+            Some(key) => key,
+            None => {
+                return ZhiHistoryFile {
+                    project_path: self.project_path.clone(),
+                    entries: VecDeque::new(),
+                    last_updated: None,
+                };
+            }
+        };
 
-        match fs::read_to_string(&path) {
+        match self.store.read(key) {
             Ok(content) => {
                 serde_json::from_str(&content).unwrap_or_else(|e| {
                     log_debug!("解析 zhi 历史文件失败: {}", e);
@@ -129,15 +151,20 @@ impl ZhiHistoryManager {
 
     /// 保存历史文件
     fn save_history(&self, history: &ZhiHistoryFile) -> Result<()> {
-        let path = self.history_file_path();
         let content = serde_json::to_string_pretty(history)?;
-        fs::write(&path, content)?;
-        log_debug!("zhi 历史已保存: {}", path.display());
+        self.store.write(&self.project_hash, &content)?;
+        log_debug!("zhi 历史已保存: {}", self.store.describe(&self.project_hash));
         Ok(())
     }
 
+    /// 获取项目历史的独占锁，保护加载-修改-保存临界区
+    fn lock_history(&self) -> Result<Option<FileLockGuard>> {
+        self.store.lock(&self.project_hash)
+    }
+
     /// 添加一条历史记录
     pub fn add_entry(&self, request_id: &str, prompt: &str, user_reply: &str, source: &str) -> Result<String> {
+        let _lock = self.lock_history()?;
         let mut history = self.load_history();
 
         // 生成唯一ID
@@ -190,8 +217,69 @@ impl ZhiHistoryManager {
         history.entries.into_iter().collect()
     }
 
+    /// 根据 ID 列表获取历史（保持传入顺序）
+    pub fn get_by_ids(&self, ids: &[String]) -> Vec<ZhiHistoryEntry> {
+        if ids.is_empty() {
+            return Vec::new();
+        }
+
+        let history = self.load_history();
+        let mut map: HashMap<String, ZhiHistoryEntry> = HashMap::new();
+        for entry in history.entries {
+            map.insert(entry.id.clone(), entry);
+        }
+
+        ids.iter()
+            .filter_map(|id| map.get(id).cloned())
+            .collect()
+    }
+
+    /// 按关键字搜索历史（匹配 prompt 与 user_reply，忽略大小写，按时间倒序）
+    pub fn search(&self, query: &str, limit: usize) -> Vec<ZhiHistoryEntry> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+
+        // 中文注释：按字符比较而非字节切片，避免中文等多字节字符被截断
+        let needle: Vec<char> = query.chars().collect();
+        let matches = |text: &str| {
+            let haystack: Vec<char> = text.to_lowercase().chars().collect();
+            haystack.windows(needle.len()).any(|w| w == needle.as_slice())
+        };
+
+        let history = self.load_history();
+        history.entries
+            .into_iter()
+            .rev()
+            .filter(|e| matches(&e.prompt) || matches(&e.user_reply))
+            .take(limit)
+            .collect()
+    }
+
+    /// 删除指定ID的历史条目
+    pub fn remove_entry(&self, entry_id: &str) -> Result<bool> {
+        if self.read_key().is_none() {
+            return Ok(false);
+        }
+
+        let _lock = self.lock_history()?;
+        let mut history = self.load_history();
+        let original_len = history.entries.len();
+        history.entries.retain(|e| e.id != entry_id);
+        if history.entries.len() == original_len {
+            return Ok(false);
+        }
+
+        history.last_updated = Some(Utc::now());
+        self.save_history(&history)?;
+        log_important!(info, "[ZhiHistory] 历史条目已删除: id={}", entry_id);
+        Ok(true)
+    }
+
     /// 清空历史
     pub fn clear(&self) -> Result<()> {
+        let _lock = self.lock_history()?;
         let history = ZhiHistoryFile {
             project_path: self.project_path.clone(),
             entries: VecDeque::new(),
@@ -202,3 +290,93 @@ impl ZhiHistoryManager {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::utils::MemoryHistoryStore;
+
+    fn memory_manager() -> ZhiHistoryManager {
+        ZhiHistoryManager::with_store("/tmp/sanshu-memory-project", Arc::new(MemoryHistoryStore::new())).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_root_reads_legacy_history_and_migrates() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let real = temp_dir.path().join("real");
+        std::fs::create_dir(&real).unwrap();
+        let link = temp_dir.path().join("link");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+        let link_path = link.to_string_lossy().to_string();
+
+        let store = Arc::new(MemoryHistoryStore::new());
+        ZhiHistoryManager::with_store(&link_path, store.clone())
+            .unwrap()
+            .add_entry("req-1", "旧提问", "旧回复", "popup")
+            .unwrap();
+
+        let canonical = crate::mcp::utils::normalize_and_validate_project_root(&link_path).unwrap();
+        let manager = ZhiHistoryManager::with_store(&canonical, store.clone()).unwrap();
+        assert_eq!(manager.get_recent(10).len(), 1);
+
+        // 写入后迁移到新哈希文件，旧条目仍然保留
+        manager.add_entry("req-2", "新提问", "新回复", "popup").unwrap();
+        assert!(store.exists(&manager.project_hash));
+        assert_eq!(manager.get_recent(10).len(), 2);
+    }
+
+    #[test]
+    fn test_get_by_ids_preserves_input_order() {
+        let manager = memory_manager();
+        let a = manager.add_entry("req-a", "问题A", "回答A", "popup").unwrap();
+        let b = manager.add_entry("req-b", "问题B", "回答B", "popup").unwrap();
+        let c = manager.add_entry("req-c", "问题C", "回答C", "popup").unwrap();
+
+        let ids = vec![c.clone(), "missing".to_string(), a.clone(), b.clone()];
+        let entries = manager.get_by_ids(&ids);
+
+        let got: Vec<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(got, vec![c.as_str(), a.as_str(), b.as_str()]);
+    }
+
+    #[test]
+    fn test_search_matches_prompt_and_reply() {
+        let manager = memory_manager();
+        manager.add_entry("req-1", "是否重构 Parser 模块？", "先不动", "popup").unwrap();
+        manager.add_entry("req-2", "选择数据库", "使用 parser 生成 SQL", "popup").unwrap();
+        manager.add_entry("req-3", "无关问题", "无关回答", "popup").unwrap();
+
+        let by_keyword = manager.search("PARSER", 10);
+        let by_chinese = manager.search("数据库", 10);
+        let limited = manager.search("parser", 1);
+        let removed = manager.remove_entry(&by_chinese[0].id).unwrap();
+        let after_remove = manager.search("数据库", 10);
+
+        assert_eq!(by_keyword.len(), 2);
+        // 按时间倒序返回
+        assert_eq!(by_keyword[0].request_id, "req-2");
+        assert_eq!(by_keyword[1].request_id, "req-1");
+        assert_eq!(by_chinese.len(), 1);
+        assert_eq!(limited.len(), 1);
+        assert!(removed);
+        assert!(after_remove.is_empty());
+    }
+
+    #[test]
+    fn test_memory_store_add_get_clear() {
+        let manager = memory_manager();
+        assert!(manager.get_all().is_empty());
+
+        let first = manager.add_entry("req-1", "问题1", "回答1", "popup").unwrap();
+        let second = manager.add_entry("req-2", "问题2", "回答2", "telegram").unwrap();
+
+        assert_eq!(manager.get_all().len(), 2);
+        assert_eq!(manager.get_recent(1)[0].id, second);
+        assert_eq!(manager.get_by_ids(&[first])[0].request_id, "req-1");
+
+        manager.clear().unwrap();
+        assert!(manager.get_all().is_empty());
+        assert!(!manager.remove_entry("missing").unwrap());
+    }
+}
@@ -0,0 +1,94 @@
+//! 记忆变更审计日志
+//!
+//! 以追加方式写入 JSONL（每行一个事件），记录记忆的新增/删除/去重/重新分类。
+//! 审计写入失败只记录日志，绝不影响主操作。
+
+use chrono::{DateTime, Utc};
+use ring::digest::{Context as ShaContext, SHA256};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::log_important;
+
+/// 审计日志子目录（位于数据目录下）
+const AUDIT_SUBDIR: &str = "memory_audit";
+
+/// 单条审计事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryAuditEvent {
+    /// 事件时间
+    pub timestamp: DateTime<Utc>,
+    /// 操作类型：add | delete | dedup | recategorize
+    pub action: String,
+    /// 记忆 ID
+    pub memory_id: String,
+    /// 记忆内容的 SHA256（不记录原文）
+    pub content_hash: String,
+}
+
+/// 记忆审计日志写入器
+#[derive(Debug, Clone)]
+pub struct MemoryAuditLog {
+    path: PathBuf,
+}
+
+impl MemoryAuditLog {
+    /// 使用指定文件路径创建审计日志
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// 按项目路径定位默认审计文件：`<data_dir>/memory_audit/<project_hash>.jsonl`
+    pub fn for_project(project_path: &str) -> Self {
+        let file_name = format!("{}.jsonl", sha256_hex(project_path.as_bytes(), 8));
+        Self::new(crate::paths::data_subdir(AUDIT_SUBDIR).join(file_name))
+    }
+
+    /// 读取配置，仅在开启审计时返回写入器
+    pub fn from_config(project_path: &str) -> Option<Self> {
+        let enabled = crate::config::load_standalone_config()
+            .ok()
+            .and_then(|c| c.mcp_config.memory_audit_enabled)
+            .unwrap_or(false);
+        enabled.then(|| Self::for_project(project_path))
+    }
+
+    /// 审计文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 追加一条审计事件；失败时仅记录日志
+    pub fn record(&self, action: &str, memory_id: &str, content: &str) {
+        let event = MemoryAuditEvent {
+            timestamp: Utc::now(),
+            action: action.to_string(),
+            memory_id: memory_id.to_string(),
+            content_hash: sha256_hex(content.as_bytes(), 32),
+        };
+        if let Err(e) = self.append(&event) {
+            log_important!(warn, "写入记忆审计日志失败（已忽略）: {} - {}", self.path.display(), e);
+        }
+    }
+
+    fn append(&self, event: &MemoryAuditEvent) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// 计算 SHA256 并取前 `bytes` 字节的十六进制
+fn sha256_hex(input: &[u8], bytes: usize) -> String {
+    let mut ctx = ShaContext::new(&SHA256);
+    ctx.update(input);
+    let digest = ctx.finish();
+    hex::encode(&digest.as_ref()[..bytes.min(digest.as_ref().len())])
+}
@@ -94,7 +94,8 @@ impl MemoryDeduplicator {
 
     /// 对记忆列表进行去重
     ///
-    /// 保留先出现的记忆，移除后出现的重复记忆
+    /// 置顶记忆始终保留并优先作为比较基准；其余保留先出现的记忆，移除后出现的重复记忆。
+    /// 去重后的列表保持原有顺序。
     ///
     /// # 参数
     /// - `entries`: 记忆列表
@@ -103,28 +104,34 @@ impl MemoryDeduplicator {
     /// (去重后的列表, 去重统计结果)
     pub fn deduplicate(&self, entries: Vec<MemoryEntry>) -> (Vec<MemoryEntry>, DedupResult) {
         let original_count = entries.len();
-        let mut result: Vec<MemoryEntry> = Vec::new();
+        let mut keep = vec![false; original_count];
+        let mut kept_indices: Vec<usize> = Vec::new();
         let mut removed_ids: Vec<String> = Vec::new();
 
-        for entry in entries {
-            let mut is_dup = false;
-
-            for kept in &result {
+        // 先放入置顶记忆，再按原顺序处理其余记忆
+        let pinned = (0..original_count).filter(|&i| entries[i].pinned);
+        let unpinned = (0..original_count).filter(|&i| !entries[i].pinned);
+        for i in pinned.chain(unpinned) {
+            let entry = &entries[i];
+            let is_dup = !entry.pinned && kept_indices.iter().any(|&k| {
                 // 使用增强版算法，包含子串检测
-                let similarity = TextSimilarity::calculate_enhanced(&entry.content, &kept.content);
-                if similarity >= self.threshold {
-                    is_dup = true;
-                    break;
-                }
-            }
+                TextSimilarity::calculate_enhanced(&entry.content, &entries[k].content) >= self.threshold
+            });
 
             if is_dup {
                 removed_ids.push(entry.id.clone());
             } else {
-                result.push(entry);
+                keep[i] = true;
+                kept_indices.push(i);
             }
         }
 
+        let result: Vec<MemoryEntry> = entries
+            .into_iter()
+            .zip(keep)
+            .filter_map(|(entry, kept)| kept.then_some(entry))
+            .collect();
+
         let remaining_count = result.len();
         let removed_count = original_count - remaining_count;
 
@@ -166,6 +173,9 @@ mod tests {
             category: MemoryCategory::Rule,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            pinned: false,
+            last_accessed: None,
+            access_count: 0,
         }
     }
 
@@ -205,4 +215,20 @@ mod tests {
         assert_eq!(stats.removed_count, 2);
         assert_eq!(deduped.len(), 3);
     }
+
+    #[test]
+    fn test_pinned_entry_survives_dedup_regardless_of_order() {
+        let dedup = MemoryDeduplicator::new(0.70);
+        let pinned = MemoryEntry { pinned: true, ..make_entry("pinned", "使用KISS原则") };
+
+        // 置顶记忆在后
+        let (deduped, stats) = dedup.deduplicate(vec![make_entry("plain", "使用 KISS 原则"), pinned.clone()]);
+        assert_eq!(deduped.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), vec!["pinned"]);
+        assert_eq!(stats.removed_ids, vec!["plain".to_string()]);
+
+        // 置顶记忆在前
+        let (deduped, stats) = dedup.deduplicate(vec![pinned, make_entry("plain", "使用 KISS 原则")]);
+        assert_eq!(deduped.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), vec!["pinned"]);
+        assert_eq!(stats.removed_ids, vec!["plain".to_string()]);
+    }
 }
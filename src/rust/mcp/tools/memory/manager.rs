@@ -6,15 +6,39 @@
 //! - JSON 格式存储
 
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-use super::types::{MemoryEntry, MemoryCategory, MemoryStore, MemoryConfig};
+use super::types::{MemoryEntry, MemoryCategory, MemoryStore, MemoryConfig, MemoryOrder, MemorySearchHit};
 use super::similarity::TextSimilarity;
 use super::dedup::MemoryDeduplicator;
 use super::migration::MemoryMigrator;
+use super::audit::MemoryAuditLog;
 use crate::log_debug;
+use crate::mcp::utils::{lock_exclusive, FileLockGuard, SanshuError};
+
+/// 累积多少次访问后写回文件（避免每次回忆都重写存储）
+const ACCESS_FLUSH_THRESHOLD: u64 = 20;
+
+/// 精确命中少于该数量时启用模糊匹配
+const RECALL_MIN_EXACT_HITS: usize = 3;
+
+/// 待写回的访问统计
+#[derive(Debug, Clone, Copy)]
+struct PendingAccess {
+    count: u64,
+    last_accessed: DateTime<Utc>,
+}
+
+/// 进程内缓存的访问统计，按记忆目录分组
+///
+/// 回忆时先记到这里，累积到阈值或下次加载/保存存储时再合并写回
+static PENDING_ACCESS: Lazy<Mutex<HashMap<PathBuf, HashMap<String, PendingAccess>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
 
 /// 记忆管理器
 pub struct MemoryManager {
@@ -24,6 +48,8 @@ pub struct MemoryManager {
     store: MemoryStore,
     /// 是否为非 Git 项目（降级模式）
     is_non_git_project: bool,
+    /// 变更审计日志（配置开启时存在）
+    audit: Option<MemoryAuditLog>,
 }
 
 /// 路径规范化结果
@@ -37,6 +63,8 @@ struct NormalizeResult {
 impl MemoryManager {
     /// 存储文件名
     const STORE_FILE: &'static str = "memories.json";
+    /// 跨进程锁文件名（保护加载-修改-保存临界区）
+    const LOCK_FILE: &'static str = "memories.json.lock";
 
     /// 创建新的记忆管理器
     ///
@@ -58,12 +86,20 @@ impl MemoryManager {
             ))?;
 
         let project_path_str = Self::clean_display_path(&normalize_result.path);
+        let audit = MemoryAuditLog::from_config(&project_path_str);
+
+        // 迁移、加载与保存期间持有锁，避免与其他进程交错写入
+        let _lock = Self::lock_store(&memory_dir)?;
+
+        // 中文注释：仅在迁移、新建或去重改变了存储时写回，访问统计按批次写回而不是每次创建都落盘
+        let mut needs_save = false;
 
         // 检查是否需要迁移
         if MemoryMigrator::needs_migration(&memory_dir) {
             log_debug!("检测到旧版记忆格式，开始迁移...");
             match MemoryMigrator::migrate(&memory_dir, &project_path_str) {
                 Ok(result) => {
+                    needs_save = true;
                     log_debug!(
                         "迁移完成: 读取 {} 条，去重后 {} 条，移除 {} 条重复",
                         result.md_entries_count,
@@ -78,28 +114,31 @@ impl MemoryManager {
         }
 
         // 加载或创建存储
-        let store_path = memory_dir.join(Self::STORE_FILE);
-        let mut store = if store_path.exists() {
-            let content = fs::read_to_string(&store_path)?;
-            serde_json::from_str(&content).unwrap_or_else(|e| {
-                log_debug!("解析存储文件失败，使用默认值: {}", e);
+        let mut store = Self::load_store(&memory_dir)
+            .unwrap_or_else(|e| {
+                log_debug!("{}，使用默认值", e);
+                None
+            })
+            .unwrap_or_else(|| {
+                needs_save = true;
                 MemoryStore {
                     project_path: project_path_str.clone(),
                     ..Default::default()
                 }
-            })
-        } else {
-            MemoryStore {
-                project_path: project_path_str.clone(),
-                ..Default::default()
-            }
-        };
+            });
+
+        // 合并尚未写回的访问统计（随后的保存会将其落盘）
+        Self::merge_pending_access(&memory_dir, &mut store);
 
         // 如果配置启用了启动时去重，执行去重
         if store.config.dedup_on_startup && !store.entries.is_empty() {
             let dedup = MemoryDeduplicator::new(store.config.similarity_threshold);
             let entries = std::mem::take(&mut store.entries);
+            let before = audit.as_ref().map(|_| entries.clone());
             let (deduped, stats) = dedup.deduplicate(entries);
+            if let (Some(audit), Some(before)) = (&audit, before) {
+                Self::audit_removed(audit, &before, &stats.removed_ids);
+            }
 
             if stats.removed_count > 0 {
                 log_debug!(
@@ -108,6 +147,7 @@ impl MemoryManager {
                     stats.remaining_count
                 );
                 store.last_dedup_at = Utc::now();
+                needs_save = true;
             }
             store.entries = deduped;
         }
@@ -116,19 +156,75 @@ impl MemoryManager {
             memory_dir,
             store,
             is_non_git_project: normalize_result.is_non_git,
+            audit,
         };
 
-        // 保存存储
-        manager.save_store()?;
+        if needs_save {
+            manager.save_store()?;
+        }
 
         Ok(manager)
     }
 
+    /// 获取记忆存储的跨进程独占锁
+    fn lock_store(memory_dir: &Path) -> Result<FileLockGuard> {
+        lock_exclusive(&memory_dir.join(Self::LOCK_FILE))
+    }
+
+    /// 从磁盘读取存储；文件不存在时返回 None，解析失败返回 Err
+    fn load_store(memory_dir: &Path) -> Result<Option<MemoryStore>> {
+        let store_path = memory_dir.join(Self::STORE_FILE);
+        if !store_path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&store_path)?;
+        let store = serde_json::from_str(&content)
+            .map_err(|e| SanshuError::Parse(format!("记忆存储文件 {}: {}", store_path.display(), e)))?;
+        Ok(Some(store))
+    }
+
+    /// 加锁并从磁盘重新加载存储
+    ///
+    /// 所有修改操作都应在返回的锁守卫存活期间完成，保证其他进程的写入不会被覆盖；
+    /// 文件缺失或损坏时保留内存中的数据
+    fn lock_and_reload(&mut self) -> Result<FileLockGuard> {
+        let lock = Self::lock_store(&self.memory_dir)?;
+        match Self::load_store(&self.memory_dir) {
+            Ok(Some(mut store)) => {
+                Self::merge_pending_access(&self.memory_dir, &mut store);
+                self.store = store;
+            }
+            Ok(None) => {}
+            Err(e) => log_debug!("重新加载记忆存储失败，沿用内存数据: {}", e),
+        }
+        Ok(lock)
+    }
+
+    /// 指定审计日志（覆盖配置中的开关与默认路径）
+    pub fn with_audit_log(mut self, audit: MemoryAuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
     /// 检查是否为非 Git 项目（降级模式）
     pub fn is_non_git_project(&self) -> bool {
         self.is_non_git_project
     }
 
+    /// 记录一条审计事件（未开启审计时忽略）
+    fn audit(&self, action: &str, memory_id: &str, content: &str) {
+        if let Some(audit) = &self.audit {
+            audit.record(action, memory_id, content);
+        }
+    }
+
+    /// 为去重移除的条目记录审计事件
+    fn audit_removed(audit: &MemoryAuditLog, before: &[MemoryEntry], removed_ids: &[String]) {
+        for entry in before.iter().filter(|e| removed_ids.contains(&e.id)) {
+            audit.record("dedup", &entry.id, &entry.content);
+        }
+    }
+
     /// 添加记忆条目
     ///
     /// 如果启用了去重检测，会检查是否与现有记忆重复
@@ -138,6 +234,7 @@ impl MemoryManager {
         if content.is_empty() {
             return Err(anyhow::anyhow!("记忆内容不能为空"));
         }
+        let _lock = self.lock_and_reload()?;
 
         // 如果启用去重检测，检查是否重复
         if self.store.config.enable_dedup {
@@ -165,18 +262,24 @@ impl MemoryManager {
             category,
             created_at: now,
             updated_at: now,
+            pinned: false,
+            last_accessed: None,
+            access_count: 0,
         };
 
         self.store.entries.push(entry);
         self.save_store()?;
+        self.audit("add", &id, content);
 
         log_debug!("已添加记忆: {} ({:?})", id, category);
         Ok(Some(id))
     }
 
-    /// 获取所有记忆
+    /// 获取所有记忆（置顶记忆排在最前，其余保持原有顺序）
     pub fn get_all_memories(&self) -> Vec<&MemoryEntry> {
-        self.store.entries.iter().collect()
+        let mut memories: Vec<&MemoryEntry> = self.store.entries.iter().collect();
+        memories.sort_by_key(|e| !e.pinned);
+        memories
     }
 
     /// 获取指定分类的记忆
@@ -191,12 +294,18 @@ impl MemoryManager {
     ///
     /// 返回移除的记忆数量
     pub fn deduplicate(&mut self) -> Result<usize> {
+        let _lock = self.lock_and_reload()?;
         let dedup = MemoryDeduplicator::new(self.store.config.similarity_threshold);
-        let (deduped, stats) = dedup.deduplicate(std::mem::take(&mut self.store.entries));
+        let entries = std::mem::take(&mut self.store.entries);
+        let before = self.audit.as_ref().map(|_| entries.clone());
+        let (deduped, stats) = dedup.deduplicate(entries);
 
         self.store.entries = deduped;
         self.store.last_dedup_at = Utc::now();
         self.save_store()?;
+        if let (Some(audit), Some(before)) = (&self.audit, before) {
+            Self::audit_removed(audit, &before, &stats.removed_ids);
+        }
 
         log_debug!("手动去重完成: 移除 {} 条重复记忆", stats.removed_count);
         Ok(stats.removed_count)
@@ -205,12 +314,18 @@ impl MemoryManager {
     /// 执行去重并返回详细统计结果
     /// 用于前端可视化展示
     pub fn deduplicate_with_stats(&mut self) -> Result<super::dedup::DedupResult> {
+        let _lock = self.lock_and_reload()?;
         let dedup = MemoryDeduplicator::new(self.store.config.similarity_threshold);
-        let (deduped, stats) = dedup.deduplicate(std::mem::take(&mut self.store.entries));
+        let entries = std::mem::take(&mut self.store.entries);
+        let before = self.audit.as_ref().map(|_| entries.clone());
+        let (deduped, stats) = dedup.deduplicate(entries);
 
         self.store.entries = deduped;
         self.store.last_dedup_at = Utc::now();
         self.save_store()?;
+        if let (Some(audit), Some(before)) = (&self.audit, before) {
+            Self::audit_removed(audit, &before, &stats.removed_ids);
+        }
 
         log_debug!("手动去重完成: 移除 {} 条重复记忆", stats.removed_count);
         Ok(stats)
@@ -219,6 +334,7 @@ impl MemoryManager {
     /// 删除指定 ID 的记忆条目
     /// 返回被删除的记忆内容（用于确认）
     pub fn delete_memory(&mut self, memory_id: &str) -> Result<Option<String>> {
+        let _lock = self.lock_and_reload()?;
         let original_count = self.store.entries.len();
         let mut deleted_content = None;
 
@@ -233,6 +349,7 @@ impl MemoryManager {
 
         if self.store.entries.len() < original_count {
             self.save_store()?;
+            self.audit("delete", memory_id, deleted_content.as_deref().unwrap_or_default());
             log_debug!("已删除记忆: {}", memory_id);
             Ok(deleted_content)
         } else {
@@ -240,6 +357,71 @@ impl MemoryManager {
         }
     }
 
+    /// 批量删除记忆条目（单次加载/保存）
+    /// 返回 (已删除的 ID, 未找到的 ID)
+    pub fn delete_memories(&mut self, memory_ids: &[String]) -> Result<(Vec<String>, Vec<String>)> {
+        let _lock = self.lock_and_reload()?;
+        let mut deleted = Vec::new();
+        let mut not_found = Vec::new();
+        let mut deleted_contents = Vec::new();
+
+        for id in memory_ids {
+            if deleted.contains(id) || not_found.contains(id) {
+                continue; // 忽略重复 ID
+            }
+            match self.store.entries.iter().position(|e| &e.id == id) {
+                Some(index) => {
+                    let entry = self.store.entries.remove(index);
+                    deleted_contents.push(entry.content);
+                    deleted.push(id.clone());
+                }
+                None => not_found.push(id.clone()),
+            }
+        }
+
+        if !deleted.is_empty() {
+            self.save_store()?;
+            for (id, content) in deleted.iter().zip(&deleted_contents) {
+                self.audit("delete", id, content);
+            }
+            log_debug!("已批量删除记忆: {} 条，未找到 {} 条", deleted.len(), not_found.len());
+        }
+        Ok((deleted, not_found))
+    }
+
+    /// 修改记忆分类
+    /// 返回是否找到该 ID
+    pub fn update_category(&mut self, memory_id: &str, category: MemoryCategory) -> Result<bool> {
+        let _lock = self.lock_and_reload()?;
+        let Some(entry) = self.store.entries.iter_mut().find(|e| e.id == memory_id) else {
+            return Ok(false);
+        };
+        if entry.category != category {
+            entry.category = category;
+            entry.updated_at = Utc::now();
+            let content = entry.content.clone();
+            self.save_store()?;
+            self.audit("recategorize", memory_id, &content);
+        }
+        log_debug!("已修改记忆分类: {} -> {:?}", memory_id, category);
+        Ok(true)
+    }
+
+    /// 设置记忆置顶状态
+    /// 返回是否找到该 ID
+    pub fn set_pinned(&mut self, memory_id: &str, pinned: bool) -> Result<bool> {
+        let _lock = self.lock_and_reload()?;
+        let Some(entry) = self.store.entries.iter_mut().find(|e| e.id == memory_id) else {
+            return Ok(false);
+        };
+        if entry.pinned != pinned {
+            entry.pinned = pinned;
+            entry.updated_at = Utc::now();
+            self.save_store()?;
+        }
+        log_debug!("已{}记忆: {}", if pinned { "置顶" } else { "取消置顶" }, memory_id);
+        Ok(true)
+    }
 
     /// 获取记忆统计信息
     pub fn get_stats(&self) -> MemoryStats {
@@ -260,6 +442,11 @@ impl MemoryManager {
 
     /// 获取项目信息供MCP调用方分析 - 压缩简化版本
     pub fn get_project_info(&self) -> String {
+        self.get_project_info_ordered(MemoryOrder::Default)
+    }
+
+    /// 获取项目信息，分类内按指定顺序排列
+    pub fn get_project_info_ordered(&self, order: MemoryOrder) -> String {
         if self.store.entries.is_empty() {
             return "📭 暂无项目记忆".to_string();
         }
@@ -275,7 +462,8 @@ impl MemoryManager {
         ];
 
         for (category, title) in categories.iter() {
-            let memories: Vec<_> = self.get_memories_by_category(*category);
+            let mut memories: Vec<_> = self.get_memories_by_category(*category);
+            Self::sort_by_order(&mut memories, order);
             if !memories.is_empty() {
                 let items: Vec<String> = memories
                     .iter()
@@ -302,6 +490,153 @@ impl MemoryManager {
         }
     }
 
+    /// 回忆：按指定顺序生成项目记忆总览，并记录被返回记忆的访问统计
+    pub fn recall(&mut self, order: MemoryOrder) -> Result<String> {
+        let info = self.get_project_info_ordered(order);
+        let ids: Vec<String> = self.store.entries
+            .iter()
+            .filter(|e| !e.content.trim().is_empty())
+            .map(|e| e.id.clone())
+            .collect();
+        self.record_access(&ids)?;
+        Ok(info)
+    }
+
+    /// 按查询检索记忆
+    ///
+    /// 先做归一化后的子串精确匹配；精确命中不足时，再用模糊相似度补充
+    /// （词级最佳匹配的平均分，适配拼写错误/轻微变体），阈值取自配置。
+    /// 精确命中始终排在模糊命中之前。
+    pub fn search(&self, query: &str, order: MemoryOrder) -> Vec<MemorySearchHit<'_>> {
+        let norm_query = TextSimilarity::normalize(query);
+        if norm_query.is_empty() {
+            return Vec::new();
+        }
+
+        let mut exact: Vec<&MemoryEntry> = self.store.entries
+            .iter()
+            .filter(|e| TextSimilarity::normalize(&e.content).contains(&norm_query))
+            .collect();
+        Self::sort_by_order(&mut exact, order);
+
+        let mut hits: Vec<MemorySearchHit<'_>> = exact
+            .iter()
+            .map(|entry| MemorySearchHit { entry, score: 1.0, exact: true })
+            .collect();
+
+        if hits.len() < RECALL_MIN_EXACT_HITS {
+            let threshold = self.store.config.recall_fuzzy_threshold;
+            let mut fuzzy: Vec<MemorySearchHit<'_>> = self.store.entries
+                .iter()
+                .filter(|e| !exact.iter().any(|x| x.id == e.id))
+                .map(|entry| MemorySearchHit { entry, score: Self::fuzzy_score(&norm_query, &entry.content), exact: false })
+                .filter(|hit| hit.score >= threshold)
+                .collect();
+            fuzzy.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+            hits.extend(fuzzy);
+        }
+
+        hits
+    }
+
+    /// 模糊匹配得分：整体相似度与“查询词 vs 内容词”最佳匹配平均分取较大值
+    fn fuzzy_score(norm_query: &str, content: &str) -> f64 {
+        let norm_content = TextSimilarity::normalize(content);
+        let whole = TextSimilarity::calculate_enhanced(norm_query, &norm_content);
+
+        let content_tokens: Vec<&str> = norm_content.split_whitespace().collect();
+        let query_tokens: Vec<&str> = norm_query.split_whitespace().collect();
+        if content_tokens.is_empty() || query_tokens.is_empty() {
+            return whole;
+        }
+        let token_avg = query_tokens
+            .iter()
+            .map(|q| {
+                content_tokens
+                    .iter()
+                    .map(|c| TextSimilarity::calculate(q, c))
+                    .fold(0.0, f64::max)
+            })
+            .sum::<f64>()
+            / query_tokens.len() as f64;
+
+        whole.max(token_avg)
+    }
+
+    /// 按查询回忆：返回命中结果（含得分），并记录访问统计
+    pub fn recall_query(&mut self, query: &str, order: MemoryOrder) -> Result<Vec<(MemoryEntry, f64, bool)>> {
+        let results: Vec<(MemoryEntry, f64, bool)> = self
+            .search(query, order)
+            .into_iter()
+            .map(|hit| (hit.entry.clone(), hit.score, hit.exact))
+            .collect();
+        let ids: Vec<String> = results.iter().map(|(e, _, _)| e.id.clone()).collect();
+        self.record_access(&ids)?;
+        Ok(results)
+    }
+
+    /// 记录记忆被访问
+    ///
+    /// 内存中的条目立即更新；文件写回按批次进行，累积到阈值才保存
+    pub fn record_access(&mut self, memory_ids: &[String]) -> Result<()> {
+        if memory_ids.is_empty() {
+            return Ok(());
+        }
+        let now = Utc::now();
+        for entry in self.store.entries.iter_mut().filter(|e| memory_ids.contains(&e.id)) {
+            entry.access_count += 1;
+            entry.last_accessed = Some(now);
+        }
+
+        let pending_total = {
+            let mut pending = PENDING_ACCESS.lock().unwrap_or_else(|e| e.into_inner());
+            let dir_pending = pending.entry(self.memory_dir.clone()).or_default();
+            for id in memory_ids {
+                let item = dir_pending.entry(id.clone()).or_insert(PendingAccess { count: 0, last_accessed: now });
+                item.count += 1;
+                item.last_accessed = now;
+            }
+            dir_pending.values().map(|p| p.count).sum::<u64>()
+        };
+
+        if pending_total >= ACCESS_FLUSH_THRESHOLD {
+            log_debug!("访问统计达到批量阈值，写回存储: {} 次", pending_total);
+            let _lock = self.lock_and_reload()?;
+            self.save_store()?;
+        }
+        Ok(())
+    }
+
+    /// 将进程内缓存的访问统计合并到存储中（缓存在保存成功后由 save_store 清空）
+    fn merge_pending_access(memory_dir: &Path, store: &mut MemoryStore) {
+        let pending = {
+            let all = PENDING_ACCESS.lock().unwrap_or_else(|e| e.into_inner());
+            all.get(memory_dir).cloned()
+        };
+        let Some(pending) = pending else {
+            return;
+        };
+        for entry in store.entries.iter_mut() {
+            if let Some(item) = pending.get(&entry.id) {
+                entry.access_count += item.count;
+                if entry.last_accessed.map_or(true, |t| t < item.last_accessed) {
+                    entry.last_accessed = Some(item.last_accessed);
+                }
+            }
+        }
+    }
+
+    /// 按排序方式重排记忆（sort_by 为稳定排序，同分时保持原有顺序）
+    fn sort_by_order(memories: &mut [&MemoryEntry], order: MemoryOrder) {
+        match order {
+            MemoryOrder::Default => {}
+            MemoryOrder::Recent => memories.sort_by(|a, b| b.last_accessed.cmp(&a.last_accessed)),
+            MemoryOrder::Frequent => memories.sort_by(|a, b| {
+                b.access_count.cmp(&a.access_count).then(b.last_accessed.cmp(&a.last_accessed))
+            }),
+        }
+    }
+
     /// 获取去重配置
     pub fn config(&self) -> &MemoryConfig {
         &self.store.config
@@ -309,15 +644,19 @@ impl MemoryManager {
 
     /// 更新去重配置
     pub fn update_config(&mut self, config: MemoryConfig) -> Result<()> {
+        let _lock = self.lock_and_reload()?;
         self.store.config = config;
         self.save_store()
     }
 
     /// 保存存储到文件
+    ///
+    /// 内存中的条目已包含本实例记录的访问统计，保存后清空该目录的待写回缓存
     fn save_store(&self) -> Result<()> {
         let store_path = self.memory_dir.join(Self::STORE_FILE);
         let json = serde_json::to_string_pretty(&self.store)?;
-        fs::write(&store_path, json)?;
+        crate::utils::write_atomic(&store_path, json)?;
+        PENDING_ACCESS.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.memory_dir);
         Ok(())
     }
 
@@ -459,3 +798,176 @@ pub struct MemoryStats {
     pub patterns: usize,
     pub contexts: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_corrupt_store_reports_parse_error() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join(MemoryManager::STORE_FILE), "{ not json").unwrap();
+
+        let err = MemoryManager::load_store(temp_dir.path()).unwrap_err();
+        assert!(matches!(err.downcast_ref::<SanshuError>(), Some(SanshuError::Parse(_))));
+        assert!(MemoryManager::load_store(&temp_dir.path().join("missing")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_update_category_persists_and_updates_stats() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_string_lossy().to_string();
+
+        let mut manager = MemoryManager::new(&project_path).unwrap();
+        let id = manager.add_memory("接口统一返回 Result", MemoryCategory::Context).unwrap().unwrap();
+        assert_eq!(manager.get_stats().contexts, 1);
+
+        assert!(manager.update_category(&id, MemoryCategory::Rule).unwrap());
+        assert!(!manager.update_category("missing-id", MemoryCategory::Rule).unwrap());
+
+        // 重新加载后分类仍为新值
+        let manager = MemoryManager::new(&project_path).unwrap();
+        let entry = manager.get_all_memories().into_iter().find(|e| e.id == id).unwrap();
+        assert_eq!(entry.category, MemoryCategory::Rule);
+        let stats = manager.get_stats();
+        assert_eq!(stats.rules, 1);
+        assert_eq!(stats.contexts, 0);
+
+        assert!(MemoryCategory::parse("unknown").is_none());
+    }
+
+    #[test]
+    fn test_delete_memories_partitions_found_and_missing() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_string_lossy().to_string();
+
+        let mut manager = MemoryManager::new(&project_path).unwrap();
+        let first = manager.add_memory("所有接口使用 snake_case 命名", MemoryCategory::Rule).unwrap().unwrap();
+        let second = manager.add_memory("用户偏好深色主题", MemoryCategory::Preference).unwrap().unwrap();
+        let kept = manager.add_memory("项目使用 Tauri 2 构建桌面端", MemoryCategory::Context).unwrap().unwrap();
+
+        let ids = vec![first.clone(), "missing-1".to_string(), second.clone(), "missing-2".to_string()];
+        let (deleted, not_found) = manager.delete_memories(&ids).unwrap();
+        assert_eq!(deleted, vec![first, second]);
+        assert_eq!(not_found, vec!["missing-1".to_string(), "missing-2".to_string()]);
+
+        // 重新加载后仅保留未删除的记忆
+        let manager = MemoryManager::new(&project_path).unwrap();
+        let remaining: Vec<_> = manager.get_all_memories().into_iter().map(|e| e.id.clone()).collect();
+        assert_eq!(remaining, vec![kept]);
+    }
+
+    #[test]
+    fn test_recall_bumps_access_count_and_timestamp() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_string_lossy().to_string();
+
+        let mut manager = MemoryManager::new(&project_path).unwrap();
+        let id = manager.add_memory("提交前运行 cargo clippy", MemoryCategory::Rule).unwrap().unwrap();
+        let before = Utc::now();
+
+        let info = manager.recall(MemoryOrder::Frequent).unwrap();
+        assert!(info.contains("cargo clippy"));
+        let entry = manager.get_all_memories().into_iter().find(|e| e.id == id).unwrap();
+        assert_eq!(entry.access_count, 1);
+        assert!(entry.last_accessed.unwrap() >= before);
+
+        // 未达批量阈值时不落盘，但重新加载会合并待写回的访问统计
+        manager.recall(MemoryOrder::Default).unwrap();
+        let store_path = manager.memory_dir.join(MemoryManager::STORE_FILE);
+        drop(manager);
+        let on_disk = fs::read_to_string(&store_path).unwrap();
+        let manager = MemoryManager::new(&project_path).unwrap();
+        let entry = manager.get_all_memories().into_iter().find(|e| e.id == id).unwrap();
+        assert_eq!(entry.access_count, 2);
+        assert!(entry.last_accessed.unwrap() >= before);
+        // 重新创建管理器不会写回存储
+        assert_eq!(fs::read_to_string(&store_path).unwrap(), on_disk);
+    }
+
+    #[test]
+    fn test_audit_log_appends_add_and_delete_events() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_string_lossy().to_string();
+        let audit_path = temp_dir.path().join("audit").join("project.jsonl");
+
+        let mut manager = MemoryManager::new(&project_path)
+            .unwrap()
+            .with_audit_log(MemoryAuditLog::new(&audit_path));
+        let id = manager.add_memory("禁止在主分支直接提交", MemoryCategory::Rule).unwrap().unwrap();
+        manager.delete_memory(&id).unwrap().unwrap();
+
+        let content = fs::read_to_string(&audit_path).unwrap();
+        let events: Vec<crate::mcp::tools::memory::MemoryAuditEvent> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("每行应为合法 JSON"))
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].action, "add");
+        assert_eq!(events[1].action, "delete");
+        assert!(events.iter().all(|e| e.memory_id == id));
+        assert_eq!(events[0].content_hash, events[1].content_hash);
+        assert_eq!(events[0].content_hash.len(), 64);
+    }
+
+    #[test]
+    fn test_concurrent_managers_do_not_lose_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_string_lossy().to_string();
+
+        // 关闭去重，避免相似内容被静默拒绝干扰计数
+        let mut manager = MemoryManager::new(&project_path).unwrap();
+        manager.update_config(MemoryConfig {
+            enable_dedup: false,
+            dedup_on_startup: false,
+            ..MemoryConfig::default()
+        }).unwrap();
+
+        let handles: Vec<_> = (0..2)
+            .map(|worker| {
+                let project_path = project_path.clone();
+                std::thread::spawn(move || {
+                    // 每个线程持有独立的管理器实例，模拟 MCP 服务器与 GUI 两个进程
+                    let mut manager = MemoryManager::new(&project_path).unwrap();
+                    for i in 0..10 {
+                        manager
+                            .add_memory(&format!("worker-{} entry-{}", worker, i), MemoryCategory::Context)
+                            .unwrap()
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let manager = MemoryManager::new(&project_path).unwrap();
+        assert_eq!(manager.get_stats().total, 20);
+    }
+
+    #[test]
+    fn test_misspelled_query_recalls_memory_via_fuzzy_match() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_string_lossy().to_string();
+
+        let mut manager = MemoryManager::new(&project_path).unwrap();
+        let target = manager.add_memory("Run database migrations before deploy", MemoryCategory::Rule).unwrap().unwrap();
+        let exact = manager.add_memory("Rollback plan: databse migrations are reversible", MemoryCategory::Context).unwrap().unwrap();
+        manager.add_memory("用户偏好深色主题", MemoryCategory::Preference).unwrap().unwrap();
+
+        let threshold = manager.config().recall_fuzzy_threshold;
+        let hits = manager.search("databse migrations", MemoryOrder::Default);
+
+        // 精确命中排在模糊命中之前
+        assert_eq!(hits[0].entry.id, exact);
+        assert!(hits[0].exact);
+        assert_eq!(hits[0].score, 1.0);
+
+        let fuzzy = hits.iter().find(|h| h.entry.id == target).expect("拼写错误的查询应模糊命中目标记忆");
+        assert!(!fuzzy.exact);
+        assert!(fuzzy.score >= threshold && fuzzy.score < 1.0);
+        assert_eq!(hits.len(), 2);
+    }
+}
@@ -1,8 +1,8 @@
 use anyhow::Result;
 use rmcp::model::{ErrorData as McpError, CallToolResult, Content};
 
-use super::{MemoryManager, MemoryCategory};
-use crate::mcp::{JiyiRequest, utils::{validate_project_path, project_path_error}};
+use super::{MemoryManager, MemoryCategory, MemoryOrder};
+use crate::mcp::{JiyiRequest, utils::{normalize_and_validate_project_root, validate_project_path, project_path_error, to_mcp_error, SanshuError}};
 use crate::{log_debug, log_important};
 
 /// 全局记忆管理工具
@@ -21,23 +21,28 @@ impl MemoryTool {
             request.content.len()
         );
 
-        // 使用增强的路径验证功能
-        if let Err(e) = validate_project_path(&request.project_path) {
-            log_important!(warn, "[ji] 路径验证失败: {}", e);
-            return Err(project_path_error(format!(
-                "路径验证失败: {}\n原始路径: {}\n请检查路径格式是否正确，特别是 Windows 路径应使用正确的盘符格式（如 C:\\path）",
-                e,
-                request.project_path
-            )).into());
-        }
+        // 规范化路径（去除引号、末尾斜杠等）后再做增强验证
+        let validated = normalize_and_validate_project_root(&request.project_path)
+            .and_then(|path| validate_project_path(&path).map(|_| path));
+        let project_path = match validated {
+            Ok(path) => path,
+            Err(e) => {
+                log_important!(warn, "[ji] 路径验证失败: {}", e);
+                return Err(project_path_error(format!(
+                    "路径验证失败: {}\n原始路径: {}\n请检查路径格式是否正确，特别是 Windows 路径应使用正确的盘符格式（如 C:\\path）",
+                    e,
+                    request.project_path
+                )).into());
+            }
+        };
 
         // 创建记忆管理器（会自动执行迁移和启动时去重）
         // 支持非 Git 项目降级模式
         let start = std::time::Instant::now();
-        let mut manager = MemoryManager::new(&request.project_path)
+        let mut manager = MemoryManager::new(&project_path)
             .map_err(|e| {
                 log_important!(error, "[ji] 创建记忆管理器失败: {}", e);
-                McpError::internal_error(format!("创建记忆管理器失败: {}", e), None)
+                to_mcp_error("创建记忆管理器失败", e)
             })?;
         log_debug!("[ji] 记忆管理器创建完成: elapsed={}ms, is_non_git={}", 
             start.elapsed().as_millis(), manager.is_non_git_project());
@@ -52,7 +57,7 @@ impl MemoryTool {
         // 检查 sou 工具是否启用，如果启用则尝试触发后台索引
         let mut index_hint = String::new();
         if is_sou_enabled() {
-            if let Err(e) = try_trigger_background_index(&request.project_path).await {
+            if let Err(e) = try_trigger_background_index(&project_path).await {
                 log_debug!("触发后台索引失败（不影响记忆操作）: {}", e);
             } else {
                 index_hint = "\n\n💡 已为当前项目后台启动代码索引，以便后续 sou 工具使用。".to_string();
@@ -96,15 +101,48 @@ impl MemoryTool {
                     }
                     Err(e) => {
                         log_important!(error, "[ji] 添加记忆失败: {}", e);
-                        return Err(McpError::internal_error(format!("添加记忆失败: {}", e), None));
+                        return Err(to_mcp_error("添加记忆失败", e));
                     }
                 }
             }
             "回忆" => {
-                log_debug!("[ji] 执行回忆操作");
-                let info = manager.get_project_info();
-                log_important!(info, "[ji] 回忆完成: info_len={}", info.len());
-                format!("{}{}{}", info, index_hint, non_git_hint)
+                let order = MemoryOrder::from_str(request.order.as_deref().unwrap_or_default());
+                let query = request.content.trim();
+                log_debug!("[ji] 执行回忆操作: order={:?}, query_len={}", order, query.len());
+                if query.is_empty() {
+                    let info = manager.recall(order).map_err(|e| {
+                        log_important!(error, "[ji] 回忆失败: {}", e);
+                        to_mcp_error("回忆失败", e)
+                    })?;
+                    log_important!(info, "[ji] 回忆完成: info_len={}", info.len());
+                    format!("{}{}{}", info, index_hint, non_git_hint)
+                } else {
+                    // 带查询的回忆：精确匹配优先，不足时模糊匹配补充，每条结果附带得分
+                    let hits = manager.recall_query(query, order).map_err(|e| {
+                        log_important!(error, "[ji] 回忆失败: {}", e);
+                        to_mcp_error("回忆失败", e)
+                    })?;
+                    log_important!(info, "[ji] 回忆完成: query_hits={}", hits.len());
+                    if hits.is_empty() {
+                        format!("📭 未找到与查询相关的记忆: {}{}{}", query, index_hint, non_git_hint)
+                    } else {
+                        let items: Vec<serde_json::Value> = hits.iter().map(|(entry, score, exact)| {
+                            serde_json::json!({
+                                "id": entry.id,
+                                "content": entry.content,
+                                "category": entry.category.display_name(),
+                                "score": (score * 1000.0).round() / 1000.0,
+                                "match": if *exact { "exact" } else { "fuzzy" }
+                            })
+                        }).collect();
+                        format!("🔍 找到 {} 条相关记忆\n{}{}{}",
+                            hits.len(),
+                            serde_json::to_string_pretty(&items).unwrap_or_default(),
+                            index_hint,
+                            non_git_hint
+                        )
+                    }
+                }
             }
             // === 新增: 整理 (执行去重) ===
             "整理" => {
@@ -125,7 +163,7 @@ impl MemoryTool {
                     }
                     Err(e) => {
                         log_important!(error, "[ji] 去重整理失败: {}", e);
-                        return Err(McpError::internal_error(format!("去重整理失败: {}", e), None));
+                        return Err(to_mcp_error("去重整理失败", e));
                     }
                 }
             }
@@ -138,7 +176,8 @@ impl MemoryTool {
                         "id": m.id,
                         "content": m.content,
                         "category": m.category.display_name(),
-                        "created_at": m.created_at.to_rfc3339()
+                        "created_at": m.created_at.to_rfc3339(),
+                        "pinned": m.pinned
                     })
                 }).collect();
                 
@@ -206,11 +245,14 @@ impl MemoryTool {
                     if let Some(enable_dedup) = config_req.enable_dedup {
                         new_config.enable_dedup = enable_dedup;
                     }
+                    if let Some(fuzzy_threshold) = config_req.recall_fuzzy_threshold {
+                        new_config.recall_fuzzy_threshold = fuzzy_threshold.clamp(0.3, 0.95);
+                    }
                     
                     manager.update_config(new_config.clone())
                         .map_err(|e| {
                             log_important!(error, "[ji] 更新配置失败: {}", e);
-                            McpError::internal_error(format!("更新配置失败: {}", e), None)
+                            to_mcp_error("更新配置失败", e)
                         })?;
                     
                     log_important!(info, "[ji] 配置更新成功: threshold={}, dedup_on_startup={}, enable_dedup={}",
@@ -222,7 +264,8 @@ impl MemoryTool {
                         "config": {
                             "similarity_threshold": new_config.similarity_threshold,
                             "dedup_on_startup": new_config.dedup_on_startup,
-                            "enable_dedup": new_config.enable_dedup
+                            "enable_dedup": new_config.enable_dedup,
+                            "recall_fuzzy_threshold": new_config.recall_fuzzy_threshold
                         }
                     });
                     format!("✅ 配置已更新\n{}", serde_json::to_string_pretty(&json_result).unwrap_or_default())
@@ -233,7 +276,8 @@ impl MemoryTool {
                     let json_result = serde_json::json!({
                         "similarity_threshold": config.similarity_threshold,
                         "dedup_on_startup": config.dedup_on_startup,
-                        "enable_dedup": config.enable_dedup
+                        "enable_dedup": config.enable_dedup,
+                        "recall_fuzzy_threshold": config.recall_fuzzy_threshold
                     });
                     format!("📋 当前配置\n{}", serde_json::to_string_pretty(&json_result).unwrap_or_default())
                 }
@@ -254,18 +298,101 @@ impl MemoryTool {
                     }
                     Ok(None) => {
                         log_debug!("[ji] 删除失败: 未找到记忆 id={}", memory_id);
-                        format!("⚠️ 未找到指定 ID 的记忆: {}", memory_id)
+                        return Err(memory_not_found(memory_id));
                     }
                     Err(e) => {
                         log_important!(error, "[ji] 删除记忆失败: {}", e);
-                        return Err(McpError::internal_error(format!("删除记忆失败: {}", e), None));
+                        return Err(to_mcp_error("删除记忆失败", e));
+                    }
+                }
+            }
+            // === 新增: 置顶 / 取消置顶 (保护记忆不被去重移除) ===
+            "置顶" | "取消置顶" => {
+                let pinned = request.action == "置顶";
+                let memory_id = request.memory_id.as_deref()
+                    .ok_or_else(|| {
+                        log_important!(warn, "[ji] {}失败: 缺少 memory_id", request.action);
+                        McpError::invalid_params("缺少 memory_id 参数".to_string(), None)
+                    })?;
+
+                log_debug!("[ji] 执行{}操作: memory_id={}", request.action, memory_id);
+                match manager.set_pinned(memory_id, pinned) {
+                    Ok(true) => {
+                        log_important!(info, "[ji] {}成功: id={}", request.action, memory_id);
+                        format!("✅ 已{}记忆\n🆔 ID: {}", request.action, memory_id)
+                    }
+                    Ok(false) => {
+                        log_debug!("[ji] {}失败: 未找到记忆 id={}", request.action, memory_id);
+                        return Err(memory_not_found(memory_id));
+                    }
+                    Err(e) => {
+                        log_important!(error, "[ji] {}记忆失败: {}", request.action, e);
+                        return Err(to_mcp_error(&format!("{}记忆失败", request.action), e));
+                    }
+                }
+            }
+            // === 新增: 批量删除 (单次加载/保存) ===
+            "批量删除" => {
+                if request.memory_ids.is_empty() {
+                    log_important!(warn, "[ji] 批量删除失败: 缺少 memory_ids");
+                    return Err(McpError::invalid_params("缺少 memory_ids 参数".to_string(), None));
+                }
+
+                log_debug!("[ji] 执行批量删除操作: count={}", request.memory_ids.len());
+                match manager.delete_memories(&request.memory_ids) {
+                    Ok((deleted, not_found)) => {
+                        log_important!(info, "[ji] 批量删除完成: deleted={}, not_found={}", deleted.len(), not_found.len());
+                        let mut result = format!("✅ 已删除 {} 条记忆", deleted.len());
+                        if !deleted.is_empty() {
+                            result.push_str(&format!("\n🆔 已删除: {}", deleted.join(", ")));
+                        }
+                        if !not_found.is_empty() {
+                            result.push_str(&format!("\n⚠️ 未找到: {}", not_found.join(", ")));
+                        }
+                        result
+                    }
+                    Err(e) => {
+                        log_important!(error, "[ji] 批量删除记忆失败: {}", e);
+                        return Err(to_mcp_error("批量删除记忆失败", e));
+                    }
+                }
+            }
+            // === 新增: 重新分类 (修改已有记忆的分类) ===
+            "重新分类" => {
+                let memory_id = request.memory_id.as_deref()
+                    .ok_or_else(|| {
+                        log_important!(warn, "[ji] 重新分类失败: 缺少 memory_id");
+                        McpError::invalid_params("缺少 memory_id 参数".to_string(), None)
+                    })?;
+                let category = MemoryCategory::parse(&request.category)
+                    .ok_or_else(|| {
+                        log_important!(warn, "[ji] 重新分类失败: 未知分类 {}", request.category);
+                        McpError::invalid_params(
+                            format!("未知的记忆分类: {}。支持的分类: rule | preference | pattern | context", request.category),
+                            None
+                        )
+                    })?;
+
+                log_debug!("[ji] 执行重新分类操作: memory_id={}, category={:?}", memory_id, category);
+                match manager.update_category(memory_id, category) {
+                    Ok(true) => {
+                        log_important!(info, "[ji] 重新分类成功: id={}, category={:?}", memory_id, category);
+                        format!("✅ 已重新分类记忆\n🆔 ID: {}\n📂 分类: {}", memory_id, category.display_name())
+                    }
+                    Ok(false) => {
+                        log_debug!("[ji] 重新分类失败: 未找到记忆 id={}", memory_id);
+                        return Err(memory_not_found(memory_id));
+                    }
+                    Err(e) => {
+                        log_important!(error, "[ji] 重新分类记忆失败: {}", e);
+                        return Err(to_mcp_error("重新分类记忆失败", e));
                     }
                 }
             }
             _ => {
                 log_important!(warn, "[ji] 未知操作类型: {}", request.action);
                 return Err(McpError::invalid_params(
-                    format!("未知的操作类型: {}。支持的操作: 记忆 | 回忆 | 整理 | 列表 | 预览相似 | 配置 | 删除", request.action),
+                    format!("未知的操作类型: {}。支持的操作: 记忆 | 回忆 | 整理 | 列表 | 预览相似 | 配置 | 删除 | 置顶 | 取消置顶 | 重新分类 | 批量删除", request.action),
                     None
                 ));
             }
@@ -284,6 +411,11 @@ fn is_sou_enabled() -> bool {
     }
 }
 
+/// 指定 ID 的记忆不存在（映射为 MCP resource_not_found）
+fn memory_not_found(memory_id: &str) -> McpError {
+    SanshuError::NotFound(format!("记忆 {}", memory_id)).into()
+}
+
 /// 尝试触发后台索引（仅在项目未初始化或索引失败时）
 async fn try_trigger_background_index(project_root: &str) -> Result<()> {
     use super::super::acemcp::mcp::{get_initial_index_state, ensure_initial_index_background, InitialIndexState};
@@ -303,3 +435,17 @@ async fn try_trigger_background_index(project_root: &str) -> Result<()> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::ErrorCode;
+
+    #[test]
+    fn test_missing_memory_maps_to_not_found() {
+        let err = memory_not_found("m-404");
+        assert_eq!(err.code, ErrorCode::RESOURCE_NOT_FOUND);
+        assert!(err.message.contains("m-404"));
+        assert_eq!(err.data.unwrap()["kind"], "not_found");
+    }
+}
@@ -122,7 +122,7 @@ impl MemoryMigrator {
         // 4. 写入新版 JSON 文件
         let store_path = memory_dir.join(Self::STORE_FILE);
         let json = serde_json::to_string_pretty(&store)?;
-        fs::write(&store_path, json)?;
+        crate::utils::write_atomic(&store_path, json)?;
         log_debug!("已写入新版存储文件: {}", store_path.display());
 
         // 5. 备份旧文件
@@ -171,6 +171,9 @@ impl MemoryMigrator {
                         category,
                         created_at: Utc::now(),
                         updated_at: Utc::now(),
+                        pinned: false,
+                        last_accessed: None,
+                        access_count: 0,
                     };
                     entries.push(entry);
                 }
@@ -7,6 +7,7 @@
 //! - `similarity` - 文本相似度算法
 //! - `dedup` - 去重检测器
 //! - `migration` - 旧格式迁移
+//! - `audit` - 记忆变更审计日志
 //! - `manager` - 核心管理器
 //! - `mcp` - MCP 接口
 
@@ -14,13 +15,15 @@ pub mod types;
 pub mod similarity;
 pub mod dedup;
 pub mod migration;
+pub mod audit;
 pub mod manager;
 pub mod mcp;
 
 // 重新导出主要类型和功能
 pub use manager::MemoryManager;
-pub use types::{MemoryEntry, MemoryCategory, MemoryMetadata, MemoryStore, MemoryConfig};
+pub use types::{MemoryEntry, MemoryCategory, MemoryMetadata, MemoryStore, MemoryConfig, MemoryOrder, MemorySearchHit};
 pub use mcp::MemoryTool;
 pub use similarity::TextSimilarity;
 pub use dedup::{MemoryDeduplicator, DuplicateInfo, DedupResult};
 pub use migration::{MemoryMigrator, MigrationResult};
+pub use audit::{MemoryAuditLog, MemoryAuditEvent};
@@ -17,6 +17,15 @@ pub struct MemoryEntry {
     pub created_at: DateTime<Utc>,
     /// 更新时间
     pub updated_at: DateTime<Utc>,
+    /// 是否置顶（置顶记忆在去重时不会被移除，列表中排在最前）
+    #[serde(default)]
+    pub pinned: bool,
+    /// 最近一次被回忆返回的时间
+    #[serde(default)]
+    pub last_accessed: Option<DateTime<Utc>>,
+    /// 被回忆返回的累计次数
+    #[serde(default)]
+    pub access_count: u64,
 }
 
 /// 记忆分类
@@ -35,12 +44,17 @@ pub enum MemoryCategory {
 impl MemoryCategory {
     /// 从字符串解析分类
     pub fn from_str(s: &str) -> Self {
-        match s.to_lowercase().as_str() {
-            "rule" | "规范" | "规则" => Self::Rule,
-            "preference" | "偏好" => Self::Preference,
-            "pattern" | "模式" | "最佳实践" => Self::Pattern,
-            "context" | "背景" | "上下文" => Self::Context,
-            _ => Self::Rule, // 默认为规则
+        Self::parse(s).unwrap_or(Self::Rule) // 默认为规则
+    }
+
+    /// 严格解析分类，未知分类返回 None
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "rule" | "规范" | "规则" => Some(Self::Rule),
+            "preference" | "偏好" => Some(Self::Preference),
+            "pattern" | "模式" | "最佳实践" => Some(Self::Pattern),
+            "context" | "背景" | "上下文" => Some(Self::Context),
+            _ => None,
         }
     }
 
@@ -55,6 +69,40 @@ impl MemoryCategory {
     }
 }
 
+/// 回忆结果排序方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MemoryOrder {
+    /// 按创建顺序（默认）
+    #[default]
+    Default,
+    /// 最近访问优先
+    Recent,
+    /// 访问次数优先
+    Frequent,
+}
+
+impl MemoryOrder {
+    /// 从字符串解析排序方式，未知值回退为默认顺序
+    pub fn from_str(s: &str) -> Self {
+        match s.trim().to_lowercase().as_str() {
+            "recent" | "最近" => Self::Recent,
+            "frequent" | "常用" => Self::Frequent,
+            _ => Self::Default,
+        }
+    }
+}
+
+/// 回忆查询命中结果
+#[derive(Debug, Clone)]
+pub struct MemorySearchHit<'a> {
+    /// 命中的记忆条目
+    pub entry: &'a MemoryEntry,
+    /// 匹配得分（精确匹配为 1.0）
+    pub score: f64,
+    /// 是否为精确（子串）匹配
+    pub exact: bool,
+}
+
 /// 新版记忆存储结构（v2.0）
 ///
 /// 使用单一 JSON 文件存储所有记忆
@@ -96,6 +144,9 @@ pub struct MemoryConfig {
     /// 是否启用去重检测，默认 true
     #[serde(default = "default_enable_dedup")]
     pub enable_dedup: bool,
+    /// 回忆模糊匹配阈值（0.0 ~ 1.0），默认 0.60
+    #[serde(default = "default_recall_fuzzy_threshold")]
+    pub recall_fuzzy_threshold: f64,
 }
 
 fn default_similarity_threshold() -> f64 {
@@ -110,12 +161,17 @@ fn default_enable_dedup() -> bool {
     true
 }
 
+fn default_recall_fuzzy_threshold() -> f64 {
+    0.60
+}
+
 impl Default for MemoryConfig {
     fn default() -> Self {
         Self {
             similarity_threshold: default_similarity_threshold(),
             dedup_on_startup: default_dedup_on_startup(),
             enable_dedup: default_enable_dedup(),
+            recall_fuzzy_threshold: default_recall_fuzzy_threshold(),
         }
     }
 }
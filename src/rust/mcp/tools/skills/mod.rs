@@ -1,16 +1,27 @@
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use rmcp::model::{CallToolResult, Content, ErrorData as McpError, Tool};
 use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::process::Command;
 
 use crate::config::load_standalone_config;
 use crate::{log_debug, log_important};
 use crate::mcp::types::SkillRunRequest;
+use crate::mcp::utils::{timeout_error, to_mcp_error, McpToolError};
+
+/// 技能单次执行超时默认值（秒）
+const DEFAULT_SKILL_TIMEOUT_SECS: u64 = 60;
+/// 技能 stdout/stderr 各自保留的最大字节数默认值
+const DEFAULT_SKILL_MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+/// 技能运行时自身保留的工具名，不可被发现的技能占用
+const RESERVED_SKILL_TOOL_NAMES: &[&str] = &["skill_run", "skill_list"];
 
 /// 技能运行时工具
 /// 负责发现 skills、动态注册 MCP 工具并执行 Python 入口
@@ -24,6 +35,23 @@ struct SkillInfo {
     config: Option<SkillConfig>,
 }
 
+/// skill_list 返回的技能信息
+#[derive(Debug, Clone, Serialize)]
+struct SkillListItem {
+    name: String,
+    description: Option<String>,
+    path: String,
+    enabled: bool,
+}
+
+/// 技能进程的执行结果（输出已按上限截断）
+#[derive(Debug)]
+struct SkillRunOutput {
+    status: ExitStatus,
+    stdout: String,
+    stderr: String,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 struct SkillConfig {
     #[serde(default)]
@@ -45,17 +73,33 @@ struct SkillActionConfig {
 }
 
 impl SkillsTool {
-    /// 获取动态工具列表（包含 skill_run 与各个 skill_<name>）
+    /// 获取动态工具列表（包含 skill_run、skill_list 与各个已启用的 skill_<name>）
     pub fn list_dynamic_tools(project_root: &Path) -> Vec<Tool> {
+        Self::build_dynamic_tools(project_root, &load_skill_enabled_map())
+    }
+
+    fn build_dynamic_tools(project_root: &Path, enabled_map: &HashMap<String, bool>) -> Vec<Tool> {
         let mut tools = Vec::new();
         tools.push(Self::get_skill_run_tool_definition());
+        tools.push(Self::get_skill_list_tool_definition());
 
         let skills = scan_skills(project_root);
         let input_schema = skills_input_schema();
+        let mut registered: HashSet<String> = HashSet::new();
 
         // 兼容 Antigravity：动态技能工具名使用下划线分隔
         for skill in skills {
-            let tool_name = format!("skill_{}", skill.name);
+            if !is_skill_enabled(&skill.name, enabled_map) {
+                log_debug!("[skills] 技能已禁用，不注册工具: {}", skill.name);
+                continue;
+            }
+            let tool_name = skill_tool_name(&skill.name);
+            if let Some(reason) = tool_name_conflict(&tool_name, &registered) {
+                log_important!(warn, "[skills] 跳过技能: name={}, tool={}, 原因={}, path={}",
+                    skill.name, tool_name, reason, skill.path.display());
+                continue;
+            }
+            registered.insert(tool_name.clone());
             let description = skill.description.clone().unwrap_or_else(|| "技能工具".to_string());
             tools.push(Tool {
                 name: Cow::Owned(tool_name),
@@ -72,6 +116,16 @@ impl SkillsTool {
         tools
     }
 
+    /// 列出已发现的技能及其启用状态
+    pub fn list_skills(project_root: &Path) -> Result<CallToolResult, McpError> {
+        let items = collect_skill_list(project_root, &load_skill_enabled_map());
+        log_important!(info, "[skills] 列出技能: count={}", items.len());
+
+        let text = serde_json::to_string_pretty(&items)
+            .map_err(|e| to_mcp_error("序列化技能列表失败", e.into()))?;
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
     /// 处理技能工具调用
     pub async fn call_tool(
         tool_name: &str,
@@ -117,6 +171,16 @@ impl SkillsTool {
 
         log_debug!("[skills] 找到技能: name={}, path={}", skill.name, skill.path.display());
 
+        if let Some(reason) = tool_name_conflict(&skill_tool_name(&skill.name), &HashSet::new()) {
+            log_important!(warn, "[skills] 拒绝调用冲突技能: name={}, 原因={}", skill.name, reason);
+            return Err(McpError::invalid_params(format!("技能 {} 不可用: {}", skill.name, reason), None));
+        }
+
+        if !is_skill_enabled(&skill.name, &load_skill_enabled_map()) {
+            log_important!(warn, "[skills] 技能已禁用: {}", skill.name);
+            return Err(McpError::invalid_params(format!("技能已禁用: {}", skill.name), None));
+        }
+
         // 优先请求里的 action，其次使用配置默认 action，最后兜底 search
         let action_name = request
             .action
@@ -148,40 +212,51 @@ impl SkillsTool {
             return Err(McpError::invalid_params("入口路径不在技能目录内".to_string(), None));
         }
 
-        // 选择 Python 执行器：配置优先，其次 PATH
-        let python_bin = load_standalone_config()
-            .ok()
-            .and_then(|c| c.mcp_config.skill_python_path.clone())
+        // 选择 Python 执行器、超时与输出上限：配置优先，其次默认值
+        let mcp_config = load_standalone_config().ok().map(|c| c.mcp_config);
+        let python_bin = mcp_config.as_ref()
+            .and_then(|c| c.skill_python_path.clone())
             .unwrap_or_else(|| "python".to_string());
+        let timeout_secs = mcp_config.as_ref()
+            .and_then(|c| c.skill_timeout_secs)
+            .unwrap_or(DEFAULT_SKILL_TIMEOUT_SECS);
+        let max_output_bytes = mcp_config.as_ref()
+            .and_then(|c| c.skill_max_output_bytes)
+            .unwrap_or(DEFAULT_SKILL_MAX_OUTPUT_BYTES);
 
         log_important!(
             info,
-            "[skills] 执行 Python 脚本: skill={}, action={}, entry={}, python={}, args_count={}",
+            "[skills] 执行 Python 脚本: skill={}, action={}, entry={}, python={}, args_count={}, timeout={}s",
             skill.name,
             action_name,
             entry_path.display(),
             python_bin,
-            args.len()
+            args.len(),
+            timeout_secs
         );
         log_debug!("[skills] 参数详情: {:?}", args);
 
         let exec_start = std::time::Instant::now();
-        let output = Command::new(&python_bin)
+        let mut command = Command::new(&python_bin);
+        command
             .arg(&entry_path)
             .args(&args)
             .current_dir(&skill.path)
             // 确保 Python 输出统一编码，避免控制台乱码
-            .env("PYTHONIOENCODING", "utf-8")
-            .output()
+            .env("PYTHONIOENCODING", "utf-8");
+        let output = run_skill_process(command, &skill.name, timeout_secs, max_output_bytes)
             .await
             .map_err(|e| {
-                log_important!(error, "[skills] Python 执行失败: {}", e);
-                McpError::internal_error(format!("Python 执行失败: {}", e), None)
+                log_important!(error, "[skills] Python 执行失败: skill={}, error={}", skill.name, e);
+                match e {
+                    e @ McpToolError::Timeout { .. } => McpError::from(e),
+                    e => to_mcp_error("Python 执行失败", e.into()),
+                }
             })?;
         
         let exec_duration = exec_start.elapsed().as_millis();
-        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let stdout = output.stdout.trim().to_string();
+        let stderr = output.stderr.trim().to_string();
 
         log_debug!("[skills] Python 执行完成: exit_code={:?}, stdout_len={}, stderr_len={}, duration={}ms",
             output.status.code(), stdout.len(), stderr.len(), exec_duration);
@@ -219,6 +294,152 @@ impl SkillsTool {
             title: None,
         }
     }
+
+    fn get_skill_list_tool_definition() -> Tool {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {}
+        });
+        let schema = match schema {
+            serde_json::Value::Object(map) => map,
+            _ => serde_json::Map::new(),
+        };
+        Tool {
+            name: Cow::Borrowed("skill_list"),
+            description: Some(Cow::Borrowed("列出已发现的技能（名称、描述、路径、是否启用）")),
+            input_schema: Arc::new(schema),
+            annotations: None,
+            icons: None,
+            meta: None,
+            output_schema: None,
+            title: None,
+        }
+    }
+}
+
+/// 运行技能进程：超时后杀掉进程，stdout/stderr 超过上限时截断并追加标记
+async fn run_skill_process(
+    mut command: Command,
+    skill_name: &str,
+    timeout_secs: u64,
+    max_output_bytes: usize,
+) -> Result<SkillRunOutput, McpToolError> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    // 中文注释：超出上限后继续读取并丢弃，避免管道写满导致子进程阻塞
+    let run = async {
+        tokio::join!(
+            read_capped(stdout, max_output_bytes),
+            read_capped(stderr, max_output_bytes),
+            child.wait()
+        )
+    };
+    let result = tokio::time::timeout(Duration::from_secs(timeout_secs), run).await;
+    let (stdout, stderr, status) = match result {
+        Ok(result) => result,
+        Err(_) => {
+            log_important!(warn, "[skills] 技能执行超时，终止进程: skill={}, timeout={}s", skill_name, timeout_secs);
+            let _ = child.kill().await;
+            return Err(timeout_error(format!("技能 {}", skill_name), timeout_secs));
+        }
+    };
+
+    Ok(SkillRunOutput {
+        status: status?,
+        stdout: capped_to_string(stdout?, max_output_bytes),
+        stderr: capped_to_string(stderr?, max_output_bytes),
+    })
+}
+
+/// 读取流内容，最多保留 `max_bytes` 字节；返回内容与是否发生截断
+async fn read_capped<R: AsyncRead + Unpin>(reader: Option<R>, max_bytes: usize) -> std::io::Result<(Vec<u8>, bool)> {
+    let mut buf = Vec::new();
+    let mut truncated = false;
+    let Some(mut reader) = reader else {
+        return Ok((buf, truncated));
+    };
+
+    let mut chunk = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        let remaining = max_bytes.saturating_sub(buf.len());
+        if n > remaining {
+            truncated = true;
+        }
+        buf.extend_from_slice(&chunk[..n.min(remaining)]);
+    }
+    Ok((buf, truncated))
+}
+
+/// 将截断后的字节转为文本（截断时回退到完整的 UTF-8 字符边界并追加标记）
+fn capped_to_string((mut bytes, truncated): (Vec<u8>, bool), max_bytes: usize) -> String {
+    if !truncated {
+        return String::from_utf8_lossy(&bytes).to_string();
+    }
+    if let Err(e) = std::str::from_utf8(&bytes) {
+        // 中文注释：仅裁掉末尾被截断的半个字符，中间的非法字节交给 lossy 处理
+        if e.error_len().is_none() {
+            bytes.truncate(e.valid_up_to());
+        }
+    }
+    format!("{}\n...[输出已截断，超过 {} 字节上限]", String::from_utf8_lossy(&bytes), max_bytes)
+}
+
+/// 技能对应的动态工具名
+fn skill_tool_name(skill_name: &str) -> String {
+    format!("skill_{}", skill_name)
+}
+
+/// 检查动态工具名是否与保留名或已注册技能冲突，返回冲突原因
+///
+/// 技能工具名统一带 `skill_` 前缀，不会与内置工具（zhi、ji 等）同名
+fn tool_name_conflict(tool_name: &str, registered: &HashSet<String>) -> Option<&'static str> {
+    if RESERVED_SKILL_TOOL_NAMES.iter().any(|n| n.eq_ignore_ascii_case(tool_name)) {
+        return Some("占用了保留工具名");
+    }
+    if registered.contains(tool_name) {
+        return Some("与其他技能工具同名");
+    }
+    None
+}
+
+/// 读取配置中的技能启用状态
+fn load_skill_enabled_map() -> HashMap<String, bool> {
+    load_standalone_config()
+        .ok()
+        .and_then(|c| c.mcp_config.skill_enabled)
+        .unwrap_or_default()
+}
+
+/// 技能是否启用（配置键按技能名规范化后匹配，未配置视为启用）
+fn is_skill_enabled(name: &str, enabled_map: &HashMap<String, bool>) -> bool {
+    enabled_map
+        .iter()
+        .find(|(key, _)| normalize_skill_name(key) == name)
+        .map(|(_, enabled)| *enabled)
+        .unwrap_or(true)
+}
+
+fn collect_skill_list(project_root: &Path, enabled_map: &HashMap<String, bool>) -> Vec<SkillListItem> {
+    scan_skills(project_root)
+        .into_iter()
+        .map(|skill| SkillListItem {
+            enabled: is_skill_enabled(&skill.name, enabled_map),
+            name: skill.name,
+            description: skill.description,
+            path: skill.path.to_string_lossy().to_string(),
+        })
+        .collect()
 }
 
 fn skills_input_schema() -> serde_json::Map<String, serde_json::Value> {
@@ -472,3 +693,104 @@ fn normalize_skill_name(name: &str) -> String {
         out
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sleeping_skill_is_killed_on_timeout() {
+        let mut command = Command::new("sleep");
+        command.arg("30");
+
+        let start = std::time::Instant::now();
+        let err = run_skill_process(command, "sleepy", 1, 1024).await.unwrap_err();
+
+        assert!(matches!(err, McpToolError::Timeout { secs: 1, .. }));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_flooding_skill_output_is_truncated() {
+        // 中文注释：输出约 4MB，上限 64KB
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("head -c 4194304 /dev/zero | tr '\\0' 'x'");
+
+        let output = run_skill_process(command, "flood", 30, 64 * 1024).await.unwrap();
+
+        assert!(output.status.success());
+        assert!(output.stdout.starts_with("xxxx"));
+        assert!(output.stdout.ends_with("...[输出已截断，超过 65536 字节上限]"));
+        assert_eq!(output.stdout.matches('x').count(), 64 * 1024);
+    }
+
+    #[test]
+    fn test_truncated_output_keeps_utf8_boundary() {
+        // "技" 占 3 字节，截断在第 2 字节处
+        let bytes = "a技".as_bytes()[..3].to_vec();
+        let text = capped_to_string((bytes, true), 3);
+        assert!(text.starts_with("a\n...[输出已截断"));
+    }
+
+    fn write_skill(root: &Path, name: &str) {
+        let dir = root.join("skills").join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("SKILL.md"),
+            format!("---\nname: {}\ndescription: {} 技能\n---\n", name, name),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_colliding_skill_is_skipped() {
+        let temp_dir = TempDir::new().unwrap();
+        write_skill(temp_dir.path(), "run");
+        write_skill(temp_dir.path(), "list");
+        write_skill(temp_dir.path(), "gamma");
+
+        let names: Vec<String> = SkillsTool::build_dynamic_tools(temp_dir.path(), &HashMap::new())
+            .into_iter()
+            .map(|t| t.name.to_string())
+            .collect();
+
+        // skill_run / skill_list 只能出现一次，且为内置定义
+        assert_eq!(names.iter().filter(|n| *n == "skill_run").count(), 1);
+        assert_eq!(names.iter().filter(|n| *n == "skill_list").count(), 1);
+        assert!(names.contains(&"skill_gamma".to_string()));
+
+        let mut registered = HashSet::new();
+        assert!(tool_name_conflict("skill_gamma", &registered).is_none());
+        registered.insert("skill_gamma".to_string());
+        assert_eq!(tool_name_conflict("skill_gamma", &registered), Some("与其他技能工具同名"));
+    }
+
+    #[test]
+    fn test_disabled_skill_excluded_from_dynamic_tools() {
+        let temp_dir = TempDir::new().unwrap();
+        write_skill(temp_dir.path(), "alpha");
+        write_skill(temp_dir.path(), "beta");
+
+        let mut enabled_map = HashMap::new();
+        enabled_map.insert("Beta".to_string(), false);
+
+        let names: Vec<String> = SkillsTool::build_dynamic_tools(temp_dir.path(), &enabled_map)
+            .into_iter()
+            .map(|t| t.name.to_string())
+            .collect();
+        assert!(names.contains(&"skill_run".to_string()));
+        assert!(names.contains(&"skill_list".to_string()));
+        assert!(names.contains(&"skill_alpha".to_string()));
+        assert!(!names.contains(&"skill_beta".to_string()));
+
+        // skill_list 仍展示被禁用的技能，便于用户排查
+        let items = collect_skill_list(temp_dir.path(), &enabled_map);
+        let beta = items.iter().find(|i| i.name == "beta").unwrap();
+        assert!(!beta.enabled);
+        assert_eq!(beta.description.as_deref(), Some("beta 技能"));
+        assert!(items.iter().find(|i| i.name == "alpha").unwrap().enabled);
+    }
+}
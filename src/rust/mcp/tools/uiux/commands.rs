@@ -0,0 +1,9 @@
+// UI/UX 工具的 Tauri 命令
+
+use super::engine;
+
+/// 重新加载 UI/UX 语料（用户修改 uiux_corpus 目录后调用，下次检索时重建索引）
+#[tauri::command]
+pub fn reload_uiux_corpus() {
+    engine::reload_uiux_corpus();
+}
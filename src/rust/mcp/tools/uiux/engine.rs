@@ -1,23 +1,30 @@
 // UI/UX Pro Max Rust 原生实现
 // 负责数据加载、搜索引擎、设计系统生成与输出格式化
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
 
-use chrono::Local;
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
 use regex::Regex;
 use rust_embed::RustEmbed;
 use serde::Serialize;
 use serde_json::Value;
 
 use crate::mcp::tools::memory::TextSimilarity;
-use crate::log_debug;
+use crate::{log_debug, log_important};
 use super::lexicon;
+use super::response::UiuxError;
 use super::sanitize::{sanitize_path_segment, sanitize_slug};
 
 const MAX_RESULTS: usize = 3;
+/// 无结果时返回的候选关键词数量
+const MAX_QUERY_SUGGESTIONS: usize = 5;
+const SEARCH_STATUS_OK: &str = "ok";
+const SEARCH_STATUS_NO_RESULTS: &str = "no_results";
+const SEARCH_STATUS_ERROR: &str = "error";
 const BOX_WIDTH: usize = 90;
 
 #[derive(RustEmbed)]
@@ -257,6 +264,33 @@ static STACK_CONFIGS: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
     map
 });
 
+/// 技术栈别名（规范化后的输入 -> STACK_CONFIGS 中的规范名）
+static STACK_ALIASES: &[(&str, &str)] = &[
+    ("reactjs", "react"),
+    ("react-js", "react"),
+    ("next", "nextjs"),
+    ("next-js", "nextjs"),
+    ("vuejs", "vue"),
+    ("vue-js", "vue"),
+    ("vue3", "vue"),
+    ("nuxt", "nuxtjs"),
+    ("nuxt-js", "nuxtjs"),
+    ("nuxtui", "nuxt-ui"),
+    ("sveltekit", "svelte"),
+    ("svelte-kit", "svelte"),
+    ("swift-ui", "swiftui"),
+    ("reactnative", "react-native"),
+    ("rn", "react-native"),
+    ("html", "html-tailwind"),
+    ("tailwind", "html-tailwind"),
+    ("tailwindcss", "html-tailwind"),
+    ("html+tailwind", "html-tailwind"),
+    ("shadcn-ui", "shadcn"),
+    ("shadcnui", "shadcn"),
+    ("compose", "jetpack-compose"),
+    ("jetpackcompose", "jetpack-compose"),
+];
+
 static STACK_SEARCH_COLS: &[&str] = &["Category", "Guideline", "Description", "Do", "Don't"];
 static STACK_OUTPUT_COLS: &[&str] = &[
     "Category",
@@ -652,8 +686,10 @@ struct DomainIndex {
 }
 
 impl DomainIndex {
-    fn new(config: &DomainConfig) -> Result<Self, String> {
-        let rows = load_csv(config.file)?;
+    /// 构建索引：内嵌数据 + 用户语料（追加在内嵌行之后）
+    fn new(config: &DomainConfig, extra_rows: Vec<HashMap<String, String>>) -> Result<Self, String> {
+        let mut rows = load_csv(config.file)?;
+        rows.extend(extra_rows);
 
         let documents: Vec<String> = rows
             .iter()
@@ -692,34 +728,49 @@ impl DomainIndex {
     }
 
     fn search(&self, query: &str, max_results: usize) -> Vec<HashMap<String, String>> {
-        let max_results = max_results.max(1);
+        self.search_page(query, 0, max_results).0
+    }
 
-        // 1) Query Expansion：先把中文/同义概念映射成额外英文 token，再喂给 BM25
-        let expanded_query = expand_query_for_bm25(query);
-        let ranked = self.bm25.score(&expanded_query);
-        let mut results = Vec::new();
+    /// 分页检索：先按相关度排序全部匹配，再取 `[offset, offset + limit)`；返回当前页与匹配总数
+    fn search_page(&self, query: &str, offset: usize, limit: usize) -> (Vec<HashMap<String, String>>, usize) {
+        let matches = self.ranked_matches(query);
+        let total = matches.len();
+        let page = matches
+            .into_iter()
+            .skip(offset)
+            .take(limit.max(1))
+            .filter_map(|idx| self.output_row(idx))
+            .collect();
+        (page, total)
+    }
 
-        for (idx, score) in ranked {
-            if results.len() >= max_results {
-                break;
-            }
-            if score <= 0.0 {
-                continue;
-            }
-            if let Some(row) = self.rows.get(idx) {
-                let mut out = HashMap::new();
-                for col in self.output_cols {
-                    if let Some(value) = row.get(*col) {
-                        out.insert((*col).to_string(), value.clone());
-                    }
-                }
-                results.push(out);
+    /// 按输出列顺序提取单行
+    fn output_row(&self, idx: usize) -> Option<HashMap<String, String>> {
+        let row = self.rows.get(idx)?;
+        let mut out = HashMap::new();
+        for col in self.output_cols {
+            if let Some(value) = row.get(*col) {
+                out.insert((*col).to_string(), value.clone());
             }
         }
+        Some(out)
+    }
+
+    /// 按相关度降序返回全部匹配的行号
+    fn ranked_matches(&self, query: &str) -> Vec<usize> {
+        // 1) Query Expansion：先把中文/同义概念映射成额外英文 token，再喂给 BM25
+        let expanded_query = expand_query_for_bm25(query);
+        let matches: Vec<usize> = self
+            .bm25
+            .score(&expanded_query)
+            .into_iter()
+            .filter(|(idx, score)| *score > 0.0 && *idx < self.rows.len())
+            .map(|(idx, _)| idx)
+            .collect();
 
         // 2) 语义回退：BM25 没命中时，使用轻量文本相似度做兜底（数据规模百级，性能可控）
-        if !results.is_empty() {
-            return results;
+        if !matches.is_empty() {
+            return matches;
         }
 
         // 注意：fuzzy 回退优先用于“拼写错误/轻微变体”，因此使用短文档 + 原始 query 比对。
@@ -753,25 +804,13 @@ impl DomainIndex {
         }
         let min_item = (best * FUZZY_FALLBACK_RELATIVE_RATIO).max(FUZZY_FALLBACK_MIN_ITEM_FLOOR);
 
-        for (sim, idx) in scored {
-            if results.len() >= max_results {
-                break;
-            }
-            if sim < min_item {
-                break; // 已按降序排序
-            }
-            if let Some(row) = self.rows.get(idx) {
-                let mut out = HashMap::new();
-                for col in self.output_cols {
-                    if let Some(value) = row.get(*col) {
-                        out.insert((*col).to_string(), value.clone());
-                    }
-                }
-                results.push(out);
-            }
-        }
-
-        results
+        // 已按降序排序，低于阈值后全部丢弃
+        scored
+            .into_iter()
+            .take_while(|(sim, _)| *sim >= min_item)
+            .filter(|(_, idx)| *idx < self.rows.len())
+            .map(|(_, idx)| idx)
+            .collect()
     }
 }
 
@@ -781,9 +820,16 @@ pub struct SearchResult {
     pub query: String,
     pub file: Option<String>,
     pub count: usize,
+    /// 匹配总数（分页前），大于 offset + count 时说明还有下一页
+    pub total_matched: usize,
     pub results: Vec<HashMap<String, String>>,
     pub stack: Option<String>,
     pub error: Option<String>,
+    /// 检索状态："ok" | "no_results" | "error"
+    pub status: String,
+    /// 无结果时的候选关键词（来自扩展/同义词表）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
 }
 
 impl SearchResult {
@@ -793,13 +839,48 @@ impl SearchResult {
             query: query.to_string(),
             file: None,
             count: 0,
+            total_matched: 0,
             results: Vec::new(),
             stack: None,
             error: Some(message.to_string()),
+            status: SEARCH_STATUS_ERROR.to_string(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    /// 模糊回退后仍无任何匹配时，标记为 no_results 并附带候选关键词
+    fn with_no_results_hint(mut self) -> Self {
+        if self.error.is_none() && self.total_matched == 0 {
+            self.status = SEARCH_STATUS_NO_RESULTS.to_string();
+            self.suggestions = suggest_query_terms(&self.query, MAX_QUERY_SUGGESTIONS);
         }
+        self
     }
 }
 
+/// 为无结果的查询推荐相近的关键词（中文扩展短语与英文同义词表中的词条）
+fn suggest_query_terms(query: &str, limit: usize) -> Vec<String> {
+    let query = query.trim().to_lowercase();
+    let mut scored: Vec<(f64, &'static str)> = lexicon::ZH_TO_EN_EXPANSIONS
+        .iter()
+        .map(|(zh, _)| *zh)
+        .chain(lexicon::EN_SYNONYMS.iter().map(|(en, _)| *en))
+        .map(|term| (TextSimilarity::calculate_enhanced(&query, term), term))
+        .collect();
+    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut out: Vec<String> = Vec::new();
+    for (_, term) in scored {
+        if out.len() >= limit {
+            break;
+        }
+        if !out.iter().any(|t| t == term) {
+            out.push(term.to_string());
+        }
+    }
+    out
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct SuggestResult {
     pub should_suggest: bool,
@@ -824,58 +905,107 @@ pub struct PersistSummary {
 pub struct DesignSystemOutput {
     pub design_system: DesignSystem,
     pub persisted: Option<PersistSummary>,
+    /// 完整的设计系统文档（markdown 或 ascii），无论是否落盘都会返回，供无法读取文件系统的调用方直接使用
+    pub document: String,
+    /// 返回给调用方的文本：文档正文 + 落盘提示（仅 persist 时追加）
     pub formatted: String,
 }
 
+/// 领域索引缓存：首次访问时构建，之后复用（加载失败记为 None，不再重试）
+type LazyIndex = OnceCell<Option<DomainIndex>>;
+
+/// UI/UX 数据仓库：各领域/技术栈的 BM25 索引按需构建一次，跨请求复用（OnceCell 保证并发安全）
 struct UiuxStore {
-    domains: HashMap<&'static str, DomainIndex>,
-    stacks: HashMap<&'static str, DomainIndex>,
-    reasoning: Vec<HashMap<String, String>>,
-    keyword_set: HashSet<String>,
+    /// 用户语料目录（`<目录>/<domain>.csv|json`），None 表示仅使用内嵌数据
+    corpus_dir: Option<PathBuf>,
+    domains: HashMap<&'static str, LazyIndex>,
+    stacks: HashMap<&'static str, LazyIndex>,
+    reasoning: OnceCell<Vec<HashMap<String, String>>>,
+    keyword_set: OnceCell<HashSet<String>>,
+    /// 已构建的领域/技术栈索引数（每个索引在仓库生命周期内只应构建一次）
+    index_builds: AtomicUsize,
 }
 
-static UIUX_STORE: Lazy<UiuxStore> = Lazy::new(UiuxStore::load);
+/// 用户语料子目录（位于数据目录下）
+const USER_CORPUS_DIR: &str = "uiux_corpus";
+
+// 中文注释：重新加载语料时整体替换仓库实例；已持有旧 Arc 的请求继续使用旧索引直至结束
+static UIUX_STORE: Lazy<RwLock<Arc<UiuxStore>>> = Lazy::new(|| RwLock::new(Arc::new(UiuxStore::new())));
+
+fn uiux_store() -> Arc<UiuxStore> {
+    UIUX_STORE.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// 丢弃已缓存的索引，下次检索时重新读取内嵌数据与用户语料
+pub fn reload_uiux_corpus() {
+    *UIUX_STORE.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(UiuxStore::new());
+    log_debug!("UIUX 语料缓存已清空，将在下次检索时重建");
+}
 
 impl UiuxStore {
-    fn load() -> Self {
-        let mut domains = HashMap::new();
-        for (name, config) in DOMAIN_CONFIGS.iter() {
-            match DomainIndex::new(config) {
-                Ok(index) => {
-                    domains.insert(*name, index);
-                }
-                Err(err) => {
-                    log_debug!("UIUX 域数据加载失败: {} -> {}", name, err);
-                }
-            }
-        }
+    fn new() -> Self {
+        Self::with_corpus_dir(Some(crate::paths::data_subdir(USER_CORPUS_DIR)))
+    }
 
-        let mut stacks = HashMap::new();
-        for (name, file) in STACK_CONFIGS.iter() {
+    fn with_corpus_dir(corpus_dir: Option<PathBuf>) -> Self {
+        Self {
+            corpus_dir,
+            domains: DOMAIN_CONFIGS.keys().map(|name| (*name, OnceCell::new())).collect(),
+            stacks: STACK_CONFIGS.keys().map(|name| (*name, OnceCell::new())).collect(),
+            reasoning: OnceCell::new(),
+            keyword_set: OnceCell::new(),
+            index_builds: AtomicUsize::new(0),
+        }
+    }
+
+    fn index_builds(&self) -> usize {
+        self.index_builds.load(Ordering::Relaxed)
+    }
+
+    /// 获取领域索引（首次调用时构建）
+    fn domain(&self, name: &str) -> Option<&DomainIndex> {
+        let (key, cell) = self.domains.get_key_value(name)?;
+        cell.get_or_init(|| {
+            let config = DOMAIN_CONFIGS.get(key)?;
+            self.index_builds.fetch_add(1, Ordering::Relaxed);
+            log_debug!("UIUX 构建领域索引: {}", key);
+            let extra_rows = self
+                .corpus_dir
+                .as_deref()
+                .map(|dir| load_user_corpus(dir, key))
+                .unwrap_or_default();
+            DomainIndex::new(config, extra_rows)
+                .map_err(|err| log_debug!("UIUX 域数据加载失败: {} -> {}", key, err))
+                .ok()
+        })
+        .as_ref()
+    }
+
+    /// 获取技术栈索引（首次调用时构建）
+    fn stack(&self, name: &str) -> Option<&DomainIndex> {
+        let (key, cell) = self.stacks.get_key_value(name)?;
+        cell.get_or_init(|| {
             let config = DomainConfig {
-                file,
+                file: *STACK_CONFIGS.get(key)?,
                 search_cols: STACK_SEARCH_COLS,
                 output_cols: STACK_OUTPUT_COLS,
             };
-            match DomainIndex::new(&config) {
-                Ok(index) => {
-                    stacks.insert(*name, index);
-                }
-                Err(err) => {
-                    log_debug!("UIUX 栈数据加载失败: {} -> {}", name, err);
-                }
-            }
-        }
+            self.index_builds.fetch_add(1, Ordering::Relaxed);
+            log_debug!("UIUX 构建技术栈索引: {}", key);
+            DomainIndex::new(&config, Vec::new())
+                .map_err(|err| log_debug!("UIUX 栈数据加载失败: {} -> {}", key, err))
+                .ok()
+        })
+        .as_ref()
+    }
 
-        let reasoning = load_csv("ui-reasoning.csv").unwrap_or_default();
-        let keyword_set = build_keyword_set();
+    fn reasoning(&self) -> &Vec<HashMap<String, String>> {
+        self.reasoning
+            .get_or_init(|| load_csv("ui-reasoning.csv").unwrap_or_default())
+    }
 
-        Self {
-            domains,
-            stacks,
-            reasoning,
-            keyword_set,
-        }
+    fn keyword_set(&self) -> &HashSet<String> {
+        self.keyword_set.get_or_init(build_keyword_set)
     }
 }
 
@@ -913,6 +1043,104 @@ fn load_csv(path: &str) -> Result<Vec<HashMap<String, String>>, String> {
     Ok(rows)
 }
 
+/// 读取某领域的用户语料：`<dir>/<domain>.csv` 与 `<dir>/<domain>.json`（对象数组），文件不存在时返回空
+///
+/// 列名需与内嵌数据一致；列数不符、非对象或检索列全为空的条目跳过并记录警告。
+fn load_user_corpus(dir: &Path, domain: &str) -> Vec<HashMap<String, String>> {
+    let Some(config) = DOMAIN_CONFIGS.get(domain) else {
+        return Vec::new();
+    };
+
+    let mut rows = Vec::new();
+    let csv_path = dir.join(format!("{}.csv", domain));
+    if csv_path.is_file() {
+        rows.extend(load_user_corpus_csv(&csv_path));
+    }
+    let json_path = dir.join(format!("{}.json", domain));
+    if json_path.is_file() {
+        rows.extend(load_user_corpus_json(&json_path));
+    }
+
+    let before = rows.len();
+    rows.retain(|row| {
+        config
+            .search_cols
+            .iter()
+            .any(|col| row.get(*col).is_some_and(|v| !v.trim().is_empty()))
+    });
+    if rows.len() < before {
+        log_important!(warn, "UIUX 用户语料 {} 中有 {} 条记录缺少检索列，已跳过", domain, before - rows.len());
+    }
+    if !rows.is_empty() {
+        log_debug!("UIUX 用户语料已加载: {} -> {} 条", domain, rows.len());
+    }
+    rows
+}
+
+fn load_user_corpus_csv(path: &Path) -> Vec<HashMap<String, String>> {
+    let mut reader = match csv::ReaderBuilder::new().has_headers(true).from_path(path) {
+        Ok(reader) => reader,
+        Err(e) => {
+            log_important!(warn, "读取 UIUX 用户语料失败: {} -> {}", path.display(), e);
+            return Vec::new();
+        }
+    };
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(e) => {
+            log_important!(warn, "读取 UIUX 用户语料 CSV 头失败: {} -> {}", path.display(), e);
+            return Vec::new();
+        }
+    };
+
+    let mut rows = Vec::new();
+    // 中文注释：与内嵌数据不同，用户语料不开启 flexible，列数不符的行视为格式错误逐行跳过
+    for (line, record) in reader.records().enumerate() {
+        match record {
+            Ok(record) => rows.push(
+                headers
+                    .iter()
+                    .zip(record.iter())
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            ),
+            Err(e) => log_important!(warn, "跳过 UIUX 用户语料坏行: {} 第 {} 条 -> {}", path.display(), line + 1, e),
+        }
+    }
+    rows
+}
+
+fn load_user_corpus_json(path: &Path) -> Vec<HashMap<String, String>> {
+    let entries = match std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str::<Vec<Value>>(&content).map_err(|e| e.to_string()))
+    {
+        Ok(entries) => entries,
+        Err(e) => {
+            log_important!(warn, "读取 UIUX 用户语料失败（需为 JSON 对象数组）: {} -> {}", path.display(), e);
+            return Vec::new();
+        }
+    };
+
+    let mut rows = Vec::new();
+    for (idx, entry) in entries.into_iter().enumerate() {
+        let Value::Object(map) = entry else {
+            log_important!(warn, "跳过 UIUX 用户语料坏条目: {} 第 {} 条不是对象", path.display(), idx + 1);
+            continue;
+        };
+        let row = map
+            .into_iter()
+            .filter_map(|(key, value)| match value {
+                Value::String(s) => Some((key, s)),
+                Value::Number(_) | Value::Bool(_) => Some((key, value.to_string())),
+                _ => None,
+            })
+            .collect();
+        rows.push(row);
+    }
+    rows
+}
+
 fn detect_domain(query: &str) -> &'static str {
     let query_lower = query.to_lowercase();
 
@@ -971,54 +1199,217 @@ pub fn cap_max_results(value: Option<u32>, cap: u32, default: u32) -> usize {
 }
 
 pub fn search_domain(query: &str, domain: Option<&str>, max_results: Option<usize>) -> SearchResult {
-    let store = &*UIUX_STORE;
+    search_domain_page(query, domain, max_results, None)
+}
+
+/// 分页检索单个领域：`offset` 作用于排序之后，超出匹配总数时返回空页
+pub fn search_domain_page(
+    query: &str,
+    domain: Option<&str>,
+    max_results: Option<usize>,
+    offset: Option<u32>,
+) -> SearchResult {
+    let store = uiux_store();
     let requested_domain = domain.unwrap_or_else(|| detect_domain(query));
-    let domain = if store.domains.contains_key(requested_domain) {
+    let domain = if store.domain(requested_domain).is_some() {
         requested_domain
     } else {
         "style"
     };
     let max_results = max_results.unwrap_or(MAX_RESULTS);
+    let offset = offset.unwrap_or(0) as usize;
 
-    match store.domains.get(domain) {
+    match store.domain(domain) {
         Some(index) => {
-            let results = index.search(query, max_results);
+            let (results, total_matched) = index.search_page(query, offset, max_results);
             SearchResult {
                 domain: domain.to_string(),
                 query: query.to_string(),
                 file: Some(index.file.to_string()),
                 count: results.len(),
+                total_matched,
                 results,
                 stack: None,
                 error: None,
+                status: SEARCH_STATUS_OK.to_string(),
+                suggestions: Vec::new(),
             }
+            .with_no_results_hint()
         }
         None => SearchResult::error(domain, query, &format!("未知领域: {}", domain)),
     }
 }
 
+/// 多领域搜索结果中标记来源领域的字段名（同一条目出现在多个领域时以逗号连接）
+pub const RESULT_DOMAIN_FIELD: &str = "Domain";
+
+/// 解析领域参数：逗号分隔列表或 `all`；忽略未知领域，保持输入顺序并去重
+fn parse_domain_list(domain: &str) -> Vec<&'static str> {
+    if domain.trim().eq_ignore_ascii_case("all") {
+        let mut all: Vec<&'static str> = DOMAIN_CONFIGS.keys().copied().collect();
+        all.sort();
+        return all;
+    }
+    let mut domains = Vec::new();
+    for name in domain.split(',').map(|d| d.trim().to_lowercase()) {
+        match DOMAIN_CONFIGS.get_key_value(name.as_str()) {
+            Some((key, _)) if !domains.contains(key) => domains.push(*key),
+            Some(_) => {}
+            None => log_debug!("UIUX 忽略未知领域: {}", name),
+        }
+    }
+    domains
+}
+
+/// 按排名交替合并各领域结果，并为每条结果标记来源领域；内容相同的条目只保留一条
+fn merge_domain_results(per_domain: Vec<(&str, Vec<HashMap<String, String>>)>) -> Vec<HashMap<String, String>> {
+    let mut merged: Vec<HashMap<String, String>> = Vec::new();
+    let mut seen: HashMap<Vec<(String, String)>, usize> = HashMap::new();
+    let max_len = per_domain.iter().map(|(_, rows)| rows.len()).max().unwrap_or(0);
+
+    for rank in 0..max_len {
+        for (domain, rows) in &per_domain {
+            let Some(row) = rows.get(rank) else {
+                continue;
+            };
+            let mut key: Vec<(String, String)> = row.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+            key.sort();
+            if let Some(&idx) = seen.get(&key) {
+                if let Some(tag) = merged[idx].get_mut(RESULT_DOMAIN_FIELD) {
+                    tag.push(',');
+                    tag.push_str(domain);
+                }
+                continue;
+            }
+            let mut tagged = row.clone();
+            tagged.insert(RESULT_DOMAIN_FIELD.to_string(), domain.to_string());
+            seen.insert(key, merged.len());
+            merged.push(tagged);
+        }
+    }
+    merged
+}
+
+/// 多领域搜索：`domain` 支持逗号分隔列表（如 `color,typography`）或 `all`
+///
+/// 只有一个有效领域（或未指定）时与 [`search_domain_page`] 行为一致；
+/// 多领域时 `offset` 作用于合并后的结果，`total_matched` 为各领域匹配数之和
+pub fn search_domains(
+    query: &str,
+    domain: Option<&str>,
+    max_results: Option<usize>,
+    offset: Option<u32>,
+) -> SearchResult {
+    let domains = match domain {
+        Some(d) if d.contains(',') || d.trim().eq_ignore_ascii_case("all") => parse_domain_list(d),
+        _ => return search_domain_page(query, domain, max_results, offset),
+    };
+    if domains.len() <= 1 {
+        return search_domain_page(query, domains.first().copied(), max_results, offset);
+    }
+
+    let store = uiux_store();
+    let max_results = max_results.unwrap_or(MAX_RESULTS);
+    let offset = offset.unwrap_or(0) as usize;
+    let mut files = Vec::new();
+    let mut per_domain = Vec::new();
+    let mut total_matched = 0usize;
+    for name in &domains {
+        if let Some(index) = store.domain(name) {
+            files.push(index.file.to_string());
+            // 中文注释：每个领域取到 offset + max_results 条，保证合并后的当前页完整
+            let (rows, matched) = index.search_page(query, 0, offset.saturating_add(max_results));
+            total_matched += matched;
+            per_domain.push((*name, rows));
+        }
+    }
+
+    let results: Vec<HashMap<String, String>> = merge_domain_results(per_domain)
+        .into_iter()
+        .skip(offset)
+        .take(max_results)
+        .collect();
+    SearchResult {
+        domain: domains.join(","),
+        query: query.to_string(),
+        file: Some(files.join(", ")),
+        count: results.len(),
+        total_matched,
+        results,
+        stack: None,
+        error: None,
+        status: SEARCH_STATUS_OK.to_string(),
+        suggestions: Vec::new(),
+    }
+    .with_no_results_hint()
+}
+
+/// 将用户输入的技术栈解析为规范名（大小写、空格/下划线/斜杠、`.js` 写法与常见别名）
+///
+/// 未知技术栈返回 `uiux_unknown_stack` 错误，附带相近候选与全部支持列表
+pub fn resolve_stack(stack: &str) -> Result<&'static str, UiuxError> {
+    let normalized = stack
+        .trim()
+        .to_lowercase()
+        .replace(".js", "js")
+        .replace([' ', '_', '/'], "-");
+
+    let canonical = STACK_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == normalized)
+        .map(|(_, canonical)| *canonical)
+        .unwrap_or(normalized.as_str());
+    if let Some((name, _)) = STACK_CONFIGS.get_key_value(canonical) {
+        return Ok(name);
+    }
+
+    let mut suggestions: Vec<(f64, &'static str)> = available_stacks()
+        .into_iter()
+        .map(|name| (TextSimilarity::levenshtein_similarity(&normalized, name), name))
+        .filter(|(score, _)| *score >= 0.5)
+        .collect();
+    suggestions.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut message = format!("Unknown stack: {}.", stack.trim());
+    if !suggestions.is_empty() {
+        let names: Vec<&str> = suggestions.iter().map(|(_, name)| *name).collect();
+        message.push_str(&format!(" Did you mean: {}?", names.join(", ")));
+    }
+    message.push_str(&format!(" Available: {}", available_stacks().join(", ")));
+    Err(UiuxError::new("uiux_unknown_stack", &message))
+}
+
 pub fn search_stack(query: &str, stack: &str, max_results: Option<usize>) -> SearchResult {
-    let store = &*UIUX_STORE;
+    let store = uiux_store();
     let max_results = max_results.unwrap_or(MAX_RESULTS);
 
-    match store.stacks.get(stack) {
+    let stack = match resolve_stack(stack) {
+        Ok(stack) => stack,
+        Err(err) => return SearchResult::error("stack", query, &err.message),
+    };
+
+    match store.stack(stack) {
         Some(index) => {
-            let results = index.search(query, max_results);
+            let (results, total_matched) = index.search_page(query, 0, max_results);
             SearchResult {
                 domain: "stack".to_string(),
                 query: query.to_string(),
                 file: Some(index.file.to_string()),
                 count: results.len(),
+                total_matched,
                 results,
                 stack: Some(stack.to_string()),
                 error: None,
+                status: SEARCH_STATUS_OK.to_string(),
+                suggestions: Vec::new(),
             }
+            .with_no_results_hint()
         }
-        None => SearchResult::error(
-            "stack",
-            query,
-            &format!("Unknown stack: {}. Available: {}", stack, available_stacks().join(", ")),
-        ),
+        // 中文注释：技术栈合法但数据文件加载失败，仍返回规范名便于排查
+        None => SearchResult {
+            stack: Some(stack.to_string()),
+            ..SearchResult::error("stack", query, &format!("Stack data not available: {}", stack))
+        },
     }
 }
 
@@ -1042,15 +1433,24 @@ pub fn format_search_output(result: &SearchResult) -> String {
         output.push(format!("**Domain:** {} | **Query:** {}", result.domain, result.query));
     }
     output.push(format!(
-        "**Source:** {} | **Found:** {} results\n",
+        "**Source:** {} | **Found:** {} results (total matched: {})\n",
         result.file.clone().unwrap_or_default(),
-        result.count
+        result.count,
+        result.total_matched
     ));
 
     for (idx, row) in result.results.iter().enumerate() {
         output.push(format!("### Result {}", idx + 1));
+        // 多领域结果按条目自身的来源领域选择输出列
+        let row_domain = match row.get(RESULT_DOMAIN_FIELD) {
+            Some(tag) => {
+                output.push(format!("- **{}:** {}", RESULT_DOMAIN_FIELD, tag));
+                tag.split(',').next().unwrap_or_default()
+            }
+            None => result.domain.as_str(),
+        };
         // 使用输出列顺序保证稳定展示
-        if let Some(config) = DOMAIN_CONFIGS.get(result.domain.as_str()) {
+        if let Some(config) = DOMAIN_CONFIGS.get(row_domain) {
             for col in config.output_cols {
                 if let Some(value) = row.get(*col) {
                     let mut value_str = value.clone();
@@ -1196,9 +1596,9 @@ struct DesignSystemGenerator {
 
 impl DesignSystemGenerator {
     fn new() -> Self {
-        let store = &*UIUX_STORE;
+        let store = uiux_store();
         Self {
-            reasoning_data: store.reasoning.clone(),
+            reasoning_data: store.reasoning().clone(),
         }
     }
 
@@ -1312,7 +1712,15 @@ impl DesignSystemGenerator {
 
         let mut scored: Vec<(i32, &HashMap<String, String>)> = Vec::new();
         for result in results {
-            let result_str = format!("{:?}", result).to_lowercase();
+            // 按列名排序后拼接，避免 HashMap 迭代顺序影响匹配
+            let result_str = result
+                .iter()
+                .collect::<BTreeMap<_, _>>()
+                .into_iter()
+                .map(|(k, v)| format!("{}: {}", k, v))
+                .collect::<Vec<_>>()
+                .join(" | ")
+                .to_lowercase();
             let mut score = 0;
             for kw in priority_keywords {
                 let kw_lower = kw.to_lowercase();
@@ -1335,6 +1743,7 @@ impl DesignSystemGenerator {
             scored.push((score, result));
         }
 
+        // 稳定排序：同分时保持检索结果原有顺序
         scored.sort_by(|a, b| b.0.cmp(&a.0));
         if let Some((score, best)) = scored.first() {
             if *score > 0 {
@@ -1483,7 +1892,10 @@ impl DesignSystemGenerator {
                     .get("Text (Hex)")
                     .cloned()
                     .unwrap_or_else(|| "#1E293B".to_string()),
-                notes: best_color.get("Notes").cloned().unwrap_or_default(),
+                notes: best_color
+                    .get("Notes")
+                    .cloned()
+                    .unwrap_or_else(|| reasoning.color_mood.clone()),
             },
             typography: TypographyInfo {
                 heading: best_typography
@@ -1781,8 +2193,8 @@ pub fn format_markdown(design_system: &DesignSystem) -> String {
     lines.join("\n")
 }
 
+// 中文注释：落盘文件不写入生成时间，保证相同输入重复生成时内容一致，避免提交到仓库后产生无意义 diff
 fn format_master_md(design_system: &DesignSystem) -> String {
-    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
     let mut lines = Vec::new();
 
     lines.push("# Design System Master File".to_string());
@@ -1794,7 +2206,6 @@ fn format_master_md(design_system: &DesignSystem) -> String {
     lines.push("---".to_string());
     lines.push(String::new());
     lines.push(format!("**Project:** {}", design_system.project_name));
-    lines.push(format!("**Generated:** {}", timestamp));
     lines.push(format!("**Category:** {}", design_system.category));
     lines.push(String::new());
     lines.push("---".to_string());
@@ -2124,11 +2535,12 @@ fn generate_intelligent_overrides(
 
     let page_type = detect_page_type(&combined_context, &style_results);
 
-    let mut layout = HashMap::new();
-    let mut spacing = HashMap::new();
-    // 显式类型，避免空 HashMap 无法推断类型
-    let typography: HashMap<String, String> = HashMap::new();
-    let mut colors = HashMap::new();
+    // 使用 BTreeMap 保证输出键顺序稳定，相同输入生成逐字节一致的覆盖文件
+    let mut layout = BTreeMap::new();
+    let mut spacing = BTreeMap::new();
+    // 显式类型，避免空 BTreeMap 无法推断类型
+    let typography: BTreeMap<String, String> = BTreeMap::new();
+    let mut colors = BTreeMap::new();
     let mut components: Vec<String> = Vec::new();
     let unique_components: Vec<String> = Vec::new();
     let mut recommendations: Vec<String> = Vec::new();
@@ -2216,7 +2628,6 @@ fn generate_intelligent_overrides(
 }
 
 fn format_page_override_md(design_system: &DesignSystem, page_name: &str, page_query: Option<&str>) -> String {
-    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
     let page_title = page_name.replace(['-', '_'], " ").split_whitespace().map(|s| {
         let mut chars = s.chars();
         match chars.next() {
@@ -2231,7 +2642,6 @@ fn format_page_override_md(design_system: &DesignSystem, page_name: &str, page_q
     lines.push(format!("# {} Page Overrides", page_title));
     lines.push(String::new());
     lines.push(format!("> **PROJECT:** {}", design_system.project_name));
-    lines.push(format!("> **Generated:** {}", timestamp));
     lines.push(format!(
         "> **Page Type:** {}",
         overrides
@@ -2407,10 +2817,11 @@ pub fn generate_design_system(
         None
     };
 
-    let mut formatted = match normalize_format(format, "ascii").as_str() {
+    let document = match normalize_format(format, "ascii").as_str() {
         "markdown" => format_markdown(&design_system),
         _ => format_ascii_box(&design_system),
     };
+    let mut formatted = document.clone();
 
     if persist {
         let project_slug = sanitize_slug(&design_system.project_name);
@@ -2444,17 +2855,18 @@ pub fn generate_design_system(
     Ok(DesignSystemOutput {
         design_system,
         persisted,
+        document,
         formatted,
     })
 }
 
 pub fn suggest(text: &str) -> SuggestResult {
-    let store = &*UIUX_STORE;
+    let store = uiux_store();
     let mut matched: HashSet<String> = HashSet::new();
 
     // 1) 基础分词（允许 ui/ux 等短信号 + 处理中英粘连）
     for token in tokenize_for_suggest(text) {
-        if store.keyword_set.contains(&token) {
+        if store.keyword_set().contains(&token) {
             matched.insert(token);
         }
     }
@@ -2468,7 +2880,7 @@ pub fn suggest(text: &str) -> SuggestResult {
 
     // 3) Query Expansion：将中文/同义概念映射为英文 token，再尝试命中 keyword_set
     for token in collect_query_expansion(text) {
-        if store.keyword_set.contains(&token) {
+        if store.keyword_set().contains(&token) {
             matched.insert(token);
         }
     }
@@ -2611,6 +3023,225 @@ mod tests {
         }
     }
 
+    #[test]
+    fn stack_alias_resolves_to_canonical_name() {
+        assert_eq!(resolve_stack("ReactJS").unwrap(), "react");
+        assert_eq!(resolve_stack("Next.js").unwrap(), "nextjs");
+        assert_eq!(resolve_stack("vuejs").unwrap(), "vue");
+        assert_eq!(resolve_stack("react native").unwrap(), "react-native");
+        assert_eq!(resolve_stack("shadcn/ui").unwrap(), "shadcn");
+
+        let result = search_stack("button", "reactjs", Some(1));
+        assert_eq!(result.stack.as_deref(), Some("react"));
+    }
+
+    #[test]
+    fn unknown_stack_returns_error_with_suggestions() {
+        let err = resolve_stack("fluter").unwrap_err();
+        assert_eq!(err.code, "uiux_unknown_stack");
+        assert!(err.message.contains("Did you mean: flutter"), "{}", err.message);
+        assert!(err.message.contains("Available: "), "{}", err.message);
+
+        let result = search_stack("button", "angular", None);
+        assert!(result.error.unwrap().contains("Unknown stack: angular"));
+    }
+
+    #[test]
+    fn multi_domain_search_tags_results_from_each_domain() {
+        let result = search_domains("modern elegant professional", Some("color, typography"), Some(2), None);
+
+        assert_eq!(result.domain, "color,typography");
+        assert!(result.error.is_none());
+        assert_eq!(result.count, result.results.len());
+        let tags: Vec<&str> = result
+            .results
+            .iter()
+            .map(|r| r.get(RESULT_DOMAIN_FIELD).map(|s| s.as_str()).unwrap_or_default())
+            .collect();
+        assert!(tags.iter().any(|t| t.contains("color")), "tags: {:?}", tags);
+        assert!(tags.iter().any(|t| t.contains("typography")), "tags: {:?}", tags);
+    }
+
+    #[test]
+    fn merge_domain_results_interleaves_and_dedupes() {
+        let row = |v: &str| HashMap::from([("Name".to_string(), v.to_string())]);
+        let merged = merge_domain_results(vec![
+            ("color", vec![row("a"), row("shared")]),
+            ("typography", vec![row("shared"), row("b")]),
+        ]);
+
+        let names: Vec<&str> = merged.iter().map(|r| r["Name"].as_str()).collect();
+        assert_eq!(names, vec!["a", "shared", "b"]);
+        assert_eq!(merged[1][RESULT_DOMAIN_FIELD], "typography,color");
+    }
+
+    #[test]
+    fn domain_search_pages_do_not_overlap() {
+        let first = search_domain_page("modern", Some("style"), Some(2), None);
+        let second = search_domain_page("modern", Some("style"), Some(2), Some(2));
+        assert!(first.total_matched >= 4, "total_matched: {}", first.total_matched);
+        assert_eq!(second.total_matched, first.total_matched);
+        assert_eq!(first.count, 2);
+        assert_eq!(second.count, 2);
+        for row in &second.results {
+            assert!(!first.results.contains(row));
+        }
+
+        // offset 超出匹配总数时返回空页而不是报错
+        let beyond = search_domain_page("modern", Some("style"), Some(2), Some(10_000));
+        assert!(beyond.error.is_none());
+        assert_eq!(beyond.count, 0);
+        assert_eq!(beyond.total_matched, first.total_matched);
+    }
+
+    #[test]
+    fn index_page_reports_total_matches() {
+        let index = make_test_index();
+        let (page, total) = index.search_page("minimal clean", 0, 5);
+        assert!(total >= 1);
+        assert_eq!(page.len(), total);
+
+        let (rest, rest_total) = index.search_page("minimal clean", 1, 5);
+        assert_eq!(rest_total, total);
+        assert_eq!(rest.len(), total - 1);
+    }
+
+    #[test]
+    fn nonsense_query_reports_no_results_with_suggestions() {
+        let result = search_domain("zzqxv qqwxk", Some("style"), None);
+        assert_eq!(result.count, 0);
+        assert_eq!(result.status, "no_results");
+        assert!(!result.suggestions.is_empty());
+
+        let json: Value = serde_json::from_str(&format_search_json(&result).unwrap()).unwrap();
+        assert_eq!(json["status"], "no_results");
+        assert!(json["suggestions"].as_array().is_some_and(|s| !s.is_empty()));
+    }
+
+    #[test]
+    fn domain_index_is_built_once_and_reused() {
+        // 使用独立的仓库实例，避免其他测试已预热全局缓存
+        let store = Arc::new(UiuxStore::with_corpus_dir(None));
+        assert_eq!(store.index_builds(), 0);
+
+        let first = store.domain("typography").expect("typography 索引应能构建").search("elegant serif", 3);
+        let second = store.domain("typography").unwrap().search("elegant serif", 3);
+        assert_eq!(first, second);
+        assert_eq!(store.index_builds(), 1);
+
+        // 并发首次访问同一领域时也只构建一次，且所有调用方拿到同一个索引
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let store = Arc::clone(&store);
+                std::thread::spawn(move || store.domain("color").map(|index| index as *const DomainIndex as usize))
+            })
+            .collect();
+        let addrs: HashSet<Option<usize>> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(addrs.len(), 1);
+        assert_eq!(store.index_builds(), 2);
+    }
+
+    #[test]
+    fn reload_replaces_the_shared_store() {
+        let before = uiux_store();
+        assert!(Arc::ptr_eq(&before, &uiux_store()));
+
+        reload_uiux_corpus();
+        assert!(!Arc::ptr_eq(&before, &uiux_store()));
+    }
+
+    #[test]
+    fn design_system_document_is_returned_without_persisting() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        let output = generate_design_system(
+            "saas dashboard",
+            Some("Acme"),
+            Some("markdown"),
+            false,
+            Some("pricing"),
+            Some(temp_dir.path()),
+        )
+        .unwrap();
+
+        assert!(output.persisted.is_none());
+        assert_eq!(output.document, format_markdown(&output.design_system));
+        assert_eq!(output.formatted, output.document);
+        assert!(output.formatted.contains("## Design System: ACME"));
+        assert!(output.formatted.contains("### Style"));
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+
+        // persist=true 时正文同样完整返回，仅额外写入文件并追加提示
+        let persisted = generate_design_system(
+            "saas dashboard",
+            Some("Acme"),
+            Some("markdown"),
+            true,
+            None,
+            Some(temp_dir.path()),
+        )
+        .unwrap();
+        assert!(persisted.formatted.starts_with(&persisted.document));
+        assert!(temp_dir.path().join("design-system/acme/MASTER.md").is_file());
+    }
+
+    #[test]
+    fn design_system_generation_is_deterministic() {
+        let first_dir = tempfile::TempDir::new().unwrap();
+        let second_dir = tempfile::TempDir::new().unwrap();
+
+        let run = |dir: &Path| {
+            generate_design_system("fintech dashboard", Some("Ledger"), Some("markdown"), true, Some("dashboard"), Some(dir))
+                .unwrap()
+        };
+        let first = run(first_dir.path());
+        let second = run(second_dir.path());
+
+        assert_eq!(first.document, second.document);
+        assert_eq!(format_ascii_box(&first.design_system), format_ascii_box(&second.design_system));
+        for file in ["design-system/ledger/MASTER.md", "design-system/ledger/pages/dashboard.md"] {
+            let a = std::fs::read(first_dir.path().join(file)).unwrap();
+            let b = std::fs::read(second_dir.path().join(file)).unwrap();
+            assert_eq!(a, b, "{} 两次生成内容不一致", file);
+        }
+    }
+
+    #[test]
+    fn user_corpus_entry_is_searchable_and_bad_rows_skipped() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("color.csv"),
+            "Product Type,Keywords,Primary (Hex),Notes\n\
+             Zorblax Console,zorblax neon,#39FF14,团队自定义配色\n\
+             坏行,只有两列\n",
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join("color.json"),
+            r##"[{"Product Type": "Quixel Studio", "Keywords": "quixel", "Primary (Hex)": "#123456"}, "not-an-object"]"##,
+        )
+        .unwrap();
+
+        let store = UiuxStore::with_corpus_dir(Some(temp_dir.path().to_path_buf()));
+        let index = store.domain("color").expect("color 索引应能构建");
+
+        let results = index.search("zorblax", 3);
+        assert_eq!(results[0].get("Product Type").map(String::as_str), Some("Zorblax Console"));
+        assert_eq!(results[0].get("Primary (Hex)").map(String::as_str), Some("#39FF14"));
+        let results = index.search("quixel", 3);
+        assert_eq!(results[0].get("Product Type").map(String::as_str), Some("Quixel Studio"));
+        assert!(index.search("只有两列", 3).iter().all(|row| row.get("Product Type").map(String::as_str) != Some("坏行")));
+
+        // 未配置用户语料时不包含自定义条目
+        let baseline = UiuxStore::with_corpus_dir(None);
+        assert!(baseline
+            .domain("color")
+            .unwrap()
+            .search("zorblax", 3)
+            .iter()
+            .all(|row| row.get("Product Type").map(String::as_str) != Some("Zorblax Console")));
+    }
+
     #[test]
     fn tokenize_for_suggest_keeps_uiux_and_mixed_tokens() {
         let t1 = tokenize_for_suggest("UI/UX");
@@ -11,12 +11,17 @@ use serde::Serialize;
 use crate::config::load_standalone_config;
 use crate::mcp::tools::acemcp::types::AcemcpRequest;
 use crate::mcp::tools::AcemcpTool;
+use crate::mcp::utils::{normalize_optional_project_root, to_mcp_error};
 use crate::{log_debug, log_important};
 
+use super::engine;
 use super::localize;
 use super::markdown_search;
 use super::response::{UiuxError, UiuxResponse};
-use super::types::{UiuxAction, UiuxLang, UiuxOutputFormat, UiuxRequest};
+use super::types::{
+    UiuxAction, UiuxDesignSystemRequest, UiuxLang, UiuxOutputFormat, UiuxRequest, UiuxSearchRequest,
+    UiuxStackRequest,
+};
 
 const DEFAULT_MAX_RESULTS: u32 = 3;
 const UIUX_MARKDOWN_FILENAME: &str = "ui-ux-pro-max-skill.md";
@@ -116,23 +121,29 @@ impl UiuxTool {
         });
 
         if let serde_json::Value::Object(schema_map) = schema {
-            vec![Tool {
+            let output_schema = match uiux_output_schema() {
+                serde_json::Value::Object(map) => Some(Arc::new(map)),
+                _ => None,
+            };
+            let mut tools = vec![Tool {
                 name: Cow::Borrowed("uiux"),
                 description: Some(Cow::Borrowed("单一 UI/UX 工具：优先通过 sou 检索项目页面与 UI/UX 资料，并在 sou 不可用时回退到本地 markdown 检索，统一生成可直接喂给 AI 的 UI 提示词。")),
                 input_schema: Arc::new(schema_map),
                 annotations: None,
                 icons: None,
                 meta: None,
-                output_schema: None,
+                output_schema,
                 title: Some("UI/UX".to_string()),
-            }]
+            }];
+            tools.extend(engine_tool_definitions());
+            tools
         } else {
             Vec::new()
         }
     }
 
     pub async fn call_tool(tool_name: &str, arguments: serde_json::Value) -> Result<CallToolResult, McpError> {
-        if tool_name != "uiux" {
+        if !UIUX_TOOL_NAMES.contains(&tool_name) {
             return Err(McpError::invalid_params(format!("未知的工具: {}", tool_name), None));
         }
 
@@ -140,13 +151,225 @@ impl UiuxTool {
         log_debug!("[uiux] 参数: {:?}", arguments);
 
         let defaults = UiuxDefaults::load();
-        let req: UiuxRequest = serde_json::from_value(arguments)
-            .map_err(|e| McpError::invalid_params(format!("参数解析失败: {}", e), None))?;
-
-        handle_request(req, defaults).await
+        match tool_name {
+            "uiux_search" => handle_search(parse_arguments(arguments)?, defaults),
+            "uiux_stack" => handle_stack(parse_arguments(arguments)?, defaults),
+            "uiux_design_system" => handle_design_system(parse_arguments(arguments)?, defaults),
+            _ => handle_request(parse_arguments(arguments)?, defaults).await,
+        }
     }
 }
 
+/// uiux 模块提供的全部工具名（均受 `uiux` 开关控制）
+pub const UIUX_TOOL_NAMES: &[&str] = &["uiux", "uiux_search", "uiux_stack", "uiux_design_system"];
+
+fn parse_arguments<T: serde::de::DeserializeOwned>(arguments: serde_json::Value) -> Result<T, McpError> {
+    serde_json::from_value(arguments)
+        .map_err(|e| McpError::invalid_params(format!("参数解析失败: {}", e), None))
+}
+
+/// 基于内嵌语料 BM25 引擎的细粒度工具定义
+fn engine_tool_definitions() -> Vec<Tool> {
+    let lang = serde_json::json!({ "type": "string", "enum": ["zh", "en"], "description": "输出语言（zh/en）" });
+    let definitions = [
+        (
+            "uiux_search",
+            "UI/UX 语料检索：按领域（style/color/typography/ux 等）检索内嵌的 UI/UX 设计数据，支持多领域合并与分页。",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "检索关键词（中英文均可）" },
+                    "domain": { "type": "string", "description": "领域（可选）：单个领域、逗号分隔列表（如 color,typography）或 all；不传时自动识别" },
+                    "max_results": { "type": "number", "description": "最大返回结果数（可选）" },
+                    "offset": { "type": "number", "description": "分页偏移（可选，作用于排序之后）" },
+                    "lang": lang.clone()
+                },
+                "required": ["query"]
+            }),
+        ),
+        (
+            "uiux_stack",
+            "技术栈 UI 实现规范检索：按技术栈（react、vue、nextjs 等，支持常见别名）检索组件与交互实现建议。",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "检索关键词（如 button、form validation）" },
+                    "stack": { "type": "string", "description": "技术栈名称，未知名称会返回支持列表" },
+                    "max_results": { "type": "number", "description": "最大返回结果数（可选）" },
+                    "lang": lang.clone()
+                },
+                "required": ["query", "stack"]
+            }),
+        ),
+        (
+            "uiux_design_system",
+            "设计系统生成：根据产品描述生成配色、字体、风格与页面规范文档，可选写入项目 design-system 目录。",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "产品/页面描述" },
+                    "project_name": { "type": "string", "description": "项目名称（可选）" },
+                    "format": { "type": "string", "enum": ["ascii", "markdown"], "description": "文档格式，默认 ascii" },
+                    "persist": { "type": "boolean", "description": "是否写入 design-system 目录，默认 false；无论是否写入都会返回完整文档" },
+                    "page": { "type": "string", "description": "页面名称（可选，persist 时额外生成页面覆盖文件）" },
+                    "project_root_path": { "type": "string", "description": "写入目录所在的项目根路径（persist 时建议提供，默认当前目录）" },
+                    "lang": lang
+                },
+                "required": ["query"]
+            }),
+        ),
+    ];
+
+    definitions
+        .into_iter()
+        .filter_map(|(name, description, schema)| match schema {
+            serde_json::Value::Object(schema_map) => Some(Tool {
+                name: Cow::Borrowed(name),
+                description: Some(Cow::Borrowed(description)),
+                input_schema: Arc::new(schema_map),
+                annotations: None,
+                icons: None,
+                meta: None,
+                output_schema: None,
+                title: None,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn search_errors(result: &engine::SearchResult, code: &str) -> Vec<UiuxError> {
+    result
+        .error
+        .as_deref()
+        .map(|message| vec![UiuxError::new(code, message)])
+        .unwrap_or_default()
+}
+
+fn handle_search(req: UiuxSearchRequest, defaults: UiuxDefaults) -> Result<CallToolResult, McpError> {
+    let lang = resolve_lang(req.lang, defaults);
+    let max_results = engine::cap_max_results(req.max_results, defaults.max_results_cap, DEFAULT_MAX_RESULTS);
+    let result = engine::search_domains(&req.query, req.domain.as_deref(), Some(max_results), req.offset);
+    let errors = search_errors(&result, "uiux_search_failed");
+    let text = engine::format_search_output(&result);
+    build_response("uiux_search", lang, result, text, errors)
+}
+
+fn handle_stack(req: UiuxStackRequest, defaults: UiuxDefaults) -> Result<CallToolResult, McpError> {
+    let lang = resolve_lang(req.lang, defaults);
+    let max_results = engine::cap_max_results(req.max_results, defaults.max_results_cap, DEFAULT_MAX_RESULTS);
+    let result = engine::search_stack(&req.query, &req.stack, Some(max_results));
+    // 中文注释：未知技术栈返回带候选列表的 uiux_unknown_stack 错误，其余失败统一归为数据不可用
+    let errors = match engine::resolve_stack(&req.stack) {
+        Err(err) => vec![err],
+        Ok(_) => search_errors(&result, "uiux_stack_unavailable"),
+    };
+    let text = engine::format_search_output(&result);
+    build_response("uiux_stack", lang, result, text, errors)
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct UiuxDesignSystemData {
+    project_name: String,
+    document: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    persisted: Option<engine::PersistSummary>,
+}
+
+fn handle_design_system(req: UiuxDesignSystemRequest, defaults: UiuxDefaults) -> Result<CallToolResult, McpError> {
+    let lang = resolve_lang(req.lang, defaults);
+    let output_dir = normalize_optional_project_root(req.project_root_path.as_deref())
+        .map_err(|e| McpError::invalid_params(format!("项目路径无效: {}", e), None))?;
+    let output = engine::generate_design_system(
+        &req.query,
+        req.project_name.as_deref(),
+        req.format.as_deref(),
+        req.persist.unwrap_or(false),
+        req.page.as_deref(),
+        output_dir.as_deref().map(Path::new),
+    )
+    .map_err(|e| McpError::internal_error(format!("设计系统生成失败: {}", e), None))?;
+
+    let data = UiuxDesignSystemData {
+        project_name: output.design_system.project_name.clone(),
+        document: output.document,
+        persisted: output.persisted,
+    };
+    build_response("uiux_design_system", lang, data, output.formatted, Vec::new())
+}
+
+/// uiux 返回结构（`UiuxResponse<UiuxData>`）对应的 JSON Schema，供严格客户端校验
+fn uiux_output_schema() -> serde_json::Value {
+    let snippet = serde_json::json!({
+        "type": "object",
+        "properties": {
+            "source": { "type": "string" },
+            "location": { "type": "string" },
+            "excerpt": { "type": "string" }
+        },
+        "required": ["source", "excerpt"]
+    });
+
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "meta": {
+                "type": "object",
+                "properties": {
+                    "tool": { "type": "string" },
+                    "lang": { "type": "string", "enum": ["zh", "en"] },
+                    "request_id": { "type": ["string", "null"] },
+                    "version": { "type": "string" }
+                },
+                "required": ["tool", "lang", "request_id", "version"]
+            },
+            "data": {
+                "type": "object",
+                "properties": {
+                    "action": { "type": "string", "enum": ["beautify", "describe", "audit", "design_system"] },
+                    "query": { "type": "string" },
+                    "prompt": { "type": "string" },
+                    "uiux_hits": { "type": "array", "items": snippet.clone() },
+                    "project_context": { "type": "array", "items": snippet },
+                    "retrieval": {
+                        "type": "object",
+                        "properties": {
+                            "knowledge_source": { "type": "string" },
+                            "project_context_source": { "type": "string" },
+                            "project_context_enabled": { "type": "boolean" },
+                            "degraded": { "type": "boolean" },
+                            "queries": {
+                                "type": "object",
+                                "properties": {
+                                    "knowledge_query": { "type": "string" },
+                                    "project_context_query": { "type": "string" }
+                                },
+                                "required": ["knowledge_query"]
+                            },
+                            "messages": { "type": "array", "items": { "type": "string" } }
+                        },
+                        "required": ["knowledge_source", "project_context_source", "project_context_enabled", "degraded", "queries", "messages"]
+                    }
+                },
+                "required": ["action", "query", "prompt", "uiux_hits", "project_context", "retrieval"]
+            },
+            "text": { "type": "string" },
+            "errors": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "code": { "type": "string" },
+                        "message": { "type": "string" }
+                    },
+                    "required": ["code", "message"]
+                }
+            }
+        },
+        "required": ["meta", "data", "text", "errors"]
+    })
+}
+
 async fn handle_request(req: UiuxRequest, defaults: UiuxDefaults) -> Result<CallToolResult, McpError> {
     let lang = resolve_lang(req.lang, defaults);
     let _output_format = resolve_output_format(req.output_format, defaults);
@@ -277,9 +500,14 @@ fn build_response<T: Serialize>(
     errors: Vec<UiuxError>,
 ) -> Result<CallToolResult, McpError> {
     let response = UiuxResponse::new(tool, lang, data, text, errors);
-    let output = serde_json::to_string_pretty(&response)
-        .map_err(|e| McpError::internal_error(format!("JSON 序列化失败: {}", e), None))?;
-    Ok(CallToolResult::success(vec![Content::text(output)]))
+    let structured = serde_json::to_value(&response)
+        .map_err(|e| to_mcp_error("JSON 序列化失败", e.into()))?;
+    let output = serde_json::to_string_pretty(&structured)
+        .map_err(|e| to_mcp_error("JSON 序列化失败", e.into()))?;
+    // 中文注释：声明了 output_schema 的工具需同时返回 structured_content
+    let mut result = CallToolResult::success(vec![Content::text(output)]);
+    result.structured_content = Some(structured);
+    Ok(result)
 }
 
 #[derive(Debug)]
@@ -755,3 +983,33 @@ fn is_project_context_candidate(location: &str) -> bool {
         || normalized.ends_with(".html")
         || normalized.ends_with(".rs")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uiux_tool_declares_output_schema() {
+        let tools = UiuxTool::get_tool_definitions();
+        let uiux = tools.iter().find(|t| t.name == "uiux").unwrap();
+        let schema = uiux.output_schema.as_ref().expect("uiux 应声明 output_schema");
+
+        let required: Vec<&str> = schema["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|v| v.as_str())
+            .collect();
+        assert_eq!(required, vec!["meta", "data", "text", "errors"]);
+        assert!(schema["properties"]["data"]["properties"].get("retrieval").is_some());
+    }
+
+    #[test]
+    fn test_engine_tools_are_listed_with_uiux() {
+        let names: Vec<String> = UiuxTool::get_tool_definitions()
+            .iter()
+            .map(|t| t.name.to_string())
+            .collect();
+        assert_eq!(names, UIUX_TOOL_NAMES);
+    }
+}
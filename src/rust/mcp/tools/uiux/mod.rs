@@ -1,10 +1,15 @@
 // UI/UX MCP 工具模块
-// 当前主链路已收敛为单一 uiux 工具：sou-first + 本地 markdown 降级。
+// 主链路为 uiux 工具：sou-first + 本地 markdown 降级；
+// uiux_search / uiux_stack / uiux_design_system 基于内嵌语料的 BM25 引擎（engine）。
 
+pub mod commands;
+pub mod engine;
+pub mod lexicon;
 pub mod localize;
 pub mod markdown_search;
 pub mod mcp;
 pub mod response;
+pub mod sanitize;
 pub mod types;
 
 pub use mcp::UiuxTool;
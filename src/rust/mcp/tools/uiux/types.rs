@@ -65,3 +65,51 @@ pub struct UiuxRequest {
     #[serde(default)]
     pub lang: Option<UiuxLang>,
 }
+
+/// `uiux_search` 请求：按领域检索内嵌 UI/UX 语料
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UiuxSearchRequest {
+    pub query: String,
+    /// 领域（单个、逗号分隔列表或 `all`；不传时按查询自动识别）
+    #[serde(default)]
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub max_results: Option<u32>,
+    #[serde(default)]
+    pub offset: Option<u32>,
+    #[serde(default)]
+    pub lang: Option<UiuxLang>,
+}
+
+/// `uiux_stack` 请求：检索指定技术栈的实现规范
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UiuxStackRequest {
+    pub query: String,
+    /// 技术栈名称（支持 `reactjs`、`vuejs` 等别名）
+    pub stack: String,
+    #[serde(default)]
+    pub max_results: Option<u32>,
+    #[serde(default)]
+    pub lang: Option<UiuxLang>,
+}
+
+/// `uiux_design_system` 请求：生成设计系统文档，可选落盘
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UiuxDesignSystemRequest {
+    pub query: String,
+    #[serde(default)]
+    pub project_name: Option<String>,
+    /// 文档格式：`ascii`（默认）或 `markdown`
+    #[serde(default)]
+    pub format: Option<String>,
+    /// 是否写入 `<project_root_path>/design-system/<project>/`
+    #[serde(default)]
+    pub persist: Option<bool>,
+    /// 页面名称（persist 时额外生成页面覆盖文件）
+    #[serde(default)]
+    pub page: Option<String>,
+    #[serde(default)]
+    pub project_root_path: Option<String>,
+    #[serde(default)]
+    pub lang: Option<UiuxLang>,
+}
@@ -23,6 +23,15 @@ pub struct ZhiRequest {
     #[schemars(description = "UI/UX 上下文追加原因（可选）")]
     #[serde(default)]
     pub uiux_reason: Option<String>,
+    #[schemars(description = "输入框预填的默认文本（可选，用户可编辑后提交）")]
+    #[serde(default)]
+    pub default_input: Option<String>,
+    #[schemars(description = "等待用户回复的超时秒数（可选，适用于无人值守场景）")]
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    #[schemars(description = "超时后自动提交的默认回复（可选，未提供时超时视为取消）")]
+    #[serde(default)]
+    pub default_on_timeout: Option<String>,
 }
 
 fn default_is_markdown() -> bool {
@@ -39,28 +48,37 @@ pub struct MemoryConfigRequest {
     pub dedup_on_startup: Option<bool>,
     #[schemars(description = "启用去重检测")]
     pub enable_dedup: Option<bool>,
+    #[schemars(description = "回忆模糊匹配阈值 (0.3~0.95)，低于此值的模糊结果被忽略")]
+    #[serde(default)]
+    pub recall_fuzzy_threshold: Option<f64>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct JiyiRequest {
-    #[schemars(description = "操作类型：记忆(添加) | 回忆(查询) | 整理(去重) | 列表(全部记忆) | 预览相似(检测相似度) | 配置(获取/更新) | 删除(移除记忆)")]
+    #[schemars(description = "操作类型：记忆(添加) | 回忆(查询) | 整理(去重) | 列表(全部记忆) | 预览相似(检测相似度) | 配置(获取/更新) | 删除(移除记忆) | 置顶(保护记忆不被去重) | 取消置顶 | 重新分类(修改记忆分类) | 批量删除(一次移除多条记忆)")]
     pub action: String,
     #[schemars(description = "项目路径（必需）")]
     pub project_path: String,
-    #[schemars(description = "记忆内容（记忆/预览相似操作时必需）")]
+    #[schemars(description = "记忆内容（记忆/预览相似操作时必需；回忆操作时作为可选查询）")]
     #[serde(default)]
     pub content: String,
     #[schemars(
-        description = "记忆分类：rule(规范规则), preference(用户偏好), pattern(最佳实践), context(项目上下文)"
+        description = "记忆分类：rule(规范规则), preference(用户偏好), pattern(最佳实践), context(项目上下文)；重新分类时为目标分类"
     )]
     #[serde(default = "default_category")]
     pub category: String,
     #[schemars(description = "配置参数（配置操作时使用）")]
     #[serde(default)]
     pub config: Option<MemoryConfigRequest>,
-    #[schemars(description = "记忆ID（删除操作时必需）")]
+    #[schemars(description = "记忆ID（删除/置顶/取消置顶/重新分类操作时必需）")]
     #[serde(default)]
     pub memory_id: Option<String>,
+    #[schemars(description = "记忆ID列表（批量删除操作时必需）")]
+    #[serde(default)]
+    pub memory_ids: Vec<String>,
+    #[schemars(description = "回忆结果排序：default(创建顺序) | recent(最近访问优先) | frequent(常用优先)")]
+    #[serde(default)]
+    pub order: Option<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -142,6 +160,15 @@ pub struct PopupRequest {
     pub uiux_intent: Option<String>,
     pub uiux_context_policy: Option<String>,
     pub uiux_reason: Option<String>,
+    /// 输入框预填的默认文本（未提供时不序列化）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_input: Option<String>,
+    /// 等待用户回复的超时秒数（到期后 GUI 自动提交默认回复或取消）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// 超时后自动提交的默认回复
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_on_timeout: Option<String>,
 }
 
 /// 新的结构化响应数据格式
@@ -3,7 +3,10 @@
 /// 包含 MCP 相关的通用工具函数和辅助方法
 
 use anyhow::Result;
+use once_cell::sync::Lazy;
+use std::collections::{BTreeSet, HashMap};
 use std::path::Path;
+use std::sync::Mutex;
 use percent_encoding;
 use regex::Regex;
 
@@ -79,6 +82,81 @@ fn is_windows_absolute_path(path: &str) -> bool {
     re.is_match(path)
 }
 
+/// 规范化结果 -> 规范化前的原始路径（如符号链接路径）
+///
+/// 规范化前历史文件以原始路径计算哈希，历史管理器据此额外读取旧键，避免已有历史失联
+static PROJECT_ROOT_ALIASES: Lazy<Mutex<HashMap<String, BTreeSet<String>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 获取规范化路径在本进程中对应过的原始路径（不含规范化路径本身）
+pub fn project_root_aliases(normalized: &str) -> Vec<String> {
+    PROJECT_ROOT_ALIASES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(normalized)
+        .map(|aliases| aliases.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+fn register_project_root_aliases(normalized: &str, originals: &[&str]) {
+    let mut aliases = PROJECT_ROOT_ALIASES.lock().unwrap_or_else(|e| e.into_inner());
+    for original in originals.iter().filter(|o| !o.is_empty() && **o != normalized) {
+        aliases
+            .entry(normalized.to_string())
+            .or_default()
+            .insert(original.to_string());
+    }
+}
+
+/// 规范化并校验项目根路径
+///
+/// 各入口（zhi、enhance、记忆命令）在哈希或定位项目前统一调用：
+/// 去除首尾空白与引号、拒绝空路径、解码 URL 编码；路径存在时 canonicalize，
+/// 不存在时相对路径基于当前目录补全并去除末尾分隔符，保证同一项目得到相同结果
+pub fn normalize_and_validate_project_root(path: &str) -> Result<String> {
+    let trimmed = path
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'')
+        .trim();
+    if trimmed.is_empty() {
+        anyhow::bail!("项目路径不能为空");
+    }
+
+    let decoded = decode_and_normalize_path(trimmed)?;
+    let path_obj = Path::new(&decoded);
+
+    let resolved = match path_obj.canonicalize() {
+        Ok(canonical) => canonical,
+        Err(_) if path_obj.is_absolute() || is_windows_absolute_path(&decoded) => path_obj.to_path_buf(),
+        Err(_) => std::env::current_dir()?.join(path_obj),
+    };
+
+    let mut normalized = resolved.to_string_lossy().to_string();
+    // canonicalize 在 Windows 下返回 \\?\ 扩展前缀，去除以保持与用户输入一致
+    if let Some(stripped) = normalized.strip_prefix(r"\\?\") {
+        normalized = stripped.to_string();
+    }
+    // 去除末尾分隔符（保留根目录本身）
+    while normalized.len() > 1 && (normalized.ends_with('/') || normalized.ends_with('\\')) {
+        if normalized.ends_with(":\\") || normalized.ends_with(":/") {
+            break;
+        }
+        normalized.pop();
+    }
+
+    // 中文注释：记录规范化前的写法，兼容旧历史文件的哈希键
+    register_project_root_aliases(&normalized, &[path.trim(), trimmed, &decoded]);
+    Ok(normalized)
+}
+
+/// 规范化可选的项目根路径（未提供或为空白时返回 None）
+pub fn normalize_optional_project_root(path: Option<&str>) -> Result<Option<String>> {
+    match path {
+        Some(p) if !p.trim().is_empty() => normalize_and_validate_project_root(p).map(Some),
+        _ => Ok(None),
+    }
+}
+
 /// 验证项目路径是否存在
 pub fn validate_project_path(path: &str) -> Result<()> {
     // 先对路径进行解码和规范化
@@ -119,9 +197,10 @@ fn validate_path_format(path: &str) -> Result<()> {
     Ok(())
 }
 
-/// 生成唯一的请求 ID
+/// 生成唯一的请求 ID（格式由 `request_id_format` 配置决定，默认 UUID）
 pub fn generate_request_id() -> String {
-    uuid::Uuid::new_v4().to_string()
+    use super::request_id::{configured_request_id_format, next_request_id};
+    next_request_id(configured_request_id_format())
 }
 
 // ============================================================================
@@ -171,6 +250,46 @@ pub fn safe_truncate_clean(text: &str, max_chars: usize) -> String {
     format!("{}...", truncated)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
 
+    #[test]
+    fn test_project_root_quoted_and_trailing_slash_normalize_consistently() {
+        let temp_dir = TempDir::new().unwrap();
+        let raw = temp_dir.path().to_string_lossy().to_string();
+        let expected = normalize_and_validate_project_root(&raw).unwrap();
 
+        for input in [
+            format!("\"{}\"", raw),
+            format!("  '{}'  ", raw),
+            format!("{}\"", raw),
+            format!("{}/", raw),
+            format!("{}{}", raw, std::path::MAIN_SEPARATOR),
+        ] {
+            assert_eq!(normalize_and_validate_project_root(&input).unwrap(), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_project_root_relative_resolves_against_cwd() {
+        let cwd = std::env::current_dir().unwrap();
+        let expected = normalize_and_validate_project_root(&cwd.to_string_lossy()).unwrap();
 
+        assert_eq!(normalize_and_validate_project_root(".").unwrap(), expected);
+        assert_eq!(normalize_and_validate_project_root("./").unwrap(), expected);
+
+        let missing = normalize_and_validate_project_root("no-such-dir/").unwrap();
+        assert!(Path::new(&missing).is_absolute());
+        assert!(missing.ends_with("no-such-dir"));
+    }
+
+    #[test]
+    fn test_project_root_rejects_empty() {
+        assert!(normalize_and_validate_project_root("").is_err());
+        assert!(normalize_and_validate_project_root("  \"\"  ").is_err());
+        assert_eq!(normalize_optional_project_root(Some("   ")).unwrap(), None);
+        assert_eq!(normalize_optional_project_root(None).unwrap(), None);
+    }
+}
@@ -18,6 +18,9 @@ pub enum McpToolError {
     
     #[error("记忆管理错误: {0}")]
     Memory(String),
+
+    #[error("{service} 请求超时（{secs} 秒内未响应），请稍后重试")]
+    Timeout { service: String, secs: u64 },
     
     #[error("IO 错误: {0}")]
     Io(#[from] std::io::Error),
@@ -40,6 +43,9 @@ impl From<McpToolError> for McpError {
             McpToolError::Memory(msg) => {
                 McpError::internal_error(msg, None)
             }
+            e @ McpToolError::Timeout { .. } => {
+                McpError::internal_error(e.to_string(), None)
+            }
             McpToolError::Io(e) => {
                 McpError::internal_error(format!("IO 错误: {}", e), None)
             }
@@ -53,6 +59,88 @@ impl From<McpToolError> for McpError {
     }
 }
 
+/// 业务模块错误类型（增强 / 历史 / 记忆 / 图标）
+///
+/// 模块内部仍返回 `anyhow::Result`，关键失败路径以此类型作为根因，
+/// 调用方可通过 `downcast_ref` 区分错误种类，MCP 层据此映射错误码
+#[derive(Debug, thiserror::Error)]
+pub enum SanshuError {
+    #[error("网络请求失败: {0}")]
+    Network(String),
+
+    #[error("上游服务返回错误状态码 {status}: {message}")]
+    Upstream { status: u16, message: String },
+
+    #[error("解析失败: {0}")]
+    Parse(String),
+
+    #[error("未找到: {0}")]
+    NotFound(String),
+
+    #[error("IO 错误: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl SanshuError {
+    /// 错误种类标识（写入 MCP 错误的 data.kind，供客户端分支处理）
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SanshuError::Network(_) => "network",
+            SanshuError::Upstream { .. } => "upstream",
+            SanshuError::Parse(_) => "parse",
+            SanshuError::NotFound(_) => "not_found",
+            SanshuError::Io(_) => "io",
+        }
+    }
+}
+
+impl From<serde_json::Error> for SanshuError {
+    fn from(error: serde_json::Error) -> Self {
+        SanshuError::Parse(error.to_string())
+    }
+}
+
+impl From<reqwest::Error> for SanshuError {
+    fn from(error: reqwest::Error) -> Self {
+        match error.status() {
+            Some(status) => SanshuError::Upstream {
+                status: status.as_u16(),
+                message: error.to_string(),
+            },
+            None if error.is_decode() => SanshuError::Parse(error.to_string()),
+            None => SanshuError::Network(error.to_string()),
+        }
+    }
+}
+
+impl From<SanshuError> for McpError {
+    fn from(error: SanshuError) -> Self {
+        sanshu_error_to_mcp(error.to_string(), &error)
+    }
+}
+
+fn sanshu_error_to_mcp(message: String, error: &SanshuError) -> McpError {
+    let mut data = serde_json::json!({ "kind": error.kind() });
+    if let SanshuError::Upstream { status, .. } = error {
+        data["status"] = serde_json::json!(status);
+    }
+    match error {
+        SanshuError::NotFound(_) => McpError::resource_not_found(message, Some(data)),
+        _ => McpError::internal_error(message, Some(data)),
+    }
+}
+
+/// 将模块返回的 anyhow 错误转换为 MCP 错误（`context` 作为消息前缀）
+///
+/// 根因为 `SanshuError` 时按种类映射错误码并附带 data.kind，其余错误视为内部错误
+pub fn to_mcp_error(context: &str, error: anyhow::Error) -> McpError {
+    let message = format!("{}: {}", context, error);
+    match error.downcast_ref::<SanshuError>() {
+        Some(sanshu) => sanshu_error_to_mcp(message, sanshu),
+        None => McpError::internal_error(message, None),
+    }
+}
+
 /// 创建项目路径错误
 pub fn project_path_error(msg: impl Into<String>) -> McpToolError {
     McpToolError::ProjectPath(msg.into())
@@ -72,3 +160,36 @@ pub fn response_error(msg: impl Into<String>) -> McpToolError {
 pub fn memory_error(msg: impl Into<String>) -> McpToolError {
     McpToolError::Memory(msg.into())
 }
+
+/// 创建上游请求超时错误
+pub fn timeout_error(service: impl Into<String>, secs: u64) -> McpToolError {
+    McpToolError::Timeout { service: service.into(), secs }
+}
+
+/// 判断错误是否为上游请求超时
+pub fn is_timeout_error(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<McpToolError>(), Some(McpToolError::Timeout { .. }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::ErrorCode;
+
+    #[test]
+    fn test_sanshu_error_maps_to_mcp_error_code() {
+        let err = to_mcp_error("删除记忆失败", anyhow::Error::from(SanshuError::NotFound("m-1".to_string())));
+        assert_eq!(err.code, ErrorCode::RESOURCE_NOT_FOUND);
+        assert!(err.message.starts_with("删除记忆失败: "));
+        assert_eq!(err.data.unwrap()["kind"], "not_found");
+
+        let err = McpError::from(SanshuError::Upstream { status: 502, message: "bad gateway".to_string() });
+        assert_eq!(err.code, ErrorCode::INTERNAL_ERROR);
+        assert_eq!(err.data.unwrap()["status"], 502);
+
+        // 非 SanshuError 根因保持内部错误
+        let err = to_mcp_error("回忆失败", anyhow::anyhow!("boom"));
+        assert_eq!(err.code, ErrorCode::INTERNAL_ERROR);
+        assert!(err.data.is_none());
+    }
+}
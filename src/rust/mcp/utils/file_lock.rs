@@ -0,0 +1,100 @@
+// 跨进程文件锁
+// MCP 服务器与 GUI 可能同时读写同一个 JSON 存储（记忆、对话历史、zhi 历史），
+// 使用 fs2 咨询锁保护「加载-修改-保存」临界区；锁被占用时短暂重试，超时后报错。
+
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// 获取锁的最大尝试次数
+pub const LOCK_RETRY_ATTEMPTS: u32 = 40;
+/// 两次尝试之间的等待间隔（总计约 2 秒）
+pub const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 独占文件锁守卫，离开作用域时自动释放
+#[derive(Debug)]
+pub struct FileLockGuard {
+    file: File,
+    path: PathBuf,
+}
+
+impl FileLockGuard {
+    /// 锁文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for FileLockGuard {
+    fn drop(&mut self) {
+        // 释放失败时文件句柄关闭也会释放锁，这里忽略错误
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+/// 获取独占锁（默认重试策略）
+pub fn lock_exclusive(lock_path: &Path) -> Result<FileLockGuard> {
+    lock_exclusive_with_retry(lock_path, LOCK_RETRY_ATTEMPTS, LOCK_RETRY_INTERVAL)
+}
+
+/// 获取独占锁：锁被占用时按间隔重试，超过次数后返回错误
+pub fn lock_exclusive_with_retry(lock_path: &Path, attempts: u32, interval: Duration) -> Result<FileLockGuard> {
+    if let Some(parent) = lock_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("创建锁文件目录失败: {}", parent.display()))?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .truncate(false)
+        .open(lock_path)
+        .with_context(|| format!("打开锁文件失败: {}", lock_path.display()))?;
+
+    let attempts = attempts.max(1);
+    for attempt in 1..=attempts {
+        match file.try_lock_exclusive() {
+            Ok(()) => {
+                return Ok(FileLockGuard { file, path: lock_path.to_path_buf() });
+            }
+            Err(e) if attempt < attempts && is_contended(&e) => std::thread::sleep(interval),
+            Err(e) if is_contended(&e) => break,
+            Err(e) => {
+                return Err(anyhow::anyhow!("获取文件锁失败: {} - {}", lock_path.display(), e));
+            }
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "文件正被其他进程占用，重试 {} 次后仍无法获取锁: {}",
+        attempts,
+        lock_path.display()
+    ))
+}
+
+/// 是否为「锁被占用」类错误（其余错误无需重试）
+fn is_contended(err: &std::io::Error) -> bool {
+    err.kind() == std::io::ErrorKind::WouldBlock
+        || err.raw_os_error() == fs2::lock_contended_error().raw_os_error()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_contended_lock_times_out_then_succeeds_after_release() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock_path = temp_dir.path().join("store.lock");
+
+        let guard = lock_exclusive(&lock_path).unwrap();
+        let err = lock_exclusive_with_retry(&lock_path, 2, Duration::from_millis(10)).unwrap_err();
+        assert!(err.to_string().contains("无法获取锁"));
+
+        drop(guard);
+        assert!(lock_exclusive_with_retry(&lock_path, 2, Duration::from_millis(10)).is_ok());
+    }
+}
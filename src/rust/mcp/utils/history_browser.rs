@@ -0,0 +1,247 @@
+// 跨项目历史浏览
+// 扫描 `<数据目录>/chat_history` 与 `<数据目录>/zhi_history` 下的历史文件，
+// 汇总每个文件对应的项目路径、条目数与最后更新时间，供 GUI 展示项目选择列表
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{log_debug, log_important};
+use super::history_store::{FsHistoryStore, HistoryStore};
+
+/// 清空全部历史时必须传入的确认口令（防止误操作批量删除）
+pub const CLEAR_ALL_CONFIRM_TOKEN: &str = "CONFIRM";
+
+/// 单个历史文件的汇总信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryProjectSummary {
+    /// 历史类型："chat" | "zhi"
+    pub kind: String,
+    /// 历史文件记录的项目路径
+    pub project_path: String,
+    /// 历史条目数
+    pub entry_count: usize,
+    /// 最后更新时间
+    pub last_updated: Option<DateTime<Utc>>,
+    /// 项目路径已不存在（孤立历史文件）
+    pub orphaned: bool,
+    /// 历史文件路径
+    pub file: String,
+}
+
+/// 只解析汇总所需的字段，兼容对话历史与 zhi 历史两种文件结构
+#[derive(Debug, Deserialize)]
+struct HistoryFileHeader {
+    #[serde(default)]
+    project_path: String,
+    #[serde(default)]
+    entries: Vec<serde::de::IgnoredAny>,
+    #[serde(default)]
+    last_updated: Option<DateTime<Utc>>,
+}
+
+/// 清空全部历史的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClearAllHistoryResult {
+    /// 删除的对话历史文件数
+    pub chat_files: usize,
+    /// 删除的 zhi 历史文件数
+    pub zhi_files: usize,
+}
+
+fn read_header(path: &Path) -> Result<HistoryFileHeader> {
+    let content = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// 扫描单个历史目录；无法读取或解析的文件跳过（仅 debug 日志）
+pub fn scan_history_dir(dir: &Path, kind: &str) -> Vec<HistoryProjectSummary> {
+    let read_dir = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(e) => {
+            log_debug!("历史目录不可读，跳过: {} ({})", dir.display(), e);
+            return Vec::new();
+        }
+    };
+
+    let mut out = Vec::new();
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        // 只处理 `<hash>.json`，忽略锁文件与隔离的损坏文件
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let header = match read_header(&path) {
+            Ok(header) => header,
+            Err(e) => {
+                log_debug!("解析历史文件失败，跳过: {} ({})", path.display(), e);
+                continue;
+            }
+        };
+
+        let orphaned = header.project_path.trim().is_empty() || !Path::new(&header.project_path).exists();
+        out.push(HistoryProjectSummary {
+            kind: kind.to_string(),
+            project_path: header.project_path,
+            entry_count: header.entries.len(),
+            last_updated: header.last_updated,
+            orphaned,
+            file: path.to_string_lossy().to_string(),
+        });
+    }
+    out
+}
+
+/// 扫描多个历史目录并按最后更新时间倒序返回
+pub fn list_history_projects_in(dirs: &[(&str, PathBuf)]) -> Vec<HistoryProjectSummary> {
+    let mut out: Vec<HistoryProjectSummary> = dirs
+        .iter()
+        .flat_map(|(kind, dir)| scan_history_dir(dir, kind))
+        .collect();
+    out.sort_by(|a, b| b.last_updated.cmp(&a.last_updated).then_with(|| a.project_path.cmp(&b.project_path)));
+    out
+}
+
+/// 列出数据目录下全部项目的对话历史与 zhi 历史
+pub fn list_history_projects() -> Vec<HistoryProjectSummary> {
+    list_history_projects_in(&[
+        ("chat", crate::paths::data_subdir("chat_history")),
+        ("zhi", crate::paths::data_subdir("zhi_history")),
+    ])
+}
+
+/// 删除目录下的全部历史文件（逐个持锁删除，锁文件保留），返回删除数量
+fn clear_history_dir(dir: &Path) -> Result<usize> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let store = FsHistoryStore::new(dir.to_path_buf());
+    let mut cleared = 0;
+    for entry in fs::read_dir(dir).with_context(|| format!("读取历史目录失败: {}", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(key) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        // 中文注释：与单项目写入共用同一把锁，避免删除时被并发写入重新创建半份数据
+        let _lock = store.lock(key)?;
+        fs::remove_file(&path).with_context(|| format!("删除历史文件失败: {}", path.display()))?;
+        cleared += 1;
+    }
+    Ok(cleared)
+}
+
+/// 清空指定目录下的全部对话历史与 zhi 历史；口令不匹配时拒绝执行
+pub fn clear_all_history_in(confirm_token: &str, chat_dir: &Path, zhi_dir: &Path) -> Result<ClearAllHistoryResult> {
+    if confirm_token != CLEAR_ALL_CONFIRM_TOKEN {
+        anyhow::bail!("确认口令不正确，已拒绝清空全部历史（需传入 \"{}\"）", CLEAR_ALL_CONFIRM_TOKEN);
+    }
+    let result = ClearAllHistoryResult {
+        chat_files: clear_history_dir(chat_dir)?,
+        zhi_files: clear_history_dir(zhi_dir)?,
+    };
+    log_important!(
+        info,
+        "已清空全部历史: chat_files={}, zhi_files={}",
+        result.chat_files,
+        result.zhi_files
+    );
+    Ok(result)
+}
+
+/// 清空数据目录下全部项目的对话历史与 zhi 历史
+pub fn clear_all_history(confirm_token: &str) -> Result<ClearAllHistoryResult> {
+    clear_all_history_in(
+        confirm_token,
+        &crate::paths::data_subdir("chat_history"),
+        &crate::paths::data_subdir("zhi_history"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lists_history_files_with_counts_and_orphans() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = TempDir::new().unwrap();
+        let chat_dir = temp_dir.path().join("chat_history");
+        let zhi_dir = temp_dir.path().join("zhi_history");
+        fs::create_dir_all(&chat_dir).unwrap();
+        fs::create_dir_all(&zhi_dir).unwrap();
+
+        let existing = project.path().to_string_lossy().to_string();
+        fs::write(
+            chat_dir.join("aaaa.json"),
+            serde_json::json!({
+                "project_path": existing,
+                "entries": [{"id": "1"}, {"id": "2"}, {"id": "3"}],
+                "last_updated": "2026-01-02T00:00:00Z"
+            })
+            .to_string(),
+        )
+        .unwrap();
+        fs::write(
+            zhi_dir.join("bbbb.json"),
+            serde_json::json!({
+                "project_path": "/nonexistent/sanshu-removed-project",
+                "entries": [{"id": "1"}],
+                "last_updated": "2026-01-01T00:00:00Z"
+            })
+            .to_string(),
+        )
+        .unwrap();
+        // 锁文件不应被当作历史文件
+        fs::write(zhi_dir.join("bbbb.json.lock"), "").unwrap();
+
+        let projects = list_history_projects_in(&[("chat", chat_dir), ("zhi", zhi_dir)]);
+
+        assert_eq!(projects.len(), 2);
+        assert_eq!(projects[0].kind, "chat");
+        assert_eq!(projects[0].project_path, existing);
+        assert_eq!(projects[0].entry_count, 3);
+        assert!(!projects[0].orphaned);
+        assert_eq!(projects[1].kind, "zhi");
+        assert_eq!(projects[1].entry_count, 1);
+        assert!(projects[1].orphaned);
+    }
+
+    fn write_history(dir: &Path, key: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(format!("{}.json", key)), r#"{"project_path":"/tmp/p","entries":[]}"#).unwrap();
+    }
+
+    #[test]
+    fn test_clear_all_history_refuses_without_exact_token() {
+        let temp_dir = TempDir::new().unwrap();
+        let chat_dir = temp_dir.path().join("chat_history");
+        let zhi_dir = temp_dir.path().join("zhi_history");
+        write_history(&chat_dir, "aaaa");
+
+        assert!(clear_all_history_in("", &chat_dir, &zhi_dir).is_err());
+        assert!(clear_all_history_in("confirm", &chat_dir, &zhi_dir).is_err());
+        assert!(chat_dir.join("aaaa.json").exists());
+    }
+
+    #[test]
+    fn test_clear_all_history_removes_files_and_reports_counts() {
+        let temp_dir = TempDir::new().unwrap();
+        let chat_dir = temp_dir.path().join("chat_history");
+        let zhi_dir = temp_dir.path().join("zhi_history");
+        write_history(&chat_dir, "aaaa");
+        write_history(&chat_dir, "bbbb");
+        write_history(&zhi_dir, "cccc");
+
+        let result = clear_all_history_in(CLEAR_ALL_CONFIRM_TOKEN, &chat_dir, &zhi_dir).unwrap();
+
+        assert_eq!(result.chat_files, 2);
+        assert_eq!(result.zhi_files, 1);
+        assert!(list_history_projects_in(&[("chat", chat_dir), ("zhi", zhi_dir)]).is_empty());
+    }
+}
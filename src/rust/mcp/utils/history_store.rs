@@ -0,0 +1,139 @@
+// 历史记录存储抽象
+// 对话历史与 zhi 历史均以「键 -> JSON 文本」的形式存取；
+// 默认落盘到 `<数据目录>/<子目录>/<键>.json`（见 `crate::paths::data_dir`），测试可注入内存实现避免污染用户目录
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use super::file_lock::{lock_exclusive, FileLockGuard};
+
+/// 历史记录存储接口
+pub trait HistoryStore: Send + Sync {
+    /// 键是否存在
+    fn exists(&self, key: &str) -> bool;
+    /// 读取键对应的内容
+    fn read(&self, key: &str) -> Result<String>;
+    /// 写入键对应的内容（覆盖）
+    fn write(&self, key: &str, content: &str) -> Result<()>;
+    /// 键的可读位置描述（用于日志与错误信息）
+    fn describe(&self, key: &str) -> String;
+    /// 将键对应的内容移到隔离位置（`<名称>.corrupt.<时间戳>`），返回隔离后的位置描述
+    fn quarantine(&self, key: &str, timestamp: &str) -> Result<String>;
+    /// 获取键的独占锁，保护「加载-修改-保存」临界区（默认无需加锁）
+    fn lock(&self, _key: &str) -> Result<Option<FileLockGuard>> {
+        Ok(None)
+    }
+}
+
+/// 文件系统存储：每个键对应目录下的一个 `<键>.json` 文件
+pub struct FsHistoryStore {
+    dir: PathBuf,
+}
+
+impl FsHistoryStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path_for(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    fn lock_path_for(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json.lock", key))
+    }
+}
+
+impl HistoryStore for FsHistoryStore {
+    fn exists(&self, key: &str) -> bool {
+        self.path_for(key).exists()
+    }
+
+    fn read(&self, key: &str) -> Result<String> {
+        let path = self.path_for(key);
+        fs::read_to_string(&path).with_context(|| format!("读取历史文件失败: {}", path.display()))
+    }
+
+    fn write(&self, key: &str, content: &str) -> Result<()> {
+        // 确保目录存在
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("创建历史目录失败: {}", self.dir.display()))?;
+        let path = self.path_for(key);
+        crate::utils::write_atomic(&path, content)
+            .with_context(|| format!("写入历史文件失败: {}", path.display()))
+    }
+
+    fn describe(&self, key: &str) -> String {
+        self.path_for(key).display().to_string()
+    }
+
+    fn quarantine(&self, key: &str, timestamp: &str) -> Result<String> {
+        let path = self.path_for(key);
+        let target = self.dir.join(format!("{}.json.corrupt.{}", key, timestamp));
+        fs::rename(&path, &target)
+            .with_context(|| format!("隔离损坏的历史文件失败: {}", path.display()))?;
+        Ok(target.display().to_string())
+    }
+
+    fn lock(&self, key: &str) -> Result<Option<FileLockGuard>> {
+        lock_exclusive(&self.lock_path_for(key)).map(Some)
+    }
+}
+
+/// 内存存储：不触碰磁盘，适用于测试与临时会话
+#[derive(Default)]
+pub struct MemoryHistoryStore {
+    entries: Mutex<HashMap<String, String>>,
+}
+
+impl MemoryHistoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 当前保存的键数量
+    pub fn len(&self) -> usize {
+        self.entries.lock().map(|m| m.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl HistoryStore for MemoryHistoryStore {
+    fn exists(&self, key: &str) -> bool {
+        self.entries.lock().map(|m| m.contains_key(key)).unwrap_or(false)
+    }
+
+    fn read(&self, key: &str) -> Result<String> {
+        let entries = self.entries.lock().map_err(|_| anyhow::anyhow!("内存历史存储锁已被毒化"))?;
+        entries
+            .get(key)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("历史记录不存在: {}", self.describe(key)))
+    }
+
+    fn write(&self, key: &str, content: &str) -> Result<()> {
+        let mut entries = self.entries.lock().map_err(|_| anyhow::anyhow!("内存历史存储锁已被毒化"))?;
+        entries.insert(key.to_string(), content.to_string());
+        Ok(())
+    }
+
+    fn describe(&self, key: &str) -> String {
+        format!("memory://{}", key)
+    }
+
+    fn quarantine(&self, key: &str, timestamp: &str) -> Result<String> {
+        let mut entries = self.entries.lock().map_err(|_| anyhow::anyhow!("内存历史存储锁已被毒化"))?;
+        let content = entries
+            .remove(key)
+            .ok_or_else(|| anyhow::anyhow!("历史记录不存在: {}", self.describe(key)))?;
+        let target = format!("{}.corrupt.{}", key, timestamp);
+        entries.insert(target.clone(), content);
+        Ok(self.describe(&target))
+    }
+}
@@ -1,5 +1,16 @@
 pub mod common;
 pub mod errors;
+pub mod file_lock;
+pub mod history_browser;
+pub mod history_store;
+pub mod request_id;
 
 pub use common::*;
 pub use errors::*;
+pub use file_lock::{lock_exclusive, FileLockGuard};
+pub use history_browser::{ClearAllHistoryResult, HistoryProjectSummary};
+pub use history_store::{FsHistoryStore, HistoryStore, MemoryHistoryStore};
+pub use request_id::{
+    configured_request_id_format, next_request_id, request_id_source, PrefixedRequestIdSource,
+    RequestIdFormat, RequestIdSource, SequentialRequestIdSource, UuidRequestIdSource,
+};
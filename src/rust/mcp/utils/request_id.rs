@@ -0,0 +1,182 @@
+// 请求 ID 来源抽象
+// 生产环境默认使用随机 UUID，也可配置为可读的前缀格式；测试可注入顺序序列，使请求负载可稳定断言
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+
+/// 前缀格式默认使用的前缀
+pub const DEFAULT_REQUEST_ID_PREFIX: &str = "sanshu";
+
+/// 请求 ID 来源接口
+pub trait RequestIdSource: Send + Sync {
+    /// 生成下一个 ID
+    fn next_id(&self) -> String;
+}
+
+/// 随机 UUID 来源（默认）
+pub struct UuidRequestIdSource;
+
+impl RequestIdSource for UuidRequestIdSource {
+    fn next_id(&self) -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+}
+
+/// 顺序 ID 来源：依次生成 `<前缀>-1`、`<前缀>-2`……
+pub struct SequentialRequestIdSource {
+    prefix: String,
+    counter: AtomicU64,
+}
+
+impl SequentialRequestIdSource {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            counter: AtomicU64::new(0),
+        }
+    }
+}
+
+impl RequestIdSource for SequentialRequestIdSource {
+    fn next_id(&self) -> String {
+        let n = self.counter.fetch_add(1, Ordering::Relaxed) + 1;
+        format!("{}-{}", self.prefix, n)
+    }
+}
+
+/// 可读前缀 ID 来源：`<前缀>-<毫秒时间戳>-<进程号>-<序号>`
+///
+/// 序号由原子计数器递增，同一时间戳内并发生成也不会重复；
+/// 进程号区分同时运行的多个进程（如多个 MCP 服务实例），避免跨进程撞号
+pub struct PrefixedRequestIdSource {
+    prefix: String,
+    counter: AtomicU64,
+}
+
+impl PrefixedRequestIdSource {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            counter: AtomicU64::new(0),
+        }
+    }
+}
+
+impl RequestIdSource for PrefixedRequestIdSource {
+    fn next_id(&self) -> String {
+        let n = self.counter.fetch_add(1, Ordering::Relaxed) + 1;
+        format!(
+            "{}-{}-{}-{}",
+            self.prefix,
+            chrono::Utc::now().timestamp_millis(),
+            std::process::id(),
+            n
+        )
+    }
+}
+
+/// 请求 ID 格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RequestIdFormat {
+    /// 随机 UUID（默认）
+    #[default]
+    Uuid,
+    /// `sanshu-<毫秒时间戳>-<进程号>-<序号>`
+    Prefixed,
+}
+
+impl RequestIdFormat {
+    /// 解析配置值（忽略大小写），无法识别时返回 None
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "uuid" => Some(Self::Uuid),
+            "prefixed" => Some(Self::Prefixed),
+            _ => None,
+        }
+    }
+}
+
+// 中文注释：进程内共享的前缀来源，保证所有调用方共用同一计数器
+static PREFIXED_SOURCE: Lazy<Arc<PrefixedRequestIdSource>> =
+    Lazy::new(|| Arc::new(PrefixedRequestIdSource::new(DEFAULT_REQUEST_ID_PREFIX)));
+
+// 中文注释：配置的格式只在首次使用时读取一次，修改 request_id_format 后需重启进程生效
+static CONFIGURED_FORMAT: Lazy<RequestIdFormat> = Lazy::new(|| {
+    crate::config::load_standalone_config()
+        .ok()
+        .and_then(|c| c.mcp_config.request_id_format)
+        .and_then(|v| RequestIdFormat::parse(&v))
+        .unwrap_or_default()
+});
+
+/// 配置文件中的请求 ID 格式（进程内缓存）
+pub fn configured_request_id_format() -> RequestIdFormat {
+    *CONFIGURED_FORMAT
+}
+
+/// 指定格式对应的 ID 来源（前缀格式与 `next_request_id` 共用同一计数器）
+pub fn request_id_source(format: RequestIdFormat) -> Arc<dyn RequestIdSource> {
+    match format {
+        RequestIdFormat::Uuid => Arc::new(UuidRequestIdSource),
+        RequestIdFormat::Prefixed => PREFIXED_SOURCE.clone(),
+    }
+}
+
+/// 按指定格式生成请求 ID
+pub fn next_request_id(format: RequestIdFormat) -> String {
+    match format {
+        RequestIdFormat::Uuid => UuidRequestIdSource.next_id(),
+        RequestIdFormat::Prefixed => PREFIXED_SOURCE.next_id(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_prefixed_ids_unique_under_concurrency() {
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    (0..1000)
+                        .map(|_| next_request_id(RequestIdFormat::Prefixed))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut ids = HashSet::new();
+        for handle in handles {
+            for id in handle.join().unwrap() {
+                assert!(id.starts_with("sanshu-"), "{}", id);
+                assert_eq!(id.split('-').count(), 4, "{}", id);
+                assert_eq!(id.split('-').nth(2), Some(std::process::id().to_string().as_str()), "{}", id);
+                assert!(ids.insert(id.clone()), "重复的请求 ID: {}", id);
+            }
+        }
+        assert_eq!(ids.len(), 8000);
+    }
+
+    #[test]
+    fn test_parse_request_id_format() {
+        assert_eq!(RequestIdFormat::parse(" Prefixed "), Some(RequestIdFormat::Prefixed));
+        assert_eq!(RequestIdFormat::parse("uuid"), Some(RequestIdFormat::Uuid));
+        assert_eq!(RequestIdFormat::parse("snowflake"), None);
+        assert_eq!(RequestIdFormat::default(), RequestIdFormat::Uuid);
+        assert!(uuid::Uuid::parse_str(&next_request_id(RequestIdFormat::Uuid)).is_ok());
+    }
+
+    #[test]
+    fn test_prefixed_source_shares_process_counter() {
+        let source = request_id_source(RequestIdFormat::Prefixed);
+        let first = source.next_id();
+        let second = next_request_id(RequestIdFormat::Prefixed);
+        let seq = |id: &str| id.rsplit('-').next().unwrap().parse::<u64>().unwrap();
+        assert!(seq(&second) > seq(&first), "{} / {}", first, second);
+        assert!(uuid::Uuid::parse_str(&request_id_source(RequestIdFormat::Uuid).next_id()).is_ok());
+    }
+}
@@ -2,6 +2,35 @@
 use super::proxy::ProxyInfo;
 use crate::{log_important, log_debug};
 
+/// 强制直连环境变量（值为 "1" 时所有客户端忽略检测到的代理与系统代理）
+pub const NO_PROXY_ALL_ENV: &str = "SANSHU_NO_PROXY_ALL";
+
+/// 是否启用强制直连（环境变量优先，其次为配置项 `proxy_config.force_direct`）
+pub fn force_direct_enabled() -> bool {
+    if std::env::var(NO_PROXY_ALL_ENV).map(|v| v.trim() == "1").unwrap_or(false) {
+        return true;
+    }
+    crate::config::load_standalone_config()
+        .map(|c| c.proxy_config.force_direct)
+        .unwrap_or(false)
+}
+
+/// 强制直连时为客户端禁用所有代理（包括系统代理环境变量）
+pub fn apply_force_direct(builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+    if force_direct_enabled() {
+        builder.no_proxy()
+    } else {
+        builder
+    }
+}
+
+/// 启动时记录强制直连状态
+pub fn log_force_direct_status() {
+    if force_direct_enabled() {
+        log_important!(info, "[network] 强制直连模式已启用：忽略全部代理（{}=1 或 proxy_config.force_direct）", NO_PROXY_ALL_ENV);
+    }
+}
+
 /// 创建HTTP客户端
 /// 
 /// 根据是否提供代理信息，创建带代理或不带代理的HTTP客户端
@@ -20,8 +49,11 @@ pub fn create_http_client(
     let mut client_builder = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(timeout_secs));
     
-    // 如果提供了代理信息，配置代理
-    if let Some(proxy) = proxy_info {
+    if force_direct_enabled() {
+        log_debug!("[network] 强制直连模式，忽略代理配置");
+        client_builder = client_builder.no_proxy();
+    } else if let Some(proxy) = proxy_info {
+        // 如果提供了代理信息，配置代理
         log_important!(info, "[network] 配置HTTP客户端使用代理: {}:{} ({})", 
             proxy.host, proxy.port, proxy.proxy_type);
         
@@ -34,6 +34,8 @@ pub async fn set_proxy_config(
             .map_err(|e| format!("获取配置失败: {}", e))?;
         config.proxy_config = proxy_config;
     }
+    // 中文注释：配置变更后旧的检测结果可能不再适用
+    ProxyDetector::invalidate_detection_cache();
 
     // 保存配置到文件
     save_config(&state, &app)
@@ -72,11 +74,13 @@ pub async fn test_proxy_connection(
 }
 
 /// 自动检测可用代理
+///
+/// 用户主动触发，默认忽略缓存重新扫描；`force` 为 false 时允许复用 60 秒内的检测结果
 #[tauri::command]
-pub async fn detect_available_proxy() -> Result<Option<ProxyInfo>, String> {
+pub async fn detect_available_proxy(force: Option<bool>) -> Result<Option<ProxyInfo>, String> {
     log_important!(info, "[network] 开始自动检测可用代理");
     
-    let proxy_info = ProxyDetector::detect_available_proxy().await;
+    let proxy_info = ProxyDetector::detect_available_proxy_with(force.unwrap_or(true)).await;
     
     if let Some(ref info) = proxy_info {
         log_important!(info, "[network] 检测到可用代理: {}:{} ({})", info.host, info.port, info.proxy_type);
@@ -0,0 +1,346 @@
+// 带完整性校验的下载模块
+// 边下载边计算 SHA-256，校验失败时删除临时文件；中断的下载通过 HTTP Range 续传
+
+use ring::digest::{Context, SHA256};
+use std::fs;
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{log_debug, log_important};
+
+/// 下载进度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadProgress {
+    /// 已下载字节数
+    pub downloaded: u64,
+    /// 总字节数（服务器未返回 Content-Length 时为 None）
+    pub total: Option<u64>,
+}
+
+/// 下载过程中使用的临时文件路径（`<dest>.part`）
+pub fn partial_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+/// 下载文件并校验 SHA-256
+///
+/// 数据先写入 `<dest>.part`，校验通过后再重命名为 `dest`；
+/// 已存在的 `.part` 文件会通过 Range 请求续传（服务器不支持时退化为完整下载）。
+/// 下载中断时保留临时文件以便下次续传；哈希不匹配时删除临时文件并返回错误
+pub async fn download_verified<F>(
+    client: &reqwest::Client,
+    url: &str,
+    expected_sha256: &str,
+    dest: &Path,
+    mut on_progress: F,
+) -> Result<(), String>
+where
+    F: FnMut(DownloadProgress),
+{
+    let part_path = partial_path(dest);
+    let actual = download_to_part(client, url, &part_path, &mut on_progress).await?;
+
+    if !actual.eq_ignore_ascii_case(expected_sha256.trim()) {
+        let _ = fs::remove_file(&part_path);
+        log_important!(
+            warn,
+            "[download] SHA-256 校验失败: url={}, expected={}, actual={}",
+            url,
+            expected_sha256,
+            actual
+        );
+        return Err(format!("文件校验失败: 期望 SHA-256 {}，实际 {}", expected_sha256, actual));
+    }
+
+    fs::rename(&part_path, dest).map_err(|e| {
+        let _ = fs::remove_file(&part_path);
+        format!("保存下载文件失败: {}", e)
+    })?;
+    log_debug!("[download] 下载完成并通过校验: {}", dest.display());
+    Ok(())
+}
+
+/// 解析 `Content-Range: bytes <start>-<end>/<total>` 中的总大小
+fn content_range_total(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit('/')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// 解析 `Content-Range: bytes <start>-<end>/<total>` 中的起始偏移
+fn content_range_start(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .trim()
+        .strip_prefix("bytes")?
+        .trim()
+        .split('-')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// 以固定大小的块读取已下载的临时文件并计入哈希，避免一次性读入大文件
+fn hash_existing(hasher: &mut Context, part_path: &Path) -> std::io::Result<()> {
+    let mut reader = BufReader::new(fs::File::open(part_path)?);
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+/// 不带 Range 头重新完整下载
+async fn send_full_request(client: &reqwest::Client, url: &str) -> Result<(reqwest::Response, u64), String> {
+    let response = client.get(url).send().await.map_err(|e| format!("下载请求失败: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("下载失败: HTTP {}", response.status()));
+    }
+    Ok((response, 0))
+}
+
+/// 发送下载请求；`.part` 已有数据时附带 Range 头，返回响应与实际续传的起始偏移
+async fn send_download_request(
+    client: &reqwest::Client,
+    url: &str,
+    resume_from: u64,
+) -> Result<(reqwest::Response, u64), String> {
+    let mut request = client.get(url);
+    if resume_from > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", resume_from));
+    }
+    let response = request.send().await.map_err(|e| format!("下载请求失败: {}", e))?;
+
+    match response.status() {
+        reqwest::StatusCode::PARTIAL_CONTENT if resume_from > 0 => {
+            // 中文注释：服务器返回的起始偏移必须与临时文件长度一致，否则拼接后的内容会错位
+            match content_range_start(&response) {
+                Some(start) if start == resume_from => Ok((response, resume_from)),
+                start => {
+                    log_debug!(
+                        "[download] Content-Range 起始偏移 {:?} 与已下载 {} 字节不符，重新完整下载: {}",
+                        start,
+                        resume_from,
+                        url
+                    );
+                    send_full_request(client, url).await
+                }
+            }
+        }
+        // 中文注释：临时文件可能已损坏或超出远端大小，丢弃后完整重下
+        reqwest::StatusCode::RANGE_NOT_SATISFIABLE if resume_from > 0 => {
+            log_debug!("[download] 续传范围无效，重新完整下载: {}", url);
+            send_full_request(client, url).await
+        }
+        status if status.is_success() => {
+            if resume_from > 0 {
+                log_debug!("[download] 服务器不支持 Range（HTTP {}），重新完整下载: {}", status, url);
+            }
+            Ok((response, 0))
+        }
+        status => Err(format!("下载失败: HTTP {}", status)),
+    }
+}
+
+/// 流式写入临时文件（支持续传），返回完整内容的 SHA-256（小写十六进制）
+async fn download_to_part<F>(
+    client: &reqwest::Client,
+    url: &str,
+    part_path: &Path,
+    on_progress: &mut F,
+) -> Result<String, String>
+where
+    F: FnMut(DownloadProgress),
+{
+    let existing = fs::metadata(part_path).map(|m| m.len()).unwrap_or(0);
+    let (mut response, offset) = send_download_request(client, url, existing).await?;
+
+    if let Some(parent) = part_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建下载目录失败: {}", e))?;
+    }
+
+    let mut hasher = Context::new(&SHA256);
+    let mut file = if offset > 0 {
+        // 中文注释：续传时先把已有数据计入哈希，再追加写入
+        hash_existing(&mut hasher, part_path).map_err(|e| format!("读取已下载数据失败: {}", e))?;
+        log_important!(info, "[download] 从 {} 字节处续传: {}", offset, url);
+        fs::OpenOptions::new()
+            .append(true)
+            .open(part_path)
+            .map_err(|e| format!("打开下载文件失败: {}", e))?
+    } else {
+        fs::File::create(part_path).map_err(|e| format!("创建下载文件失败: {}", e))?
+    };
+
+    let total = if offset > 0 {
+        content_range_total(&response).or_else(|| response.content_length().map(|len| len + offset))
+    } else {
+        response.content_length()
+    };
+    let mut downloaded = offset;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("下载数据失败: {}", e))?
+    {
+        file.write_all(&chunk).map_err(|e| format!("写入下载文件失败: {}", e))?;
+        hasher.update(&chunk);
+        downloaded += chunk.len() as u64;
+        on_progress(DownloadProgress { downloaded, total });
+    }
+    file.sync_all().map_err(|e| format!("写入下载文件失败: {}", e))?;
+
+    // 中文注释：大小不符说明连接提前断开，保留临时文件供下次续传
+    if let Some(total) = total {
+        if downloaded != total {
+            return Err(format!("下载不完整: 已下载 {} 字节，期望 {} 字节", downloaded, total));
+        }
+    }
+
+    Ok(hex::encode(hasher.finish().as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::http_mock::{MockResponse, MockServer};
+
+    const PAYLOAD: &[u8] = b"sanshu download payload";
+
+    /// 测试服务器对 Range 请求的处理方式
+    #[derive(Clone, Copy)]
+    enum RangeSupport {
+        /// 忽略 Range，总是返回完整内容
+        Ignore,
+        /// 正确返回 206 与对应的 Content-Range
+        Honor,
+        /// 返回 206，但 Content-Range 起始偏移与请求不一致（内容从头开始）
+        WrongStart,
+    }
+
+    /// 启动 HTTP 服务器，返回固定内容；按 `ranges` 决定如何响应 Range 请求
+    fn start_payload_server(body: &'static [u8], ranges: RangeSupport) -> String {
+        let server = MockServer::start(move |request| {
+            let range_start = request
+                .header("range")
+                .and_then(|v| v.strip_prefix("bytes="))
+                .and_then(|v| v.trim().trim_end_matches('-').parse::<usize>().ok());
+            match (range_start, ranges) {
+                (Some(start), RangeSupport::Honor) => MockResponse::status(206, "Partial Content")
+                    .with_header("Content-Range", &format!("bytes {}-{}/{}", start, body.len() - 1, body.len()))
+                    .with_body(&body[start..]),
+                (Some(_), RangeSupport::WrongStart) => MockResponse::status(206, "Partial Content")
+                    .with_header("Content-Range", &format!("bytes 0-{}/{}", body.len() - 1, body.len()))
+                    .with_body(body),
+                _ => MockResponse::ok(body),
+            }
+        });
+        server.url("/asset.bin")
+    }
+
+    fn sha256_hex(data: &[u8]) -> String {
+        hex::encode(ring::digest::digest(&SHA256, data).as_ref())
+    }
+
+    #[tokio::test]
+    async fn test_download_with_correct_hash_succeeds() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dest = temp_dir.path().join("asset.bin");
+        let url = start_payload_server(PAYLOAD, RangeSupport::Ignore);
+
+        let mut last = None;
+        download_verified(&reqwest::Client::new(), &url, &sha256_hex(PAYLOAD), &dest, |p| last = Some(p))
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), PAYLOAD);
+        assert!(!partial_path(&dest).exists());
+        assert_eq!(
+            last,
+            Some(DownloadProgress { downloaded: PAYLOAD.len() as u64, total: Some(PAYLOAD.len() as u64) })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_with_wrong_hash_fails_and_cleans_up() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dest = temp_dir.path().join("asset.bin");
+        let url = start_payload_server(PAYLOAD, RangeSupport::Ignore);
+
+        let err = download_verified(&reqwest::Client::new(), &url, &sha256_hex(b"other"), &dest, |_| {})
+            .await
+            .unwrap_err();
+
+        assert!(err.contains("校验失败"));
+        assert!(!dest.exists());
+        assert!(!partial_path(&dest).exists());
+    }
+
+    #[tokio::test]
+    async fn test_resumes_from_partial_file_with_range_request() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dest = temp_dir.path().join("asset.bin");
+        fs::write(partial_path(&dest), &PAYLOAD[..7]).unwrap();
+        let url = start_payload_server(PAYLOAD, RangeSupport::Honor);
+
+        let mut first = None;
+        download_verified(&reqwest::Client::new(), &url, &sha256_hex(PAYLOAD), &dest, |p| {
+            first.get_or_insert(p);
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), PAYLOAD);
+        // 首个进度事件应从已有的 7 字节之后累计
+        let first = first.unwrap();
+        assert!(first.downloaded > 7);
+        assert_eq!(first.total, Some(PAYLOAD.len() as u64));
+    }
+
+    #[tokio::test]
+    async fn test_server_ignoring_range_triggers_full_download() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dest = temp_dir.path().join("asset.bin");
+        // 临时文件内容与远端不一致，完整重下后才能通过校验
+        fs::write(partial_path(&dest), b"stale").unwrap();
+        let url = start_payload_server(PAYLOAD, RangeSupport::Ignore);
+
+        download_verified(&reqwest::Client::new(), &url, &sha256_hex(PAYLOAD), &dest, |_| {})
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), PAYLOAD);
+        assert!(!partial_path(&dest).exists());
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_content_range_start_triggers_full_download() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dest = temp_dir.path().join("asset.bin");
+        fs::write(partial_path(&dest), &PAYLOAD[..7]).unwrap();
+        let url = start_payload_server(PAYLOAD, RangeSupport::WrongStart);
+
+        download_verified(&reqwest::Client::new(), &url, &sha256_hex(PAYLOAD), &dest, |_| {})
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), PAYLOAD);
+        assert!(!partial_path(&dest).exists());
+    }
+}
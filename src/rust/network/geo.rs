@@ -1,7 +1,16 @@
 // IP地理位置检测模块
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use crate::{log_important, log_debug};
 
+/// 地理位置缓存有效期（进程内缓存，避免每次请求都访问 ipinfo.io）
+const GEO_CACHE_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// 最近一次成功检测到的国家代码
+static GEO_CACHE: Lazy<Mutex<Option<(Instant, String)>>> = Lazy::new(|| Mutex::new(None));
+
 /// IP地理位置信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeoLocation {
@@ -28,7 +37,7 @@ pub async fn detect_geo_location() -> String {
     log_important!(info, "[network] 开始检测IP地理位置");
     
     // 创建HTTP客户端，设置较短的超时时间
-    let client = match reqwest::Client::builder()
+    let client = match super::client::apply_force_direct(reqwest::Client::builder())
         .timeout(std::time::Duration::from_secs(5))
         .build()
     {
@@ -72,6 +81,26 @@ pub async fn detect_geo_location() -> String {
     }
 }
 
+/// 检测当前IP的国家代码（带进程内缓存）
+///
+/// 仅缓存成功结果；检测失败（UNKNOWN）时下次调用会重新检测
+pub async fn detect_geo_location_cached() -> String {
+    if let Some((at, country)) = GEO_CACHE.lock().ok().and_then(|c| c.clone()) {
+        if at.elapsed() < GEO_CACHE_TTL {
+            log_debug!("[network] 使用缓存的地理位置: {}", country);
+            return country;
+        }
+    }
+
+    let country = detect_geo_location().await;
+    if country != "UNKNOWN" {
+        if let Ok(mut cache) = GEO_CACHE.lock() {
+            *cache = Some((Instant::now(), country.clone()));
+        }
+    }
+    country
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -4,9 +4,14 @@
 pub mod geo;
 pub mod proxy;
 pub mod client;
+pub mod download;
 pub mod commands;
 
-pub use geo::detect_geo_location;
+pub use geo::{detect_geo_location, detect_geo_location_cached};
 pub use proxy::{ProxyDetector, ProxyInfo};
-pub use client::{create_http_client, create_update_client, create_download_client};
+pub use client::{
+    apply_force_direct, create_download_client, create_http_client, create_update_client, force_direct_enabled,
+    log_force_direct_status, NO_PROXY_ALL_ENV,
+};
+pub use download::{download_verified, DownloadProgress};
 
@@ -1,7 +1,48 @@
 // 代理检测和配置模块
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use crate::{log_important, log_debug};
 
+/// 默认的代理可用性探测地址（专用于连通性测试，返回 HTTP 204）
+pub const DEFAULT_PROXY_TEST_URL: &str = "http://www.gstatic.com/generate_204";
+
+/// 代理探测地址覆盖环境变量（优先于配置项 `proxy_config.test_url`）
+pub const PROXY_TEST_URL_ENV: &str = "SANSHU_PROXY_TEST_URL";
+
+/// 按优先级选择探测地址：环境变量 > 配置项 > 默认地址
+pub fn resolve_proxy_test_url(env_value: Option<String>, configured: Option<String>) -> String {
+    env_value
+        .into_iter()
+        .chain(configured)
+        .map(|url| url.trim().to_string())
+        .find(|url| !url.is_empty())
+        .unwrap_or_else(|| DEFAULT_PROXY_TEST_URL.to_string())
+}
+
+/// 当前生效的代理探测地址
+pub fn proxy_test_url() -> String {
+    let configured = crate::config::load_standalone_config()
+        .ok()
+        .and_then(|c| c.proxy_config.test_url);
+    resolve_proxy_test_url(std::env::var(PROXY_TEST_URL_ENV).ok(), configured)
+}
+
+/// 探测地址列表：HTTP 探测失败时追加同地址的 HTTPS 探测
+///
+/// 部分代理只放行 HTTPS CONNECT 隧道，仅用 HTTP 探测会误判为不可用
+pub fn probe_urls(test_url: &str) -> Vec<String> {
+    let mut urls = vec![test_url.to_string()];
+    if let Some(rest) = test_url.strip_prefix("http://") {
+        urls.push(format!("https://{}", rest));
+    }
+    urls
+}
+
 /// 代理类型
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -43,12 +84,111 @@ impl ProxyInfo {
         }
     }
     
+    /// 获取 `host:port`（IPv6 地址自动加方括号）
+    pub fn authority(&self) -> String {
+        let host = self.host.trim_start_matches('[').trim_end_matches(']');
+        if host.contains(':') {
+            format!("[{}]:{}", host, self.port)
+        } else {
+            format!("{}:{}", host, self.port)
+        }
+    }
+
     /// 获取代理URL
     pub fn to_url(&self) -> String {
-        format!("{}://{}:{}", self.proxy_type, self.host, self.port)
+        format!("{}://{}", self.proxy_type, self.authority())
+    }
+
+    /// 解析用户配置的代理地址：`[scheme://]host:port`，IPv6 需写成 `[::1]:7890`
+    ///
+    /// 未指定协议时按 HTTP 处理
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (proxy_type, rest) = if let Some(rest) = value.strip_prefix("socks5://") {
+            (ProxyType::Socks5, rest)
+        } else if let Some(rest) = value.strip_prefix("http://") {
+            (ProxyType::Http, rest)
+        } else {
+            (ProxyType::Http, value)
+        };
+        let (host, port) = rest.trim_end_matches('/').rsplit_once(':')?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let port = port.parse::<u16>().ok().filter(|p| *p > 0)?;
+        if host.is_empty() {
+            return None;
+        }
+        Some(Self::new(proxy_type, host.to_string(), port))
+    }
+}
+
+/// 代理检测结果缓存的默认有效期
+pub const DEFAULT_PROXY_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// 代理检测结果缓存
+///
+/// 有效期内复用上次的检测结果（包括“未找到代理”），避免每次构建客户端都重新扫描端口
+pub struct ProxyDetectionCache {
+    ttl: Duration,
+    entry: Mutex<Option<(Instant, Option<ProxyInfo>)>>,
+}
+
+impl ProxyDetectionCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entry: Mutex::new(None),
+        }
+    }
+
+    /// 未过期的缓存结果；外层 `None` 表示没有可用缓存
+    pub fn get(&self) -> Option<Option<ProxyInfo>> {
+        let entry = self.entry.lock().ok()?;
+        match entry.as_ref() {
+            Some((detected_at, result)) if detected_at.elapsed() < self.ttl => Some(result.clone()),
+            _ => None,
+        }
+    }
+
+    /// 记录最新的检测结果
+    pub fn store(&self, result: Option<ProxyInfo>) {
+        if let Ok(mut entry) = self.entry.lock() {
+            *entry = Some((Instant::now(), result));
+        }
+    }
+
+    /// 清除缓存（代理配置变更后调用）
+    pub fn invalidate(&self) {
+        if let Ok(mut entry) = self.entry.lock() {
+            *entry = None;
+        }
+    }
+
+    /// 优先返回缓存结果，缓存失效或 `force` 为 true 时执行检测并更新缓存
+    pub async fn get_or_detect<F, Fut>(&self, force: bool, detect: F) -> Option<ProxyInfo>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Option<ProxyInfo>>,
+    {
+        if !force {
+            if let Some(cached) = self.get() {
+                log_debug!("[network] 使用缓存的代理检测结果: {:?}", cached.as_ref().map(|p| p.authority()));
+                return cached;
+            }
+        }
+        // 中文注释：检测期间不持有锁，避免阻塞其他读取缓存的调用方
+        let result = detect().await;
+        self.store(result.clone());
+        result
     }
 }
 
+// 中文注释：进程内共享的检测结果缓存
+static PROXY_DETECTION_CACHE: Lazy<ProxyDetectionCache> =
+    Lazy::new(|| ProxyDetectionCache::new(DEFAULT_PROXY_CACHE_TTL));
+
+/// 默认扫描的本地代理主机（IPv4 与 IPv6 回环地址）
+pub const DEFAULT_PROXY_SCAN_HOSTS: &[&str] = &["127.0.0.1", "::1"];
+
 /// 代理检测器
 pub struct ProxyDetector;
 
@@ -70,23 +210,74 @@ impl ProxyDetector {
         (8080, ProxyType::Http),    // 通用HTTP端口
     ];
     
+    /// 生成待检测的代理列表
+    ///
+    /// 用户自定义的代理优先，其后是每个扫描主机上的常用端口
+    pub fn scan_candidates(scan_hosts: &[String], custom_proxies: &[String]) -> Vec<ProxyInfo> {
+        let mut candidates: Vec<ProxyInfo> = custom_proxies
+            .iter()
+            .filter_map(|value| {
+                let parsed = ProxyInfo::parse(value);
+                if parsed.is_none() {
+                    log_debug!("[network] 忽略无效的自定义代理: {}", value);
+                }
+                parsed
+            })
+            .collect();
+        for host in scan_hosts.iter().map(|h| h.trim()).filter(|h| !h.is_empty()) {
+            for (port, proxy_type) in Self::COMMON_PORTS {
+                candidates.push(ProxyInfo::new(proxy_type.clone(), host.to_string(), *port));
+            }
+        }
+        candidates
+    }
+
     /// 检测本地可用的代理
     /// 
-    /// 按优先级顺序检测常用代理端口，返回第一个可用的代理
+    /// 按优先级顺序检测自定义代理与常用代理端口，返回第一个可用的代理；
+    /// 60 秒内重复调用直接返回缓存结果
     /// 
     /// # 返回值
     /// - `Some(ProxyInfo)`: 找到可用的代理
     /// - `None`: 没有找到可用的代理
     pub async fn detect_available_proxy() -> Option<ProxyInfo> {
-        log_important!(info, "[network] 开始检测本地代理");
+        Self::detect_available_proxy_with(false).await
+    }
+
+    /// 检测本地可用的代理，`force` 为 true 时忽略缓存重新扫描
+    pub async fn detect_available_proxy_with(force: bool) -> Option<ProxyInfo> {
+        if super::client::force_direct_enabled() {
+            log_debug!("[network] 强制直连模式，跳过代理检测");
+            return None;
+        }
+        PROXY_DETECTION_CACHE.get_or_detect(force, Self::scan_configured_proxies).await
+    }
+
+    /// 清除代理检测缓存（代理配置变更后调用）
+    pub fn invalidate_detection_cache() {
+        PROXY_DETECTION_CACHE.invalidate();
+    }
+
+    /// 按当前配置扫描自定义代理与常用端口
+    async fn scan_configured_proxies() -> Option<ProxyInfo> {
+        let proxy_config = crate::config::load_standalone_config().ok().map(|c| c.proxy_config);
+        let scan_hosts = proxy_config
+            .as_ref()
+            .and_then(|c| c.scan_hosts.clone())
+            .unwrap_or_else(|| DEFAULT_PROXY_SCAN_HOSTS.iter().map(|h| h.to_string()).collect());
+        let custom_proxies = proxy_config.map(|c| c.custom_proxies).unwrap_or_default();
+        Self::detect_available_proxy_in(Self::scan_candidates(&scan_hosts, &custom_proxies)).await
+    }
+
+    /// 按顺序检测给定的代理列表，返回第一个可用的代理
+    pub async fn detect_available_proxy_in(candidates: Vec<ProxyInfo>) -> Option<ProxyInfo> {
+        log_important!(info, "[network] 开始检测本地代理: candidates={}", candidates.len());
         
-        for (port, proxy_type) in Self::COMMON_PORTS {
-            let proxy_info = ProxyInfo::new(proxy_type.clone(), "127.0.0.1".to_string(), *port);
-            
-            log_debug!("[network] 检测代理端口: {} ({})", port, proxy_type);
+        for proxy_info in candidates {
+            log_debug!("[network] 检测代理: {} ({})", proxy_info.authority(), proxy_info.proxy_type);
             
             if Self::check_proxy(&proxy_info).await {
-                log_important!(info, "[network] 找到可用代理: {}:{} ({})", proxy_info.host, proxy_info.port, proxy_info.proxy_type);
+                log_important!(info, "[network] 找到可用代理: {} ({})", proxy_info.authority(), proxy_info.proxy_type);
                 return Some(proxy_info);
             }
         }
@@ -97,8 +288,8 @@ impl ProxyDetector {
     
     /// 检测指定代理是否可用
     /// 
-    /// 通过代理发送测试请求到 Google 的 generate_204 端点
-    /// 该端点专门用于网络连接测试，返回 HTTP 204 状态码
+    /// 通过代理发送测试请求到探测地址（默认 Google 的 generate_204 端点，
+    /// 可通过 `SANSHU_PROXY_TEST_URL` 或配置项 `proxy_config.test_url` 修改）
     /// 
     /// # 参数
     /// - `proxy_info`: 要检测的代理信息
@@ -107,10 +298,20 @@ impl ProxyDetector {
     /// - `true`: 代理可用
     /// - `false`: 代理不可用
     pub async fn check_proxy(proxy_info: &ProxyInfo) -> bool {
+        Self::check_proxy_with_urls(proxy_info, &probe_urls(&proxy_test_url())).await
+    }
+
+    /// 使用指定探测地址检测代理是否可用（任意 2xx 视为成功）
+    pub async fn check_proxy_with_url(proxy_info: &ProxyInfo, test_url: &str) -> bool {
+        Self::check_proxy_with_urls(proxy_info, &[test_url.to_string()]).await
+    }
+
+    /// 依次使用多个探测地址检测代理，任一成功即视为可用
+    pub async fn check_proxy_with_urls(proxy_info: &ProxyInfo, test_urls: &[String]) -> bool {
         // 先检测本地端口是否存在（TCP 连接预检）
         // 说明：端口可连通不代表一定是代理，但可以快速过滤掉“端口未监听”的情况，
         // 避免直接进行 HTTP 探测导致额外等待（符合需求：先测端口存在，再进行 3 秒 HTTP 探测）。
-        let addr = format!("{}:{}", proxy_info.host, proxy_info.port);
+        let addr = proxy_info.authority();
         let tcp_timeout = std::time::Duration::from_millis(300);
         match tokio::time::timeout(tcp_timeout, tokio::net::TcpStream::connect(&addr)).await {
             Ok(Ok(_stream)) => {
@@ -166,31 +367,91 @@ impl ProxyDetector {
             }
         };
         
-        // 发送测试请求
-        // 使用 Google 的 generate_204 端点进行连接测试
-        match client
-            .get("http://www.gstatic.com/generate_204")
-            .send()
-            .await
-        {
-            Ok(response) => {
-                let is_success = response.status().is_success() || response.status() == 204;
-                if is_success {
-                    log_debug!("[network] 代理 {}:{} 可用", proxy_info.host, proxy_info.port);
-                } else {
-                    log_debug!("[network] 代理 {}:{} 响应异常: HTTP {}", 
-                        proxy_info.host, proxy_info.port, response.status());
+        // 依次发送测试请求，任一成功即视为可用（每次请求受 3 秒超时约束）
+        for test_url in test_urls {
+            // 中文注释：HTTPS 探测经 HTTP 代理时只需确认 CONNECT 隧道可建立，
+            // 只放行隧道的代理会拒绝普通 GET，但这类代理对 HTTPS 请求是可用的
+            if proxy_info.proxy_type == ProxyType::Http && test_url.starts_with("https://") {
+                if Self::probe_connect_tunnel(proxy_info, test_url).await {
+                    log_debug!("[network] 代理 {}:{} 可用（CONNECT {}）", proxy_info.host, proxy_info.port, test_url);
+                    return true;
                 }
-                is_success
+                continue;
             }
+            match client
+                .get(test_url.as_str())
+                .send()
+                .await
+            {
+                Ok(response) if response.status().is_success() => {
+                    log_debug!("[network] 代理 {}:{} 可用（{}）", proxy_info.host, proxy_info.port, test_url);
+                    return true;
+                }
+                Ok(response) => {
+                    log_debug!("[network] 代理 {}:{} 响应异常: HTTP {}（{}）", 
+                        proxy_info.host, proxy_info.port, response.status(), test_url);
+                }
+                Err(e) => {
+                    log_debug!("[network] 代理 {}:{} 连接失败: {}（{}）", 
+                        proxy_info.host, proxy_info.port, e, test_url);
+                }
+            }
+        }
+        false
+    }
+    
+    /// 通过 HTTP 代理发送 CONNECT 请求，代理返回 2xx 即视为隧道可用
+    async fn probe_connect_tunnel(proxy_info: &ProxyInfo, test_url: &str) -> bool {
+        let target = match reqwest::Url::parse(test_url) {
+            Ok(url) => match (url.host_str(), url.port_or_known_default()) {
+                (Some(host), Some(port)) => ProxyInfo::new(ProxyType::Http, host.to_string(), port).authority(),
+                _ => return false,
+            },
             Err(e) => {
-                log_debug!("[network] 代理 {}:{} 连接失败: {}", 
-                    proxy_info.host, proxy_info.port, e);
+                log_debug!("[network] 探测地址无效: {}（{}）", e, test_url);
+                return false;
+            }
+        };
+
+        let probe = async {
+            let mut stream = tokio::net::TcpStream::connect(proxy_info.authority()).await?;
+            let request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n\r\n", target, target);
+            stream.write_all(request.as_bytes()).await?;
+
+            // 中文注释：只需读取状态行，响应头之后的数据属于隧道内容
+            let mut head = Vec::new();
+            let mut buf = [0u8; 512];
+            while !head.windows(2).any(|w| w == b"\r\n") && head.len() < 4096 {
+                let n = stream.read(&mut buf).await?;
+                if n == 0 {
+                    break;
+                }
+                head.extend_from_slice(&buf[..n]);
+            }
+            Ok::<_, std::io::Error>(String::from_utf8_lossy(&head).into_owned())
+        };
+
+        match tokio::time::timeout(Duration::from_secs(3), probe).await {
+            Ok(Ok(head)) => {
+                let status = head.lines().next().unwrap_or_default().split_whitespace().nth(1).unwrap_or_default();
+                if status.starts_with('2') {
+                    true
+                } else {
+                    log_debug!("[network] 代理 {}:{} 拒绝 CONNECT {}: {}", proxy_info.host, proxy_info.port, target, status);
+                    false
+                }
+            }
+            Ok(Err(e)) => {
+                log_debug!("[network] 代理 {}:{} CONNECT 失败: {}（{}）", proxy_info.host, proxy_info.port, e, test_url);
+                false
+            }
+            Err(_) => {
+                log_debug!("[network] 代理 {}:{} CONNECT 超时（{}）", proxy_info.host, proxy_info.port, test_url);
                 false
             }
         }
     }
-    
+
     /// 检测指定端口的代理是否可用
     /// 
     /// 便捷方法，用于检测单个端口
@@ -217,11 +478,157 @@ mod tests {
         }
     }
     
+    #[tokio::test]
+    async fn test_detection_cache_scans_once_within_ttl() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let cache = ProxyDetectionCache::new(Duration::from_secs(60));
+        let counter = AtomicUsize::new(0);
+        let scans = &counter;
+        let detect = move || async move {
+            scans.fetch_add(1, Ordering::SeqCst);
+            Some(ProxyInfo::new(ProxyType::Http, "127.0.0.1".to_string(), 7890))
+        };
+
+        let first = cache.get_or_detect(false, detect).await;
+        let second = cache.get_or_detect(false, detect).await;
+        assert_eq!(scans.load(Ordering::SeqCst), 1);
+        assert_eq!(first.unwrap().port, 7890);
+        assert_eq!(second.unwrap().port, 7890);
+
+        // force 与配置变更都会触发重新扫描
+        cache.get_or_detect(true, detect).await;
+        assert_eq!(scans.load(Ordering::SeqCst), 2);
+        cache.invalidate();
+        cache.get_or_detect(false, detect).await;
+        assert_eq!(scans.load(Ordering::SeqCst), 3);
+
+        // “未找到代理”同样缓存，过期后重新扫描
+        let expired = ProxyDetectionCache::new(Duration::ZERO);
+        let none = move || async move {
+            scans.fetch_add(1, Ordering::SeqCst);
+            None
+        };
+        assert!(expired.get_or_detect(false, none).await.is_none());
+        assert!(expired.get_or_detect(false, none).await.is_none());
+        assert_eq!(scans.load(Ordering::SeqCst), 5);
+    }
+
     #[tokio::test]
     async fn test_check_specific_port() {
         // 测试 Clash 默认端口
         let is_available = ProxyDetector::check_port(7890, ProxyType::Http).await;
         println!("端口 7890 (HTTP) 可用: {}", is_available);
     }
-}
 
+    #[test]
+    fn test_to_url_brackets_ipv6_host() {
+        let proxy = ProxyInfo::new(ProxyType::Http, "::1".to_string(), 7890);
+        assert_eq!(proxy.to_url(), "http://[::1]:7890");
+        let proxy = ProxyInfo::new(ProxyType::Socks5, "[fe80::1]".to_string(), 1080);
+        assert_eq!(proxy.to_url(), "socks5://[fe80::1]:1080");
+        let proxy = ProxyInfo::new(ProxyType::Http, "127.0.0.1".to_string(), 7890);
+        assert_eq!(proxy.to_url(), "http://127.0.0.1:7890");
+    }
+
+    #[test]
+    fn test_scan_includes_custom_proxies_and_hosts() {
+        let hosts = vec!["127.0.0.1".to_string(), "::1".to_string()];
+        let custom = vec![
+            "socks5://192.168.1.10:1081".to_string(),
+            "[::1]:8888".to_string(),
+            "invalid".to_string(),
+        ];
+        let candidates = ProxyDetector::scan_candidates(&hosts, &custom);
+        let urls: Vec<String> = candidates.iter().map(ProxyInfo::to_url).collect();
+
+        // 自定义代理排在最前，无效条目被忽略
+        assert_eq!(urls[0], "socks5://192.168.1.10:1081");
+        assert_eq!(urls[1], "http://[::1]:8888");
+        assert!(urls.contains(&"http://127.0.0.1:7890".to_string()));
+        assert!(urls.contains(&"http://[::1]:7890".to_string()));
+        assert_eq!(candidates.len(), 2 + hosts.len() * ProxyDetector::COMMON_PORTS.len());
+    }
+
+    #[test]
+    fn test_proxy_test_url_priority() {
+        let custom = Some("http://example.com/ping".to_string());
+        assert_eq!(resolve_proxy_test_url(None, None), DEFAULT_PROXY_TEST_URL);
+        assert_eq!(resolve_proxy_test_url(None, custom.clone()), "http://example.com/ping");
+        assert_eq!(
+            resolve_proxy_test_url(Some("http://env.example/204".to_string()), custom),
+            "http://env.example/204"
+        );
+        // 空字符串视为未配置
+        assert_eq!(resolve_proxy_test_url(Some(" ".to_string()), None), DEFAULT_PROXY_TEST_URL);
+    }
+
+    #[test]
+    fn test_http_probe_is_followed_by_https_probe() {
+        assert_eq!(
+            probe_urls(DEFAULT_PROXY_TEST_URL),
+            vec![
+                "http://www.gstatic.com/generate_204".to_string(),
+                "https://www.gstatic.com/generate_204".to_string(),
+            ]
+        );
+        // 已是 HTTPS 的地址不重复探测
+        assert_eq!(probe_urls("https://example.com/204").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_available_when_only_second_probe_succeeds() {
+        // 模拟只放行第二个探测地址的代理：第一个探测返回 502，第二个返回 204
+        let server = MockServer::start(|request| {
+            if request.target.contains("/http-blocked") {
+                MockResponse::status(502, "Bad Gateway")
+            } else {
+                MockResponse::status(204, "No Content")
+            }
+        });
+
+        let proxy = ProxyInfo::new(ProxyType::Http, "127.0.0.1".to_string(), server.addr().port());
+        let urls = vec![
+            "http://sanshu-probe.example/http-blocked".to_string(),
+            "http://sanshu-probe.example/tunnel-ok".to_string(),
+        ];
+        assert!(ProxyDetector::check_proxy_with_urls(&proxy, &urls).await);
+    }
+
+    #[tokio::test]
+    async fn test_connect_only_proxy_is_available_via_https_probe() {
+        // 模拟只放行 CONNECT 隧道的代理：普通 GET 返回 403，CONNECT 返回 200
+        let server = MockServer::start(|request| {
+            if request.method == "CONNECT" {
+                MockResponse::status(200, "Connection Established")
+            } else {
+                MockResponse::status(403, "Forbidden")
+            }
+        });
+
+        let proxy = ProxyInfo::new(ProxyType::Http, "127.0.0.1".to_string(), server.addr().port());
+        let urls = probe_urls("http://sanshu-probe.example/generate_204");
+        assert!(ProxyDetector::check_proxy_with_urls(&proxy, &urls).await);
+
+        let requests: Vec<String> = server.requests().iter().map(|r| r.request_line()).collect();
+        assert_eq!(requests.len(), 2, "{:?}", requests);
+        assert!(requests[0].starts_with("GET http://sanshu-probe.example/generate_204"));
+        assert!(requests[1].starts_with("CONNECT sanshu-probe.example:443"));
+    }
+
+    #[tokio::test]
+    async fn test_check_proxy_uses_custom_test_url() {
+        // 模拟 HTTP 代理：记录经代理转发的请求并返回 204（端口预检的空连接不计入）
+        let server = MockServer::respond_with(MockResponse::status(204, "No Content"));
+
+        let proxy = ProxyInfo::new(ProxyType::Http, "127.0.0.1".to_string(), server.addr().port());
+        let available = ProxyDetector::check_proxy_with_url(&proxy, "http://sanshu-probe.example/ping").await;
+
+        assert!(available);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        let request_line = requests[0].request_line();
+        assert!(request_line.contains("http://sanshu-probe.example/ping"), "{}", request_line);
+        assert!(!request_line.contains("gstatic"));
+    }
+}
@@ -0,0 +1,46 @@
+// 三术数据目录
+// 历史记录、文档缓存等本地数据统一存放在此目录下，便于便携安装与沙箱环境重定向
+
+use std::path::PathBuf;
+
+/// 数据目录覆盖环境变量（优先级最高）
+pub const DATA_DIR_ENV: &str = "SANSHU_DATA_DIR";
+
+/// 默认数据目录名（位于用户主目录下）
+const DEFAULT_DATA_DIR_NAME: &str = ".sanshu";
+
+/// 获取数据根目录
+///
+/// 优先级：`SANSHU_DATA_DIR` 环境变量 > 配置项 `mcp_config.data_dir` > `~/.sanshu`
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = non_empty(std::env::var(DATA_DIR_ENV).ok()) {
+        return PathBuf::from(dir);
+    }
+
+    let configured = crate::config::load_standalone_config()
+        .ok()
+        .and_then(|c| non_empty(c.mcp_config.data_dir));
+    if let Some(dir) = configured {
+        return PathBuf::from(dir);
+    }
+
+    default_data_dir()
+}
+
+/// 默认数据目录（~/.sanshu，无法获取主目录时退化为当前目录）
+pub fn default_data_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(DEFAULT_DATA_DIR_NAME)
+}
+
+/// 数据目录下的子目录
+pub fn data_subdir(name: &str) -> PathBuf {
+    data_dir().join(name)
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
@@ -3,17 +3,20 @@ use std::collections::HashSet;
 use teloxide::prelude::*;
 
 use crate::config::load_standalone_config;
+use crate::mcp::handlers::summarize_mcp_response;
+use crate::mcp::tools::interaction::ZhiHistoryManager;
 use crate::mcp::types::{build_continue_response, build_send_response, PopupRequest};
+use crate::mcp::utils::normalize_optional_project_root;
 use crate::telegram::{handle_callback_query, handle_text_message, TelegramCore, TelegramEvent};
 use crate::{log_important, log_debug};
 
 /// 处理纯Telegram模式的MCP请求（不启动GUI）
-pub async fn handle_telegram_only_mcp_request(request_file: &str) -> Result<()> {
-    log_important!(info, "[telegram-mcp] 处理纯 Telegram 模式请求: file={}", request_file);
+///
+/// `request_json` 为已读取的请求内容（文件、stdin 或 base64 参数均在 CLI 层解析）
+pub async fn handle_telegram_only_mcp_request(request_json: &str) -> Result<()> {
+    log_important!(info, "[telegram-mcp] 处理纯 Telegram 模式请求: request_len={}", request_json.len());
 
-    // 读取MCP请求文件
-    let request_json = std::fs::read_to_string(request_file)?;
-    let request: PopupRequest = serde_json::from_str(&request_json)?;
+    let request: PopupRequest = serde_json::from_str(request_json)?;
 
     log_debug!("[telegram-mcp] 请求解析成功: id={}, msg_len={}, options={:?}", 
         request.id, 
@@ -292,6 +295,8 @@ async fn handle_send_pressed(
     );
 
     // 输出JSON响应到stdout（MCP协议要求）
+    // 中文注释：先写入历史再输出响应，MCP 侧按 request_id 去重时可以看到这条 telegram 记录
+    record_telegram_history(request, &response);
     log_debug!("[telegram-mcp] 输出 MCP 响应: len={}", response.len());
     println!("{}", response);
 
@@ -321,6 +326,7 @@ async fn handle_continue_pressed(
     );
 
     // 输出JSON响应到stdout（MCP协议要求）
+    record_telegram_history(request, &response);
     log_debug!("[telegram-mcp] 输出继续响应: len={}", response.len());
     println!("{}", response);
 
@@ -336,6 +342,40 @@ async fn handle_continue_pressed(
     Ok(())
 }
 
+/// 将 Telegram 回复写入 zhi 交互历史（失败不影响主流程）
+///
+/// 中文注释：项目路径与弹窗路径使用同一套规范化规则，保证 hash 一致；
+/// MCP 侧的 zhi 历史记录按 request_id 去重，不会再以 popup 来源重复写入
+fn record_telegram_history(request: &PopupRequest, response: &str) {
+    let project_root = match normalize_optional_project_root(request.project_root_path.as_deref()) {
+        Ok(Some(path)) => path,
+        Ok(None) => return,
+        Err(e) => {
+            log_debug!("[telegram-mcp] 项目路径无效，跳过历史记录: {}", e);
+            return;
+        }
+    };
+
+    match ZhiHistoryManager::new(&project_root) {
+        Ok(manager) => {
+            add_telegram_entry(&manager, request, response);
+        }
+        Err(e) => log_debug!("[telegram-mcp] 创建历史管理器失败: {}", e),
+    }
+}
+
+/// 以 `telegram` 来源记录一条交互；用户取消或回复为空时跳过
+fn add_telegram_entry(manager: &ZhiHistoryManager, request: &PopupRequest, response: &str) -> Option<String> {
+    let reply_summary = summarize_mcp_response(response)?;
+    match manager.add_entry(&request.id, &request.message, &reply_summary, "telegram") {
+        Ok(id) => Some(id),
+        Err(e) => {
+            log_important!(warn, "[telegram-mcp] 记录交互历史失败: request_id={}, error={}", request.id, e);
+            None
+        }
+    }
+}
+
 /// 处理完成标记（用于从监听循环中退出）
 #[derive(Debug)]
 struct ProcessingComplete;
@@ -347,3 +387,52 @@ impl std::fmt::Display for ProcessingComplete {
 }
 
 impl std::error::Error for ProcessingComplete {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::utils::MemoryHistoryStore;
+    use std::sync::Arc;
+
+    fn telegram_request() -> PopupRequest {
+        PopupRequest {
+            id: "req-335".to_string(),
+            message: "是否继续重构？".to_string(),
+            predefined_options: Some(vec!["继续".to_string()]),
+            is_markdown: false,
+            project_root_path: Some("/tmp/sanshu-telegram-project".to_string()),
+            uiux_intent: None,
+            uiux_context_policy: None,
+            uiux_reason: None,
+            default_input: None,
+            timeout_secs: None,
+            default_on_timeout: None,
+        }
+    }
+
+    #[test]
+    fn test_telegram_reply_records_history_with_source() {
+        let store = Arc::new(MemoryHistoryStore::new());
+        let manager = ZhiHistoryManager::with_store("/tmp/sanshu-telegram-project", store).unwrap();
+        let request = telegram_request();
+        let response = build_send_response(
+            Some("好的".to_string()),
+            vec!["继续".to_string()],
+            vec![],
+            Some(request.id.clone()),
+            "telegram",
+        );
+
+        let id = add_telegram_entry(&manager, &request, &response).unwrap();
+        // 取消的回复不应写入历史
+        assert!(add_telegram_entry(&manager, &request, "CANCELLED").is_none());
+
+        let entries = manager.get_all();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, id);
+        assert_eq!(entries[0].request_id, "req-335");
+        assert_eq!(entries[0].source, "telegram");
+        assert_eq!(entries[0].prompt, "是否继续重构？");
+        assert_eq!(entries[0].user_reply, "用户输入: 好的\n选项: 继续");
+    }
+}
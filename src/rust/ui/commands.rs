@@ -1,7 +1,8 @@
 use crate::config::{save_config, load_config, AppState, ReplyConfig, WindowConfig, CustomPrompt, CustomPromptConfig, ShortcutConfig, ShortcutBinding};
 use crate::constants::{window, ui, validation};
 use crate::mcp::types::{build_continue_response, build_send_response, ImageAttachment, PopupRequest};
-use crate::mcp::handlers::create_tauri_popup;
+use crate::mcp::handlers::{create_tauri_popup, MCP_REQUEST_BASE64_PREFIX, MCP_REQUEST_STDIN_ARG};
+use crate::app::cli::MCP_REQUEST_JSON_ENV;
 use tauri::{AppHandle, Manager, State};
 
 #[tauri::command]
@@ -524,6 +525,16 @@ pub fn get_cli_args() -> Result<serde_json::Value, String> {
 
 #[tauri::command]
 pub fn read_mcp_request(file_path: String) -> Result<serde_json::Value, String> {
+    // 内联请求（stdin / base64 参数）已在 CLI 层解码并写入环境变量
+    if file_path == MCP_REQUEST_STDIN_ARG || file_path.starts_with(MCP_REQUEST_BASE64_PREFIX) {
+        let content = std::env::var(MCP_REQUEST_JSON_ENV)
+            .map_err(|_| "内联MCP请求不存在".to_string())?;
+        return serde_json::from_str(&content).map_err(|e| {
+            log::warn!("[read_mcp_request] 解析内联请求失败: error={}", e);
+            format!("解析JSON失败: {}", e)
+        });
+    }
+
     if !std::path::Path::new(&file_path).exists() {
         log::warn!("[read_mcp_request] 文件不存在: {}", file_path);
         return Err(format!("文件不存在: {}", file_path));
@@ -93,74 +93,23 @@ pub async fn check_for_updates(app: AppHandle, state: State<'_, AppState>) -> Re
 
     log::info!("📡 发送 GitHub API 请求");
 
-    let response = client
-        .get("https://api.github.com/repos/yuaotian/sanshu/releases/latest")
-        .header("User-Agent", "sanshu-app/1.0")
-        .header("Accept", "application/vnd.github.v3+json")
-        .send()
-        .await
-        .map_err(|e| {
-            log::error!("❌ 网络请求失败: {}", e);
-            format!("网络请求失败: {}", e)
-        })?;
-
-    log::info!("📊 GitHub API 响应状态: {}", response.status());
-
-    // 更新 GitHub 可达状态
-    network_status.github_reachable = response.status().is_success();
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_msg = if status == 403 {
-            "网络请求受限，请手动下载最新版本".to_string()
-        } else if status == 404 {
-            "网络连接异常，请检查网络后重试".to_string()
-        } else {
-            format!("网络请求失败: {}", status)
-        };
-        log::error!("❌ {}", error_msg);
-        return Err(error_msg);
-    }
-
-    let release: serde_json::Value = response
-        .json()
-        .await
-        .map_err(|e| {
-            log::error!("❌ 解析响应失败: {}", e);
-            format!("解析响应失败: {}", e)
-        })?;
-
-    log::info!("📋 成功获取 release 数据");
-
     let current_version = app.package_info().version.to_string();
     log::info!("📦 当前版本: {}", current_version);
 
-    // 提取最新版本号，处理中文tag
-    let tag_name = release["tag_name"]
-        .as_str()
-        .unwrap_or("")
-        .to_string();
-
-    log::info!("🏷️ GitHub tag: {}", tag_name);
+    let check = check_for_update_with(&client, RELEASE_API_URL, &current_version).await;
 
-    // 移除前缀v和中文字符，只保留数字和点
-    let latest_version = tag_name
-        .replace("v", "")
-        .chars()
-        .filter(|c| c.is_numeric() || *c == '.')
-        .collect::<String>();
-
-    log::info!("🆕 解析后的最新版本: {}", latest_version);
+    // 更新 GitHub 可达状态
+    network_status.github_reachable = check.release.is_some();
 
-    if latest_version.is_empty() {
-        let error_msg = "无法解析版本号".to_string();
+    if let Some(error_msg) = check.error {
         log::error!("❌ {}", error_msg);
         return Err(error_msg);
     }
-
-    // 比较版本号
-    let has_update = compare_versions(&latest_version, &current_version);
-    log::info!("🔄 版本比较结果 - 有更新: {}", has_update);
+    let (Some(release), Some(latest_version)) = (check.release, check.latest) else {
+        return Err("无法解析版本号".to_string());
+    };
+    let has_update = check.update_available;
+    log::info!("🔄 版本比较结果 - 最新版本: {}, 有更新: {}", latest_version, has_update);
 
     // 获取实际的下载URL（从assets中找到对应平台的文件）
     let download_url = get_platform_download_url(&release)?;
@@ -178,25 +127,123 @@ pub async fn check_for_updates(app: AppHandle, state: State<'_, AppState>) -> Re
     Ok(update_info)
 }
 
-/// 简单的版本比较函数
-fn compare_versions(v1: &str, v2: &str) -> bool {
-    let v1_parts: Vec<u32> = v1.split('.').filter_map(|s| s.parse().ok()).collect();
-    let v2_parts: Vec<u32> = v2.split('.').filter_map(|s| s.parse().ok()).collect();
-    
-    let max_len = v1_parts.len().max(v2_parts.len());
-    
-    for i in 0..max_len {
-        let v1_part = v1_parts.get(i).unwrap_or(&0);
-        let v2_part = v2_parts.get(i).unwrap_or(&0);
-        
-        if v1_part > v2_part {
-            return true;
-        } else if v1_part < v2_part {
-            return false;
+/// GitHub 最新 release 查询接口
+pub const RELEASE_API_URL: &str = "https://api.github.com/repos/yuaotian/sanshu/releases/latest";
+
+/// 轻量更新检查结果（网络失败时 update_available 为 false，失败原因记录在 error 中）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateCheckResult {
+    pub current: String,
+    pub latest: Option<String>,
+    pub update_available: bool,
+    pub url: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// 原始 release 数据（完整更新流程用于读取更新说明与下载地址，不返回给前端）
+    #[serde(skip)]
+    pub release: Option<serde_json::Value>,
+}
+
+/// 将 release tag 解析为语义化版本（兼容 v 前缀与带中文的 tag）
+fn parse_release_version(tag: &str) -> Option<semver::Version> {
+    let trimmed = tag.trim().trim_start_matches(['v', 'V']);
+    if let Ok(version) = semver::Version::parse(trimmed) {
+        return Some(version);
+    }
+    // 中文注释：只保留数字和点，缺失的段补 0（例如 "v1.2 正式版" -> 1.2.0）
+    let digits: String = trimmed.chars().filter(|c| c.is_ascii_digit() || *c == '.').collect();
+    let mut parts: Vec<&str> = digits.split('.').filter(|p| !p.is_empty()).take(3).collect();
+    if parts.is_empty() {
+        return None;
+    }
+    while parts.len() < 3 {
+        parts.push("0");
+    }
+    semver::Version::parse(&parts.join(".")).ok()
+}
+
+/// 查询指定 release 接口并与当前版本比较
+pub async fn check_for_update_with(client: &reqwest::Client, endpoint: &str, current: &str) -> UpdateCheckResult {
+    let mut result = UpdateCheckResult {
+        current: current.to_string(),
+        latest: None,
+        update_available: false,
+        url: None,
+        error: None,
+        release: None,
+    };
+
+    let response = match client
+        .get(endpoint)
+        .header("User-Agent", "sanshu-app/1.0")
+        .header("Accept", "application/vnd.github.v3+json")
+        .send()
+        .await
+    {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            let status = response.status();
+            log::warn!("⚠️ 更新检查失败: HTTP {}", status);
+            result.error = Some(if status == 403 {
+                "网络请求受限，请手动下载最新版本".to_string()
+            } else if status == 404 {
+                "网络连接异常，请检查网络后重试".to_string()
+            } else {
+                format!("网络请求失败: {}", status)
+            });
+            return result;
         }
+        Err(e) => {
+            log::warn!("⚠️ 更新检查网络请求失败: {}", e);
+            result.error = Some(format!("网络请求失败: {}", e));
+            return result;
+        }
+    };
+
+    let release: serde_json::Value = match response.json().await {
+        Ok(release) => release,
+        Err(e) => {
+            log::warn!("⚠️ 解析 release 响应失败: {}", e);
+            result.error = Some(format!("解析响应失败: {}", e));
+            return result;
+        }
+    };
+
+    let Some(latest) = release["tag_name"].as_str().and_then(parse_release_version) else {
+        log::warn!("⚠️ 无法解析 release 版本号: {}", release["tag_name"]);
+        result.error = Some("无法解析版本号".to_string());
+        result.release = Some(release);
+        return result;
+    };
+    if let Some(current) = parse_release_version(current) {
+        result.update_available = latest > current;
     }
-    
-    false
+    result.latest = Some(latest.to_string());
+    result.url = release["html_url"].as_str().map(str::to_string);
+    result.release = Some(release);
+    result
+}
+
+/// 轻量检查是否有新版本（仅比较版本号，不做网络诊断与下载地址解析）
+#[tauri::command]
+pub async fn check_for_update(state: State<'_, AppState>) -> Result<UpdateCheckResult, String> {
+    let current = env!("CARGO_PKG_VERSION");
+    let proxy_info = detect_and_configure_proxy(&state).await;
+    let client = match create_update_client(proxy_info.as_ref()) {
+        Ok(client) => client,
+        Err(e) => {
+            log::warn!("⚠️ 创建更新检查客户端失败: {}", e);
+            return Ok(UpdateCheckResult {
+                current: current.to_string(),
+                latest: None,
+                update_available: false,
+                url: None,
+                error: Some(format!("创建HTTP客户端失败: {}", e)),
+                release: None,
+            });
+        }
+    };
+    Ok(check_for_update_with(&client, RELEASE_API_URL, current).await)
 }
 
 /// 下载并安装更新
@@ -935,6 +982,11 @@ fn replace_all_files_unix(app_dir: &PathBuf, files: &[PathBuf]) -> Result<(), St
 /// - `Some(ProxyInfo)`: 使用代理
 /// - `None`: 使用直连
 async fn detect_and_configure_proxy(state: &State<'_, AppState>) -> Option<ProxyInfo> {
+    if crate::network::force_direct_enabled() {
+        log::info!("ℹ️ 强制直连模式，跳过代理检测");
+        return None;
+    }
+
     // 读取代理配置
     let proxy_config = {
         let config = state.config.lock().ok()?;
@@ -1012,7 +1064,7 @@ async fn detect_geo_location_full() -> GeoLocation {
     log::info!("🌍 开始检测完整地理位置信息");
 
     // 创建HTTP客户端，设置较短的超时时间
-    let client = match reqwest::Client::builder()
+    let client = match crate::network::apply_force_direct(reqwest::Client::builder())
         .timeout(std::time::Duration::from_secs(5))
         .build()
     {
@@ -1092,3 +1144,35 @@ async fn detect_geo_location_full() -> GeoLocation {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::http_mock::{MockResponse, MockServer};
+
+    /// 启动 HTTP 服务器，返回固定 JSON 响应
+    fn start_release_server(body: &'static str) -> String {
+        MockServer::respond_with(MockResponse::json(body)).url("/releases/latest")
+    }
+
+    #[tokio::test]
+    async fn test_higher_release_version_reports_update_available() {
+        let endpoint = start_release_server(r#"{"tag_name":"v99.1.0","html_url":"https://example.com/release"}"#);
+        let result = check_for_update_with(&reqwest::Client::new(), &endpoint, "0.5.0").await;
+
+        assert!(result.update_available);
+        assert_eq!(result.latest.as_deref(), Some("99.1.0"));
+        assert_eq!(result.url.as_deref(), Some("https://example.com/release"));
+    }
+
+    #[tokio::test]
+    async fn test_network_failure_reports_no_update() {
+        // 绑定后立即释放端口，保证连接被拒绝
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let result = check_for_update_with(&reqwest::Client::new(), &format!("http://{}", addr), "0.5.0").await;
+
+        assert!(!result.update_available);
+        assert!(result.latest.is_none());
+        assert!(result.error.as_deref().unwrap_or_default().contains("网络请求失败"));
+    }
+}
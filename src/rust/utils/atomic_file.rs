@@ -0,0 +1,83 @@
+// 原子写入
+// 先写入同目录下的临时文件并落盘，再 rename 覆盖目标文件；
+// 写入中途崩溃或失败时，目标文件保持为上一次的完整内容，不会出现截断的 JSON
+
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 原子写入文件内容
+pub fn write_atomic(path: &Path, content: impl AsRef<[u8]>) -> Result<()> {
+    write_atomic_with(path, |file| {
+        file.write_all(content.as_ref())?;
+        Ok(())
+    })
+}
+
+/// 原子写入：由回调向临时文件写入内容
+///
+/// 回调返回错误时删除临时文件并返回错误，目标文件不受影响
+pub fn write_atomic_with<F>(path: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&mut File) -> Result<()>,
+{
+    let tmp_path = temp_path_for(path);
+    let result = (|| -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&tmp_path)
+            .with_context(|| format!("创建临时文件失败: {}", tmp_path.display()))?;
+        write(&mut file)?;
+        file.sync_all()
+            .with_context(|| format!("同步临时文件失败: {}", tmp_path.display()))?;
+        drop(file);
+        fs::rename(&tmp_path, path)
+            .with_context(|| format!("替换目标文件失败: {}", path.display()))
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// 临时文件路径：与目标同目录，保证 rename 在同一文件系统内完成
+fn temp_path_for(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "file".to_string());
+    let tmp_name = format!(".{}.{}.{}.tmp", file_name, std::process::id(), fastrand::u32(..));
+    match path.parent() {
+        Some(parent) => parent.join(tmp_name),
+        None => PathBuf::from(tmp_name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_failed_write_keeps_previous_file_intact() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("store.json");
+        write_atomic(&path, r#"{"entries":[1,2,3]}"#).unwrap();
+
+        // 模拟写入到一半时失败
+        let result = write_atomic_with(&path, |file| {
+            file.write_all(br#"{"entries":[1,"#)?;
+            Err(anyhow::anyhow!("磁盘已满"))
+        });
+        assert!(result.is_err());
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"entries":[1,2,3]}"#);
+        // 临时文件已清理，目录中只剩目标文件
+        let names: Vec<_> = fs::read_dir(temp_dir.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(names, vec![std::ffi::OsString::from("store.json")]);
+    }
+}
@@ -0,0 +1,249 @@
+// 测试用 HTTP 模拟服务器
+// 基于 std::net::TcpListener，每个连接在独立线程中处理，按处理函数返回的响应作答；
+// 单元测试通过 `crate::utils::http_mock` 使用，集成测试以 `#[path]` 引入同一文件
+#![allow(dead_code)] // 中文注释：各测试 crate 只用到部分接口
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 收到的请求
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    /// 请求方法（GET / POST / CONNECT ...）
+    pub method: String,
+    /// 请求目标（路径与查询串；经 HTTP 代理时为完整 URL）
+    pub target: String,
+    /// 请求头（名称统一为小写）
+    pub headers: Vec<(String, String)>,
+    /// 请求体
+    pub body: Vec<u8>,
+}
+
+impl MockRequest {
+    /// 按名称（忽略大小写）读取请求头
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let name = name.to_ascii_lowercase();
+        self.headers.iter().find(|(k, _)| *k == name).map(|(_, v)| v.as_str())
+    }
+
+    /// 读取查询参数
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        let query = self.target.split_once('?')?.1;
+        query.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key == name).then(|| value.to_string())
+        })
+    }
+
+    /// 请求方法与目标（不含协议版本）
+    pub fn request_line(&self) -> String {
+        format!("{} {}", self.method, self.target)
+    }
+}
+
+/// 模拟响应
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    status: u16,
+    reason: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    delay: Option<Duration>,
+    /// 分块推送的响应体与块间隔（设置后忽略 `body`，不带 Content-Length）
+    chunks: Option<(Vec<Vec<u8>>, Duration)>,
+}
+
+impl MockResponse {
+    /// 指定状态码的空响应
+    pub fn status(status: u16, reason: &str) -> Self {
+        Self {
+            status,
+            reason: reason.to_string(),
+            headers: Vec::new(),
+            body: Vec::new(),
+            delay: None,
+            chunks: None,
+        }
+    }
+
+    /// 200 响应，响应体为任意字节
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self::status(200, "OK").with_body(body)
+    }
+
+    /// 200 JSON 响应
+    pub fn json(body: impl Into<String>) -> Self {
+        Self::ok(body.into()).with_header("Content-Type", "application/json")
+    }
+
+    /// 修改状态码
+    pub fn with_status(mut self, status: u16, reason: &str) -> Self {
+        self.status = status;
+        self.reason = reason.to_string();
+        self
+    }
+
+    /// 设置响应体
+    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// 追加响应头
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// 分块推送响应体，每块之后等待 `interval`（模拟流式接口）
+    pub fn with_chunks<T: Into<Vec<u8>>>(mut self, chunks: Vec<T>, interval: Duration) -> Self {
+        self.chunks = Some((chunks.into_iter().map(Into::into).collect(), interval));
+        self
+    }
+
+    /// 响应前等待（模拟慢接口）
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    fn write_to(&self, stream: &mut TcpStream) {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, self.reason);
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        // 中文注释：204/304 不允许携带 Content-Length；分块推送时以关闭连接标记结束
+        if self.status != 204 && self.status != 304 && self.chunks.is_none() {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        head.push_str("Connection: close\r\n\r\n");
+        let _ = stream.write_all(head.as_bytes());
+        match &self.chunks {
+            Some((chunks, interval)) => {
+                for chunk in chunks {
+                    let _ = stream.write_all(chunk);
+                    let _ = stream.flush();
+                    std::thread::sleep(*interval);
+                }
+            }
+            None => {
+                let _ = stream.write_all(&self.body);
+            }
+        }
+    }
+}
+
+type Handler = dyn Fn(&MockRequest) -> MockResponse + Send + Sync;
+
+/// 模拟服务器（测试结束后随进程退出，无需显式关闭）
+pub struct MockServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+    peak_connections: Arc<AtomicUsize>,
+}
+
+impl MockServer {
+    /// 启动服务器，每个请求交给 `handler` 生成响应
+    pub fn start(handler: impl Fn(&MockRequest) -> MockResponse + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handler: Arc<Handler> = Arc::new(handler);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak_connections = Arc::new(AtomicUsize::new(0));
+
+        let (recorded, peak) = (requests.clone(), peak_connections.clone());
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let (handler, recorded, active, peak) = (handler.clone(), recorded.clone(), active.clone(), peak.clone());
+                std::thread::spawn(move || {
+                    // 中文注释：端口预检等空连接直接关闭，不计入请求
+                    let Some(request) = read_request(&mut stream) else {
+                        return;
+                    };
+                    let current = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(current, Ordering::SeqCst);
+                    recorded.lock().unwrap().push(request.clone());
+
+                    let response = handler(&request);
+                    if let Some(delay) = response.delay {
+                        std::thread::sleep(delay);
+                    }
+                    active.fetch_sub(1, Ordering::SeqCst);
+                    response.write_to(&mut stream);
+                });
+            }
+        });
+
+        Self { addr, requests, peak_connections }
+    }
+
+    /// 对所有请求返回同一响应
+    pub fn respond_with(response: MockResponse) -> Self {
+        Self::start(move |_| response.clone())
+    }
+
+    /// 监听地址
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 拼接指定路径的完整 URL（`path` 以 `/` 开头）
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// 已收到的请求（按处理顺序）
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// 同时处理中的请求数峰值
+    pub fn peak_connections(&self) -> usize {
+        self.peak_connections.load(Ordering::SeqCst)
+    }
+}
+
+/// 读取请求头与请求体（按 Content-Length），连接无数据时返回 None
+fn read_request(stream: &mut TcpStream) -> Option<MockRequest> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    let header_end = loop {
+        let n = stream.read(&mut buf).ok()?;
+        if n == 0 {
+            return None;
+        }
+        data.extend_from_slice(&buf[..n]);
+        if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let target = request_line.next().unwrap_or_default().to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let content_length = headers
+        .iter()
+        .find(|(k, _)| k == "content-length")
+        .and_then(|(_, v)| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = data[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => body.extend_from_slice(&buf[..n]),
+        }
+    }
+
+    Some(MockRequest { method, target, headers, body })
+}
@@ -0,0 +1,187 @@
+// 图片元数据清理
+// 移除 JPEG/PNG/WebP 中的 EXIF、XMP、文本注释等元数据块，像素数据与色彩相关的块（ICC、Adobe）原样保留
+
+use crate::log_debug;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// 需要移除的 PNG 元数据块
+const PNG_METADATA_CHUNKS: &[&[u8; 4]] = &[b"eXIf", b"tEXt", b"zTXt", b"iTXt", b"tIME"];
+
+/// 需要移除的 WebP 元数据块
+const WEBP_METADATA_CHUNKS: &[&[u8; 4]] = &[b"EXIF", b"XMP "];
+
+/// VP8X 标志位：EXIF / XMP 存在
+const VP8X_EXIF_FLAG: u8 = 0x08;
+const VP8X_XMP_FLAG: u8 = 0x04;
+
+/// 移除图片元数据；格式不支持或结构异常时原样返回
+pub fn strip_image_metadata(data: &[u8]) -> Vec<u8> {
+    let stripped = if data.starts_with(&[0xFF, 0xD8]) {
+        strip_jpeg(data)
+    } else if data.starts_with(PNG_SIGNATURE) {
+        strip_png(data)
+    } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        strip_webp(data)
+    } else {
+        return data.to_vec();
+    };
+
+    stripped.unwrap_or_else(|| {
+        log_debug!("图片结构无法解析，跳过元数据清理");
+        data.to_vec()
+    })
+}
+
+/// JPEG：移除 APP1（EXIF/XMP）、APP13（IPTC）与 COM 段，SOS 之后的扫描数据原样复制
+fn strip_jpeg(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..2]);
+    let mut pos = 2;
+
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        // 跳过填充字节 0xFF
+        while *data.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        let marker = data[pos + 1];
+
+        // 无长度字段的独立标记
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            out.extend_from_slice(&data[pos..pos + 2]);
+            pos += 2;
+            continue;
+        }
+        if marker == 0xD9 {
+            out.extend_from_slice(&data[pos..pos + 2]);
+            return Some(out);
+        }
+
+        let len = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]) as usize;
+        let end = pos + 2 + len;
+        if len < 2 || end > data.len() {
+            return None;
+        }
+
+        if marker == 0xDA {
+            out.extend_from_slice(&data[pos..]);
+            return Some(out);
+        }
+        if !matches!(marker, 0xE1 | 0xED | 0xFE) {
+            out.extend_from_slice(&data[pos..end]);
+        }
+        pos = end;
+    }
+}
+
+/// PNG：按块复制，跳过 eXIf 与文本类块
+fn strip_png(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(PNG_SIGNATURE);
+    let mut pos = PNG_SIGNATURE.len();
+
+    while pos < data.len() {
+        let len = u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let chunk_type = data.get(pos + 4..pos + 8)?;
+        // 长度 + 类型 + 数据 + CRC
+        let end = pos.checked_add(12)?.checked_add(len)?;
+        if end > data.len() {
+            return None;
+        }
+        if !PNG_METADATA_CHUNKS.iter().any(|t| t.as_slice() == chunk_type) {
+            out.extend_from_slice(&data[pos..end]);
+        }
+        pos = end;
+        if chunk_type == b"IEND" {
+            break;
+        }
+    }
+    Some(out)
+}
+
+/// WebP：跳过 EXIF/XMP 块，清除 VP8X 中对应标志位并重算 RIFF 大小
+fn strip_webp(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..12]);
+    let mut pos = 12;
+
+    while pos + 8 <= data.len() {
+        let fourcc = &data[pos..pos + 4];
+        let size = u32::from_le_bytes(data[pos + 4..pos + 8].try_into().ok()?) as usize;
+        // 块数据按偶数字节对齐
+        let end = pos.checked_add(8)?.checked_add(size + (size & 1))?;
+        if end > data.len() {
+            return None;
+        }
+        if !WEBP_METADATA_CHUNKS.iter().any(|t| t.as_slice() == fourcc) {
+            let start = out.len();
+            out.extend_from_slice(&data[pos..end]);
+            if fourcc == b"VP8X" && size > 0 {
+                out[start + 8] &= !(VP8X_EXIF_FLAG | VP8X_XMP_FLAG);
+            }
+        }
+        pos = end;
+    }
+
+    let riff_size = u32::try_from(out.len() - 8).ok()?;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[test]
+    fn test_png_text_and_exif_chunks_removed() {
+        let chunk = |kind: &[u8], body: &[u8]| {
+            let mut c = (body.len() as u32).to_be_bytes().to_vec();
+            c.extend_from_slice(kind);
+            c.extend_from_slice(body);
+            c.extend_from_slice(&[0, 0, 0, 0]);
+            c
+        };
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend(chunk(b"IHDR", &[0; 13]));
+        png.extend(chunk(b"eXIf", b"GPS"));
+        png.extend(chunk(b"tEXt", b"Author\0me"));
+        png.extend(chunk(b"IDAT", b"pixels"));
+        png.extend(chunk(b"IEND", b""));
+
+        let stripped = strip_image_metadata(&png);
+
+        assert!(!contains(&stripped, b"eXIf"));
+        assert!(!contains(&stripped, b"Author"));
+        assert!(contains(&stripped, b"IDATpixels"));
+        assert!(stripped.ends_with(&chunk(b"IEND", b"")));
+    }
+
+    #[test]
+    fn test_webp_exif_chunk_removed_and_flags_cleared() {
+        let mut webp = b"RIFF\0\0\0\0WEBP".to_vec();
+        webp.extend_from_slice(b"VP8X\x0a\0\0\0");
+        webp.extend_from_slice(&[VP8X_EXIF_FLAG, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        webp.extend_from_slice(b"VP8L\x02\0\0\0ab");
+        webp.extend_from_slice(b"EXIF\x03\0\0\0GPS\0");
+
+        let stripped = strip_image_metadata(&webp);
+
+        assert!(!contains(&stripped, b"EXIF"));
+        assert_eq!(stripped[20], 0);
+        assert_eq!(u32::from_le_bytes(stripped[4..8].try_into().unwrap()) as usize, stripped.len() - 8);
+    }
+
+    #[test]
+    fn test_unknown_or_truncated_data_returned_unchanged() {
+        assert_eq!(strip_image_metadata(b"not an image"), b"not an image");
+        let truncated = [0xFF, 0xD8, 0xFF, 0xE1, 0x10, 0x00, b'E'];
+        assert_eq!(strip_image_metadata(&truncated), truncated);
+    }
+}
@@ -1,3 +1,11 @@
+pub mod atomic_file;
+#[cfg(test)]
+pub mod http_mock;
+pub mod image_metadata;
 pub mod logger;
+pub mod redact;
 
-pub use logger::{LogConfig, init_logger, auto_init_logger};
+pub use atomic_file::{write_atomic, write_atomic_with};
+pub use image_metadata::strip_image_metadata;
+pub use redact::redact;
+pub use logger::{LogConfig, init_logger, auto_init_logger, with_request_id, current_request_id};
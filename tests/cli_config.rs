@@ -0,0 +1,27 @@
+// --config get/set 读写独立配置文件；SANSHU_CONFIG_DIR 会修改进程级环境变量，独立为集成测试
+
+use sanshu::config::keys::{config_get, config_set};
+use sanshu::config::{load_standalone_config, CONFIG_DIR_ENV};
+
+#[test]
+fn config_get_tool_flag_and_set_proxy_host_persist() {
+    let config_root = tempfile::TempDir::new().unwrap();
+    std::env::set_var(CONFIG_DIR_ENV, config_root.path());
+
+    // 无配置文件时返回默认工具开关
+    assert_eq!(config_get("tools.zhi").unwrap(), "true");
+    assert_eq!(config_get("tools.sou").unwrap(), "false");
+
+    config_set("proxy.host", "10.0.0.2").unwrap();
+    config_set("tools.sou", "true").unwrap();
+
+    let config = load_standalone_config().unwrap();
+    assert_eq!(config.proxy_config.host, "10.0.0.2");
+    assert_eq!(config.mcp_config.tools.get("sou"), Some(&true));
+    assert_eq!(config_get("proxy.host").unwrap(), "10.0.0.2");
+    assert!(config_root.path().join("config.json").exists());
+
+    assert!(config_set("proxy.nonexistent", "x").is_err());
+
+    std::env::remove_var(CONFIG_DIR_ENV);
+}
@@ -0,0 +1,120 @@
+// --enhance CLI 模式：启动真实二进制，增强 API 指向本地模拟服务器
+
+#[path = "../src/rust/utils/http_mock.rs"]
+mod http_mock;
+
+use std::process::Command;
+
+use http_mock::{MockResponse, MockServer};
+use sanshu::config::CONFIG_DIR_ENV;
+use sanshu::mcp::tools::enhance::{
+    ENHANCE_BASE_URL_ENV, ENHANCE_RECORD_DIR_ENV, ENHANCE_TOKEN_ENV, ENHANCE_TRACE_ENV,
+};
+use sanshu::paths::DATA_DIR_ENV;
+
+/// 启动 SSE 模拟服务器，返回 base_url
+fn start_mock_enhance_server(events: &[&str]) -> String {
+    let body: String = events.iter().map(|e| format!("data: {}\n\n", e)).collect();
+    MockServer::respond_with(MockResponse::ok(body).with_header("Content-Type", "text/event-stream")).url("")
+}
+
+#[test]
+fn enhance_cli_streams_chunks_and_prints_final_prompt() {
+    let data_root = tempfile::TempDir::new().unwrap();
+    let config_root = tempfile::TempDir::new().unwrap();
+    let project = tempfile::TempDir::new().unwrap();
+    let base_url = start_mock_enhance_server(&[
+        r#"{"text":"<augment-enhanced-prompt>请修复登录"}"#,
+        r#"{"text":"页面的空指针异常"}"#,
+        r#"{"text":"</augment-enhanced-prompt>"}"#,
+    ]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_等一下"))
+        .args(["--enhance", "修复登录 bug", "--project-root"])
+        .arg(project.path())
+        .env(ENHANCE_BASE_URL_ENV, &base_url)
+        .env(ENHANCE_TOKEN_ENV, "test-token")
+        .env(DATA_DIR_ENV, data_root.path())
+        // 中文注释：隔离独立配置文件目录，避免读取本机真实配置
+        .env(CONFIG_DIR_ENV, config_root.path())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("页面的空指针异常"));
+    let (_, result) = stdout.split_once("=== 增强结果 ===").unwrap();
+    assert_eq!(result.trim(), "请修复登录页面的空指针异常");
+}
+
+#[test]
+fn enhance_cli_writes_redacted_trace_when_enabled() {
+    let data_root = tempfile::TempDir::new().unwrap();
+    let config_root = tempfile::TempDir::new().unwrap();
+    let project = tempfile::TempDir::new().unwrap();
+    let base_url = start_mock_enhance_server(&[
+        r#"{"text":"<augment-enhanced-prompt>请补充单元测试"}"#,
+        r#"{"text":"</augment-enhanced-prompt>"}"#,
+    ]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_等一下"))
+        .args(["--enhance", "加测试", "--no-history", "--project-root"])
+        .arg(project.path())
+        .env(ENHANCE_BASE_URL_ENV, &base_url)
+        .env(ENHANCE_TOKEN_ENV, "trace-secret-token")
+        .env(ENHANCE_TRACE_ENV, "1")
+        .env(DATA_DIR_ENV, data_root.path())
+        .env(CONFIG_DIR_ENV, config_root.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+
+    let traces: Vec<_> = std::fs::read_dir(data_root.path().join("trace"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(traces.len(), 1);
+    let name = traces[0].file_name().unwrap().to_string_lossy().to_string();
+    assert!(name.starts_with("enhance_") && name.ends_with(".json"));
+
+    let content = std::fs::read_to_string(&traces[0]).unwrap();
+    assert!(!content.contains("trace-secret-token"));
+    let trace: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(trace["headers"]["Authorization"], "Bearer ***");
+    assert!(trace["payload"].is_object());
+    assert!(trace["raw_response"].as_str().unwrap().contains("请补充单元测试"));
+}
+
+#[test]
+fn enhance_cli_records_raw_sse_when_record_dir_set() {
+    let data_root = tempfile::TempDir::new().unwrap();
+    let config_root = tempfile::TempDir::new().unwrap();
+    let record_root = tempfile::TempDir::new().unwrap();
+    let project = tempfile::TempDir::new().unwrap();
+    let base_url = start_mock_enhance_server(&[
+        r#"{"text":"<augment-enhanced-prompt>请补充回归用例"}"#,
+        r#"{"text":"</augment-enhanced-prompt>"}"#,
+    ]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_等一下"))
+        .args(["--enhance", "加用例", "--no-history", "--project-root"])
+        .arg(project.path())
+        .env(ENHANCE_BASE_URL_ENV, &base_url)
+        .env(ENHANCE_TOKEN_ENV, "test-token")
+        .env(ENHANCE_RECORD_DIR_ENV, record_root.path())
+        .env(DATA_DIR_ENV, data_root.path())
+        .env(CONFIG_DIR_ENV, config_root.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+
+    let records: Vec<_> = std::fs::read_dir(record_root.path())
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(records.len(), 1);
+    let name = records[0].file_name().unwrap().to_string_lossy().to_string();
+    assert!(name.starts_with("enhance_") && name.ends_with(".sse"));
+    let raw = std::fs::read_to_string(&records[0]).unwrap();
+    assert!(raw.starts_with("data: ") && raw.contains("请补充回归用例"));
+}
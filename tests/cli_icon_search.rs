@@ -0,0 +1,42 @@
+// --icon-search --no-gui：启动真实二进制，图标搜索 API 指向本地模拟服务器
+
+#[path = "../src/rust/utils/http_mock.rs"]
+mod http_mock;
+
+use std::process::Command;
+
+use http_mock::{MockResponse, MockServer};
+use sanshu::config::CONFIG_DIR_ENV;
+use sanshu::mcp::tools::icon::api::ICON_SEARCH_API_ENV;
+use sanshu::paths::DATA_DIR_ENV;
+
+#[test]
+fn icon_search_no_gui_prints_result_json() {
+    let data_root = tempfile::TempDir::new().unwrap();
+    let config_root = tempfile::TempDir::new().unwrap();
+    let server = MockServer::respond_with(MockResponse::json(
+        r#"{"code":200,"data":{"count":1,"icons":[{"id":42,"name":"home","font_class":"home","show_svg":"<svg></svg>","repository":{"id":7,"name":"base"}}]}}"#,
+    ));
+    let api_url = server.url("/api/icon/search.json");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_等一下"))
+        .args(["--icon-search", "--no-gui", "--query", "home", "--style", "line"])
+        .env(ICON_SEARCH_API_ENV, &api_url)
+        .env(DATA_DIR_ENV, data_root.path())
+        .env(CONFIG_DIR_ENV, config_root.path())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["total"], 1);
+    assert_eq!(result["icons"][0]["id"], 42);
+    assert_eq!(result["icons"][0]["name"], "home");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    // 搜索参数以表单形式提交
+    let request = String::from_utf8_lossy(&requests[0].body);
+    assert!(request.contains("q=home"), "{}", request);
+    assert!(request.contains("sType=line"), "{}", request);
+}
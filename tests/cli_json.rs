@@ -0,0 +1,67 @@
+// --json 全局参数：启动真实二进制，校验机器可读输出
+
+use std::process::Command;
+
+use sanshu::config::CONFIG_DIR_ENV;
+
+#[test]
+fn version_json_prints_parseable_object() {
+    let config_root = tempfile::TempDir::new().unwrap();
+
+    for args in [["--version", "--json"], ["--json", "--version"]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_等一下"))
+            .args(args)
+            .env(CONFIG_DIR_ENV, config_root.path())
+            .output()
+            .unwrap();
+
+        assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+        let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(parsed["version"], env!("CARGO_PKG_VERSION"));
+    }
+
+    // 默认保持人类可读输出
+    let output = Command::new(env!("CARGO_BIN_EXE_等一下"))
+        .arg("--version")
+        .env(CONFIG_DIR_ENV, config_root.path())
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("三术 v"));
+}
+
+#[test]
+fn config_get_json_includes_key_and_value() {
+    let config_root = tempfile::TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_等一下"))
+        .args(["--config", "get", "tools.zhi", "--json"])
+        .env(CONFIG_DIR_ENV, config_root.path())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(parsed["key"], "tools.zhi");
+    assert_eq!(parsed["value"], "true");
+}
+
+#[test]
+fn json_as_option_value_is_not_taken_as_flag() {
+    let config_root = tempfile::TempDir::new().unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_等一下"))
+            .args(args)
+            .env(CONFIG_DIR_ENV, config_root.path())
+            .output()
+            .unwrap()
+    };
+
+    // 参数未齐全时 --json 作为配置值写入
+    let output = run(&["--config", "set", "proxy.host", "--json"]);
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "已更新 proxy.host");
+
+    let output = run(&["--json", "--config", "get", "proxy.host"]);
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(parsed["value"], "--json");
+}
@@ -0,0 +1,27 @@
+// SANSHU_DATA_DIR 会修改进程级环境变量，独立为集成测试以免影响其他单元测试
+
+use sanshu::mcp::tools::enhance::ChatHistoryManager;
+use sanshu::paths::{data_dir, DATA_DIR_ENV};
+
+#[test]
+fn data_dir_env_redirects_chat_history_files() {
+    let data_root = tempfile::TempDir::new().unwrap();
+    let project = tempfile::TempDir::new().unwrap();
+    std::env::set_var(DATA_DIR_ENV, data_root.path());
+
+    assert_eq!(data_dir(), data_root.path());
+
+    let manager = ChatHistoryManager::new(&project.path().to_string_lossy()).unwrap();
+    manager.add_entry("问题", "回答", "popup").unwrap();
+
+    let history_dir = data_root.path().join("chat_history");
+    let files: Vec<_> = std::fs::read_dir(&history_dir)
+        .unwrap()
+        .flatten()
+        .filter(|e| e.path().extension().map(|ext| ext == "json").unwrap_or(false))
+        .collect();
+    assert_eq!(files.len(), 1);
+    assert_eq!(manager.get_all().unwrap().len(), 1);
+
+    std::env::remove_var(DATA_DIR_ENV);
+}
@@ -0,0 +1,34 @@
+// MCP_IMAGE_MODE 会修改进程级环境变量，独立为集成测试以免影响其他单元测试
+
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::{json, Value};
+
+use sanshu::handlers::{parse_mcp_response_with_limit, IMAGE_MODE_ENV};
+
+#[test]
+fn file_mode_saves_images_for_non_augment_client() {
+    std::env::remove_var("MCP_AI_CLIENT");
+    std::env::set_var(IMAGE_MODE_ENV, "file");
+
+    let png = b"\x89PNG\r\n\x1a\n\0\0\0\0IEND";
+    let response = json!({
+        "user_input": "看这张截图",
+        "selected_options": [],
+        "images": [{ "data": general_purpose::STANDARD.encode(png), "media_type": "image/png", "filename": null }],
+        "metadata": { "timestamp": null, "request_id": "req-384", "source": "popup" }
+    })
+    .to_string();
+
+    let contents = parse_mcp_response_with_limit(&response, 8).unwrap();
+    std::env::remove_var(IMAGE_MODE_ENV);
+
+    assert_eq!(contents.len(), 1);
+    assert!(contents[0].raw.as_image().is_none());
+    let text = &contents[0].raw.as_text().expect("文件模式应返回文本").text;
+    let payload: Value = serde_json::from_str(text).unwrap();
+    assert_eq!(payload["text"], "看这张截图");
+    let path = payload["images"][0]["path"].as_str().unwrap();
+    assert_eq!(payload["images"][0]["type"], "png");
+    assert_eq!(std::fs::read(path).unwrap(), png);
+    let _ = std::fs::remove_file(path);
+}
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use sanshu::mcp::shutdown::ShutdownCoordinator;
+
+#[tokio::test]
+async fn shutdown_drains_in_flight_call_before_returning() {
+    // 协调器需为 'static，以便在后台任务中持有调用守卫
+    let coordinator: &'static ShutdownCoordinator = Box::leak(Box::new(ShutdownCoordinator::new()));
+    let finished = Arc::new(AtomicBool::new(false));
+
+    let guard_ready = Arc::new(tokio::sync::Notify::new());
+    let task = {
+        let finished = finished.clone();
+        let guard_ready = guard_ready.clone();
+        tokio::spawn(async move {
+            let _guard = coordinator.try_enter().expect("关闭前应允许进入");
+            guard_ready.notify_one();
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            finished.store(true, Ordering::SeqCst);
+        })
+    };
+    guard_ready.notified().await;
+    assert_eq!(coordinator.in_flight(), 1);
+
+    let report = coordinator.shutdown(Duration::from_secs(5)).await;
+
+    assert!(finished.load(Ordering::SeqCst), "shutdown 应等待进行中的调用完成");
+    assert!(report.drained);
+    assert_eq!(report.remaining, 0);
+    assert!(coordinator.try_enter().is_none(), "关闭后不应接收新调用");
+    task.await.unwrap();
+}
+
+#[tokio::test]
+async fn shutdown_cleans_registered_temp_files_after_timeout() {
+    let coordinator: &'static ShutdownCoordinator = Box::leak(Box::new(ShutdownCoordinator::new()));
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let temp_file = temp_dir.path().join("mcp_request_test.json");
+    std::fs::write(&temp_file, "{}").unwrap();
+    coordinator.register_temp_file(&temp_file);
+
+    // 持有一个永不结束的调用，验证超时后依然返回并清理
+    let _stuck = coordinator.try_enter().unwrap();
+    let report = coordinator.shutdown(Duration::from_millis(100)).await;
+
+    assert!(!report.drained);
+    assert_eq!(report.remaining, 1);
+    assert_eq!(report.cleaned_files, 1);
+    assert!(!temp_file.exists());
+}
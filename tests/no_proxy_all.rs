@@ -0,0 +1,28 @@
+// SANSHU_NO_PROXY_ALL 会修改进程级环境变量，独立为集成测试以免影响其他单元测试
+
+#[path = "../src/rust/utils/http_mock.rs"]
+mod http_mock;
+
+use http_mock::{MockResponse, MockServer};
+use sanshu::network::proxy::ProxyType;
+use sanshu::network::{create_http_client, ProxyDetector, ProxyInfo, NO_PROXY_ALL_ENV};
+
+#[tokio::test]
+async fn force_direct_ignores_available_proxy() {
+    std::env::set_var(NO_PROXY_ALL_ENV, "1");
+
+    // 代理端返回不同内容：若请求经过代理，响应体会是 "via-proxy"
+    let proxy_server = MockServer::respond_with(MockResponse::ok("via-proxy"));
+    let proxy_addr = proxy_server.addr();
+    let proxy = ProxyInfo::new(ProxyType::Http, proxy_addr.ip().to_string(), proxy_addr.port());
+    let target_url = MockServer::respond_with(MockResponse::ok("direct")).url("/");
+
+    let client = create_http_client(Some(&proxy), 5).unwrap();
+    let body = client.get(&target_url).send().await.unwrap().text().await.unwrap();
+    assert_eq!(body, "direct");
+
+    // 强制直连时不进行代理检测
+    assert!(ProxyDetector::detect_available_proxy().await.is_none());
+
+    std::env::remove_var(NO_PROXY_ALL_ENV);
+}
@@ -91,3 +91,48 @@ async fn uiux_design_system_keeps_project_context_disabled_without_project_root(
     );
     assert!(v["text"].as_str().unwrap_or_default().contains("提示词"));
 }
+
+#[tokio::test]
+async fn uiux_stack_resolves_alias_and_rejects_unknown_stack() {
+    let result = UiuxTool::call_tool(
+        "uiux_stack",
+        json!({ "query": "button loading state", "stack": "ReactJS" }),
+    )
+    .await
+    .expect("uiux_stack 调用应成功");
+    let v = parse_uiux_json(&extract_first_text(&result));
+    assert_eq!(v["meta"]["tool"].as_str(), Some("uiux_stack"));
+    assert_eq!(v["data"]["stack"].as_str(), Some("react"));
+
+    let result = UiuxTool::call_tool(
+        "uiux_stack",
+        json!({ "query": "button", "stack": "angular" }),
+    )
+    .await
+    .expect("未知技术栈应以结构化错误返回");
+    let v = parse_uiux_json(&extract_first_text(&result));
+    assert_eq!(v["errors"][0]["code"].as_str(), Some("uiux_unknown_stack"));
+    assert!(v["errors"][0]["message"].as_str().unwrap_or_default().contains("Available:"));
+}
+
+#[tokio::test]
+async fn uiux_search_merges_requested_domains() {
+    let result = UiuxTool::call_tool(
+        "uiux_search",
+        json!({ "query": "modern elegant professional", "domain": "color,typography", "max_results": 4 }),
+    )
+    .await
+    .expect("uiux_search 调用应成功");
+    let v = parse_uiux_json(&extract_first_text(&result));
+
+    assert_eq!(v["meta"]["tool"].as_str(), Some("uiux_search"));
+    assert_eq!(v["data"]["domain"].as_str(), Some("color,typography"));
+    let tags: Vec<&str> = v["data"]["results"]
+        .as_array()
+        .expect("results 应为数组")
+        .iter()
+        .filter_map(|r| r["Domain"].as_str())
+        .collect();
+    assert!(tags.iter().any(|t| t.contains("color")), "tags: {:?}", tags);
+    assert!(tags.iter().any(|t| t.contains("typography")), "tags: {:?}", tags);
+}